@@ -0,0 +1,644 @@
+use eframe::{
+    egui,
+    egui_wgpu::{self, RenderState},
+};
+use glam::{Mat4, Vec3};
+use std::f32::consts::PI;
+use wgpu::util::DeviceExt;
+
+use crate::{
+    meshes::mesh::{Mesh, Vertex},
+    offscreen::OffscreenTarget,
+    profiler::GpuProfiler,
+    readback::Readback,
+    stats::{paint_callback, CountedPass, FrameStats},
+};
+
+const CANVAS: (f32, f32) = (600.0, 600.0);
+/// Read by the translucent pass as plain values, which wgpu allows for 32 bit float depth.
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+/// Translucent fragments per pixel the fragment buffer has room for, on average.
+const DEFAULT_BUDGET: f32 = 4.0;
+/// Laid out to match `Fragment` in `shader.wgsl`.
+const FRAGMENT_SIZE: u64 = 12;
+
+/// Laid out to match `Uniforms` in `shader.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    view_proj: [[f32; 4]; 4],
+    knot_model: [[f32; 4]; 4],
+    cube_model: [[f32; 4]; 4],
+    opacity: f32,
+    max_fragments: u32,
+    width: u32,
+    _padding: u32,
+}
+
+/// A mesh uploaded to the GPU.
+struct GpuMesh {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    index_count: u32,
+}
+
+impl GpuMesh {
+    fn new(device: &wgpu::Device, mesh: &Mesh, label: &str) -> Self {
+        Self {
+            vertex_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("{label} Vertex Buffer")),
+                contents: bytemuck::cast_slice(&mesh.vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+            index_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("{label} Index Buffer")),
+                contents: bytemuck::cast_slice(&mesh.indices),
+                usage: wgpu::BufferUsages::INDEX,
+            }),
+            index_count: mesh.indices.len() as u32,
+        }
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut CountedPass<'_, 'a>) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.index_count, 0, 0..1);
+    }
+}
+
+/// The per-pixel linked lists, sized for the target and the fragment budget.
+struct LinkedLists {
+    heads: wgpu::Buffer,
+    fragment_count: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    max_fragments: u32,
+    budget: f32,
+}
+
+impl LinkedLists {
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        depth_view: &wgpu::TextureView,
+        size: (u32, u32),
+        budget: f32,
+    ) -> Self {
+        let pixels = size.0.max(1) * size.1.max(1);
+        // Large canvases are limited by how large a storage buffer can be bound
+        let max_fragments = ((pixels as f32 * budget) as u64)
+            .min(device.limits().max_storage_buffer_binding_size as u64 / FRAGMENT_SIZE)
+            as u32;
+
+        let heads = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("ABuffer Heads Buffer"),
+            size: pixels as u64 * std::mem::size_of::<u32>() as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let fragments = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("ABuffer Fragments Buffer"),
+            size: max_fragments as u64 * FRAGMENT_SIZE,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let fragment_count = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("ABuffer Fragment Count Buffer"),
+            size: std::mem::size_of::<u32>() as u64,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ABuffer Linked Lists Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(depth_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: heads.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: fragments.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: fragment_count.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self {
+            heads,
+            fragment_count,
+            bind_group,
+            max_fragments,
+            budget,
+        }
+    }
+}
+
+/// What the UI shows about the last frame read back.
+#[derive(Clone, Copy, Default)]
+struct FragmentStats {
+    /// Fragments the translucent pass tried to store.
+    count: u32,
+    max_fragments: u32,
+}
+
+pub struct ABuffer {
+    start_time: std::time::Instant,
+    opacity: f32,
+    budget: f32,
+    rotate: bool,
+    angle: f32,
+    camera_yaw: f32,
+    camera_pitch: f32,
+    stats: Option<FragmentStats>,
+}
+
+impl ABuffer {
+    pub fn new_with_render_state(wgpu_render_state: &RenderState) -> Option<Self> {
+        let device = &wgpu_render_state.device;
+
+        let knot = GpuMesh::new(
+            device,
+            &Mesh::create_torus_knot(1.0, 0.3, 256, 24, 2, 3),
+            "ABuffer Knot",
+        );
+        let cube = GpuMesh::new(device, &Mesh::create_box(0.8, 0.8, 0.8), "ABuffer Cube");
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("ABuffer Uniform Buffer"),
+            size: std::mem::size_of::<Uniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let uniform_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("ABuffer Uniform Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ABuffer Uniform Bind Group"),
+            layout: &uniform_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let storage_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let lists_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("ABuffer Linked Lists Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    storage_entry(1),
+                    storage_entry(2),
+                    storage_entry(3),
+                ],
+            });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("ABuffer Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./shader.wgsl").into()),
+        });
+
+        let opaque_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("ABuffer Opaque Pipeline Layout"),
+                bind_group_layouts: &[&uniform_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let lists_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("ABuffer Linked Lists Pipeline Layout"),
+                bind_group_layouts: &[&uniform_bind_group_layout, &lists_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let opaque_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("ABuffer Opaque Pipeline"),
+            layout: Some(&opaque_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_opaque",
+                buffers: &[Vertex::layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_opaque",
+                targets: &[Some(wgpu_render_state.target_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        // Both faces of the translucent mesh are stored, and the color target is left as is
+        let translucent_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("ABuffer Translucent Pipeline"),
+            layout: Some(&lists_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_translucent",
+                buffers: &[Vertex::layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_translucent",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: wgpu_render_state.target_format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::empty(),
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let resolve_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("ABuffer Resolve Pipeline"),
+            layout: Some(&lists_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_resolve",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_resolve",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: wgpu_render_state.target_format,
+                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let size = (CANVAS.0 as u32, CANVAS.1 as u32);
+        let target = OffscreenTarget::new(
+            device,
+            wgpu_render_state.target_format,
+            Some(DEPTH_FORMAT),
+            size,
+        );
+        let lists = LinkedLists::new(
+            device,
+            &lists_bind_group_layout,
+            target.depth_view().unwrap(),
+            size,
+            DEFAULT_BUDGET,
+        );
+        let readback = Readback::new(
+            device,
+            std::mem::size_of::<u32>() as u64,
+            "ABuffer Fragment Count Readback Buffer",
+        );
+
+        // Because the graphics pipeline must have the same lifetime as the egui render pass,
+        // instead of storing the pipeline in our struct, we insert it into the
+        // `paint_callback_resources` type map, which is stored alongside the render pass.
+        wgpu_render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(AppRenderResources {
+                target,
+                opaque_pipeline,
+                translucent_pipeline,
+                resolve_pipeline,
+                uniform_buffer,
+                uniform_bind_group,
+                lists_bind_group_layout,
+                lists,
+                knot,
+                cube,
+                readback,
+                readback_max_fragments: 0,
+                stats: None,
+            });
+
+        Some(Self {
+            start_time: std::time::Instant::now(),
+            opacity: 0.4,
+            budget: DEFAULT_BUDGET,
+            rotate: true,
+            angle: 0.0,
+            camera_yaw: 0.0,
+            camera_pitch: 0.3,
+            stats: None,
+        })
+    }
+
+    fn uniforms(&self, size: (u32, u32)) -> Uniforms {
+        let projection =
+            Mat4::perspective_rh((2.0 * PI) / 5.0, size.0 as f32 / size.1 as f32, 0.1, 100.0);
+        let eye = Mat4::from_rotation_y(self.camera_yaw)
+            * Mat4::from_rotation_x(-self.camera_pitch)
+            * Vec3::new(0.0, 0.0, 3.6).extend(1.0);
+        let view = Mat4::look_at_rh(eye.truncate(), Vec3::ZERO, Vec3::Y);
+
+        Uniforms {
+            view_proj: (projection * view).to_cols_array_2d(),
+            knot_model: (Mat4::from_rotation_y(self.angle) * Mat4::from_rotation_x(PI / 2.0))
+                .to_cols_array_2d(),
+            cube_model: (Mat4::from_rotation_y(-self.angle * 0.7)
+                * Mat4::from_rotation_x(self.angle * 0.4))
+            .to_cols_array_2d(),
+            opacity: self.opacity,
+            // Filled in by `prepare`, which knows the size of the buffers
+            max_fragments: 0,
+            width: size.0,
+            _padding: 0,
+        }
+    }
+}
+
+impl eframe::App for ABuffer {
+    fn update(&mut self, ctx: &eframe::egui::Context, frame: &mut eframe::Frame) {
+        if let Some(wgpu_render_state) = frame.wgpu_render_state() {
+            let renderer = wgpu_render_state.renderer.read();
+            if let Some(resources) = renderer.callback_resources.get::<AppRenderResources>() {
+                self.stats = resources.stats;
+            }
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                        self.custom_painting(ui);
+                    });
+                    ui.vertical(|ui| self.controls(ui));
+                });
+            });
+        });
+        // This is needed to animate the scene. It tells eframe to call update() again on the next event loop iteration.
+        ctx.request_repaint();
+    }
+}
+
+impl ABuffer {
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("a_buffer_controls")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("opacity");
+                ui.add(egui::Slider::new(&mut self.opacity, 0.05..=1.0));
+                ui.end_row();
+                ui.label("fragment budget")
+                    .on_hover_text("Translucent fragments per pixel, on average");
+                ui.add(egui::Slider::new(&mut self.budget, 0.05..=8.0).logarithmic(true));
+                ui.end_row();
+                ui.label("rotate");
+                ui.checkbox(&mut self.rotate, "");
+                ui.end_row();
+            });
+
+        if let Some(stats) = self.stats {
+            ui.label(format!(
+                "Fragments: {} of {}",
+                stats.count.min(stats.max_fragments),
+                stats.max_fragments
+            ));
+            if stats.count > stats.max_fragments {
+                ui.colored_label(
+                    egui::Color32::RED,
+                    format!(
+                        "Overflow: {} fragments dropped",
+                        stats.count - stats.max_fragments
+                    ),
+                );
+            } else {
+                ui.label("Overflow: none");
+            }
+        }
+        ui.label("Drag to orbit the camera.");
+    }
+
+    fn custom_painting(&mut self, ui: &mut egui::Ui) {
+        let (rect, response) =
+            ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::drag());
+
+        if self.rotate {
+            self.angle = self.start_time.elapsed().as_secs_f32() * 0.4;
+        }
+        let drag = response.drag_delta();
+        self.camera_yaw -= drag.x * 0.01;
+        self.camera_pitch = (self.camera_pitch + drag.y * 0.01).clamp(-1.5, 1.5);
+
+        let size = crate::offscreen::size_in_pixels(ui, rect);
+        ui.painter().add(paint_callback(
+            rect,
+            CustomPaintCallback {
+                size,
+                uniforms: self.uniforms(size),
+                budget: self.budget,
+            },
+        ));
+    }
+}
+
+/// A pass drawing over the color of the opaque pass. It has no depth attachment, as the passes
+/// using it read the opaque depth as a texture.
+fn begin_color_pass<'a>(
+    encoder: &'a mut wgpu::CommandEncoder,
+    view: &'a wgpu::TextureView,
+    label: &str,
+    profiler: &'a GpuProfiler,
+) -> wgpu::RenderPass<'a> {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Load,
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: profiler.render_pass_writes(label),
+        occlusion_query_set: None,
+    })
+}
+
+struct CustomPaintCallback {
+    size: (u32, u32),
+    uniforms: Uniforms,
+    budget: f32,
+}
+
+impl egui_wgpu::CallbackTrait for CustomPaintCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let profiler = GpuProfiler::get(callback_resources);
+        let stats = FrameStats::get(callback_resources);
+        let resources: &mut AppRenderResources = callback_resources.get_mut().unwrap();
+        if resources.target.resize(device, self.size) || resources.lists.budget != self.budget {
+            resources.lists = LinkedLists::new(
+                device,
+                &resources.lists_bind_group_layout,
+                resources.target.depth_view().unwrap(),
+                self.size,
+                self.budget,
+            );
+        }
+        if let Some(data) = resources.readback.poll(device) {
+            resources.stats = Some(FragmentStats {
+                count: bytemuck::pod_read_unaligned(&data),
+                max_fragments: resources.readback_max_fragments,
+            });
+        }
+
+        let uniforms = Uniforms {
+            max_fragments: resources.lists.max_fragments,
+            ..self.uniforms
+        };
+        stats.write_buffer(
+            queue,
+            &resources.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&uniforms),
+        );
+
+        let mut render_pass = stats.pass(resources.target.begin_pass(
+            egui_encoder,
+            wgpu::Color {
+                r: 0.1,
+                g: 0.1,
+                b: 0.12,
+                a: 1.0,
+            },
+            "ABuffer Opaque Pass",
+            &profiler,
+        ));
+        render_pass.set_pipeline(&resources.opaque_pipeline);
+        render_pass.set_bind_group(0, &resources.uniform_bind_group, &[]);
+        resources.cube.draw(&mut render_pass);
+        drop(render_pass);
+
+        egui_encoder.clear_buffer(&resources.lists.heads, 0, None);
+        egui_encoder.clear_buffer(&resources.lists.fragment_count, 0, None);
+
+        let mut render_pass = stats.pass(begin_color_pass(
+            egui_encoder,
+            resources.target.color_view(),
+            "ABuffer Translucent Pass",
+            &profiler,
+        ));
+        render_pass.set_pipeline(&resources.translucent_pipeline);
+        render_pass.set_bind_group(0, &resources.uniform_bind_group, &[]);
+        render_pass.set_bind_group(1, &resources.lists.bind_group, &[]);
+        resources.knot.draw(&mut render_pass);
+        drop(render_pass);
+
+        let mut render_pass = stats.pass(begin_color_pass(
+            egui_encoder,
+            resources.target.color_view(),
+            "ABuffer Resolve Pass",
+            &profiler,
+        ));
+        render_pass.set_pipeline(&resources.resolve_pipeline);
+        render_pass.set_bind_group(0, &resources.uniform_bind_group, &[]);
+        render_pass.set_bind_group(1, &resources.lists.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+        drop(render_pass);
+
+        if resources.readback.copy_from(
+            egui_encoder,
+            &resources.lists.fragment_count,
+            resources.readback.size(),
+        ) {
+            resources.readback_max_fragments = resources.lists.max_fragments;
+        }
+
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: eframe::egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
+        let resources: &AppRenderResources = callback_resources.get().unwrap();
+        resources.target.blit(&mut render_pass);
+    }
+}
+
+struct AppRenderResources {
+    pub target: OffscreenTarget,
+    pub opaque_pipeline: wgpu::RenderPipeline,
+    pub translucent_pipeline: wgpu::RenderPipeline,
+    pub resolve_pipeline: wgpu::RenderPipeline,
+    pub uniform_buffer: wgpu::Buffer,
+    pub uniform_bind_group: wgpu::BindGroup,
+    pub lists_bind_group_layout: wgpu::BindGroupLayout,
+    pub lists: LinkedLists,
+    pub knot: GpuMesh,
+    pub cube: GpuMesh,
+    pub readback: Readback,
+    /// The fragment budget when the readback in flight was recorded.
+    pub readback_max_fragments: u32,
+    pub stats: Option<FragmentStats>,
+}
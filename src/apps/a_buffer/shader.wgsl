@@ -0,0 +1,146 @@
+struct Uniforms {
+    view_proj: mat4x4f,
+    knot_model: mat4x4f,
+    cube_model: mat4x4f,
+    opacity: f32,
+    // Size of the fragment buffer, fragments past it are counted but dropped
+    max_fragments: u32,
+    // Width of the target in pixels, to index the heads
+    width: u32,
+}
+
+// One translucent fragment, linked to the one stored before it at the same pixel
+struct Fragment {
+    color: u32,
+    depth: f32,
+    // Index + 1 of the next fragment, 0 ends the list
+    next: u32,
+}
+
+struct VertexInput {
+    @location(0) position: vec3f,
+    @location(1) normal: vec3f,
+    @location(2) uv: vec2f,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) normal: vec3f,
+    @location(1) uv: vec2f,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+// The depth of the opaque pass, bound as plain values
+@group(1) @binding(0) var opaque_depth: texture_2d<f32>;
+// Index + 1 of the last fragment stored at every pixel, 0 when there is none
+@group(1) @binding(1) var<storage, read_write> heads: array<atomic<u32>>;
+@group(1) @binding(2) var<storage, read_write> fragments: array<Fragment>;
+// Fragments the translucent pass tried to store, which may exceed `max_fragments`
+@group(1) @binding(3) var<storage, read_write> fragment_count: atomic<u32>;
+
+const LIGHT_DIRECTION = vec3(0.4, 0.8, 0.45);
+// Fragments sorted per pixel, the farthest ones are dropped past this
+const MAX_LAYERS = 16u;
+
+fn transform(model: mat4x4f, vertex: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    output.position = uniforms.view_proj * model * vec4(vertex.position, 1.0);
+    // Only rotations are applied, so the model matrix maps normals fine
+    output.normal = (model * vec4(vertex.normal, 0.0)).xyz;
+    output.uv = vertex.uv;
+    return output;
+}
+
+fn lambert(normal: vec3f) -> f32 {
+    return 0.3 + 0.7 * abs(dot(normalize(normal), normalize(LIGHT_DIRECTION)));
+}
+
+@vertex
+fn vs_opaque(vertex: VertexInput) -> VertexOutput {
+    return transform(uniforms.cube_model, vertex);
+}
+
+@fragment
+fn fs_opaque(in: VertexOutput) -> @location(0) vec4f {
+    // Checkered, so it shows through the translucent mesh
+    let checker = select(0.4, 0.8, (u32(in.uv.x * 4.0) + u32(in.uv.y * 4.0)) % 2u == 0u);
+    return vec4(vec3(checker) * lambert(in.normal), 1.0);
+}
+
+@vertex
+fn vs_translucent(vertex: VertexInput) -> VertexOutput {
+    return transform(uniforms.knot_model, vertex);
+}
+
+@fragment
+fn fs_translucent(in: VertexOutput) -> @location(0) vec4f {
+    let pixel = vec2u(in.position.xy);
+    // Depth testing may only happen after the fragment shader ran and already stored the
+    // fragment, so the opaque depth is tested here instead
+    if (in.position.z >= textureLoad(opaque_depth, pixel, 0).r) {
+        discard;
+    }
+
+    // Hue along the knot
+    let hue = in.uv.x * 6.28318 * 3.0;
+    let base = 0.5 + 0.5 * cos(vec3(hue, hue + 2.09, hue + 4.19));
+    let color = vec4(base * lambert(in.normal), uniforms.opacity);
+
+    let index = atomicAdd(&fragment_count, 1u);
+    if (index < uniforms.max_fragments) {
+        let next = atomicExchange(&heads[pixel.y * uniforms.width + pixel.x], index + 1u);
+        fragments[index] = Fragment(pack4x8unorm(color), in.position.z, next);
+    }
+    // Nothing is written to the color target, its write mask is empty
+    return color;
+}
+
+// Full screen triangle
+@vertex
+fn vs_resolve(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4f {
+    let uv = vec2f(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// Sort the fragments of the pixel and blend them front to back, into a premultiplied color
+// that is blended over the opaque pass
+@fragment
+fn fs_resolve(@builtin(position) position: vec4f) -> @location(0) vec4f {
+    let pixel = vec2u(position.xy);
+    var colors: array<u32, MAX_LAYERS>;
+    var depths: array<f32, MAX_LAYERS>;
+
+    var count = 0u;
+    var index = atomicLoad(&heads[pixel.y * uniforms.width + pixel.x]);
+    while (index != 0u && count < MAX_LAYERS) {
+        let fragment = fragments[index - 1u];
+        colors[count] = fragment.color;
+        depths[count] = fragment.depth;
+        count++;
+        index = fragment.next;
+    }
+    if (count == 0u) {
+        discard;
+    }
+
+    // Insertion sort, nearest first
+    for (var i = 1u; i < count; i++) {
+        let color = colors[i];
+        let depth = depths[i];
+        var j = i;
+        while (j > 0u && depths[j - 1u] > depth) {
+            colors[j] = colors[j - 1u];
+            depths[j] = depths[j - 1u];
+            j--;
+        }
+        colors[j] = color;
+        depths[j] = depth;
+    }
+
+    var result = vec4(0.0);
+    for (var i = 0u; i < count; i++) {
+        let color = unpack4x8unorm(colors[i]);
+        result += (1.0 - result.a) * vec4(color.rgb * color.a, color.a);
+    }
+    return result;
+}
@@ -0,0 +1,253 @@
+//! Running the animometer without a window for a fixed number of frames, so that numbers can be
+//! compared across commits:
+//!
+//! ```text
+//! cargo run --release -- animometer --frames 600 --triangles 20000 --mode bind-groups --render-bundles
+//! ```
+//!
+//! Every run animates the same triangles through the same times, one sixtieth of a second
+//! apart, so only the time it takes differs.
+
+use std::time::{Duration, Instant};
+
+use super::scene::{BindingMode, Scene, SceneSettings};
+use crate::headless;
+use crate::stats::FrameStats;
+
+const USAGE: &str = "usage: animometer [--frames N] [--triangles N] \
+                     [--mode bind-groups|dynamic-offsets] [--render-bundles]";
+/// Size of the target rendered to, the same as the canvas of the sample.
+const TARGET_SIZE: u32 = 600;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BenchmarkOptions {
+    pub frames: u32,
+    pub settings: SceneSettings,
+}
+
+impl Default for BenchmarkOptions {
+    fn default() -> Self {
+        Self {
+            frames: 600,
+            settings: SceneSettings::default(),
+        }
+    }
+}
+
+impl BenchmarkOptions {
+    /// Parse the command line arguments following `animometer`.
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{arg} needs a value\n{USAGE}"));
+            let count = |value: &String| {
+                value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|&count| count > 0)
+                    .ok_or(format!("{arg} expects a positive number, found {value}"))
+            };
+            match arg.as_str() {
+                "--frames" => options.frames = count(value()?)?,
+                "--triangles" => options.settings.triangle_count = count(value()?)?,
+                "--mode" => {
+                    options.settings.binding_mode = match value()?.as_str() {
+                        "bind-groups" => BindingMode::BindGroups,
+                        "dynamic-offsets" => BindingMode::DynamicOffsets,
+                        mode => return Err(format!("unknown mode {mode}\n{USAGE}")),
+                    }
+                }
+                "--render-bundles" => options.settings.render_bundles = true,
+                _ => return Err(format!("unknown argument {arg}\n{USAGE}")),
+            }
+        }
+        Ok(options)
+    }
+}
+
+pub struct BenchmarkResult {
+    pub options: BenchmarkOptions,
+    pub adapter: String,
+    /// Mean time from the start of encoding a frame until the GPU finished it.
+    pub frame_time: Duration,
+    /// Mean time spent encoding the render pass of a frame on the CPU.
+    pub encode_time: Duration,
+}
+
+impl std::fmt::Display for BenchmarkResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let milliseconds = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        writeln!(
+            f,
+            "animometer: {}, {} frames on {}",
+            self.options.settings, self.options.frames, self.adapter
+        )?;
+        write!(
+            f,
+            "frame time {:.3} ms ({:.1} fps), cpu encode {:.3} ms",
+            milliseconds(self.frame_time),
+            1.0 / self.frame_time.as_secs_f64(),
+            milliseconds(self.encode_time)
+        )
+    }
+}
+
+/// Render `options.frames` frames of the animometer scene to an offscreen texture, waiting for
+/// each to finish before starting the next.
+pub fn run(options: BenchmarkOptions) -> Result<BenchmarkResult, String> {
+    let (adapter, device, queue) =
+        headless::request_adapter_and_device().ok_or("no wgpu adapter available")?;
+
+    let format = wgpu::TextureFormat::Rgba8Unorm;
+    let target = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Animometer Benchmark Target"),
+        size: wgpu::Extent3d {
+            width: TARGET_SIZE,
+            height: TARGET_SIZE,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+    let max_triangle_count = Scene::max_triangle_count(&device);
+    if options.settings.triangle_count > max_triangle_count {
+        return Err(format!(
+            "{} triangles do not fit in a buffer, at most {max_triangle_count} do on this device",
+            options.settings.triangle_count
+        ));
+    }
+    let scene = Scene::new(&device, format, options.settings);
+
+    let mut frame_time = Duration::ZERO;
+    let mut encode_time = Duration::ZERO;
+    // Nothing shows the statistics of a headless run
+    let stats = FrameStats::default();
+    for frame in 0..options.frames {
+        let frame_start = Instant::now();
+        scene.update(&queue, &stats, frame as f32 / 60.0);
+
+        let encode_start = Instant::now();
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Animometer Benchmark Encoder"),
+        });
+        let mut render_pass = stats.pass(encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Animometer Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        }));
+        scene.draw(&mut render_pass);
+        drop(render_pass);
+        let command_buffer = encoder.finish();
+        encode_time += encode_start.elapsed();
+
+        queue.submit(Some(command_buffer));
+        device.poll(wgpu::Maintain::Wait);
+        frame_time += frame_start.elapsed();
+    }
+
+    Ok(BenchmarkResult {
+        options,
+        adapter: adapter.get_info().name,
+        frame_time: frame_time / options.frames,
+        encode_time: encode_time / options.frames,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<BenchmarkOptions, String> {
+        BenchmarkOptions::parse(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn parses_options() {
+        assert_eq!(parse(&[]).unwrap(), BenchmarkOptions::default());
+
+        let options = parse(&[
+            "--frames",
+            "10",
+            "--triangles",
+            "500",
+            "--mode",
+            "bind-groups",
+            "--render-bundles",
+        ])
+        .unwrap();
+        assert_eq!(options.frames, 10);
+        assert_eq!(
+            options.settings,
+            SceneSettings {
+                triangle_count: 500,
+                binding_mode: BindingMode::BindGroups,
+                render_bundles: true,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_bad_options() {
+        assert!(parse(&["--frames"]).is_err());
+        assert!(parse(&["--frames", "0"]).is_err());
+        assert!(parse(&["--triangles", "many"]).is_err());
+        assert!(parse(&["--mode", "instancing"]).is_err());
+        assert!(parse(&["--fast"]).is_err());
+    }
+
+    #[test]
+    fn runs_every_mode() {
+        if headless::request_device().is_none() {
+            eprintln!("no wgpu adapter available, skipping");
+            return;
+        }
+        for binding_mode in BindingMode::ALL {
+            for render_bundles in [false, true] {
+                let options = BenchmarkOptions {
+                    frames: 3,
+                    settings: SceneSettings {
+                        triangle_count: 100,
+                        binding_mode,
+                        render_bundles,
+                    },
+                };
+                let result = run(options).unwrap();
+                assert!(result.encode_time <= result.frame_time);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_more_triangles_than_fit_in_a_buffer() {
+        if headless::request_device().is_none() {
+            eprintln!("no wgpu adapter available, skipping");
+            return;
+        }
+        let options = BenchmarkOptions {
+            frames: 1,
+            settings: SceneSettings {
+                triangle_count: u32::MAX,
+                ..Default::default()
+            },
+        };
+        let Err(err) = run(options) else {
+            panic!("{} triangles fit in a buffer", u32::MAX);
+        };
+        assert!(err.contains("do not fit in a buffer"), "{err}");
+    }
+}
@@ -0,0 +1,232 @@
+pub mod benchmark;
+mod scene;
+
+use eframe::{
+    egui,
+    egui_wgpu::{self, RenderState},
+};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::offscreen::{self, OffscreenTarget};
+use crate::profiler::GpuProfiler;
+use crate::stats::{paint_callback, FrameStats};
+use scene::{BindingMode, Scene, SceneSettings};
+
+const CANVAS: (f32, f32) = (600.0, 600.0);
+/// How many frames the reported timings are averaged over.
+const TIMING_WINDOW: usize = 60;
+
+/// The mean of the last [`TIMING_WINDOW`] durations.
+#[derive(Default)]
+struct RollingMean {
+    samples: VecDeque<Duration>,
+}
+
+impl RollingMean {
+    fn push(&mut self, sample: Duration) {
+        if self.samples.len() == TIMING_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    fn mean(&self) -> Option<Duration> {
+        let count = self.samples.len() as u32;
+        (count > 0).then(|| self.samples.iter().sum::<Duration>() / count)
+    }
+}
+
+pub struct Animometer {
+    start_time: Instant,
+    settings: SceneSettings,
+    last_frame: Instant,
+    frame_times: RollingMean,
+    encode_time: Option<Duration>,
+}
+
+impl Animometer {
+    pub fn new_with_render_state(wgpu_render_state: &RenderState) -> Option<Self> {
+        let device = &wgpu_render_state.device;
+
+        let settings = SceneSettings::default();
+        let scene = Scene::new(device, wgpu_render_state.target_format, settings);
+        let target = OffscreenTarget::new(
+            device,
+            wgpu_render_state.target_format,
+            None,
+            (CANVAS.0 as u32, CANVAS.1 as u32),
+        );
+
+        // Because the graphics pipeline must have the same lifetime as the egui render pass,
+        // instead of storing the pipeline in our struct, we insert it into the
+        // `paint_callback_resources` type map, which is stored alongside the render pass.
+        wgpu_render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(AppRenderResources {
+                target,
+                color_format: wgpu_render_state.target_format,
+                scene,
+                encode_times: RollingMean::default(),
+            });
+
+        Some(Self {
+            start_time: Instant::now(),
+            settings,
+            last_frame: Instant::now(),
+            frame_times: RollingMean::default(),
+            encode_time: None,
+        })
+    }
+}
+
+impl eframe::App for Animometer {
+    fn update(&mut self, ctx: &eframe::egui::Context, frame: &mut eframe::Frame) {
+        let now = Instant::now();
+        self.frame_times.push(now - self.last_frame);
+        self.last_frame = now;
+        if let Some(wgpu_render_state) = frame.wgpu_render_state() {
+            let renderer = wgpu_render_state.renderer.read();
+            if let Some(resources) = renderer.callback_resources.get::<AppRenderResources>() {
+                self.encode_time = resources.encode_times.mean();
+            }
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                        self.custom_painting(ui);
+                    });
+                    ui.vertical(|ui| self.controls(ui));
+                });
+            });
+        });
+        // This is needed to animate the triangles. It tells eframe to call update() again on the next event loop iteration.
+        ctx.request_repaint();
+    }
+}
+
+impl Animometer {
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        let milliseconds = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        egui::Grid::new("animometer_controls")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("triangles");
+                ui.add(
+                    egui::Slider::new(&mut self.settings.triangle_count, 100..=50000)
+                        .logarithmic(true),
+                );
+                ui.end_row();
+                ui.label("binding");
+                egui::ComboBox::from_id_source("animometer_binding_mode")
+                    .selected_text(self.settings.binding_mode.to_string())
+                    .show_ui(ui, |ui| {
+                        for mode in BindingMode::ALL {
+                            ui.selectable_value(
+                                &mut self.settings.binding_mode,
+                                mode,
+                                mode.to_string(),
+                            );
+                        }
+                    });
+                ui.end_row();
+                ui.label("render bundles");
+                ui.checkbox(&mut self.settings.render_bundles, "");
+                ui.end_row();
+                ui.label("fps");
+                if let Some(frame_time) = self.frame_times.mean() {
+                    ui.label(format!(
+                        "{:.1} ({:.2} ms)",
+                        1.0 / frame_time.as_secs_f64(),
+                        milliseconds(frame_time)
+                    ));
+                }
+                ui.end_row();
+                ui.label("cpu encode");
+                if let Some(encode_time) = self.encode_time {
+                    ui.label(format!("{:.3} ms", milliseconds(encode_time)));
+                }
+                ui.end_row();
+            });
+        ui.label(
+            "Frame rates here include egui; for comparable numbers run the benchmark headless:",
+        );
+        ui.code("cargo run --release -- animometer --frames 600");
+    }
+
+    fn custom_painting(&mut self, ui: &mut egui::Ui) {
+        let (rect, _response) =
+            ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::hover());
+
+        ui.painter().add(paint_callback(
+            rect,
+            CustomPaintCallback {
+                size: offscreen::size_in_pixels(ui, rect),
+                time: self.start_time.elapsed().as_secs_f32(),
+                settings: self.settings,
+            },
+        ));
+    }
+}
+
+struct CustomPaintCallback {
+    size: (u32, u32),
+    time: f32,
+    settings: SceneSettings,
+}
+
+impl egui_wgpu::CallbackTrait for CustomPaintCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let profiler = GpuProfiler::get(callback_resources);
+        let stats = FrameStats::get(callback_resources);
+        let resources: &mut AppRenderResources = callback_resources.get_mut().unwrap();
+        resources.target.resize(device, self.size);
+        if resources.scene.settings() != self.settings {
+            resources.scene = Scene::new(device, resources.color_format, self.settings);
+            resources.encode_times = RollingMean::default();
+        }
+        resources.scene.update(queue, &stats, self.time);
+
+        let encode_start = Instant::now();
+        let mut render_pass = stats.pass(resources.target.begin_pass(
+            egui_encoder,
+            wgpu::Color::BLACK,
+            "Animometer Render Pass",
+            &profiler,
+        ));
+        resources.scene.draw(&mut render_pass);
+        drop(render_pass);
+        resources.encode_times.push(encode_start.elapsed());
+
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: eframe::egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
+        let resources: &AppRenderResources = callback_resources.get().unwrap();
+        resources.target.blit(&mut render_pass);
+    }
+}
+
+struct AppRenderResources {
+    pub target: OffscreenTarget,
+    pub color_format: wgpu::TextureFormat,
+    pub scene: Scene,
+    pub encode_times: RollingMean,
+}
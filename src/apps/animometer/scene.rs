@@ -0,0 +1,316 @@
+//! The animometer scene: thousands of spinning triangles, each with its own uniforms, drawn
+//! with one draw call apiece so that the cost of encoding them dominates.
+
+use wgpu::util::{DeviceExt, RenderEncoder};
+
+use crate::random::Random;
+use crate::stats::{CountedPass, FrameStats};
+
+/// The seed the triangles are scattered with, the same every run so that runs compare.
+const SEED: u32 = 0x5eed;
+
+/// Position and color of each corner of the triangle.
+#[rustfmt::skip]
+const VERTICES: [f32; 24] = [
+    0.0, 0.1, 0.0, 1.0,    1.0, 0.0, 0.0, 1.0,
+    -0.1, -0.1, 0.0, 1.0,  0.0, 1.0, 0.0, 1.0,
+    0.1, -0.1, 0.0, 1.0,   0.0, 0.0, 1.0, 1.0,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BindingMode {
+    /// A bind group per triangle, pointing at its slice of the uniform buffer.
+    BindGroups,
+    /// One bind group for all triangles, moved along the uniform buffer with dynamic offsets.
+    DynamicOffsets,
+}
+
+impl BindingMode {
+    pub const ALL: [Self; 2] = [Self::BindGroups, Self::DynamicOffsets];
+}
+
+impl std::fmt::Display for BindingMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::BindGroups => "Bind groups",
+            Self::DynamicOffsets => "Dynamic offsets",
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SceneSettings {
+    pub triangle_count: u32,
+    pub binding_mode: BindingMode,
+    /// Record the draws once into a render bundle and replay it every frame.
+    pub render_bundles: bool,
+}
+
+impl Default for SceneSettings {
+    fn default() -> Self {
+        Self {
+            triangle_count: 10000,
+            binding_mode: BindingMode::DynamicOffsets,
+            render_bundles: false,
+        }
+    }
+}
+
+impl std::fmt::Display for SceneSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} triangles, {}",
+            self.triangle_count,
+            self.binding_mode.to_string().to_lowercase()
+        )?;
+        if self.render_bundles {
+            f.write_str(", render bundles")?;
+        }
+        Ok(())
+    }
+}
+
+/// Laid out to match `Object` in `shader.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Object {
+    scale: f32,
+    offset_x: f32,
+    offset_y: f32,
+    scalar: f32,
+    scalar_offset: f32,
+    _padding: [f32; 3],
+}
+
+enum ObjectBindings {
+    BindGroups(Vec<wgpu::BindGroup>),
+    DynamicOffsets(wgpu::BindGroup),
+}
+
+pub struct Scene {
+    settings: SceneSettings,
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    time_buffer: wgpu::Buffer,
+    time_bind_group: wgpu::BindGroup,
+    /// Distance between the uniforms of consecutive triangles, a multiple of the uniform
+    /// offset alignment.
+    object_stride: u32,
+    object_bindings: ObjectBindings,
+    bundle: Option<wgpu::RenderBundle>,
+}
+
+impl Scene {
+    /// The most triangles whose uniforms fit in one buffer on `device`.
+    pub fn max_triangle_count(device: &wgpu::Device) -> u32 {
+        let limits = device.limits();
+        let count = limits.max_buffer_size / u64::from(Self::object_stride(&limits));
+        count.try_into().unwrap_or(u32::MAX)
+    }
+
+    fn object_stride(limits: &wgpu::Limits) -> u32 {
+        let object_size = std::mem::size_of::<Object>() as u32;
+        object_size.next_multiple_of(limits.min_uniform_buffer_offset_alignment)
+    }
+
+    pub fn new(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        settings: SceneSettings,
+    ) -> Self {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Animometer Vertex Buffer"),
+            contents: bytemuck::cast_slice(&VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let time_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Animometer Time Buffer"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let object_size = std::mem::size_of::<Object>() as u32;
+        let object_stride = Self::object_stride(&device.limits());
+        let mut random = Random::new(SEED);
+        let objects_size = u64::from(object_stride) * u64::from(settings.triangle_count);
+        let mut objects = vec![0u8; objects_size as usize];
+        for chunk in objects.chunks_exact_mut(object_stride as usize) {
+            let object = Object {
+                scale: random.next_f32() * 0.2 + 0.2,
+                offset_x: 0.9 * 2.0 * (random.next_f32() - 0.5),
+                offset_y: 0.9 * 2.0 * (random.next_f32() - 0.5),
+                scalar: random.next_f32() * 1.5 + 0.5,
+                scalar_offset: random.next_f32() * 10.0,
+                _padding: [0.0; 3],
+            };
+            chunk[..object_size as usize].copy_from_slice(bytemuck::bytes_of(&object));
+        }
+        let object_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Animometer Object Buffer"),
+            contents: &objects,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let uniform_entry = |has_dynamic_offset| wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let time_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Animometer Time Bind Group Layout"),
+                entries: &[uniform_entry(false)],
+            });
+        let object_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Animometer Object Bind Group Layout"),
+                entries: &[uniform_entry(
+                    settings.binding_mode == BindingMode::DynamicOffsets,
+                )],
+            });
+
+        let time_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Animometer Time Bind Group"),
+            layout: &time_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: time_buffer.as_entire_binding(),
+            }],
+        });
+        let object_bind_group = |offset| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Animometer Object Bind Group"),
+                layout: &object_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &object_buffer,
+                        offset,
+                        size: wgpu::BufferSize::new(object_size as u64),
+                    }),
+                }],
+            })
+        };
+        let object_bindings = match settings.binding_mode {
+            BindingMode::BindGroups => ObjectBindings::BindGroups(
+                (0..settings.triangle_count)
+                    .map(|i| object_bind_group((i * object_stride) as u64))
+                    .collect(),
+            ),
+            BindingMode::DynamicOffsets => ObjectBindings::DynamicOffsets(object_bind_group(0)),
+        };
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Animometer Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./shader.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Animometer Pipeline Layout"),
+            bind_group_layouts: &[&time_bind_group_layout, &object_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Animometer Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: 8 * 4,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(color_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let mut scene = Self {
+            settings,
+            pipeline,
+            vertex_buffer,
+            time_buffer,
+            time_bind_group,
+            object_stride,
+            object_bindings,
+            bundle: None,
+        };
+        if settings.render_bundles {
+            let mut encoder =
+                device.create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
+                    label: Some("Animometer Render Bundle Encoder"),
+                    color_formats: &[Some(color_format)],
+                    depth_stencil: None,
+                    sample_count: 1,
+                    multiview: None,
+                });
+            scene.record(&mut encoder);
+            scene.bundle = Some(encoder.finish(&wgpu::RenderBundleDescriptor {
+                label: Some("Animometer Render Bundle"),
+            }));
+        }
+        scene
+    }
+
+    pub fn settings(&self) -> SceneSettings {
+        self.settings
+    }
+
+    /// Set the time, in seconds, the triangles are animated to.
+    pub fn update(&self, queue: &wgpu::Queue, stats: &FrameStats, time: f32) {
+        stats.write_buffer(
+            queue,
+            &self.time_buffer,
+            0,
+            bytemuck::bytes_of(&[time, 0.0, 0.0, 0.0]),
+        );
+    }
+
+    /// Draw every triangle, by replaying the bundle if there is one.
+    pub fn draw<'a>(&'a self, render_pass: &mut CountedPass<'_, 'a>) {
+        match &self.bundle {
+            Some(bundle) => {
+                let triangles = self.settings.triangle_count as u64;
+                render_pass.execute_bundle(bundle, triangles, 3 * triangles);
+            }
+            None => self.record(render_pass),
+        }
+    }
+
+    fn record<'a>(&'a self, encoder: &mut impl RenderEncoder<'a>) {
+        encoder.set_pipeline(&self.pipeline);
+        encoder.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        encoder.set_bind_group(0, &self.time_bind_group, &[]);
+        match &self.object_bindings {
+            ObjectBindings::BindGroups(bind_groups) => {
+                for bind_group in bind_groups {
+                    encoder.set_bind_group(1, bind_group, &[]);
+                    encoder.draw(0..3, 0..1);
+                }
+            }
+            ObjectBindings::DynamicOffsets(bind_group) => {
+                for i in 0..self.settings.triangle_count {
+                    encoder.set_bind_group(1, bind_group, &[i * self.object_stride]);
+                    encoder.draw(0..3, 0..1);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,47 @@
+struct Time {
+    value: f32,
+}
+
+struct Object {
+    scale: f32,
+    offset_x: f32,
+    offset_y: f32,
+    scalar: f32,
+    scalar_offset: f32,
+}
+
+@group(0) @binding(0) var<uniform> time: Time;
+@group(1) @binding(0) var<uniform> object: Object;
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) color: vec4f,
+}
+
+@vertex
+fn vs_main(@location(0) position: vec4f, @location(1) color: vec4f) -> VertexOutput {
+    // Goes from 0 to 1 and back while the triangle makes a full turn
+    var fade = (object.scalar_offset + time.value * object.scalar / 10.0) % 1.0;
+    if fade < 0.5 {
+        fade = fade * 2.0;
+    } else {
+        fade = (1.0 - fade) * 2.0;
+    }
+
+    let angle = 3.14159 * 2.0 * fade;
+    let scaled = position.xy * object.scale;
+    let rotated = vec2f(
+        scaled.x * cos(angle) - scaled.y * sin(angle),
+        scaled.x * sin(angle) + scaled.y * cos(angle),
+    );
+
+    var out: VertexOutput;
+    out.position = vec4f(rotated + vec2f(object.offset_x, object.offset_y), 0.0, 1.0);
+    out.color = vec4f(fade, 1.0 - fade, 0.0, 1.0) + color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return in.color;
+}
@@ -0,0 +1,88 @@
+// `WORKGROUP_SIZE` and `LOCAL_DATA_SIZE` (twice the former) are prepended by the host, since the
+// workgroup size can be changed at runtime.
+// Every invocation compares and swaps one pair, so a workgroup covers `2 * WORKGROUP_SIZE` elements.
+
+const ALGO_LOCAL_FLIP = 1u;
+const ALGO_LOCAL_DISPERSE = 2u;
+const ALGO_GLOBAL_FLIP = 3u;
+const ALGO_GLOBAL_DISPERSE = 4u;
+
+struct Uniforms {
+    algo: u32,
+    blockHeight: u32,
+}
+
+@group(0) @binding(0) var<storage, read_write> data: array<u32>;
+@group(0) @binding(1) var<uniform> uniforms: Uniforms;
+
+var<workgroup> local_data: array<u32, LOCAL_DATA_SIZE>;
+
+// Compare mirrored elements of a block, e.g. 0 with 7, 1 with 6... for a block height of 8
+fn get_flip_indices(thread_id: u32, block_height: u32) -> vec2u {
+    let block_offset = ((2u * thread_id) / block_height) * block_height;
+    let half_height = block_height / 2u;
+    let t = thread_id % half_height;
+    return vec2(block_offset + t, block_offset + block_height - t - 1u);
+}
+
+// Compare elements half a block apart, e.g. 0 with 4, 1 with 5... for a block height of 8
+fn get_disperse_indices(thread_id: u32, block_height: u32) -> vec2u {
+    let block_offset = ((2u * thread_id) / block_height) * block_height;
+    let half_height = block_height / 2u;
+    let t = thread_id % half_height;
+    return vec2(block_offset + t, block_offset + t + half_height);
+}
+
+fn local_compare_and_swap(idx: vec2u) {
+    if (local_data[idx.y] < local_data[idx.x]) {
+        let temp = local_data[idx.x];
+        local_data[idx.x] = local_data[idx.y];
+        local_data[idx.y] = temp;
+    }
+}
+
+fn global_compare_and_swap(idx: vec2u) {
+    if (data[idx.y] < data[idx.x]) {
+        let temp = data[idx.x];
+        data[idx.x] = data[idx.y];
+        data[idx.y] = temp;
+    }
+}
+
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3u,
+    @builtin(local_invocation_id) local_id: vec3u,
+    @builtin(workgroup_id) workgroup_id: vec3u,
+) {
+    let algo = uniforms.algo;
+
+    if (algo == ALGO_GLOBAL_FLIP) {
+        global_compare_and_swap(get_flip_indices(global_id.x, uniforms.blockHeight));
+        return;
+    }
+    if (algo == ALGO_GLOBAL_DISPERSE) {
+        global_compare_and_swap(get_disperse_indices(global_id.x, uniforms.blockHeight));
+        return;
+    }
+
+    // The local variants finish every remaining step of the block in workgroup memory
+    let offset = WORKGROUP_SIZE * 2u * workgroup_id.x;
+    local_data[local_id.x * 2u] = data[offset + local_id.x * 2u];
+    local_data[local_id.x * 2u + 1u] = data[offset + local_id.x * 2u + 1u];
+    workgroupBarrier();
+
+    var block_height = uniforms.blockHeight;
+    if (algo == ALGO_LOCAL_FLIP) {
+        local_compare_and_swap(get_flip_indices(local_id.x, block_height));
+        block_height /= 2u;
+        workgroupBarrier();
+    }
+    for (; block_height > 1u; block_height /= 2u) {
+        local_compare_and_swap(get_disperse_indices(local_id.x, block_height));
+        workgroupBarrier();
+    }
+
+    data[offset + local_id.x * 2u] = local_data[local_id.x * 2u];
+    data[offset + local_id.x * 2u + 1u] = local_data[local_id.x * 2u + 1u];
+}
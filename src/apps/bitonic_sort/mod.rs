@@ -0,0 +1,959 @@
+use eframe::{
+    egui,
+    egui_wgpu::{self, RenderState},
+};
+use wgpu::util::DeviceExt;
+
+use crate::profiler::GpuProfiler;
+use crate::stats::{paint_callback, FrameStats};
+use crate::{random::Random, readback::Readback};
+
+const CANVAS: (f32, f32) = (600.0, 600.0);
+const WORKGROUP_SIZES: [u32; 5] = [16, 32, 64, 128, 256];
+const DEFAULT_ELEMENT_COUNT: u32 = 1 << 14;
+/// Largest number of passes that get their own timestamps in a single frame.
+const MAX_TIMED_PASSES: u32 = 256;
+
+/// What a dispatch of `compute.wgsl` does, matching the `ALGO_*` constants.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Algo {
+    /// A flip followed by all the disperses of the block, in workgroup memory.
+    LocalFlip = 1,
+    /// All the disperses of the block, in workgroup memory.
+    LocalDisperse = 2,
+    /// A single flip in global memory.
+    GlobalFlip = 3,
+    /// A single disperse in global memory.
+    GlobalDisperse = 4,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SortPass {
+    pub algo: Algo,
+    pub block_height: u32,
+}
+
+impl SortPass {
+    /// The element `index` is compared with by the first step of this pass.
+    pub fn partner(&self, index: u32) -> u32 {
+        let block_offset = index / self.block_height * self.block_height;
+        let t = index % self.block_height;
+        let half_height = self.block_height / 2;
+        match self.algo {
+            Algo::LocalFlip | Algo::GlobalFlip => block_offset + self.block_height - t - 1,
+            Algo::LocalDisperse | Algo::GlobalDisperse => {
+                block_offset + (t + half_height) % self.block_height
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for SortPass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let algo = match self.algo {
+            Algo::LocalFlip => "local flip",
+            Algo::LocalDisperse => "local disperse",
+            Algo::GlobalFlip => "global flip",
+            Algo::GlobalDisperse => "global disperse",
+        };
+        write!(f, "{algo} {}", self.block_height)
+    }
+}
+
+/// The passes needed to sort `count` elements, `count` being a power of two. Blocks that fit
+/// in a workgroup (`2 * workgroup_size` elements) are finished in a single local pass.
+pub fn plan(count: u32, workgroup_size: u32) -> Vec<SortPass> {
+    let local_height = 2 * workgroup_size;
+    let mut passes = Vec::new();
+
+    let mut block_height = 2;
+    while block_height <= count {
+        if block_height <= local_height {
+            passes.push(SortPass {
+                algo: Algo::LocalFlip,
+                block_height,
+            });
+        } else {
+            passes.push(SortPass {
+                algo: Algo::GlobalFlip,
+                block_height,
+            });
+            let mut disperse_height = block_height / 2;
+            while disperse_height > local_height {
+                passes.push(SortPass {
+                    algo: Algo::GlobalDisperse,
+                    block_height: disperse_height,
+                });
+                disperse_height /= 2;
+            }
+            passes.push(SortPass {
+                algo: Algo::LocalDisperse,
+                block_height: disperse_height,
+            });
+        }
+        block_height *= 2;
+    }
+
+    passes
+}
+
+/// The largest of [`WORKGROUP_SIZES`] that `count` elements fill, since a workgroup sorts
+/// `2 * workgroup_size` elements.
+fn largest_workgroup_size(count: u32) -> u32 {
+    WORKGROUP_SIZES
+        .into_iter()
+        .rev()
+        .find(|&size| 2 * size <= count)
+        .unwrap_or(WORKGROUP_SIZES[0])
+}
+
+/// A random permutation of `0..count`, so every color of the visualisation shows up once.
+pub fn shuffled_values(count: u32, seed: u32) -> Vec<u32> {
+    let mut random = Random::new(seed);
+    let mut values: Vec<u32> = (0..count).collect();
+    for i in (1..values.len()).rev() {
+        values.swap(i, random.next_u32() as usize % (i + 1));
+    }
+    values
+}
+
+/// What the UI shows about the sort.
+#[derive(Clone, Default)]
+pub struct SortStatus {
+    pub next_pass: usize,
+    pub total_passes: usize,
+    /// The result of reading back the data once the last pass ran, `None` while pending.
+    pub sorted: Option<bool>,
+    /// GPU time of every pass of the last timed batch, in milliseconds.
+    pub pass_times: Vec<(SortPass, f64)>,
+    pub timestamps_supported: bool,
+}
+
+struct Timestamps {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback: Readback,
+    /// The passes timed by the readback in flight.
+    timed_passes: Vec<SortPass>,
+    period: f32,
+}
+
+pub struct BitonicSorter {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    data_buffer: wgpu::Buffer,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    uniform_stride: u32,
+    count: u32,
+    workgroup_size: u32,
+    plan: Vec<SortPass>,
+    next_pass: usize,
+    /// Bumped by [`Self::reset`], so that readbacks of older data are ignored.
+    generation: u64,
+    verify: Readback,
+    /// The generation of the data copied by the readback in flight.
+    verify_generation: Option<u64>,
+    /// The last pass ran but the readback was busy, so the copy is retried on the next frame.
+    verify_requested: bool,
+    sorted: Option<bool>,
+    pass_times: Vec<(SortPass, f64)>,
+    timestamps: Option<Timestamps>,
+}
+
+impl BitonicSorter {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        values: &[u32],
+        workgroup_size: u32,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("BitonicSort Compute Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(8),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let timestamps = device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+            .then(|| Timestamps {
+                query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                    label: Some("BitonicSort Timestamp Query Set"),
+                    ty: wgpu::QueryType::Timestamp,
+                    count: 2 * MAX_TIMED_PASSES,
+                }),
+                resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("BitonicSort Timestamp Resolve Buffer"),
+                    size: 2 * MAX_TIMED_PASSES as u64 * std::mem::size_of::<u64>() as u64,
+                    usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                    mapped_at_creation: false,
+                }),
+                readback: Readback::new(
+                    device,
+                    2 * MAX_TIMED_PASSES as u64 * std::mem::size_of::<u64>() as u64,
+                    "BitonicSort Timestamp Readback Buffer",
+                ),
+                timed_passes: Vec::new(),
+                period: queue.get_timestamp_period(),
+            });
+
+        let (data_buffer, uniform_buffer, bind_group, uniform_stride, plan) =
+            Self::create_buffers(device, &bind_group_layout, values, workgroup_size);
+
+        Self {
+            pipeline: Self::create_pipeline(device, &bind_group_layout, workgroup_size),
+            bind_group_layout,
+            data_buffer,
+            uniform_buffer,
+            bind_group,
+            uniform_stride,
+            count: values.len() as u32,
+            workgroup_size,
+            plan,
+            next_pass: 0,
+            generation: 0,
+            verify: Readback::new(
+                device,
+                std::mem::size_of_val(values) as u64,
+                "BitonicSort Verify Readback Buffer",
+            ),
+            verify_generation: None,
+            verify_requested: false,
+            sorted: None,
+            pass_times: Vec::new(),
+            timestamps,
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        workgroup_size: u32,
+    ) -> wgpu::ComputePipeline {
+        // wgpu has no pipeline-overridable constants yet, so the workgroup size is spliced in
+        let source = format!(
+            "const WORKGROUP_SIZE: u32 = {workgroup_size}u;\nconst LOCAL_DATA_SIZE: u32 = {}u;\n{}",
+            2 * workgroup_size,
+            include_str!("./compute.wgsl")
+        );
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("BitonicSort Compute Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("BitonicSort Compute Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("BitonicSort Compute Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "main",
+        })
+    }
+
+    /// Create the data buffer and one uniform slot per pass of the plan, each pass selecting
+    /// its slot with a dynamic offset.
+    fn create_buffers(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        values: &[u32],
+        workgroup_size: u32,
+    ) -> (
+        wgpu::Buffer,
+        wgpu::Buffer,
+        wgpu::BindGroup,
+        u32,
+        Vec<SortPass>,
+    ) {
+        let plan = plan(values.len() as u32, workgroup_size);
+        let uniform_stride = device.limits().min_uniform_buffer_offset_alignment;
+
+        let data_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("BitonicSort Data Buffer"),
+            contents: bytemuck::cast_slice(values),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let mut uniforms = vec![0u8; plan.len() * uniform_stride as usize];
+        for (pass, slot) in plan
+            .iter()
+            .zip(uniforms.chunks_exact_mut(uniform_stride as usize))
+        {
+            slot[..8].copy_from_slice(bytemuck::cast_slice(&[pass.algo as u32, pass.block_height]));
+        }
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("BitonicSort Uniform Buffer"),
+            contents: &uniforms,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("BitonicSort Compute Bind Group"),
+            layout: bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: data_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &uniform_buffer,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(8),
+                    }),
+                },
+            ],
+        });
+
+        (
+            data_buffer,
+            uniform_buffer,
+            bind_group,
+            uniform_stride,
+            plan,
+        )
+    }
+
+    /// Start over with new values.
+    pub fn reset(&mut self, device: &wgpu::Device, values: &[u32], workgroup_size: u32) {
+        if workgroup_size != self.workgroup_size {
+            self.pipeline = Self::create_pipeline(device, &self.bind_group_layout, workgroup_size);
+            self.workgroup_size = workgroup_size;
+        }
+        let (data_buffer, uniform_buffer, bind_group, uniform_stride, plan) =
+            Self::create_buffers(device, &self.bind_group_layout, values, workgroup_size);
+        self.data_buffer = data_buffer;
+        self.uniform_buffer = uniform_buffer;
+        self.bind_group = bind_group;
+        self.uniform_stride = uniform_stride;
+        self.plan = plan;
+        self.next_pass = 0;
+        self.generation += 1;
+        self.verify_requested = false;
+        self.sorted = None;
+        if self.count != values.len() as u32 {
+            self.count = values.len() as u32;
+            self.verify = Readback::new(
+                device,
+                std::mem::size_of_val(values) as u64,
+                "BitonicSort Verify Readback Buffer",
+            );
+        }
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn data_buffer(&self) -> &wgpu::Buffer {
+        &self.data_buffer
+    }
+
+    pub fn next_pass(&self) -> Option<&SortPass> {
+        self.plan.get(self.next_pass)
+    }
+
+    pub fn status(&self) -> SortStatus {
+        SortStatus {
+            next_pass: self.next_pass,
+            total_passes: self.plan.len(),
+            sorted: self.sorted,
+            pass_times: self.pass_times.clone(),
+            timestamps_supported: self.timestamps.is_some(),
+        }
+    }
+
+    /// Record up to `max_passes` of the remaining passes into `encoder`, each in its own
+    /// compute pass so it can be timed and shown on its own, then the copy of the data to
+    /// verify once the last pass ran. Passes the profiler times are left out of
+    /// [`SortStatus::pass_times`].
+    pub fn encode(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        profiler: &GpuProfiler,
+        max_passes: usize,
+    ) {
+        self.encode_passes(encoder, profiler, max_passes);
+
+        if self.verify_requested
+            && self
+                .verify
+                .copy_from(encoder, &self.data_buffer, self.verify.size())
+        {
+            self.verify_requested = false;
+            self.verify_generation = Some(self.generation);
+        }
+    }
+
+    fn encode_passes(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        profiler: &GpuProfiler,
+        max_passes: usize,
+    ) {
+        let end = self
+            .plan
+            .len()
+            .min(self.next_pass.saturating_add(max_passes));
+        let passes = self.next_pass..end;
+        if passes.is_empty() {
+            return;
+        }
+
+        let timestamps = self
+            .timestamps
+            .as_mut()
+            .filter(|timestamps| timestamps.readback.is_idle());
+        let mut timed_passes = Vec::new();
+
+        for index in passes {
+            let mut timestamp_writes = profiler.compute_pass_writes("BitonicSort Compute Pass");
+            if let Some(timestamps) = timestamps.as_ref() {
+                let timed = timed_passes.len() as u32;
+                if timestamp_writes.is_none() && timed < MAX_TIMED_PASSES {
+                    timed_passes.push(self.plan[index]);
+                    timestamp_writes = Some(wgpu::ComputePassTimestampWrites {
+                        query_set: &timestamps.query_set,
+                        beginning_of_pass_write_index: Some(2 * timed),
+                        end_of_pass_write_index: Some(2 * timed + 1),
+                    });
+                }
+            }
+
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("BitonicSort Compute Pass"),
+                timestamp_writes,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[index as u32 * self.uniform_stride]);
+            pass.dispatch_workgroups(self.count / 2 / self.workgroup_size, 1, 1);
+        }
+
+        if let Some(timestamps) = timestamps.filter(|_| !timed_passes.is_empty()) {
+            let size = timed_passes.len() as u64 * 2 * std::mem::size_of::<u64>() as u64;
+            encoder.resolve_query_set(
+                &timestamps.query_set,
+                0..2 * timed_passes.len() as u32,
+                &timestamps.resolve_buffer,
+                0,
+            );
+            timestamps
+                .readback
+                .copy_from(encoder, &timestamps.resolve_buffer, size);
+            timestamps.timed_passes = timed_passes;
+        }
+
+        self.next_pass = end;
+        if self.next_pass == self.plan.len() {
+            self.sorted = None;
+            self.verify_requested = true;
+        }
+    }
+
+    /// Pick up finished readbacks; call once per frame before [`Self::encode`].
+    pub fn poll(&mut self, device: &wgpu::Device) {
+        if let Some(data) = self.verify.poll(device) {
+            if self.verify_generation.take() == Some(self.generation) {
+                let values: &[u32] = bytemuck::cast_slice(&data);
+                self.sorted = Some(values.windows(2).all(|pair| pair[0] <= pair[1]));
+            }
+        }
+
+        if let Some(timestamps) = self.timestamps.as_mut() {
+            if let Some(data) = timestamps.readback.poll(device) {
+                let ticks: &[u64] = bytemuck::cast_slice(&data);
+                self.pass_times = timestamps
+                    .timed_passes
+                    .iter()
+                    .zip(ticks.chunks_exact(2))
+                    .map(|(pass, ticks)| {
+                        let nanoseconds =
+                            ticks[1].saturating_sub(ticks[0]) as f64 * timestamps.period as f64;
+                        (*pass, nanoseconds / 1_000_000.0)
+                    })
+                    .collect();
+            }
+        }
+    }
+}
+
+pub struct BitonicSort {
+    element_count: u32,
+    workgroup_size: u32,
+    seed: u32,
+    auto_step: bool,
+    /// Passes to run on the next frame.
+    requested_passes: usize,
+    /// New values to sort on the next frame.
+    pending_values: Option<Vec<u32>>,
+    hovered: Option<u32>,
+    status: SortStatus,
+}
+
+impl BitonicSort {
+    pub fn new_with_render_state(wgpu_render_state: &RenderState) -> Option<Self> {
+        let device = &wgpu_render_state.device;
+        let queue = &wgpu_render_state.queue;
+        let workgroup_size = 64;
+
+        let sorter = BitonicSorter::new(
+            device,
+            queue,
+            &shuffled_values(DEFAULT_ELEMENT_COUNT, 1),
+            workgroup_size,
+        );
+
+        // Create the grid uniform buffer and the bind group reading the sorted data
+        let (grid_buffer, grid_bind_group_layout) = {
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("BitonicSort Grid Buffer"),
+                size: 4 * std::mem::size_of::<u32>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+            let bind_group_layout =
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("BitonicSort Grid Bind Group Layout"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                    ],
+                });
+
+            (buffer, bind_group_layout)
+        };
+
+        let grid_bind_group =
+            create_grid_bind_group(device, &grid_bind_group_layout, &sorter, &grid_buffer);
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("BitonicSort Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./shader.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("BitonicSort Pipeline Layout"),
+            bind_group_layouts: &[&grid_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("BitonicSort Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu_render_state.target_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let status = sorter.status();
+
+        // Because the graphics pipeline must have the same lifetime as the egui render pass,
+        // instead of storing the pipeline in our struct, we insert it into the
+        // `paint_callback_resources` type map, which is stored alongside the render pass.
+        wgpu_render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(AppRenderResources {
+                sorter,
+                pipeline,
+                grid_buffer,
+                grid_bind_group_layout,
+                grid_bind_group,
+            });
+
+        Some(Self {
+            element_count: DEFAULT_ELEMENT_COUNT,
+            workgroup_size,
+            seed: 1,
+            auto_step: false,
+            requested_passes: 0,
+            pending_values: None,
+            hovered: None,
+            status,
+        })
+    }
+}
+
+fn create_grid_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    sorter: &BitonicSorter,
+    grid_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("BitonicSort Grid Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: sorter.data_buffer().as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: grid_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+/// Lay `count` elements out in a grid that is as square as possible.
+fn grid_dimensions(count: u32) -> (u32, u32) {
+    let width = 1 << count.trailing_zeros().div_ceil(2);
+    (width, count / width)
+}
+
+impl eframe::App for BitonicSort {
+    fn update(&mut self, ctx: &eframe::egui::Context, frame: &mut eframe::Frame) {
+        if let Some(wgpu_render_state) = frame.wgpu_render_state() {
+            let renderer = wgpu_render_state.renderer.read();
+            if let Some(resources) = renderer.callback_resources.get::<AppRenderResources>() {
+                self.status = resources.sorter.status();
+            }
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                        self.custom_painting(ui);
+                    });
+                    ui.vertical(|ui| self.controls(ui));
+                });
+            });
+        });
+        // Keep polling the readbacks and stepping in auto mode.
+        ctx.request_repaint();
+    }
+}
+
+impl BitonicSort {
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("bitonic_sort_controls")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("elements");
+                egui::ComboBox::from_id_source("bitonic_sort_element_count")
+                    .selected_text(self.element_count.to_string())
+                    .show_ui(ui, |ui| {
+                        // From the elements of the smallest workgroup to a million
+                        for count in (5..=20).map(|shift| 1u32 << shift) {
+                            if ui
+                                .selectable_value(&mut self.element_count, count, count.to_string())
+                                .changed()
+                            {
+                                self.workgroup_size =
+                                    largest_workgroup_size(count).min(self.workgroup_size);
+                                self.pending_values = Some(shuffled_values(count, self.seed));
+                            }
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("workgroup size");
+                egui::ComboBox::from_id_source("bitonic_sort_workgroup_size")
+                    .selected_text(self.workgroup_size.to_string())
+                    .show_ui(ui, |ui| {
+                        for size in WORKGROUP_SIZES {
+                            if 2 * size > self.element_count {
+                                continue;
+                            }
+                            if ui
+                                .selectable_value(&mut self.workgroup_size, size, size.to_string())
+                                .changed()
+                            {
+                                self.pending_values =
+                                    Some(shuffled_values(self.element_count, self.seed));
+                            }
+                        }
+                    });
+                ui.end_row();
+            });
+
+        ui.horizontal(|ui| {
+            let done = self.status.next_pass >= self.status.total_passes;
+            if ui.add_enabled(!done, egui::Button::new("Step")).clicked() {
+                self.requested_passes = 1;
+            }
+            if ui.add_enabled(!done, egui::Button::new("Sort")).clicked() {
+                self.requested_passes = usize::MAX;
+            }
+            ui.checkbox(&mut self.auto_step, "Auto step");
+            if ui.button("Randomize").clicked() {
+                self.seed = self.seed.wrapping_add(1);
+                self.pending_values = Some(shuffled_values(self.element_count, self.seed));
+            }
+        });
+
+        ui.separator();
+        ui.label(format!(
+            "Pass {} of {}",
+            self.status.next_pass, self.status.total_passes
+        ));
+        let plan = plan(self.element_count, self.workgroup_size);
+        if let Some(pass) = plan.get(self.status.next_pass) {
+            ui.label(format!("Next pass: {pass}"));
+        }
+        if let Some(index) = self.hovered {
+            match plan.get(self.status.next_pass) {
+                Some(pass) => ui.label(format!(
+                    "Element {index} is compared with element {}",
+                    pass.partner(index)
+                )),
+                None => ui.label(format!("Element {index}")),
+            };
+        }
+        if self.status.next_pass >= self.status.total_passes {
+            match self.status.sorted {
+                Some(true) => ui.colored_label(egui::Color32::GREEN, "Readback: sorted"),
+                Some(false) => ui.colored_label(egui::Color32::RED, "Readback: NOT sorted"),
+                None => ui.label("Readback: pending..."),
+            };
+        }
+
+        ui.separator();
+        if !self.status.timestamps_supported {
+            ui.label("Timestamp queries are not supported by this adapter.");
+        } else if self.status.pass_times.is_empty() {
+            ui.label("Run some passes to see their GPU time.");
+        } else {
+            let total: f64 = self.status.pass_times.iter().map(|(_, ms)| ms).sum();
+            ui.label(format!(
+                "GPU time of the last {} passes: {total:.3} ms",
+                self.status.pass_times.len()
+            ));
+            egui::CollapsingHeader::new("Per pass").show(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("bitonic_sort_pass_times")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for (pass, ms) in &self.status.pass_times {
+                                    ui.label(pass.to_string());
+                                    ui.label(format!("{ms:.3} ms"));
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            });
+        }
+    }
+
+    fn custom_painting(&mut self, ui: &mut egui::Ui) {
+        let (rect, response) =
+            ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::hover());
+
+        let (width, height) = grid_dimensions(self.element_count);
+        self.hovered = response.hover_pos().map(|pos| {
+            let uv = (pos - rect.min) / rect.size();
+            let x = ((uv.x * width as f32) as u32).min(width - 1);
+            let y = ((uv.y * height as f32) as u32).min(height - 1);
+            y * width + x
+        });
+
+        let mut passes = std::mem::take(&mut self.requested_passes);
+        if self.auto_step {
+            passes = passes.max(1);
+        }
+
+        ui.painter().add(paint_callback(
+            rect,
+            CustomPaintCallback {
+                workgroup_size: self.workgroup_size,
+                values: self.pending_values.take(),
+                passes,
+                hovered: self.hovered,
+            },
+        ));
+    }
+}
+
+struct CustomPaintCallback {
+    workgroup_size: u32,
+    values: Option<Vec<u32>>,
+    passes: usize,
+    hovered: Option<u32>,
+}
+
+impl egui_wgpu::CallbackTrait for CustomPaintCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let stats = FrameStats::get(callback_resources);
+        let profiler = GpuProfiler::get(callback_resources);
+        let resources: &mut AppRenderResources = callback_resources.get_mut().unwrap();
+
+        resources.sorter.poll(device);
+        if let Some(values) = &self.values {
+            resources.sorter.reset(device, values, self.workgroup_size);
+            resources.grid_bind_group = create_grid_bind_group(
+                device,
+                &resources.grid_bind_group_layout,
+                &resources.sorter,
+                &resources.grid_buffer,
+            );
+        }
+
+        let (width, height) = grid_dimensions(resources.sorter.count());
+        let hovered = self.hovered.unwrap_or(u32::MAX);
+        let partner = match (self.hovered, resources.sorter.next_pass()) {
+            (Some(index), Some(pass)) => pass.partner(index),
+            _ => u32::MAX,
+        };
+        stats.write_buffer(
+            queue,
+            &resources.grid_buffer,
+            0,
+            bytemuck::cast_slice(&[width, height, hovered, partner]),
+        );
+
+        resources
+            .sorter
+            .encode(egui_encoder, &profiler, self.passes);
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: eframe::egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
+        let resources: &AppRenderResources = callback_resources.get().unwrap();
+        render_pass.set_pipeline(&resources.pipeline);
+        render_pass.set_bind_group(0, &resources.grid_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+struct AppRenderResources {
+    pub sorter: BitonicSorter,
+    pub pipeline: wgpu::RenderPipeline,
+    pub grid_buffer: wgpu::Buffer,
+    pub grid_bind_group_layout: wgpu::BindGroupLayout,
+    pub grid_bind_group: wgpu::BindGroup,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless;
+
+    #[test]
+    fn workgroup_sizes_fit_the_element_count() {
+        assert_eq!(largest_workgroup_size(32), 16);
+        assert_eq!(largest_workgroup_size(96), 32);
+        assert_eq!(largest_workgroup_size(1 << 20), 256);
+    }
+
+    #[test]
+    fn readbacks_of_older_data_are_ignored() {
+        let Some((device, queue)) = headless::request_device() else {
+            eprintln!("no wgpu adapter available, skipping");
+            return;
+        };
+
+        // Run a frame: poll the readbacks, then encode and submit up to `passes` passes
+        let frame = |sorter: &mut BitonicSorter, passes: usize| {
+            device.poll(wgpu::Maintain::Wait);
+            sorter.poll(&device);
+            let mut encoder = device.create_command_encoder(&Default::default());
+            sorter.encode(&mut encoder, &GpuProfiler::default(), passes);
+            queue.submit(Some(encoder.finish()));
+            sorter.status().sorted
+        };
+
+        let mut sorter = BitonicSorter::new(&device, &queue, &shuffled_values(256, 1), 32);
+        frame(&mut sorter, usize::MAX);
+
+        // The readback of the sorted data is in flight when new values come in
+        sorter.reset(&device, &shuffled_values(256, 2), 32);
+        for _ in 0..8 {
+            assert_eq!(frame(&mut sorter, 0), None);
+        }
+
+        // Sort them, and sort others while the readback of these is still in flight, so
+        // that the copy of the others has to wait for it
+        frame(&mut sorter, usize::MAX);
+        sorter.reset(&device, &shuffled_values(256, 3), 32);
+        frame(&mut sorter, usize::MAX);
+        assert!((0..8).any(|_| frame(&mut sorter, 0) == Some(true)));
+    }
+}
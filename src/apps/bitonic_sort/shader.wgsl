@@ -0,0 +1,48 @@
+struct Uniforms {
+    width: u32,
+    height: u32,
+    // The element under the cursor and the one it is compared with in the next pass,
+    // `0xffffffff` when there is none
+    hovered: u32,
+    partner: u32,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) uv: vec2f,
+}
+
+@group(0) @binding(0) var<storage, read> data: array<u32>;
+@group(0) @binding(1) var<uniform> uniforms: Uniforms;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    // A single triangle covering the whole viewport
+    let uv = vec2f(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+
+    var output: VertexOutput;
+    output.position = vec4(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    output.uv = uv;
+    return output;
+}
+
+// Smooth rainbow ramp, so that unsorted regions stand out
+fn ramp(t: f32) -> vec3f {
+    return clamp(abs(fract(t * 0.8 + vec3(0.0, 2.0, 1.0) / 3.0) * 6.0 - 3.0) - 1.0, vec3(0.0), vec3(1.0));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let x = min(u32(in.uv.x * f32(uniforms.width)), uniforms.width - 1u);
+    let y = min(u32(in.uv.y * f32(uniforms.height)), uniforms.height - 1u);
+    let index = y * uniforms.width + x;
+    let count = uniforms.width * uniforms.height;
+
+    var color = ramp(f32(data[index]) / f32(count));
+    if (index == uniforms.hovered) {
+        color = mix(color, vec3(1.0), 0.75);
+    } else if (index == uniforms.partner) {
+        color = mix(color, vec3(0.0), 0.75);
+    }
+    return vec4(color, 1.0);
+}
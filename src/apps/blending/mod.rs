@@ -0,0 +1,826 @@
+use eframe::{
+    egui,
+    egui_wgpu::{self, RenderState},
+};
+use wgpu::util::DeviceExt;
+
+use crate::offscreen::{self, OffscreenTarget};
+use crate::profiler::GpuProfiler;
+use crate::stats::{paint_callback, FrameStats};
+
+const CANVAS: (f32, f32) = (600.0, 600.0);
+const TEXTURE_SIZE: u32 = 256;
+/// Blending happens on these 8 bit values as they are, without sRGB conversion, so that what
+/// the canvas shows follows the blend equation.
+const TARGET_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+/// Corners of the quads in normalized device coordinates, min then max.
+const DESTINATION_RECT: [f32; 4] = [-0.85, -0.3, 0.3, 0.85];
+const SOURCE_RECT: [f32; 4] = [-0.3, -0.85, 0.85, 0.3];
+
+/// Every factor but those of dual source blending, which needs `DUAL_SOURCE_BLENDING`.
+const FACTORS: [wgpu::BlendFactor; 13] = [
+    wgpu::BlendFactor::Zero,
+    wgpu::BlendFactor::One,
+    wgpu::BlendFactor::Src,
+    wgpu::BlendFactor::OneMinusSrc,
+    wgpu::BlendFactor::SrcAlpha,
+    wgpu::BlendFactor::OneMinusSrcAlpha,
+    wgpu::BlendFactor::Dst,
+    wgpu::BlendFactor::OneMinusDst,
+    wgpu::BlendFactor::DstAlpha,
+    wgpu::BlendFactor::OneMinusDstAlpha,
+    wgpu::BlendFactor::SrcAlphaSaturated,
+    wgpu::BlendFactor::Constant,
+    wgpu::BlendFactor::OneMinusConstant,
+];
+
+const OPERATIONS: [wgpu::BlendOperation; 5] = [
+    wgpu::BlendOperation::Add,
+    wgpu::BlendOperation::Subtract,
+    wgpu::BlendOperation::ReverseSubtract,
+    wgpu::BlendOperation::Min,
+    wgpu::BlendOperation::Max,
+];
+
+/// Source over destination, for the alpha of presets that only change the color.
+const OVER: wgpu::BlendComponent = wgpu::BlendComponent {
+    src_factor: wgpu::BlendFactor::One,
+    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+    operation: wgpu::BlendOperation::Add,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Preset {
+    Replace,
+    AlphaBlending,
+    PremultipliedAlphaBlending,
+    Additive,
+    Multiply,
+    Screen,
+    Darken,
+    Lighten,
+}
+
+impl Preset {
+    const ALL: [Self; 8] = [
+        Self::Replace,
+        Self::AlphaBlending,
+        Self::PremultipliedAlphaBlending,
+        Self::Additive,
+        Self::Multiply,
+        Self::Screen,
+        Self::Darken,
+        Self::Lighten,
+    ];
+
+    fn state(self) -> wgpu::BlendState {
+        let both = |component| wgpu::BlendState {
+            color: component,
+            alpha: component,
+        };
+        let color = |src_factor, dst_factor, operation| wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor,
+                dst_factor,
+                operation,
+            },
+            alpha: OVER,
+        };
+        use wgpu::{BlendFactor as F, BlendOperation as O};
+        match self {
+            Self::Replace => wgpu::BlendState::REPLACE,
+            Self::AlphaBlending => wgpu::BlendState::ALPHA_BLENDING,
+            Self::PremultipliedAlphaBlending => wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            Self::Additive => both(wgpu::BlendComponent {
+                src_factor: F::One,
+                dst_factor: F::One,
+                operation: O::Add,
+            }),
+            Self::Multiply => color(F::Dst, F::Zero, O::Add),
+            Self::Screen => color(F::One, F::OneMinusSrc, O::Add),
+            Self::Darken => color(F::One, F::One, O::Min),
+            Self::Lighten => color(F::One, F::One, O::Max),
+        }
+    }
+}
+
+impl std::fmt::Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Replace => write!(f, "Replace"),
+            Self::AlphaBlending => write!(f, "Alpha blending"),
+            Self::PremultipliedAlphaBlending => write!(f, "Premultiplied alpha blending"),
+            Self::Additive => write!(f, "Additive"),
+            Self::Multiply => write!(f, "Multiply"),
+            Self::Screen => write!(f, "Screen"),
+            Self::Darken => write!(f, "Darken (min)"),
+            Self::Lighten => write!(f, "Lighten (max)"),
+        }
+    }
+}
+
+/// How the colors of both quad textures, which have straight alpha, are given to the blender.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SourceAlpha {
+    Straight,
+    Premultiplied,
+}
+
+/// How the canvas interprets the alpha of the target, like the `CompositeAlphaMode` of a
+/// surface. Transparent parts show a checkerboard.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum AlphaMode {
+    Opaque,
+    PreMultiplied,
+    PostMultiplied,
+}
+
+impl AlphaMode {
+    const ALL: [Self; 3] = [Self::Opaque, Self::PreMultiplied, Self::PostMultiplied];
+}
+
+impl std::fmt::Display for AlphaMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Opaque => write!(f, "Opaque"),
+            Self::PreMultiplied => write!(f, "Premultiplied"),
+            Self::PostMultiplied => write!(f, "Postmultiplied"),
+        }
+    }
+}
+
+/// Laid out to match `Quad` in `shader.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct QuadUniforms {
+    rect: [f32; 4],
+    premultiply: u32,
+    _padding: [u32; 3],
+}
+
+/// Laid out to match `Composite` in `shader.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct CompositeUniforms {
+    alpha_mode: u32,
+    show_alpha: u32,
+    _padding: [u32; 2],
+}
+
+/// Min and max ignore the factors, and WebGPU requires them to be `One` for these.
+fn normalized(component: wgpu::BlendComponent) -> wgpu::BlendComponent {
+    match component.operation {
+        wgpu::BlendOperation::Min | wgpu::BlendOperation::Max => wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            ..component
+        },
+        _ => component,
+    }
+}
+
+/// The equation `component` computes, e.g. `src × SrcAlpha + dst × OneMinusSrcAlpha`.
+fn equation(component: wgpu::BlendComponent) -> String {
+    let src = format!("src × {:?}", component.src_factor);
+    let dst = format!("dst × {:?}", component.dst_factor);
+    match component.operation {
+        wgpu::BlendOperation::Add => format!("{src} + {dst}"),
+        wgpu::BlendOperation::Subtract => format!("{src} - {dst}"),
+        wgpu::BlendOperation::ReverseSubtract => format!("{dst} - {src}"),
+        wgpu::BlendOperation::Min => "min(src, dst)".to_string(),
+        wgpu::BlendOperation::Max => "max(src, dst)".to_string(),
+    }
+}
+
+/// A fully saturated color of hue `hue`, in turns.
+fn hue(hue: f32) -> [f32; 3] {
+    let channel = |offset: f32| {
+        let k = (offset + hue * 6.0) % 6.0;
+        1.0 - (k.min(4.0 - k).clamp(0.0, 1.0))
+    };
+    [channel(5.0), channel(3.0), channel(1.0)]
+}
+
+/// Straight alpha texels of a `TEXTURE_SIZE` square, from the color at each texel center.
+fn texels(color: impl Fn(f32, f32) -> ([f32; 3], f32)) -> Vec<u8> {
+    let size = TEXTURE_SIZE as f32;
+    (0..TEXTURE_SIZE * TEXTURE_SIZE)
+        .flat_map(|i| {
+            let u = ((i % TEXTURE_SIZE) as f32 + 0.5) / size;
+            let v = ((i / TEXTURE_SIZE) as f32 + 0.5) / size;
+            let ([r, g, b], a) = color(u, v);
+            [r, g, b, a].map(|channel| (channel * 255.0).round() as u8)
+        })
+        .collect()
+}
+
+/// Hues from left to right, opaque at the top and transparent at the bottom.
+fn destination_texels() -> Vec<u8> {
+    texels(|u, v| (hue(u), 1.0 - v))
+}
+
+/// Hues from top to bottom, opaque in the middle and transparent at the edges.
+fn source_texels() -> Vec<u8> {
+    texels(|u, v| {
+        let distance = ((u - 0.5).powi(2) + (v - 0.5).powi(2)).sqrt() * 2.0;
+        (hue(v), (1.0 - distance).clamp(0.0, 1.0))
+    })
+}
+
+fn create_blend_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    blend: Option<wgpu::BlendState>,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Blending Quad Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_quad",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_quad",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: TARGET_FORMAT,
+                blend,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+fn create_composite_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buffer: &wgpu::Buffer,
+    target: &OffscreenTarget,
+    sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Blending Composite Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(target.color_view()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    })
+}
+
+pub struct Blending {
+    color: wgpu::BlendComponent,
+    alpha: wgpu::BlendComponent,
+    constant: [f32; 4],
+    source_alpha: SourceAlpha,
+    clear_color: [f32; 4],
+    alpha_mode: AlphaMode,
+    show_alpha: bool,
+}
+
+impl Blending {
+    pub fn new_with_render_state(wgpu_render_state: &RenderState) -> Option<Self> {
+        let device = &wgpu_render_state.device;
+        let queue = &wgpu_render_state.queue;
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Blending Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Blending Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        // The destination quad and the source quad, each with its texture and its rectangle
+        let quads = [
+            ("Destination", destination_texels(), DESTINATION_RECT),
+            ("Source", source_texels(), SOURCE_RECT),
+        ]
+        .map(|(name, texels, rect)| {
+            let texture_view = device
+                .create_texture_with_data(
+                    queue,
+                    &wgpu::TextureDescriptor {
+                        label: Some(&format!("Blending {name} Texture")),
+                        size: wgpu::Extent3d {
+                            width: TEXTURE_SIZE,
+                            height: TEXTURE_SIZE,
+                            depth_or_array_layers: 1,
+                        },
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format: wgpu::TextureFormat::Rgba8Unorm,
+                        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                        view_formats: &[],
+                    },
+                    wgpu::util::TextureDataOrder::LayerMajor,
+                    &texels,
+                )
+                .create_view(&wgpu::TextureViewDescriptor::default());
+            let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("Blending {name} Uniform Buffer")),
+                contents: bytemuck::bytes_of(&QuadUniforms {
+                    rect,
+                    premultiply: 0,
+                    _padding: [0; 3],
+                }),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(&format!("Blending {name} Bind Group")),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&texture_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                ],
+            });
+            Quad {
+                uniform_buffer,
+                bind_group,
+            }
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Blending Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./shader.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Blending Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        // The destination is drawn as it is, the source with the blend state of the controls
+        let destination_pipeline = create_blend_pipeline(device, &pipeline_layout, &shader, None);
+        let blend = wgpu::BlendState::ALPHA_BLENDING;
+        let source_pipeline = create_blend_pipeline(device, &pipeline_layout, &shader, Some(blend));
+
+        let composite_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Blending Composite Uniform Buffer"),
+            size: std::mem::size_of::<CompositeUniforms>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let target_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Blending Target Sampler"),
+            ..Default::default()
+        });
+        let composite_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Blending Composite Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_composite",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_composite",
+                targets: &[Some(wgpu_render_state.target_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        // Not blitted: the composite pass shows its alpha
+        let target = OffscreenTarget::new(
+            device,
+            TARGET_FORMAT,
+            None,
+            (CANVAS.0 as u32, CANVAS.1 as u32),
+        );
+        let composite_bind_group = create_composite_bind_group(
+            device,
+            &bind_group_layout,
+            &composite_uniform_buffer,
+            &target,
+            &target_sampler,
+        );
+
+        // Because the graphics pipeline must have the same lifetime as the egui render pass,
+        // instead of storing the pipeline in our struct, we insert it into the
+        // `paint_callback_resources` type map, which is stored alongside the render pass.
+        wgpu_render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(AppRenderResources {
+                target,
+                shader,
+                bind_group_layout,
+                pipeline_layout,
+                quads,
+                destination_pipeline,
+                blend,
+                source_pipeline,
+                composite_pipeline,
+                composite_uniform_buffer,
+                composite_bind_group,
+                target_sampler,
+            });
+
+        Some(Self {
+            color: blend.color,
+            alpha: blend.alpha,
+            constant: [1.0, 1.0, 1.0, 0.5],
+            source_alpha: SourceAlpha::Straight,
+            clear_color: [0.0, 0.0, 0.0, 0.0],
+            alpha_mode: AlphaMode::PreMultiplied,
+            show_alpha: false,
+        })
+    }
+
+    fn blend_state(&self) -> wgpu::BlendState {
+        wgpu::BlendState {
+            color: normalized(self.color),
+            alpha: normalized(self.alpha),
+        }
+    }
+}
+
+impl eframe::App for Blending {
+    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                        self.custom_painting(ui);
+                    });
+                    ui.vertical(|ui| self.controls(ui));
+                });
+            });
+        });
+    }
+}
+
+/// A combo box choosing `value` among `options`, shown with their `Debug` names.
+fn combo<T: Copy + PartialEq + std::fmt::Debug>(
+    ui: &mut egui::Ui,
+    id: &str,
+    value: &mut T,
+    options: &[T],
+) {
+    egui::ComboBox::from_id_source(id)
+        .selected_text(format!("{value:?}"))
+        .show_ui(ui, |ui| {
+            for option in options {
+                ui.selectable_value(value, *option, format!("{option:?}"));
+            }
+        });
+}
+
+/// The operation and factors of `component`, which are only enabled when they matter.
+fn component_controls(ui: &mut egui::Ui, name: &str, component: &mut wgpu::BlendComponent) {
+    ui.label(format!("{name} operation"));
+    combo(
+        ui,
+        &format!("blending_{name}_operation"),
+        &mut component.operation,
+        &OPERATIONS,
+    );
+    ui.end_row();
+
+    let factors_used = !matches!(
+        component.operation,
+        wgpu::BlendOperation::Min | wgpu::BlendOperation::Max
+    );
+    ui.label(format!("{name} src factor"));
+    ui.add_enabled_ui(factors_used, |ui| {
+        combo(
+            ui,
+            &format!("blending_{name}_src_factor"),
+            &mut component.src_factor,
+            &FACTORS,
+        )
+    });
+    ui.end_row();
+    ui.label(format!("{name} dst factor"));
+    ui.add_enabled_ui(factors_used, |ui| {
+        combo(
+            ui,
+            &format!("blending_{name}_dst_factor"),
+            &mut component.dst_factor,
+            &FACTORS,
+        )
+    });
+    ui.end_row();
+}
+
+impl Blending {
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("blending_controls")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("preset");
+                let preset = Preset::ALL
+                    .into_iter()
+                    .find(|preset| preset.state() == self.blend_state());
+                egui::ComboBox::from_id_source("blending_preset")
+                    .selected_text(preset.map_or("Custom".to_string(), |p| p.to_string()))
+                    .show_ui(ui, |ui| {
+                        for preset in Preset::ALL {
+                            if ui.button(preset.to_string()).clicked() {
+                                let state = preset.state();
+                                self.color = state.color;
+                                self.alpha = state.alpha;
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                ui.end_row();
+
+                component_controls(ui, "color", &mut self.color);
+                component_controls(ui, "alpha", &mut self.alpha);
+
+                ui.label("constant");
+                ui.color_edit_button_rgba_unmultiplied(&mut self.constant)
+                    .on_hover_text("Used by the Constant and OneMinusConstant factors.");
+                ui.end_row();
+                ui.label("texture alpha");
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.source_alpha, SourceAlpha::Straight, "Straight");
+                    ui.selectable_value(
+                        &mut self.source_alpha,
+                        SourceAlpha::Premultiplied,
+                        "Premultiplied",
+                    );
+                });
+                ui.end_row();
+                ui.label("clear color");
+                ui.color_edit_button_rgba_unmultiplied(&mut self.clear_color);
+                ui.end_row();
+                ui.label("alpha mode");
+                egui::ComboBox::from_id_source("blending_alpha_mode")
+                    .selected_text(self.alpha_mode.to_string())
+                    .show_ui(ui, |ui| {
+                        for alpha_mode in AlphaMode::ALL {
+                            ui.selectable_value(
+                                &mut self.alpha_mode,
+                                alpha_mode,
+                                alpha_mode.to_string(),
+                            );
+                        }
+                    });
+                ui.end_row();
+                ui.label("show alpha");
+                ui.checkbox(&mut self.show_alpha, "");
+                ui.end_row();
+            });
+
+        ui.separator();
+        let state = self.blend_state();
+        ui.monospace(format!("color = {}", equation(state.color)));
+        ui.monospace(format!("alpha = {}", equation(state.alpha)));
+        ui.label(
+            "The target is cleared to the clear color, then the destination quad (top left) is \
+             drawn without blending and the source quad (bottom right) with the blend state \
+             above. The alpha mode tells how the canvas composites the target over the \
+             checkerboard behind it.",
+        );
+    }
+
+    fn custom_painting(&mut self, ui: &mut egui::Ui) {
+        let (rect, _response) =
+            ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::click());
+
+        let [r, g, b, a] = self.constant.map(f64::from);
+        let constant = wgpu::Color { r, g, b, a };
+        let [r, g, b, a] = self.clear_color.map(f64::from);
+        let clear_color = wgpu::Color { r, g, b, a };
+        let alpha_mode = AlphaMode::ALL
+            .iter()
+            .position(|alpha_mode| *alpha_mode == self.alpha_mode)
+            .unwrap_or_default() as u32;
+        ui.painter().add(paint_callback(
+            rect,
+            CustomPaintCallback {
+                size: offscreen::size_in_pixels(ui, rect),
+                blend: self.blend_state(),
+                constant,
+                premultiply: self.source_alpha == SourceAlpha::Premultiplied,
+                clear_color,
+                composite: CompositeUniforms {
+                    alpha_mode,
+                    show_alpha: self.show_alpha as u32,
+                    _padding: [0; 2],
+                },
+            },
+        ));
+    }
+}
+
+struct CustomPaintCallback {
+    size: (u32, u32),
+    blend: wgpu::BlendState,
+    constant: wgpu::Color,
+    premultiply: bool,
+    clear_color: wgpu::Color,
+    composite: CompositeUniforms,
+}
+
+impl egui_wgpu::CallbackTrait for CustomPaintCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let profiler = GpuProfiler::get(callback_resources);
+        let stats = FrameStats::get(callback_resources);
+        let resources: &mut AppRenderResources = callback_resources.get_mut().unwrap();
+        if resources.target.resize(device, self.size) {
+            resources.composite_bind_group = create_composite_bind_group(
+                device,
+                &resources.bind_group_layout,
+                &resources.composite_uniform_buffer,
+                &resources.target,
+                &resources.target_sampler,
+            );
+        }
+        if resources.blend != self.blend {
+            resources.source_pipeline = create_blend_pipeline(
+                device,
+                &resources.pipeline_layout,
+                &resources.shader,
+                Some(self.blend),
+            );
+            resources.blend = self.blend;
+        }
+
+        // Only `premultiply` changes, it follows `rect`
+        for quad in &resources.quads {
+            stats.write_buffer(
+                queue,
+                &quad.uniform_buffer,
+                std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                bytemuck::bytes_of(&(self.premultiply as u32)),
+            );
+        }
+        stats.write_buffer(
+            queue,
+            &resources.composite_uniform_buffer,
+            0,
+            bytemuck::bytes_of(&self.composite),
+        );
+
+        let mut render_pass = stats.pass(resources.target.begin_pass(
+            egui_encoder,
+            self.clear_color,
+            "Blending Render Pass",
+            &profiler,
+        ));
+        let [destination, source] = &resources.quads;
+        render_pass.set_pipeline(&resources.destination_pipeline);
+        render_pass.set_bind_group(0, &destination.bind_group, &[]);
+        render_pass.draw(0..6, 0..1);
+        render_pass.set_pipeline(&resources.source_pipeline);
+        render_pass.set_blend_constant(self.constant);
+        render_pass.set_bind_group(0, &source.bind_group, &[]);
+        render_pass.draw(0..6, 0..1);
+        drop(render_pass);
+
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: eframe::egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
+        let resources: &AppRenderResources = callback_resources.get().unwrap();
+        render_pass.set_pipeline(&resources.composite_pipeline);
+        render_pass.set_bind_group(0, &resources.composite_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+struct Quad {
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+struct AppRenderResources {
+    pub target: OffscreenTarget,
+    pub shader: wgpu::ShaderModule,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub pipeline_layout: wgpu::PipelineLayout,
+    /// The destination and the source quad.
+    pub quads: [Quad; 2],
+    pub destination_pipeline: wgpu::RenderPipeline,
+    /// The blend state `source_pipeline` was created with.
+    pub blend: wgpu::BlendState,
+    pub source_pipeline: wgpu::RenderPipeline,
+    pub composite_pipeline: wgpu::RenderPipeline,
+    pub composite_uniform_buffer: wgpu::Buffer,
+    pub composite_bind_group: wgpu::BindGroup,
+    pub target_sampler: wgpu::Sampler,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn min_and_max_use_one_factors() {
+        let component = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::SrcAlpha,
+            dst_factor: wgpu::BlendFactor::Zero,
+            operation: wgpu::BlendOperation::Max,
+        };
+        assert_eq!(normalized(component), Preset::Lighten.state().color);
+        let add = wgpu::BlendComponent {
+            operation: wgpu::BlendOperation::Add,
+            ..component
+        };
+        assert_eq!(normalized(add), add);
+        assert_eq!(
+            equation(wgpu::BlendState::ALPHA_BLENDING.color),
+            "src × SrcAlpha + dst × OneMinusSrcAlpha"
+        );
+        assert_eq!(equation(normalized(component)), "max(src, dst)");
+    }
+
+    #[test]
+    fn textures_fade_to_transparent() {
+        let alpha =
+            |texels: &[u8], x: u32, y: u32| texels[((y * TEXTURE_SIZE + x) * 4 + 3) as usize];
+        let destination = destination_texels();
+        assert_eq!(
+            destination.len(),
+            (TEXTURE_SIZE * TEXTURE_SIZE * 4) as usize
+        );
+        assert_eq!(alpha(&destination, 0, 0), 255);
+        assert_eq!(alpha(&destination, 0, TEXTURE_SIZE - 1), 0);
+        let source = source_texels();
+        let center = TEXTURE_SIZE / 2;
+        assert!(alpha(&source, center, center) > 250);
+        assert_eq!(alpha(&source, 0, 0), 0);
+        assert_eq!(hue(0.0), [1.0, 0.0, 0.0]);
+        assert_eq!(hue(1.0 / 3.0), [0.0, 1.0, 0.0]);
+    }
+}
@@ -0,0 +1,86 @@
+struct Quad {
+    // Corners in normalized device coordinates, min then max
+    rect: vec4f,
+    // Whether to premultiply the straight alpha texture by its alpha
+    premultiply: u32,
+}
+
+@group(0) @binding(0) var<uniform> quad: Quad;
+@group(0) @binding(1) var quad_texture: texture_2d<f32>;
+@group(0) @binding(2) var quad_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) uv: vec2f,
+}
+
+// Two triangles covering a unit square
+fn corner(vertex_index: u32) -> vec2f {
+    var corners = array(
+        vec2f(0.0, 0.0), vec2f(1.0, 0.0), vec2f(0.0, 1.0),
+        vec2f(0.0, 1.0), vec2f(1.0, 0.0), vec2f(1.0, 1.0),
+    );
+    return corners[vertex_index];
+}
+
+@vertex
+fn vs_quad(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let uv = corner(vertex_index);
+    var output: VertexOutput;
+    output.position = vec4f(mix(quad.rect.xy, quad.rect.zw, vec2f(uv.x, 1.0 - uv.y)), 0.0, 1.0);
+    output.uv = uv;
+    return output;
+}
+
+@fragment
+fn fs_quad(in: VertexOutput) -> @location(0) vec4f {
+    let color = textureSample(quad_texture, quad_sampler, in.uv);
+    if quad.premultiply != 0u {
+        return vec4f(color.rgb * color.a, color.a);
+    }
+    return color;
+}
+
+struct Composite {
+    // 0: opaque, 1: premultiplied, 2: postmultiplied, as `CompositeAlphaMode`
+    alpha_mode: u32,
+    show_alpha: u32,
+}
+
+@group(0) @binding(0) var<uniform> composite: Composite;
+@group(0) @binding(1) var target_texture: texture_2d<f32>;
+@group(0) @binding(2) var target_sampler: sampler;
+
+@vertex
+fn vs_composite(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    // A single triangle covering the whole viewport
+    let uv = vec2f(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+
+    var output: VertexOutput;
+    output.position = vec4(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    output.uv = uv;
+    return output;
+}
+
+@fragment
+fn fs_composite(in: VertexOutput) -> @location(0) vec4f {
+    let color = textureSample(target_texture, target_sampler, in.uv);
+    if composite.show_alpha != 0u {
+        return vec4f(vec3f(color.a), 1.0);
+    }
+
+    // What shows through transparent parts of the target, like the page behind a canvas
+    let cell = vec2u(in.uv * 20.0);
+    let background = vec3f(select(0.4, 0.6, (cell.x + cell.y) % 2u == 0u));
+    switch composite.alpha_mode {
+        case 1u: {
+            return vec4f(color.rgb + background * (1.0 - color.a), 1.0);
+        }
+        case 2u: {
+            return vec4f(mix(background, color.rgb, color.a), 1.0);
+        }
+        default: {
+            return vec4f(color.rgb, 1.0);
+        }
+    }
+}
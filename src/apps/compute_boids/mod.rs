@@ -0,0 +1,576 @@
+use eframe::{
+    egui,
+    egui_wgpu::{self, RenderState},
+};
+use wgpu::util::DeviceExt;
+
+use crate::profiler::GpuProfiler;
+use crate::random::Random;
+use crate::stats::{paint_callback, FrameStats};
+
+const CANVAS: (f32, f32) = (600.0, 600.0);
+const WORKGROUP_SIZE: u32 = 64;
+const DEFAULT_NUM_PARTICLES: u32 = 1500;
+
+/// Vertices of the triangle drawn for every boid.
+#[rustfmt::skip]
+const SPRITE_VERTICES: &[f32] = &[
+    -0.01, -0.02,
+    0.01, -0.02,
+    0.0, 0.02,
+];
+
+/// Flocking parameters, laid out to match `SimParams` in `compute.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SimParams {
+    pub delta_t: f32,
+    pub rule1_distance: f32,
+    pub rule2_distance: f32,
+    pub rule3_distance: f32,
+    pub rule1_scale: f32,
+    pub rule2_scale: f32,
+    pub rule3_scale: f32,
+}
+
+impl Default for SimParams {
+    fn default() -> Self {
+        Self {
+            delta_t: 0.04,
+            rule1_distance: 0.1,
+            rule2_distance: 0.025,
+            rule3_distance: 0.025,
+            rule1_scale: 0.02,
+            rule2_scale: 0.05,
+            rule3_scale: 0.005,
+        }
+    }
+}
+
+/// A single boid, laid out to match `Particle` in `compute.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Particle {
+    pub pos: [f32; 2],
+    pub vel: [f32; 2],
+}
+
+/// Scatter `count` particles over the clip space square with small random velocities.
+/// The generator is seeded so the CPU and GPU paths can start from the same state.
+pub fn init_particles(count: u32, seed: u32) -> Vec<Particle> {
+    let mut random = Random::new(seed);
+    let mut random = move || random.next_f32();
+
+    (0..count)
+        .map(|_| Particle {
+            pos: [2.0 * (random() - 0.5), 2.0 * (random() - 0.5)],
+            vel: [2.0 * (random() - 0.5) * 0.1, 2.0 * (random() - 0.5) * 0.1],
+        })
+        .collect()
+}
+
+/// The compute half of the sample: two particle buffers that are read and written alternately.
+pub struct BoidsSimulation {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    params_buffer: wgpu::Buffer,
+    particle_buffers: [wgpu::Buffer; 2],
+    bind_groups: [wgpu::BindGroup; 2],
+    num_particles: u32,
+    frame: usize,
+}
+
+impl BoidsSimulation {
+    pub fn new(device: &wgpu::Device, particles: &[Particle]) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("ComputeBoids Compute Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./compute.wgsl").into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("ComputeBoids Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("ComputeBoids Compute Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("ComputeBoids Compute Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "main",
+        });
+
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("ComputeBoids Params Buffer"),
+            contents: bytemuck::bytes_of(&SimParams::default()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let (particle_buffers, bind_groups) =
+            Self::create_particle_buffers(device, &bind_group_layout, &params_buffer, particles);
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            params_buffer,
+            particle_buffers,
+            bind_groups,
+            num_particles: particles.len() as u32,
+            frame: 0,
+        }
+    }
+
+    fn create_particle_buffers(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        params_buffer: &wgpu::Buffer,
+        particles: &[Particle],
+    ) -> ([wgpu::Buffer; 2], [wgpu::BindGroup; 2]) {
+        let particle_buffers = [0, 1].map(|i| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("ComputeBoids Particle Buffer {i}")),
+                contents: bytemuck::cast_slice(particles),
+                usage: wgpu::BufferUsages::VERTEX
+                    | wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_SRC,
+            })
+        });
+
+        // Bind group `i` reads buffer `i` and writes the other one
+        let bind_groups = [0, 1].map(|i| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(&format!("ComputeBoids Bind Group {i}")),
+                layout: bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: params_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: particle_buffers[i].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: particle_buffers[(i + 1) % 2].as_entire_binding(),
+                    },
+                ],
+            })
+        });
+
+        (particle_buffers, bind_groups)
+    }
+
+    /// Replace the simulated particles, e.g. after the particle count changed.
+    pub fn reset(&mut self, device: &wgpu::Device, particles: &[Particle]) {
+        let (particle_buffers, bind_groups) = Self::create_particle_buffers(
+            device,
+            &self.bind_group_layout,
+            &self.params_buffer,
+            particles,
+        );
+        self.particle_buffers = particle_buffers;
+        self.bind_groups = bind_groups;
+        self.num_particles = particles.len() as u32;
+        self.frame = 0;
+    }
+
+    pub fn num_particles(&self) -> u32 {
+        self.num_particles
+    }
+
+    /// The buffer holding the result of the last step.
+    pub fn current_buffer(&self) -> &wgpu::Buffer {
+        &self.particle_buffers[self.frame % 2]
+    }
+
+    pub fn write_params(&self, queue: &wgpu::Queue, stats: &FrameStats, params: &SimParams) {
+        stats.write_buffer(queue, &self.params_buffer, 0, bytemuck::bytes_of(params));
+    }
+
+    /// Record one simulation step into `encoder`.
+    pub fn step(&mut self, encoder: &mut wgpu::CommandEncoder, profiler: &GpuProfiler) {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("ComputeBoids Compute Pass"),
+            timestamp_writes: profiler.compute_pass_writes("ComputeBoids Compute Pass"),
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_groups[self.frame % 2], &[]);
+        pass.dispatch_workgroups(self.num_particles.div_ceil(WORKGROUP_SIZE), 1, 1);
+        drop(pass);
+
+        self.frame += 1;
+    }
+}
+
+pub struct ComputeBoids {
+    params: SimParams,
+    num_particles: u32,
+}
+
+impl ComputeBoids {
+    pub fn new_with_render_state(wgpu_render_state: &RenderState) -> Option<Self> {
+        let device = &wgpu_render_state.device;
+
+        let simulation =
+            BoidsSimulation::new(device, &init_particles(DEFAULT_NUM_PARTICLES, 0x5eed));
+
+        // The per-instance particle buffer followed by the per-vertex sprite buffer
+        let sprite_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("ComputeBoids Sprite Vertex Buffer"),
+            contents: bytemuck::cast_slice(SPRITE_VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("ComputeBoids Sprite Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./sprite.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("ComputeBoids Render Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("ComputeBoids Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[
+                    wgpu::VertexBufferLayout {
+                        array_stride: std::mem::size_of::<Particle>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Instance,
+                        attributes: &[
+                            wgpu::VertexAttribute {
+                                format: wgpu::VertexFormat::Float32x2,
+                                offset: 0,
+                                shader_location: 0,
+                            },
+                            wgpu::VertexAttribute {
+                                format: wgpu::VertexFormat::Float32x2,
+                                offset: 2 * std::mem::size_of::<f32>() as u64,
+                                shader_location: 1,
+                            },
+                        ],
+                    },
+                    wgpu::VertexBufferLayout {
+                        array_stride: 2 * std::mem::size_of::<f32>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &[wgpu::VertexAttribute {
+                            format: wgpu::VertexFormat::Float32x2,
+                            offset: 0,
+                            shader_location: 2,
+                        }],
+                    },
+                ],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu_render_state.target_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        // Because the graphics pipeline must have the same lifetime as the egui render pass,
+        // instead of storing the pipeline in our struct, we insert it into the
+        // `paint_callback_resources` type map, which is stored alongside the render pass.
+        wgpu_render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(AppRenderResources {
+                simulation,
+                pipeline,
+                sprite_vertex_buffer,
+            });
+
+        Some(Self {
+            params: SimParams::default(),
+            num_particles: DEFAULT_NUM_PARTICLES,
+        })
+    }
+}
+
+impl eframe::App for ComputeBoids {
+    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                        self.custom_painting(ui);
+                    });
+                    ui.vertical(|ui| self.controls(ui));
+                });
+            });
+        });
+        // This is needed to keep the simulation running. It tells eframe to call update() again on the next event loop iteration.
+        ctx.request_repaint();
+    }
+}
+
+impl ComputeBoids {
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("compute_boids_controls")
+            .num_columns(2)
+            .show(ui, |ui| {
+                let params = &mut self.params;
+                ui.label("particles");
+                ui.add(egui::Slider::new(&mut self.num_particles, 1..=10000).logarithmic(true));
+                ui.end_row();
+                ui.label("deltaT");
+                ui.add(egui::Slider::new(&mut params.delta_t, 0.0..=0.1));
+                ui.end_row();
+                ui.label("rule1Distance");
+                ui.add(egui::Slider::new(&mut params.rule1_distance, 0.0..=0.2));
+                ui.end_row();
+                ui.label("rule2Distance");
+                ui.add(egui::Slider::new(&mut params.rule2_distance, 0.0..=0.1));
+                ui.end_row();
+                ui.label("rule3Distance");
+                ui.add(egui::Slider::new(&mut params.rule3_distance, 0.0..=0.1));
+                ui.end_row();
+                ui.label("rule1Scale");
+                ui.add(egui::Slider::new(&mut params.rule1_scale, 0.0..=0.1));
+                ui.end_row();
+                ui.label("rule2Scale");
+                ui.add(egui::Slider::new(&mut params.rule2_scale, 0.0..=0.1));
+                ui.end_row();
+                ui.label("rule3Scale");
+                ui.add(egui::Slider::new(&mut params.rule3_scale, 0.0..=0.1));
+                ui.end_row();
+            });
+        if ui.button("Reset").clicked() {
+            self.params = SimParams::default();
+            self.num_particles = DEFAULT_NUM_PARTICLES;
+        }
+    }
+
+    fn custom_painting(&self, ui: &mut egui::Ui) {
+        let (rect, _response) =
+            ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::click());
+        ui.painter().rect_filled(rect, 0.0, egui::Color32::BLACK);
+        ui.painter().add(paint_callback(
+            rect,
+            CustomPaintCallback {
+                params: self.params,
+                num_particles: self.num_particles,
+            },
+        ));
+    }
+}
+
+struct CustomPaintCallback {
+    params: SimParams,
+    num_particles: u32,
+}
+
+impl egui_wgpu::CallbackTrait for CustomPaintCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let profiler = GpuProfiler::get(callback_resources);
+        let stats = FrameStats::get(callback_resources);
+        let resources: &mut AppRenderResources = callback_resources.get_mut().unwrap();
+        if resources.simulation.num_particles() != self.num_particles {
+            resources
+                .simulation
+                .reset(device, &init_particles(self.num_particles, 0x5eed));
+        }
+        resources
+            .simulation
+            .write_params(queue, &stats, &self.params);
+        resources.simulation.step(egui_encoder, &profiler);
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: eframe::egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
+        let resources: &AppRenderResources = callback_resources.get().unwrap();
+        render_pass.set_pipeline(&resources.pipeline);
+        render_pass.set_vertex_buffer(0, resources.simulation.current_buffer().slice(..));
+        render_pass.set_vertex_buffer(1, resources.sprite_vertex_buffer.slice(..));
+        render_pass.draw(0..3, 0..resources.simulation.num_particles());
+    }
+}
+
+struct AppRenderResources {
+    pub simulation: BoidsSimulation,
+    pub pipeline: wgpu::RenderPipeline,
+    pub sprite_vertex_buffer: wgpu::Buffer,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless;
+    use glam::Vec2;
+
+    /// CPU reference implementation of one step of `compute.wgsl`.
+    fn cpu_step(params: &SimParams, particles: &[Particle]) -> Vec<Particle> {
+        particles
+            .iter()
+            .enumerate()
+            .map(|(index, particle)| {
+                let mut v_pos = Vec2::from(particle.pos);
+                let mut v_vel = Vec2::from(particle.vel);
+                let mut c_mass = Vec2::ZERO;
+                let mut c_vel = Vec2::ZERO;
+                let mut col_vel = Vec2::ZERO;
+                let mut c_mass_count = 0u32;
+                let mut c_vel_count = 0u32;
+
+                for (i, other) in particles.iter().enumerate() {
+                    if i == index {
+                        continue;
+                    }
+
+                    let pos = Vec2::from(other.pos);
+                    let vel = Vec2::from(other.vel);
+                    let distance = pos.distance(v_pos);
+                    if distance < params.rule1_distance {
+                        c_mass += pos;
+                        c_mass_count += 1;
+                    }
+                    if distance < params.rule2_distance {
+                        col_vel -= pos - v_pos;
+                    }
+                    if distance < params.rule3_distance {
+                        c_vel += vel;
+                        c_vel_count += 1;
+                    }
+                }
+                if c_mass_count > 0 {
+                    c_mass = c_mass / c_mass_count as f32 - v_pos;
+                }
+                if c_vel_count > 0 {
+                    c_vel /= c_vel_count as f32;
+                }
+                v_vel += c_mass * params.rule1_scale
+                    + col_vel * params.rule2_scale
+                    + c_vel * params.rule3_scale;
+
+                // clamp velocity for a more pleasing simulation
+                v_vel = v_vel.normalize() * v_vel.length().clamp(0.0, 0.1);
+                // kinematic update
+                v_pos += v_vel * params.delta_t;
+                // Wrap around boundary
+                if v_pos.x < -1.0 {
+                    v_pos.x = 1.0;
+                }
+                if v_pos.x > 1.0 {
+                    v_pos.x = -1.0;
+                }
+                if v_pos.y < -1.0 {
+                    v_pos.y = 1.0;
+                }
+                if v_pos.y > 1.0 {
+                    v_pos.y = -1.0;
+                }
+
+                Particle {
+                    pos: v_pos.into(),
+                    vel: v_vel.into(),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn gpu_steps_match_cpu_reference() {
+        let Some((device, queue)) = headless::request_device() else {
+            eprintln!("no wgpu adapter available, skipping");
+            return;
+        };
+
+        const STEPS: usize = 4;
+        let params = SimParams::default();
+        let mut expected = init_particles(256, 42);
+
+        let mut simulation = BoidsSimulation::new(&device, &expected);
+        simulation.write_params(&queue, &FrameStats::default(), &params);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        for _ in 0..STEPS {
+            simulation.step(&mut encoder, &GpuProfiler::default());
+            expected = cpu_step(&params, &expected);
+        }
+        queue.submit(Some(encoder.finish()));
+
+        let bytes = headless::read_buffer(
+            &device,
+            &queue,
+            simulation.current_buffer(),
+            std::mem::size_of_val(expected.as_slice()) as wgpu::BufferAddress,
+        );
+        let actual: &[Particle] = bytemuck::cast_slice(&bytes);
+
+        for (a, e) in actual.iter().zip(&expected) {
+            let distance = Vec2::from(a.pos).distance(Vec2::from(e.pos));
+            assert!(distance < 1e-3, "gpu {a:?} != cpu {e:?}");
+        }
+    }
+}
@@ -0,0 +1,223 @@
+struct Quad {
+    center: vec3f,
+    // Half extents, the normal is `cross(right, up)`
+    right: vec3f,
+    up: vec3f,
+    color: vec3f,
+    // Radiance emitted is `color * emissive`
+    emissive: f32,
+}
+
+struct Uniforms {
+    inv_view_proj: mat4x4f,
+    eye: vec3f,
+    // Samples already accumulated per pixel, 0 restarts the accumulation
+    samples: u32,
+    // Samples already accumulated per lightmap texel
+    lightmap_samples: u32,
+    samples_per_frame: u32,
+    max_bounces: u32,
+    // Look up the lightmap where camera rays hit, rather than tracing full paths
+    use_lightmap: u32,
+}
+
+struct Hit {
+    t: f32,
+    quad: u32,
+    // Position on the quad, from -1 to 1 along `right` and `up`
+    st: vec2f,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var<storage, read> quads: array<Quad>;
+@group(0) @binding(2) var lightmap_sampler: sampler;
+@group(1) @binding(0) var lightmap_in: texture_2d_array<f32>;
+@group(1) @binding(1) var lightmap_out: texture_storage_2d_array<rgba16float, write>;
+@group(2) @binding(0) var accumulation_in: texture_2d<f32>;
+@group(2) @binding(1) var accumulation_out: texture_storage_2d<rgba32float, write>;
+
+const PI = 3.14159265;
+// The scene is set up with its only light first
+const LIGHT = 0u;
+const NO_HIT = 0xffffffffu;
+const EPSILON = 1e-4;
+
+var<private> rng_state: u32;
+
+fn hash(value: u32) -> u32 {
+    let state = value * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn init_rng(pixel: vec2u, sample: u32) {
+    rng_state = hash(pixel.x ^ hash(pixel.y ^ hash(sample)));
+}
+
+// PCG, uniform in [0, 1)
+fn random() -> f32 {
+    rng_state = hash(rng_state);
+    return f32(rng_state >> 8u) / 16777216.0;
+}
+
+fn quad_normal(quad: Quad) -> vec3f {
+    return normalize(cross(quad.right, quad.up));
+}
+
+fn intersect(origin: vec3f, direction: vec3f) -> Hit {
+    var hit = Hit(1e30, NO_HIT, vec2(0.0));
+    for (var i = 0u; i < arrayLength(&quads); i++) {
+        let quad = quads[i];
+        let normal = cross(quad.right, quad.up);
+        let denominator = dot(normal, direction);
+        if (abs(denominator) < 1e-8) {
+            continue;
+        }
+        let t = dot(quad.center - origin, normal) / denominator;
+        if (t <= EPSILON || t >= hit.t) {
+            continue;
+        }
+        let offset = origin + direction * t - quad.center;
+        let st = vec2(
+            dot(offset, quad.right) / dot(quad.right, quad.right),
+            dot(offset, quad.up) / dot(quad.up, quad.up),
+        );
+        if (any(abs(st) > vec2(1.0))) {
+            continue;
+        }
+        hit = Hit(t, i, st);
+    }
+    return hit;
+}
+
+// Cosine weighted direction around `normal`, which makes the Lambertian BRDF and the cosine
+// term cancel out with the sampling probability
+fn cosine_direction(normal: vec3f) -> vec3f {
+    let phi = 2.0 * PI * random();
+    let r2 = random();
+    let r = sqrt(r2);
+    let a = select(vec3(1.0, 0.0, 0.0), vec3(0.0, 1.0, 0.0), abs(normal.x) > 0.9);
+    let tangent = normalize(cross(a, normal));
+    let bitangent = cross(normal, tangent);
+    return tangent * (r * cos(phi)) + bitangent * (r * sin(phi)) + normal * sqrt(1.0 - r2);
+}
+
+// Radiance reaching `position` straight from a random point of the light, weighted so that
+// multiplying by the albedo gives the reflected radiance
+fn direct_light(position: vec3f, normal: vec3f) -> vec3f {
+    let light = quads[LIGHT];
+    let light_position = light.center + light.right * (random() * 2.0 - 1.0)
+        + light.up * (random() * 2.0 - 1.0);
+    let to_light = light_position - position;
+    let distance_squared = dot(to_light, to_light);
+    let direction = to_light / sqrt(distance_squared);
+
+    let cos_surface = dot(normal, direction);
+    let cos_light = -dot(quad_normal(light), direction);
+    if (cos_surface <= 0.0 || cos_light <= 0.0) {
+        return vec3(0.0);
+    }
+    // Anything closer than the light shadows it
+    if (intersect(position, direction).quad != LIGHT) {
+        return vec3(0.0);
+    }
+
+    let area = 4.0 * length(light.right) * length(light.up);
+    return light.color * light.emissive * cos_surface * cos_light * area
+        / (PI * distance_squared);
+}
+
+// Incoming radiance stored for the hit surface, to be multiplied by its albedo
+fn lightmap(hit: Hit) -> vec3f {
+    let uv = hit.st * 0.5 + 0.5;
+    return textureSampleLevel(lightmap_in, lightmap_sampler, uv, hit.quad, 0.0).rgb;
+}
+
+// Every texel of every quad gathers one more estimate of the light reaching it: directly from
+// the light, and from whatever a random ray hits, as lit by the previous lightmap
+@compute @workgroup_size(8, 8, 1)
+fn lightmap_main(@builtin(global_invocation_id) id: vec3u) {
+    let size = textureDimensions(lightmap_out);
+    if (any(id.xy >= size)) {
+        return;
+    }
+    init_rng(id.xy + vec2(0u, id.z * size.y), uniforms.lightmap_samples);
+
+    let quad = quads[id.z];
+    let normal = quad_normal(quad);
+    let st = (vec2f(id.xy) + 0.5) / vec2f(size) * 2.0 - 1.0;
+    let position = quad.center + quad.right * st.x + quad.up * st.y + normal * EPSILON;
+
+    var sample = direct_light(position, normal);
+    let hit = intersect(position, cosine_direction(normal));
+    if (hit.quad != NO_HIT && quads[hit.quad].emissive == 0.0) {
+        sample += quads[hit.quad].color * lightmap(hit);
+    }
+
+    let previous = textureLoad(lightmap_in, id.xy, id.z, 0).rgb;
+    let count = f32(uniforms.lightmap_samples);
+    textureStore(lightmap_out, id.xy, id.z, vec4((previous * count + sample) / (count + 1.0), 1.0));
+}
+
+fn radiance(ray_origin: vec3f, ray_direction: vec3f) -> vec3f {
+    var origin = ray_origin;
+    var direction = ray_direction;
+    var result = vec3(0.0);
+    var throughput = vec3(1.0);
+
+    for (var bounce = 0u; bounce < uniforms.max_bounces; bounce++) {
+        let hit = intersect(origin, direction);
+        if (hit.quad == NO_HIT) {
+            break;
+        }
+        let quad = quads[hit.quad];
+        var normal = quad_normal(quad);
+
+        if (quad.emissive > 0.0) {
+            // Bounces reaching the light are already counted by sampling it directly
+            if (bounce == 0u && dot(normal, direction) < 0.0) {
+                result += quad.color * quad.emissive;
+            }
+            break;
+        }
+
+        if (uniforms.use_lightmap != 0u) {
+            result += throughput * quad.color * lightmap(hit);
+            break;
+        }
+
+        if (dot(normal, direction) > 0.0) {
+            normal = -normal;
+        }
+        let position = origin + direction * hit.t + normal * EPSILON;
+        result += throughput * quad.color * direct_light(position, normal);
+        throughput *= quad.color;
+        origin = position;
+        direction = cosine_direction(normal);
+    }
+    return result;
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn trace_main(@builtin(global_invocation_id) id: vec3u) {
+    let size = textureDimensions(accumulation_out);
+    if (any(id.xy >= size)) {
+        return;
+    }
+
+    var sum = vec3(0.0);
+    for (var i = 0u; i < uniforms.samples_per_frame; i++) {
+        init_rng(id.xy, uniforms.samples + i);
+        // Jittering within the pixel antialiases the edges
+        let pixel = vec2f(id.xy) + vec2(random(), random());
+        let ndc = pixel / vec2f(size) * vec2(2.0, -2.0) + vec2(-1.0, 1.0);
+        let far = uniforms.inv_view_proj * vec4(ndc, 1.0, 1.0);
+        let direction = normalize(far.xyz / far.w - uniforms.eye);
+        sum += radiance(uniforms.eye, direction);
+    }
+
+    let previous = textureLoad(accumulation_in, id.xy, 0).rgb;
+    let count = f32(uniforms.samples);
+    let average = (previous * count + sum) / (count + f32(uniforms.samples_per_frame));
+    textureStore(accumulation_out, id.xy, vec4(average, 1.0));
+}
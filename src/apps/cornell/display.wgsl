@@ -0,0 +1,50 @@
+struct DisplayParams {
+    exposure: f32,
+    // 0 clamps, 1 is Reinhard, 2 is an ACES filmic fit
+    tone_mapping: u32,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) uv: vec2f,
+}
+
+@group(0) @binding(0) var<uniform> params: DisplayParams;
+@group(0) @binding(1) var image: texture_2d<f32>;
+
+// Full screen triangle, with uv pointing down like the image rows
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let uv = vec2f(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    var output: VertexOutput;
+    output.position = vec4(uv * vec2(2.0, -2.0) + vec2(-1.0, 1.0), 0.0, 1.0);
+    output.uv = uv;
+    return output;
+}
+
+fn aces(x: vec3f) -> vec3f {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), vec3(0.0), vec3(1.0));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    // The image has the size of the viewport, so texels map one to one to fragments
+    let size = vec2i(textureDimensions(image));
+    let texel = min(vec2i(in.uv * vec2f(size)), size - 1);
+    let hdr = textureLoad(image, texel, 0).rgb * params.exposure;
+
+    var color: vec3f;
+    switch params.tone_mapping {
+        case 1u: {
+            color = hdr / (1.0 + hdr);
+        }
+        case 2u: {
+            color = aces(hdr);
+        }
+        default: {
+            color = clamp(hdr, vec3(0.0), vec3(1.0));
+        }
+    }
+    // The egui framebuffer is not sRGB, so encode the gamma here
+    return vec4(pow(color, vec3(1.0 / 2.2)), 1.0);
+}
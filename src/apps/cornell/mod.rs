@@ -0,0 +1,840 @@
+use eframe::{
+    egui,
+    egui_wgpu::{self, RenderState},
+};
+use glam::{Mat4, Quat, Vec3};
+use wgpu::util::DeviceExt;
+
+use crate::offscreen;
+use crate::profiler::GpuProfiler;
+use crate::stats::{paint_callback, FrameStats};
+
+const CANVAS: (f32, f32) = (600.0, 600.0);
+const ACCUMULATION_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+/// Filterable, so the lightmap can be sampled smoothly where camera rays hit.
+const LIGHTMAP_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const LIGHTMAP_SIZE: u32 = 32;
+/// Past this many samples the half floats of the lightmap stop changing anyway.
+const MAX_LIGHTMAP_SAMPLES: u32 = 1024;
+const WORKGROUP_SIZE: u32 = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Rendering {
+    PathTracing,
+    Lightmap,
+}
+
+impl Rendering {
+    const ALL: [Self; 2] = [Self::PathTracing, Self::Lightmap];
+}
+
+impl std::fmt::Display for Rendering {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::PathTracing => "Path tracing",
+            Self::Lightmap => "Radiosity lightmap",
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ToneMapping {
+    Clamp,
+    Reinhard,
+    Aces,
+}
+
+impl ToneMapping {
+    const ALL: [Self; 3] = [Self::Clamp, Self::Reinhard, Self::Aces];
+}
+
+impl std::fmt::Display for ToneMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Clamp => "Clamp",
+            Self::Reinhard => "Reinhard",
+            Self::Aces => "ACES",
+        })
+    }
+}
+
+/// Laid out to match `Quad` in `compute.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Quad {
+    center: [f32; 3],
+    _padding0: f32,
+    right: [f32; 3],
+    _padding1: f32,
+    up: [f32; 3],
+    _padding2: f32,
+    color: [f32; 3],
+    emissive: f32,
+}
+
+impl Quad {
+    fn new(center: Vec3, right: Vec3, up: Vec3, color: Vec3) -> Self {
+        Self {
+            center: center.into(),
+            _padding0: 0.0,
+            right: right.into(),
+            _padding1: 0.0,
+            up: up.into(),
+            _padding2: 0.0,
+            color: color.into(),
+            emissive: 0.0,
+        }
+    }
+}
+
+/// Laid out to match `Uniforms` in `compute.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    inv_view_proj: [[f32; 4]; 4],
+    eye: [f32; 3],
+    samples: u32,
+    lightmap_samples: u32,
+    samples_per_frame: u32,
+    max_bounces: u32,
+    use_lightmap: u32,
+}
+
+/// Laid out to match `DisplayParams` in `display.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct DisplayParams {
+    exposure: f32,
+    tone_mapping: u32,
+    _padding: [u32; 2],
+}
+
+/// The five outer faces of a box standing on the floor, leaving out its bottom.
+fn box_quads(center: Vec3, half_size: Vec3, angle: f32, color: Vec3) -> [Quad; 5] {
+    let rotation = Quat::from_rotation_y(angle);
+    let x = rotation * Vec3::X * half_size.x;
+    let y = Vec3::Y * half_size.y;
+    let z = rotation * Vec3::Z * half_size.z;
+    // `right` and `up` are ordered so that their cross product points outwards
+    [
+        Quad::new(center + y, x, -z, color),
+        Quad::new(center + x, y, z, color),
+        Quad::new(center - x, z, y, color),
+        Quad::new(center + z, x, y, color),
+        Quad::new(center - z, y, x, color),
+    ]
+}
+
+/// The Cornell box spans -1 to 1 along x and z and 0 to 2 along y, open towards +z.
+fn scene() -> Vec<Quad> {
+    let white = Vec3::splat(0.73);
+    let red = Vec3::new(0.65, 0.05, 0.05);
+    let green = Vec3::new(0.12, 0.45, 0.15);
+
+    // The light must come first, see `LIGHT` in `compute.wgsl`
+    let mut light = Quad::new(
+        Vec3::new(0.0, 1.999, 0.0),
+        Vec3::X * 0.25,
+        Vec3::Z * 0.25,
+        Vec3::ONE,
+    );
+    light.emissive = 15.0;
+
+    let mut quads = vec![
+        light,
+        Quad::new(Vec3::ZERO, Vec3::X, -Vec3::Z, white),
+        Quad::new(Vec3::new(0.0, 2.0, 0.0), Vec3::X, Vec3::Z, white),
+        Quad::new(Vec3::new(0.0, 1.0, -1.0), Vec3::X, Vec3::Y, white),
+        Quad::new(Vec3::new(-1.0, 1.0, 0.0), Vec3::Y, Vec3::Z, red),
+        Quad::new(Vec3::new(1.0, 1.0, 0.0), Vec3::Z, Vec3::Y, green),
+    ];
+    quads.extend(box_quads(
+        Vec3::new(0.35, 0.3, 0.3),
+        Vec3::splat(0.3),
+        -0.3,
+        white,
+    ));
+    quads.extend(box_quads(
+        Vec3::new(-0.35, 0.6, -0.35),
+        Vec3::new(0.3, 0.6, 0.3),
+        0.3,
+        white,
+    ));
+    quads
+}
+
+pub struct Cornell {
+    rendering: Rendering,
+    samples_per_frame: u32,
+    max_bounces: u32,
+    exposure: f32,
+    tone_mapping: ToneMapping,
+    camera_yaw: f32,
+    camera_pitch: f32,
+    samples: u32,
+    lightmap_samples: u32,
+    size: (u32, u32),
+}
+
+impl Cornell {
+    pub fn new_with_render_state(wgpu_render_state: &RenderState) -> Option<Self> {
+        let device = &wgpu_render_state.device;
+
+        let quads = scene();
+        let quad_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Cornell Quad Buffer"),
+            contents: bytemuck::cast_slice(&quads),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cornell Uniform Buffer"),
+            size: std::mem::size_of::<Uniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let display_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cornell Display Buffer"),
+            size: std::mem::size_of::<DisplayParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Cornell Lightmap Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let scene_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Cornell Scene Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let scene_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Cornell Scene Bind Group"),
+            layout: &scene_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: quad_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let lightmap_bind_group_layout = ping_pong_bind_group_layout(
+            device,
+            LIGHTMAP_FORMAT,
+            wgpu::TextureViewDimension::D2Array,
+        );
+        let accumulation_bind_group_layout = ping_pong_bind_group_layout(
+            device,
+            ACCUMULATION_FORMAT,
+            wgpu::TextureViewDimension::D2,
+        );
+
+        let lightmap = PingPong::new(
+            device,
+            &lightmap_bind_group_layout,
+            LIGHTMAP_FORMAT,
+            wgpu::Extent3d {
+                width: LIGHTMAP_SIZE,
+                height: LIGHTMAP_SIZE,
+                depth_or_array_layers: quads.len() as u32,
+            },
+            "Cornell Lightmap",
+        );
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Cornell Compute Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./compute.wgsl").into()),
+        });
+
+        let compute_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Cornell Compute Pipeline Layout"),
+                bind_group_layouts: &[
+                    &scene_bind_group_layout,
+                    &lightmap_bind_group_layout,
+                    &accumulation_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let create_compute_pipeline = |entry_point, label| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&compute_pipeline_layout),
+                module: &shader,
+                entry_point,
+            })
+        };
+        let lightmap_pipeline =
+            create_compute_pipeline("lightmap_main", "Cornell Lightmap Pipeline");
+        let trace_pipeline = create_compute_pipeline("trace_main", "Cornell Trace Pipeline");
+
+        let display_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Cornell Display Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let display_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Cornell Display Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./display.wgsl").into()),
+        });
+
+        let display_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Cornell Display Pipeline Layout"),
+                bind_group_layouts: &[&display_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let display_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Cornell Display Pipeline"),
+            layout: Some(&display_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &display_shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &display_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu_render_state.target_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let size = (CANVAS.0 as u32, CANVAS.1 as u32);
+        let accumulation = Accumulation::new(
+            device,
+            &accumulation_bind_group_layout,
+            &display_bind_group_layout,
+            &display_buffer,
+            size,
+        );
+
+        // Because the graphics pipeline must have the same lifetime as the egui render pass,
+        // instead of storing the pipeline in our struct, we insert it into the
+        // `paint_callback_resources` type map, which is stored alongside the render pass.
+        wgpu_render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(AppRenderResources {
+                lightmap_pipeline,
+                trace_pipeline,
+                display_pipeline,
+                uniform_buffer,
+                display_buffer,
+                scene_bind_group,
+                accumulation_bind_group_layout,
+                display_bind_group_layout,
+                lightmap,
+                accumulation,
+                num_quads: quads.len() as u32,
+            });
+
+        Some(Self {
+            rendering: Rendering::PathTracing,
+            samples_per_frame: 1,
+            max_bounces: 4,
+            exposure: 1.0,
+            tone_mapping: ToneMapping::Aces,
+            camera_yaw: 0.0,
+            camera_pitch: 0.0,
+            samples: 0,
+            lightmap_samples: 0,
+            size,
+        })
+    }
+
+    fn uniforms(&self) -> Uniforms {
+        let target = Vec3::new(0.0, 1.0, 0.0);
+        let eye = target
+            + Quat::from_rotation_y(self.camera_yaw)
+                * Quat::from_rotation_x(-self.camera_pitch)
+                * Vec3::new(0.0, 0.0, 3.8);
+        let view = Mat4::look_at_rh(eye, target, Vec3::Y);
+        let projection =
+            Mat4::perspective_rh(0.7, self.size.0 as f32 / self.size.1 as f32, 0.1, 100.0);
+
+        Uniforms {
+            inv_view_proj: (projection * view).inverse().to_cols_array_2d(),
+            eye: eye.into(),
+            samples: self.samples,
+            lightmap_samples: self.lightmap_samples,
+            samples_per_frame: self.samples_per_frame,
+            max_bounces: self.max_bounces,
+            use_lightmap: (self.rendering == Rendering::Lightmap) as u32,
+        }
+    }
+
+    fn restart(&mut self) {
+        self.samples = 0;
+    }
+}
+
+fn ping_pong_bind_group_layout(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    view_dimension: wgpu::TextureViewDimension,
+) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Cornell Ping Pong Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float {
+                        filterable: format == LIGHTMAP_FORMAT,
+                    },
+                    view_dimension,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::StorageTexture {
+                    access: wgpu::StorageTextureAccess::WriteOnly,
+                    format,
+                    view_dimension,
+                },
+                count: None,
+            },
+        ],
+    })
+}
+
+/// Two textures that are alternately read from and written to, as a compute shader can't read
+/// and write the same texture.
+struct PingPong {
+    views: [wgpu::TextureView; 2],
+    // Bind group `i` reads texture `i` and writes the other one
+    bind_groups: [wgpu::BindGroup; 2],
+    // The texture written last
+    current: usize,
+}
+
+impl PingPong {
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        size: wgpu::Extent3d,
+        label: &str,
+    ) -> Self {
+        let dimension = if size.depth_or_array_layers > 1 {
+            wgpu::TextureViewDimension::D2Array
+        } else {
+            wgpu::TextureViewDimension::D2
+        };
+        let views = [0, 1].map(|i| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(&format!("{label} Texture {i}")),
+                    size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING
+                        | wgpu::TextureUsages::STORAGE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor {
+                    dimension: Some(dimension),
+                    ..Default::default()
+                })
+        });
+
+        let bind_groups = [0, 1].map(|i| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(&format!("{label} Bind Group {i}")),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&views[i]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&views[(i + 1) % 2]),
+                    },
+                ],
+            })
+        });
+
+        Self {
+            views,
+            bind_groups,
+            current: 0,
+        }
+    }
+
+    /// The bind group reading the latest texture.
+    fn read_current(&self) -> &wgpu::BindGroup {
+        &self.bind_groups[self.current]
+    }
+
+    fn swap(&mut self) {
+        self.current = (self.current + 1) % 2;
+    }
+}
+
+/// The accumulated image, with a bind group per texture to display it.
+struct Accumulation {
+    textures: PingPong,
+    display_bind_groups: [wgpu::BindGroup; 2],
+    size: (u32, u32),
+}
+
+impl Accumulation {
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        display_layout: &wgpu::BindGroupLayout,
+        display_buffer: &wgpu::Buffer,
+        size: (u32, u32),
+    ) -> Self {
+        let textures = PingPong::new(
+            device,
+            layout,
+            ACCUMULATION_FORMAT,
+            wgpu::Extent3d {
+                width: size.0.max(1),
+                height: size.1.max(1),
+                depth_or_array_layers: 1,
+            },
+            "Cornell Accumulation",
+        );
+
+        let display_bind_groups = [0, 1].map(|i| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(&format!("Cornell Display Bind Group {i}")),
+                layout: display_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: display_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&textures.views[i]),
+                    },
+                ],
+            })
+        });
+
+        Self {
+            textures,
+            display_bind_groups,
+            size,
+        }
+    }
+}
+
+impl eframe::App for Cornell {
+    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                        self.custom_painting(ui);
+                    });
+                    ui.vertical(|ui| self.controls(ui));
+                });
+            });
+        });
+        // This is needed to keep accumulating samples. It tells eframe to call update() again on the next event loop iteration.
+        ctx.request_repaint();
+    }
+}
+
+impl Cornell {
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        let mut restart = false;
+        egui::Grid::new("cornell_controls")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("rendering");
+                egui::ComboBox::from_id_source("cornell_rendering")
+                    .selected_text(self.rendering.to_string())
+                    .show_ui(ui, |ui| {
+                        for rendering in Rendering::ALL {
+                            restart |= ui
+                                .selectable_value(
+                                    &mut self.rendering,
+                                    rendering,
+                                    rendering.to_string(),
+                                )
+                                .changed();
+                        }
+                    });
+                ui.end_row();
+                ui.label("samples per frame");
+                ui.add(egui::Slider::new(&mut self.samples_per_frame, 1..=16));
+                ui.end_row();
+                ui.label("bounces");
+                restart |= ui
+                    .add_enabled(
+                        self.rendering == Rendering::PathTracing,
+                        egui::Slider::new(&mut self.max_bounces, 1..=8),
+                    )
+                    .changed();
+                ui.end_row();
+                ui.label("exposure");
+                ui.add(egui::Slider::new(&mut self.exposure, 0.1..=10.0).logarithmic(true));
+                ui.end_row();
+                ui.label("tone mapping");
+                egui::ComboBox::from_id_source("cornell_tone_mapping")
+                    .selected_text(self.tone_mapping.to_string())
+                    .show_ui(ui, |ui| {
+                        for tone_mapping in ToneMapping::ALL {
+                            ui.selectable_value(
+                                &mut self.tone_mapping,
+                                tone_mapping,
+                                tone_mapping.to_string(),
+                            );
+                        }
+                    });
+                ui.end_row();
+                ui.label("samples");
+                ui.label(self.samples.to_string());
+                ui.end_row();
+                if self.rendering == Rendering::Lightmap {
+                    ui.label("lightmap samples");
+                    ui.label(self.lightmap_samples.to_string());
+                    ui.end_row();
+                }
+            });
+        restart |= ui.button("Restart").clicked();
+        if restart {
+            self.restart();
+        }
+        ui.label("Drag to orbit the camera.");
+    }
+
+    fn custom_painting(&mut self, ui: &mut egui::Ui) {
+        let (rect, response) =
+            ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::drag());
+
+        // Moving the camera invalidates everything accumulated so far
+        let drag = response.drag_delta();
+        if drag != egui::Vec2::ZERO {
+            self.camera_yaw = (self.camera_yaw - drag.x * 0.005).clamp(-0.8, 0.8);
+            self.camera_pitch = (self.camera_pitch + drag.y * 0.005).clamp(-0.6, 0.6);
+            self.restart();
+        }
+        let size = offscreen::size_in_pixels(ui, rect);
+        if size != self.size {
+            self.size = size;
+            self.restart();
+        }
+
+        let update_lightmap =
+            self.rendering == Rendering::Lightmap && self.lightmap_samples < MAX_LIGHTMAP_SAMPLES;
+        // Averaging over lightmaps that are still converging would keep their noise around
+        if update_lightmap {
+            self.restart();
+        }
+
+        ui.painter().add(paint_callback(
+            rect,
+            CustomPaintCallback {
+                size,
+                uniforms: self.uniforms(),
+                display_params: DisplayParams {
+                    exposure: self.exposure,
+                    tone_mapping: self.tone_mapping as u32,
+                    _padding: [0; 2],
+                },
+                update_lightmap,
+            },
+        ));
+
+        self.samples += self.samples_per_frame;
+        if update_lightmap {
+            self.lightmap_samples += 1;
+        }
+    }
+}
+
+struct CustomPaintCallback {
+    size: (u32, u32),
+    uniforms: Uniforms,
+    display_params: DisplayParams,
+    update_lightmap: bool,
+}
+
+impl egui_wgpu::CallbackTrait for CustomPaintCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let profiler = GpuProfiler::get(callback_resources);
+        let stats = FrameStats::get(callback_resources);
+        let resources: &mut AppRenderResources = callback_resources.get_mut().unwrap();
+        if resources.accumulation.size != self.size {
+            resources.accumulation = Accumulation::new(
+                device,
+                &resources.accumulation_bind_group_layout,
+                &resources.display_bind_group_layout,
+                &resources.display_buffer,
+                self.size,
+            );
+        }
+        stats.write_buffer(
+            queue,
+            &resources.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&self.uniforms),
+        );
+        stats.write_buffer(
+            queue,
+            &resources.display_buffer,
+            0,
+            bytemuck::bytes_of(&self.display_params),
+        );
+
+        // Both passes read what the other one wrote last, so they are encoded one after the other
+        if self.update_lightmap {
+            let mut compute_pass = egui_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Cornell Lightmap Pass"),
+                timestamp_writes: profiler.compute_pass_writes("Cornell Lightmap Pass"),
+            });
+            compute_pass.set_pipeline(&resources.lightmap_pipeline);
+            compute_pass.set_bind_group(0, &resources.scene_bind_group, &[]);
+            compute_pass.set_bind_group(1, resources.lightmap.read_current(), &[]);
+            compute_pass.set_bind_group(2, resources.accumulation.textures.read_current(), &[]);
+            compute_pass.dispatch_workgroups(
+                LIGHTMAP_SIZE.div_ceil(WORKGROUP_SIZE),
+                LIGHTMAP_SIZE.div_ceil(WORKGROUP_SIZE),
+                resources.num_quads,
+            );
+            drop(compute_pass);
+            resources.lightmap.swap();
+        }
+
+        let mut compute_pass = egui_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Cornell Trace Pass"),
+            timestamp_writes: profiler.compute_pass_writes("Cornell Trace Pass"),
+        });
+        compute_pass.set_pipeline(&resources.trace_pipeline);
+        compute_pass.set_bind_group(0, &resources.scene_bind_group, &[]);
+        compute_pass.set_bind_group(1, resources.lightmap.read_current(), &[]);
+        compute_pass.set_bind_group(2, resources.accumulation.textures.read_current(), &[]);
+        compute_pass.dispatch_workgroups(
+            self.size.0.div_ceil(WORKGROUP_SIZE),
+            self.size.1.div_ceil(WORKGROUP_SIZE),
+            1,
+        );
+        drop(compute_pass);
+        resources.accumulation.textures.swap();
+
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: eframe::egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
+        let resources: &AppRenderResources = callback_resources.get().unwrap();
+        let accumulation = &resources.accumulation;
+        render_pass.set_pipeline(&resources.display_pipeline);
+        render_pass.set_bind_group(
+            0,
+            &accumulation.display_bind_groups[accumulation.textures.current],
+            &[],
+        );
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+struct AppRenderResources {
+    pub lightmap_pipeline: wgpu::ComputePipeline,
+    pub trace_pipeline: wgpu::ComputePipeline,
+    pub display_pipeline: wgpu::RenderPipeline,
+    pub uniform_buffer: wgpu::Buffer,
+    pub display_buffer: wgpu::Buffer,
+    pub scene_bind_group: wgpu::BindGroup,
+    pub accumulation_bind_group_layout: wgpu::BindGroupLayout,
+    pub display_bind_group_layout: wgpu::BindGroupLayout,
+    pub lightmap: PingPong,
+    pub accumulation: Accumulation,
+    pub num_quads: u32,
+}
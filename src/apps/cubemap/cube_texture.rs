@@ -5,6 +5,7 @@ pub struct CubeTexture {
 }
 
 impl CubeTexture {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         device: &wgpu::Device,
         width: u32,
@@ -10,6 +10,7 @@ use std::f32::consts::PI;
 use wgpu::util::DeviceExt;
 
 use crate::meshes::cube;
+use crate::stats::{paint_callback, FrameStats};
 
 const CANVAS: (f32, f32) = (800.0, 800.0);
 
@@ -19,8 +20,8 @@ fn get_mvp_matrix(start_time: std::time::Instant) -> Mat4 {
         .as_secs_f32();
 
     let mut model_matrix = Mat4::from_rotation_x((PI / 10.0) * now.sin());
-    model_matrix = model_matrix * Mat4::from_rotation_y(now * 0.2);
-    model_matrix = model_matrix * Mat4::from_scale(vec3(1000.0, 1000.0, 1000.0));
+    model_matrix *= Mat4::from_rotation_y(now * 0.2);
+    model_matrix *= Mat4::from_scale(vec3(1000.0, 1000.0, 1000.0));
 
     let view_matrix = Mat4::from_translation(Vec3::new(0.0, 0.0, 0.0));
 
@@ -106,7 +107,7 @@ impl Cubemap {
         };
 
         let (cubemap_bind_group, cubemap_bind_group_layout) = {
-            let images = vec![
+            let images = [
                 image::load_from_memory(include_bytes!("assets/cubemap/posx.jpg")).unwrap(),
                 image::load_from_memory(include_bytes!("assets/cubemap/negx.jpg")).unwrap(),
                 image::load_from_memory(include_bytes!("assets/cubemap/posy.jpg")).unwrap(),
@@ -283,10 +284,8 @@ impl Cubemap {
     fn custom_painting(&self, ui: &mut egui::Ui) {
         let (rect, _response) =
             ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::click());
-        ui.painter().add(egui_wgpu::Callback::new_paint_callback(
-            rect,
-            CustomPaintCallback(),
-        ));
+        ui.painter()
+            .add(paint_callback(rect, CustomPaintCallback()));
     }
 }
 
@@ -301,8 +300,10 @@ impl egui_wgpu::CallbackTrait for CustomPaintCallback {
         _egui_encoder: &mut wgpu::CommandEncoder,
         callback_resources: &mut egui_wgpu::CallbackResources,
     ) -> Vec<wgpu::CommandBuffer> {
+        let stats = FrameStats::get(callback_resources);
         let resources: &AppRenderResources = callback_resources.get().unwrap();
-        queue.write_buffer(
+        stats.write_buffer(
+            queue,
             &resources.mvp_buffer,
             0,
             bytemuck::cast_slice(&[get_mvp_matrix(resources.start_time)]),
@@ -316,6 +317,7 @@ impl egui_wgpu::CallbackTrait for CustomPaintCallback {
         render_pass: &mut wgpu::RenderPass<'a>,
         callback_resources: &'a egui_wgpu::CallbackResources,
     ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
         let resources: &AppRenderResources = callback_resources.get().unwrap();
         render_pass.set_pipeline(&resources.pipeline);
         render_pass.set_bind_group(0, &resources.mvp_bind_group, &[]);
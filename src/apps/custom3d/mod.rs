@@ -9,13 +9,32 @@ use eframe::{
     },
 };
 
-pub struct Custom3d {
+use crate::canvas::PerCanvas;
+use crate::stats::{paint_callback, CountedPass, FrameStats};
+
+/// The state of one canvas. Its id is both the id of the canvas widget and the key of its
+/// resources on the GPU side, so every canvas keeps its own uniforms.
+struct TriangleCanvas {
+    id: egui::Id,
     angle: f32,
+    spin: bool,
+}
+
+pub struct Custom3d {
+    canvases: Vec<TriangleCanvas>,
+    next_canvas: u64,
 }
 
 impl Default for Custom3d {
     fn default() -> Self {
-        Self { angle: 0.0 }
+        let mut custom3d = Self {
+            canvases: Vec::new(),
+            next_canvas: 0,
+        };
+        for _ in 0..3 {
+            custom3d.add_canvas();
+        }
+        custom3d
     }
 }
 
@@ -67,37 +86,31 @@ impl Custom3d {
             multiview: None,
         });
 
-        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("custom3d"),
-            contents: bytemuck::cast_slice(&[0.0_f32; 4]), // 16 bytes aligned!
-            // Mapping at creation (as done by the create_buffer_init utility) doesn't require us to to add the MAP_WRITE usage
-            // (this *happens* to workaround this bug )
-            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
-        });
-
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("custom3d"),
-            layout: &bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: uniform_buffer.as_entire_binding(),
-            }],
-        });
-
         // Because the graphics pipeline must have the same lifetime as the egui render pass,
         // instead of storing the pipeline in our `Custom3D` struct, we insert it into the
         // `paint_callback_resources` type map, which is stored alongside the render pass.
+        // The type map holds a single value per type, so the uniforms of every canvas live in
+        // a `PerCanvas` inside it, keyed by the id of the canvas.
         wgpu_render_state
             .renderer
             .write()
             .callback_resources
             .insert(TriangleRenderResources {
                 pipeline,
-                bind_group,
-                uniform_buffer,
+                bind_group_layout,
+                canvases: PerCanvas::default(),
             });
 
-        Some(Self { angle: 0.0 })
+        Some(Self::default())
+    }
+
+    fn add_canvas(&mut self) {
+        self.canvases.push(TriangleCanvas {
+            id: egui::Id::new(("custom3d_canvas", self.next_canvas)),
+            angle: 0.0,
+            spin: false,
+        });
+        self.next_canvas += 1;
     }
 
     #[allow(dead_code)]
@@ -112,24 +125,49 @@ impl Custom3d {
 
 impl eframe::App for Custom3d {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let mut removed = None;
         egui::CentralPanel::default().show(ctx, |ui| {
             egui::ScrollArea::both()
                 .auto_shrink(false)
                 .show(ui, |ui| {
                     ui.horizontal(|ui| {
                         ui.spacing_mut().item_spacing.x = 0.0;
-                        ui.label("The triangle is being painted using ");
+                        ui.label("The triangles are being painted using ");
                         ui.hyperlink_to("WGPU", "https://wgpu.rs");
                         ui.label(" (Portable Rust graphics API awesomeness)");
                     });
-                    ui.label("It's not a very impressive demo, but it shows you can embed 3D inside of egui.");
+                    ui.label("Every canvas is an independent wgpu paint callback with its own uniforms, laid out by egui like any other widget.");
 
-                    egui::Frame::canvas(ui.style()).show(ui, |ui| {
-                        self.custom_painting(ui);
+                    ui.horizontal_wrapped(|ui| {
+                        for (i, canvas) in self.canvases.iter_mut().enumerate() {
+                            ui.vertical(|ui| {
+                                egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                                    Self::custom_painting(ui, canvas);
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.label(format!("{:.0}°", canvas.angle.to_degrees()));
+                                    ui.checkbox(&mut canvas.spin, "spin");
+                                    if ui.button("Remove").clicked() {
+                                        removed = Some(i);
+                                    }
+                                });
+                            });
+                        }
                     });
+                    if ui.button("Add canvas").clicked() {
+                        self.add_canvas();
+                    }
                     ui.label("Drag to rotate!");
                 });
         });
+
+        // The uniforms of the canvas are dropped once it is no longer painted
+        if let Some(i) = removed {
+            self.canvases.remove(i);
+        }
+        if self.canvases.iter().any(|canvas| canvas.spin) {
+            ctx.request_repaint();
+        }
     }
 }
 
@@ -154,6 +192,8 @@ impl eframe::App for Custom3d {
 // The paint callback is called after finish prepare and is given access to egui's main render pass,
 // which can be used to issue draw commands.
 struct CustomTriangleCallback {
+    id: egui::Id,
+    frame_nr: u64,
     angle: f32,
 }
 
@@ -166,8 +206,9 @@ impl egui_wgpu::CallbackTrait for CustomTriangleCallback {
         _egui_encoder: &mut wgpu::CommandEncoder,
         resources: &mut egui_wgpu::CallbackResources,
     ) -> Vec<wgpu::CommandBuffer> {
-        let resources: &TriangleRenderResources = resources.get().unwrap();
-        resources.prepare(device, queue, self.angle);
+        let stats = FrameStats::get(resources);
+        let resources: &mut TriangleRenderResources = resources.get_mut().unwrap();
+        resources.prepare(device, queue, &stats, self.id, self.frame_nr, self.angle);
         Vec::new()
     }
 
@@ -177,44 +218,97 @@ impl egui_wgpu::CallbackTrait for CustomTriangleCallback {
         render_pass: &mut wgpu::RenderPass<'a>,
         resources: &'a egui_wgpu::CallbackResources,
     ) {
+        let mut render_pass = FrameStats::get(resources).pass(render_pass);
         let resources: &TriangleRenderResources = resources.get().unwrap();
-        resources.paint(render_pass);
+        resources.paint(&mut render_pass, self.id);
     }
 }
 
 impl Custom3d {
-    fn custom_painting(&mut self, ui: &mut egui::Ui) {
-        let (rect, response) =
-            ui.allocate_exact_size(egui::Vec2::splat(300.0), egui::Sense::drag());
+    fn custom_painting(ui: &mut egui::Ui, canvas: &mut TriangleCanvas) {
+        let (rect, _) = ui.allocate_exact_size(egui::Vec2::splat(200.0), egui::Sense::hover());
+        // Interact with the id of the canvas, so that its drag state follows it around the layout
+        let response = ui.interact(rect, canvas.id, egui::Sense::drag());
 
-        self.angle += response.drag_motion().x * 0.01;
-        ui.painter().add(egui_wgpu::Callback::new_paint_callback(
+        canvas.angle += response.drag_motion().x * 0.01;
+        if canvas.spin {
+            canvas.angle += ui.input(|i| i.stable_dt) * 1.5;
+        }
+        ui.painter().add(paint_callback(
             rect,
-            CustomTriangleCallback { angle: self.angle },
+            CustomTriangleCallback {
+                id: canvas.id,
+                frame_nr: ui.ctx().frame_nr(),
+                angle: canvas.angle,
+            },
         ));
     }
 }
 
 struct TriangleRenderResources {
     pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    canvases: PerCanvas<CanvasResources>,
+}
+
+/// The uniforms of one canvas.
+struct CanvasResources {
     bind_group: wgpu::BindGroup,
     uniform_buffer: wgpu::Buffer,
 }
 
 impl TriangleRenderResources {
-    fn prepare(&self, _device: &wgpu::Device, queue: &wgpu::Queue, angle: f32) {
+    fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        stats: &FrameStats,
+        id: egui::Id,
+        frame_nr: u64,
+        angle: f32,
+    ) {
+        // Canvases get their uniforms the first time they are drawn
+        let layout = &self.bind_group_layout;
+        let canvas = self.canvases.prepare(id, frame_nr, || {
+            let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("custom3d"),
+                contents: bytemuck::cast_slice(&[0.0_f32; 4]), // 16 bytes aligned!
+                // Mapping at creation (as done by the create_buffer_init utility) doesn't require us to to add the MAP_WRITE usage
+                // (this *happens* to workaround this bug )
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+            });
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("custom3d"),
+                layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                }],
+            });
+
+            CanvasResources {
+                bind_group,
+                uniform_buffer,
+            }
+        });
+
         // Update our uniform buffer with the angle from the UI
-        queue.write_buffer(
-            &self.uniform_buffer,
+        stats.write_buffer(
+            queue,
+            &canvas.uniform_buffer,
             0,
             bytemuck::cast_slice(&[angle, 0.0, 0.0, 0.0]),
         );
     }
 
-    fn paint<'rp>(&'rp self, render_pass: &mut wgpu::RenderPass<'rp>) {
+    fn paint<'rp>(&'rp self, render_pass: &mut CountedPass<'_, 'rp>, id: egui::Id) {
+        // Every canvas is prepared before it is painted
+        let canvas = self.canvases.get(id).unwrap();
+
         // Draw our triangle!
         render_pass.set_pipeline(&self.pipeline);
-        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_bind_group(0, &canvas.bind_group, &[]);
         render_pass.draw(0..3, 0..1);
     }
 }
@@ -0,0 +1,32 @@
+struct Light {
+    position: vec4f,
+    color: vec3f,
+    radius: f32,
+}
+
+struct Config {
+    light_count: u32,
+    delta_time: f32,
+    extent_min: vec4f,
+    extent_max: vec4f,
+}
+
+@group(0) @binding(0) var<uniform> config: Config;
+@group(0) @binding(1) var<storage, read_write> lights: array<Light>;
+
+// Lights fall through the scene at slightly different speeds, and start over from the top
+// once they leave the bottom
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) global_id: vec3u) {
+    let index = global_id.x;
+    if (index >= config.light_count) {
+        return;
+    }
+
+    let speed = 0.5 + 0.02 * f32(index % 64u);
+    var y = lights[index].position.y - speed * config.delta_time;
+    if (y < config.extent_min.y) {
+        y = config.extent_max.y;
+    }
+    lights[index].position.y = y;
+}
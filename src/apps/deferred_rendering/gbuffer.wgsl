@@ -0,0 +1,55 @@
+struct Uniforms {
+    view_proj: mat4x4f,
+    inverse_view_proj: mat4x4f,
+    near: f32,
+    far: f32,
+    light_count: u32,
+    view: u32,
+}
+
+struct VertexInput {
+    @location(0) position: vec3f,
+    @location(1) normal: vec3f,
+}
+
+struct InstanceInput {
+    @location(5) model_0: vec4f,
+    @location(6) model_1: vec4f,
+    @location(7) model_2: vec4f,
+    @location(8) model_3: vec4f,
+    @location(9) color: vec4f,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) normal: vec3f,
+    @location(1) albedo: vec3f,
+}
+
+struct GBufferOutput {
+    @location(0) albedo: vec4f,
+    // World space normal, with plenty of precision in a float format
+    @location(1) normal: vec4f,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+
+@vertex
+fn vs_main(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let model_matrix = mat4x4f(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+
+    var output: VertexOutput;
+    output.position = uniforms.view_proj * model_matrix * vec4(vertex.position, 1.0);
+    // Only boxes scaled along their axes are drawn, so the model matrix maps their normals fine
+    output.normal = normalize((model_matrix * vec4(vertex.normal, 0.0)).xyz);
+    output.albedo = instance.color.rgb;
+    return output;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> GBufferOutput {
+    var output: GBufferOutput;
+    output.albedo = vec4(in.albedo, 1.0);
+    output.normal = vec4(normalize(in.normal), 1.0);
+    return output;
+}
@@ -0,0 +1,86 @@
+const VIEW_LIT = 0u;
+const VIEW_ALBEDO = 1u;
+const VIEW_NORMAL = 2u;
+const VIEW_DEPTH = 3u;
+
+struct Uniforms {
+    view_proj: mat4x4f,
+    inverse_view_proj: mat4x4f,
+    near: f32,
+    far: f32,
+    light_count: u32,
+    view: u32,
+}
+
+struct Light {
+    position: vec4f,
+    color: vec3f,
+    radius: f32,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var<storage, read> lights: array<Light>;
+// The G-buffer is read texel by texel, so even depth can be read as a plain float texture
+@group(1) @binding(0) var gbuffer_albedo: texture_2d<f32>;
+@group(1) @binding(1) var gbuffer_normal: texture_2d<f32>;
+@group(1) @binding(2) var gbuffer_depth: texture_2d<f32>;
+
+// Full screen triangle
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4f {
+    let uv = vec2f(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// Undo the perspective projection to get the distance from the camera
+fn linear_depth(depth: f32) -> f32 {
+    return uniforms.near * uniforms.far / (uniforms.far - depth * (uniforms.far - uniforms.near));
+}
+
+fn world_position(position: vec2f, depth: f32) -> vec3f {
+    let uv = position / vec2f(textureDimensions(gbuffer_depth));
+    let clip = vec4(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    let world = uniforms.inverse_view_proj * clip;
+    return world.xyz / world.w;
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4f) -> @location(0) vec4f {
+    let texel = vec2i(floor(position.xy));
+    let depth = textureLoad(gbuffer_depth, texel, 0).r;
+    let albedo = textureLoad(gbuffer_albedo, texel, 0).rgb;
+    let normal = textureLoad(gbuffer_normal, texel, 0).xyz;
+
+    switch uniforms.view {
+        case VIEW_ALBEDO: {
+            return vec4(albedo, 1.0);
+        }
+        case VIEW_NORMAL: {
+            return vec4(normal * 0.5 + 0.5, 1.0);
+        }
+        case VIEW_DEPTH: {
+            return vec4(vec3(1.0 - linear_depth(depth) / uniforms.far), 1.0);
+        }
+        default: {}
+    }
+
+    // Nothing was drawn here
+    if (depth >= 1.0) {
+        return vec4(0.0, 0.0, 0.0, 1.0);
+    }
+
+    let position_ws = world_position(position.xy, depth);
+    var color = albedo * 0.05;
+    for (var i = 0u; i < uniforms.light_count; i++) {
+        let light = lights[i];
+        let to_light = light.position.xyz - position_ws;
+        let distance = length(to_light);
+        if (distance > light.radius) {
+            continue;
+        }
+        let lambert = max(dot(normal, to_light / distance), 0.0);
+        let attenuation = pow(1.0 - distance / light.radius, 2.0);
+        color += albedo * light.color * lambert * attenuation;
+    }
+    return vec4(color, 1.0);
+}
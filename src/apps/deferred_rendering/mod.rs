@@ -0,0 +1,744 @@
+use eframe::{
+    egui,
+    egui_wgpu::{self, RenderState},
+};
+use glam::{Mat4, Quat, Vec3};
+use std::f32::consts::PI;
+use wgpu::util::DeviceExt;
+
+use crate::{
+    meshes::mesh::{Mesh, Vertex},
+    offscreen::{self, OffscreenTarget},
+    profiler::GpuProfiler,
+    random::Random,
+    stats::{paint_callback, FrameStats},
+};
+
+const CANVAS: (f32, f32) = (600.0, 600.0);
+const MAX_LIGHTS: u32 = 1024;
+const ALBEDO_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+const NORMAL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+const NEAR: f32 = 0.5;
+const FAR: f32 = 30.0;
+/// Lights move inside this box.
+const LIGHT_EXTENT_MIN: [f32; 4] = [-6.0, -0.5, -6.0, 0.0];
+const LIGHT_EXTENT_MAX: [f32; 4] = [6.0, 3.0, 6.0, 0.0];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum View {
+    Lit,
+    Albedo,
+    Normal,
+    Depth,
+}
+
+impl View {
+    const ALL: [Self; 4] = [Self::Lit, Self::Albedo, Self::Normal, Self::Depth];
+}
+
+impl std::fmt::Display for View {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Lit => write!(f, "Lit scene"),
+            Self::Albedo => write!(f, "Albedo"),
+            Self::Normal => write!(f, "Normal"),
+            Self::Depth => write!(f, "Depth"),
+        }
+    }
+}
+
+/// Shared by `gbuffer.wgsl` and `lighting.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    view_proj: [[f32; 4]; 4],
+    inverse_view_proj: [[f32; 4]; 4],
+    near: f32,
+    far: f32,
+    light_count: u32,
+    view: u32,
+}
+
+/// Laid out to match `Config` in `compute.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Config {
+    light_count: u32,
+    delta_time: f32,
+    _padding: [u32; 2],
+    extent_min: [f32; 4],
+    extent_max: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Light {
+    position: [f32; 4],
+    color: [f32; 3],
+    radius: f32,
+}
+
+/// Every object in the scene is the same box, scaled and placed by its instance.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Instance {
+    model_matrix: [[f32; 4]; 4],
+    color: [f32; 4],
+}
+
+impl Instance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+        5 => Float32x4,
+        6 => Float32x4,
+        7 => Float32x4,
+        8 => Float32x4,
+        9 => Float32x4,
+    ];
+
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+
+    fn new(translation: Vec3, scale: Vec3, color: [f32; 3]) -> Self {
+        Self {
+            model_matrix: Mat4::from_scale_rotation_translation(scale, Quat::IDENTITY, translation)
+                .to_cols_array_2d(),
+            color: [color[0], color[1], color[2], 1.0],
+        }
+    }
+}
+
+/// A floor with a grid of pillars of random heights.
+fn scene_instances() -> Vec<Instance> {
+    let mut random = Random::new(31);
+    let mut instances = vec![Instance::new(
+        Vec3::new(0.0, -0.1, 0.0),
+        Vec3::new(12.0, 0.2, 12.0),
+        [0.8, 0.8, 0.8],
+    )];
+    for z in -3..=3 {
+        for x in -3..=3 {
+            let height = 0.3 + 2.0 * random.next_f32();
+            let gray = 0.6 + 0.4 * random.next_f32();
+            instances.push(Instance::new(
+                Vec3::new(x as f32 * 1.6, height / 2.0, z as f32 * 1.6),
+                Vec3::new(0.6, height, 0.6),
+                [gray, gray, gray],
+            ));
+        }
+    }
+    instances
+}
+
+/// Lights at random positions inside the light extent, with random saturated colors.
+fn initial_lights(count: u32) -> Vec<Light> {
+    let mut random = Random::new(7);
+    (0..count)
+        .map(|_| {
+            let position = [0, 1, 2].map(|i| {
+                LIGHT_EXTENT_MIN[i]
+                    + random.next_f32() * (LIGHT_EXTENT_MAX[i] - LIGHT_EXTENT_MIN[i])
+            });
+            let color = [0, 1, 2].map(|_| random.next_f32());
+            let max = color[0].max(color[1]).max(color[2]).max(0.01);
+            Light {
+                position: [position[0], position[1], position[2], 1.0],
+                color: color.map(|c| c / max),
+                radius: 1.5,
+            }
+        })
+        .collect()
+}
+
+pub struct DeferredRendering {
+    view: View,
+    light_count: u32,
+    rotate_camera: bool,
+    camera_yaw: f32,
+    camera_pitch: f32,
+}
+
+impl DeferredRendering {
+    pub fn new_with_render_state(wgpu_render_state: &RenderState) -> Option<Self> {
+        let device = &wgpu_render_state.device;
+
+        let mesh = Mesh::create_box(1.0, 1.0, 1.0);
+        let instances = scene_instances();
+
+        // Create the vertex, index and instance buffers
+        let (vertex_buffer, index_buffer, instance_buffer) = {
+            let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("DeferredRendering Vertex Buffer"),
+                contents: bytemuck::cast_slice(&mesh.vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+
+            let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("DeferredRendering Index Buffer"),
+                contents: bytemuck::cast_slice(&mesh.indices),
+                usage: wgpu::BufferUsages::INDEX,
+            });
+
+            let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("DeferredRendering Instance Buffer"),
+                contents: bytemuck::cast_slice(&instances),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+
+            (vertex_buffer, index_buffer, instance_buffer)
+        };
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("DeferredRendering Uniform Buffer"),
+            size: std::mem::size_of::<Uniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let config_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("DeferredRendering Config Buffer"),
+            size: std::mem::size_of::<Config>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let light_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("DeferredRendering Light Buffer"),
+            contents: bytemuck::cast_slice(&initial_lights(MAX_LIGHTS)),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let uniform_entry = |binding, visibility| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let storage_entry = |binding, visibility, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        // The compute pipeline moves the lights
+        let (compute_pipeline, compute_bind_group) = {
+            let bind_group_layout =
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("DeferredRendering Compute Bind Group Layout"),
+                    entries: &[
+                        uniform_entry(0, wgpu::ShaderStages::COMPUTE),
+                        storage_entry(1, wgpu::ShaderStages::COMPUTE, false),
+                    ],
+                });
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("DeferredRendering Compute Bind Group"),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: config_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: light_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+
+            let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("DeferredRendering Compute Shader Module"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("./compute.wgsl").into()),
+            });
+
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("DeferredRendering Compute Pipeline Layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+            let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("DeferredRendering Compute Pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: "main",
+            });
+
+            (pipeline, bind_group)
+        };
+
+        // The G-buffer pipeline writes albedo and normals, plus depth
+        let (gbuffer_pipeline, gbuffer_uniform_bind_group) = {
+            let bind_group_layout =
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("DeferredRendering G-Buffer Bind Group Layout"),
+                    entries: &[uniform_entry(0, wgpu::ShaderStages::VERTEX)],
+                });
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("DeferredRendering G-Buffer Bind Group"),
+                layout: &bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                }],
+            });
+
+            let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("DeferredRendering G-Buffer Shader Module"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("./gbuffer.wgsl").into()),
+            });
+
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("DeferredRendering G-Buffer Pipeline Layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+            let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("DeferredRendering G-Buffer Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[Vertex::layout(), Instance::layout()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(ALBEDO_FORMAT.into()), Some(NORMAL_FORMAT.into())],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DEPTH_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            });
+
+            (pipeline, bind_group)
+        };
+
+        // The lighting pipeline reads the G-buffer and adds up all the lights for every pixel
+        let (lighting_pipeline, lighting_bind_group, gbuffer_bind_group_layout) = {
+            let bind_group_layout =
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("DeferredRendering Lighting Bind Group Layout"),
+                    entries: &[
+                        uniform_entry(0, wgpu::ShaderStages::FRAGMENT),
+                        storage_entry(1, wgpu::ShaderStages::FRAGMENT, true),
+                    ],
+                });
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("DeferredRendering Lighting Bind Group"),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: light_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+
+            let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            };
+            let gbuffer_bind_group_layout =
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("DeferredRendering G-Buffer Textures Bind Group Layout"),
+                    entries: &[texture_entry(0), texture_entry(1), texture_entry(2)],
+                });
+
+            let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("DeferredRendering Lighting Shader Module"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("./lighting.wgsl").into()),
+            });
+
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("DeferredRendering Lighting Pipeline Layout"),
+                bind_group_layouts: &[&bind_group_layout, &gbuffer_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+            let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("DeferredRendering Lighting Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu_render_state.target_format.into())],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            });
+
+            (pipeline, bind_group, gbuffer_bind_group_layout)
+        };
+
+        let size = (CANVAS.0 as u32, CANVAS.1 as u32);
+        let target = OffscreenTarget::new(device, wgpu_render_state.target_format, None, size);
+        let gbuffer = GBuffer::new(device, size, &gbuffer_bind_group_layout);
+
+        // Because the graphics pipeline must have the same lifetime as the egui render pass,
+        // instead of storing the pipeline in our struct, we insert it into the
+        // `paint_callback_resources` type map, which is stored alongside the render pass.
+        wgpu_render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(AppRenderResources {
+                target,
+                gbuffer,
+                gbuffer_bind_group_layout,
+                compute_pipeline,
+                compute_bind_group,
+                gbuffer_pipeline,
+                gbuffer_uniform_bind_group,
+                lighting_pipeline,
+                lighting_bind_group,
+                uniform_buffer,
+                config_buffer,
+                vertex_buffer,
+                index_buffer,
+                instance_buffer,
+                index_count: mesh.indices.len() as u32,
+                instance_count: instances.len() as u32,
+            });
+
+        Some(Self {
+            view: View::Lit,
+            light_count: 128,
+            rotate_camera: true,
+            camera_yaw: 0.0,
+            camera_pitch: 0.6,
+        })
+    }
+
+    fn uniforms(&self) -> Uniforms {
+        let camera_position = Vec3::new(
+            10.0 * self.camera_pitch.cos() * self.camera_yaw.sin(),
+            10.0 * self.camera_pitch.sin(),
+            10.0 * self.camera_pitch.cos() * self.camera_yaw.cos(),
+        );
+        let view_matrix = Mat4::look_at_rh(camera_position, Vec3::ZERO, Vec3::Y);
+        let projection_matrix =
+            Mat4::perspective_rh((2.0 * PI) / 5.0, CANVAS.0 / CANVAS.1, NEAR, FAR);
+        let view_proj = projection_matrix * view_matrix;
+
+        Uniforms {
+            view_proj: view_proj.to_cols_array_2d(),
+            inverse_view_proj: view_proj.inverse().to_cols_array_2d(),
+            near: NEAR,
+            far: FAR,
+            light_count: self.light_count,
+            view: self.view as u32,
+        }
+    }
+}
+
+impl eframe::App for DeferredRendering {
+    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                        self.custom_painting(ui);
+                    });
+                    ui.vertical(|ui| self.controls(ui));
+                });
+            });
+        });
+        // This is needed to animate the lights. It tells eframe to call update() again on the next event loop iteration.
+        ctx.request_repaint();
+    }
+}
+
+impl DeferredRendering {
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("deferred_rendering_controls")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("view");
+                egui::ComboBox::from_id_source("deferred_rendering_view")
+                    .selected_text(self.view.to_string())
+                    .show_ui(ui, |ui| {
+                        for view in View::ALL {
+                            ui.selectable_value(&mut self.view, view, view.to_string());
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("lights");
+                ui.add(egui::Slider::new(&mut self.light_count, 1..=MAX_LIGHTS).logarithmic(true));
+                ui.end_row();
+
+                ui.label("rotate camera");
+                ui.checkbox(&mut self.rotate_camera, "");
+                ui.end_row();
+            });
+        ui.label("Drag to orbit the camera.");
+    }
+
+    fn custom_painting(&mut self, ui: &mut egui::Ui) {
+        let (rect, response) =
+            ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::drag());
+
+        let delta_time = ui.input(|i| i.stable_dt).min(0.1);
+        if self.rotate_camera {
+            self.camera_yaw += delta_time * 0.1;
+        }
+        let drag = response.drag_delta();
+        self.camera_yaw -= drag.x * 0.01;
+        self.camera_pitch = (self.camera_pitch + drag.y * 0.01).clamp(0.1, 1.5);
+
+        ui.painter().add(paint_callback(
+            rect,
+            CustomPaintCallback {
+                size: offscreen::size_in_pixels(ui, rect),
+                uniforms: self.uniforms(),
+                config: Config {
+                    light_count: self.light_count,
+                    delta_time,
+                    _padding: [0; 2],
+                    extent_min: LIGHT_EXTENT_MIN,
+                    extent_max: LIGHT_EXTENT_MAX,
+                },
+            },
+        ));
+    }
+}
+
+/// The G-buffer attachments, and a bind group to read all of them in the lighting pass.
+struct GBuffer {
+    albedo_view: wgpu::TextureView,
+    normal_view: wgpu::TextureView,
+    depth_view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+impl GBuffer {
+    fn new(device: &wgpu::Device, size: (u32, u32), layout: &wgpu::BindGroupLayout) -> Self {
+        let create_view = |format, label| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width: size.0.max(1),
+                        height: size.1.max(1),
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        let albedo_view = create_view(ALBEDO_FORMAT, "DeferredRendering G-Buffer Albedo");
+        let normal_view = create_view(NORMAL_FORMAT, "DeferredRendering G-Buffer Normal");
+        let depth_view = create_view(DEPTH_FORMAT, "DeferredRendering G-Buffer Depth");
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("DeferredRendering G-Buffer Textures Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&albedo_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&normal_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&depth_view),
+                },
+            ],
+        });
+
+        Self {
+            albedo_view,
+            normal_view,
+            depth_view,
+            bind_group,
+        }
+    }
+}
+
+struct CustomPaintCallback {
+    size: (u32, u32),
+    uniforms: Uniforms,
+    config: Config,
+}
+
+impl egui_wgpu::CallbackTrait for CustomPaintCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let profiler = GpuProfiler::get(callback_resources);
+        let stats = FrameStats::get(callback_resources);
+        let resources: &mut AppRenderResources = callback_resources.get_mut().unwrap();
+        if resources.target.resize(device, self.size) {
+            resources.gbuffer =
+                GBuffer::new(device, self.size, &resources.gbuffer_bind_group_layout);
+        }
+
+        stats.write_buffer(
+            queue,
+            &resources.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&self.uniforms),
+        );
+        stats.write_buffer(
+            queue,
+            &resources.config_buffer,
+            0,
+            bytemuck::bytes_of(&self.config),
+        );
+
+        // Move the lights
+        {
+            let mut compute_pass = egui_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("DeferredRendering Compute Pass"),
+                timestamp_writes: profiler.compute_pass_writes("DeferredRendering Compute Pass"),
+            });
+            compute_pass.set_pipeline(&resources.compute_pipeline);
+            compute_pass.set_bind_group(0, &resources.compute_bind_group, &[]);
+            compute_pass.dispatch_workgroups(self.config.light_count.div_ceil(64), 1, 1);
+        }
+
+        // Write the G-buffer
+        {
+            let clear = |view| {
+                Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })
+            };
+            let mut gbuffer_pass =
+                stats.pass(egui_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("DeferredRendering G-Buffer Pass"),
+                    color_attachments: &[
+                        clear(&resources.gbuffer.albedo_view),
+                        clear(&resources.gbuffer.normal_view),
+                    ],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &resources.gbuffer.depth_view,
+                        depth_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(1.0),
+                            store: wgpu::StoreOp::Store,
+                        }),
+                        stencil_ops: None,
+                    }),
+                    timestamp_writes:
+                        profiler.render_pass_writes("DeferredRendering G-Buffer Pass"),
+                    occlusion_query_set: None,
+                }));
+            gbuffer_pass.set_pipeline(&resources.gbuffer_pipeline);
+            gbuffer_pass.set_bind_group(0, &resources.gbuffer_uniform_bind_group, &[]);
+            gbuffer_pass.set_vertex_buffer(0, resources.vertex_buffer.slice(..));
+            gbuffer_pass.set_vertex_buffer(1, resources.instance_buffer.slice(..));
+            gbuffer_pass
+                .set_index_buffer(resources.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            gbuffer_pass.draw_indexed(0..resources.index_count, 0, 0..resources.instance_count);
+        }
+
+        // Light the scene, or show one of the G-buffer attachments
+        let mut render_pass = stats.pass(resources.target.begin_pass(
+            egui_encoder,
+            wgpu::Color::BLACK,
+            "DeferredRendering Lighting Pass",
+            &profiler,
+        ));
+        render_pass.set_pipeline(&resources.lighting_pipeline);
+        render_pass.set_bind_group(0, &resources.lighting_bind_group, &[]);
+        render_pass.set_bind_group(1, &resources.gbuffer.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+        drop(render_pass);
+
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: eframe::egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
+        let resources: &AppRenderResources = callback_resources.get().unwrap();
+        resources.target.blit(&mut render_pass);
+    }
+}
+
+struct AppRenderResources {
+    pub target: OffscreenTarget,
+    pub gbuffer: GBuffer,
+    pub gbuffer_bind_group_layout: wgpu::BindGroupLayout,
+    pub compute_pipeline: wgpu::ComputePipeline,
+    pub compute_bind_group: wgpu::BindGroup,
+    pub gbuffer_pipeline: wgpu::RenderPipeline,
+    pub gbuffer_uniform_bind_group: wgpu::BindGroup,
+    pub lighting_pipeline: wgpu::RenderPipeline,
+    pub lighting_bind_group: wgpu::BindGroup,
+    pub uniform_buffer: wgpu::Buffer,
+    pub config_buffer: wgpu::Buffer,
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub instance_buffer: wgpu::Buffer,
+    pub index_count: u32,
+    pub instance_count: u32,
+}
@@ -0,0 +1,40 @@
+// `blockSize` is prepended by the host, since the workgroup size can be changed at runtime.
+
+@binding(0) @group(0) var<uniform> size: vec2u;
+@binding(1) @group(0) var<storage, read> current: array<u32>;
+@binding(2) @group(0) var<storage, read_write> next: array<u32>;
+
+fn getIndex(x: u32, y: u32) -> u32 {
+    let h = size.y;
+    let w = size.x;
+
+    return (y % h) * w + (x % w);
+}
+
+fn getCell(x: u32, y: u32) -> u32 {
+    return current[getIndex(x, y)];
+}
+
+fn countNeighbors(x: u32, y: u32) -> u32 {
+    // Step backwards by adding `size - 1` so the grid wraps around without underflowing
+    let left = x + size.x - 1u;
+    let right = x + 1u;
+    let up = y + size.y - 1u;
+    let down = y + 1u;
+
+    return getCell(left, up) + getCell(x, up) + getCell(right, up) +
+           getCell(left, y) + getCell(right, y) +
+           getCell(left, down) + getCell(x, down) + getCell(right, down);
+}
+
+@compute @workgroup_size(blockSize, blockSize)
+fn main(@builtin(global_invocation_id) grid: vec3u) {
+    let x = grid.x;
+    let y = grid.y;
+    if (x >= size.x || y >= size.y) {
+        return;
+    }
+
+    let n = countNeighbors(x, y);
+    next[getIndex(x, y)] = select(u32(n == 3u), u32(n == 2u || n == 3u), getCell(x, y) == 1u);
+}
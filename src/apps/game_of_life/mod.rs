@@ -0,0 +1,795 @@
+mod rle;
+
+use eframe::{
+    egui,
+    egui_wgpu::{self, RenderState},
+};
+use rle::Pattern;
+use wgpu::util::DeviceExt;
+
+use crate::profiler::GpuProfiler;
+use crate::random::Random;
+use crate::stats::{paint_callback, FrameStats};
+
+const CANVAS: (f32, f32) = (600.0, 600.0);
+const WORKGROUP_SIZES: [u32; 3] = [4, 8, 16];
+const DEFAULT_GRID_SIZE: u32 = 128;
+/// Largest width and height of the grid, from the sliders or a loaded pattern.
+const MAX_GRID_SIZE: u32 = 1024;
+
+/// Patterns bundled with the sample, as `(name, rle)`.
+const PATTERNS: [(&str, &str); 5] = [
+    ("Glider", include_str!("assets/glider.rle")),
+    ("Blinker", include_str!("assets/blinker.rle")),
+    ("Pulsar", include_str!("assets/pulsar.rle")),
+    ("R-pentomino", include_str!("assets/r_pentomino.rle")),
+    (
+        "Gosper glider gun",
+        include_str!("assets/gosper_glider_gun.rle"),
+    ),
+];
+
+/// Square made of two triangles in a strip, in units of cells.
+#[rustfmt::skip]
+const SQUARE_VERTICES: &[u32] = &[
+    0, 0,
+    0, 1,
+    1, 0,
+    1, 1,
+];
+
+/// Fill a grid with cells that are alive with a probability of 1 in 4.
+pub fn random_cells(width: u32, height: u32, seed: u32) -> Vec<u32> {
+    let mut random = Random::new(seed);
+    (0..width * height)
+        .map(|_| u32::from(random.next_f32() < 0.25))
+        .collect()
+}
+
+/// The compute half of the sample: two cell buffers that are read and written alternately.
+pub struct LifeSimulation {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    size_buffer: wgpu::Buffer,
+    cell_buffers: [wgpu::Buffer; 2],
+    bind_groups: [wgpu::BindGroup; 2],
+    size: (u32, u32),
+    workgroup_size: u32,
+    generation: usize,
+}
+
+impl LifeSimulation {
+    pub fn new(
+        device: &wgpu::Device,
+        size: (u32, u32),
+        workgroup_size: u32,
+        cells: &[u32],
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("GameOfLife Compute Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        // Padded to 16 bytes, the smallest uniform binding every backend accepts
+        let size_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("GameOfLife Size Buffer"),
+            contents: bytemuck::cast_slice(&[size.0, size.1, 0, 0]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let pipeline = Self::create_pipeline(device, &bind_group_layout, workgroup_size);
+        let (cell_buffers, bind_groups) =
+            Self::create_cell_buffers(device, &bind_group_layout, &size_buffer, cells);
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            size_buffer,
+            cell_buffers,
+            bind_groups,
+            size,
+            workgroup_size,
+            generation: 0,
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        workgroup_size: u32,
+    ) -> wgpu::ComputePipeline {
+        // wgpu has no pipeline-overridable constants yet, so the block size is spliced in
+        let source = format!(
+            "const blockSize: u32 = {workgroup_size}u;\n{}",
+            include_str!("./compute.wgsl")
+        );
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("GameOfLife Compute Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("GameOfLife Compute Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("GameOfLife Compute Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "main",
+        })
+    }
+
+    fn create_cell_buffers(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        size_buffer: &wgpu::Buffer,
+        cells: &[u32],
+    ) -> ([wgpu::Buffer; 2], [wgpu::BindGroup; 2]) {
+        let cell_buffers = [0, 1].map(|i| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("GameOfLife Cell Buffer {i}")),
+                contents: bytemuck::cast_slice(cells),
+                usage: wgpu::BufferUsages::VERTEX
+                    | wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_DST
+                    | wgpu::BufferUsages::COPY_SRC,
+            })
+        });
+
+        // Bind group `i` reads buffer `i` and writes the other one
+        let bind_groups = [0, 1].map(|i| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(&format!("GameOfLife Compute Bind Group {i}")),
+                layout: bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: size_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: cell_buffers[i].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: cell_buffers[(i + 1) % 2].as_entire_binding(),
+                    },
+                ],
+            })
+        });
+
+        (cell_buffers, bind_groups)
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    pub fn size_buffer(&self) -> &wgpu::Buffer {
+        &self.size_buffer
+    }
+
+    pub fn workgroup_size(&self) -> u32 {
+        self.workgroup_size
+    }
+
+    pub fn set_workgroup_size(&mut self, device: &wgpu::Device, workgroup_size: u32) {
+        self.pipeline = Self::create_pipeline(device, &self.bind_group_layout, workgroup_size);
+        self.workgroup_size = workgroup_size;
+    }
+
+    /// Replace the whole grid, resizing it if needed.
+    pub fn reset(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        stats: &FrameStats,
+        size: (u32, u32),
+        cells: &[u32],
+    ) {
+        stats.write_buffer(
+            queue,
+            &self.size_buffer,
+            0,
+            bytemuck::cast_slice(&[size.0, size.1]),
+        );
+        let (cell_buffers, bind_groups) =
+            Self::create_cell_buffers(device, &self.bind_group_layout, &self.size_buffer, cells);
+        self.cell_buffers = cell_buffers;
+        self.bind_groups = bind_groups;
+        self.size = size;
+        self.generation = 0;
+    }
+
+    /// Set individual cells of the current generation, as `(index, state)`.
+    pub fn write_cells(&self, queue: &wgpu::Queue, stats: &FrameStats, edits: &[(u32, u32)]) {
+        for (index, state) in edits {
+            stats.write_buffer(
+                queue,
+                self.current_buffer(),
+                *index as wgpu::BufferAddress * std::mem::size_of::<u32>() as wgpu::BufferAddress,
+                bytemuck::bytes_of(state),
+            );
+        }
+    }
+
+    /// The buffer holding the current generation.
+    pub fn current_buffer(&self) -> &wgpu::Buffer {
+        &self.cell_buffers[self.generation % 2]
+    }
+
+    /// Record one generation into `encoder`.
+    pub fn step(&mut self, encoder: &mut wgpu::CommandEncoder, profiler: &GpuProfiler) {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("GameOfLife Compute Pass"),
+            timestamp_writes: profiler.compute_pass_writes("GameOfLife Compute Pass"),
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_groups[self.generation % 2], &[]);
+        pass.dispatch_workgroups(
+            self.size.0.div_ceil(self.workgroup_size),
+            self.size.1.div_ceil(self.workgroup_size),
+            1,
+        );
+        drop(pass);
+
+        self.generation += 1;
+    }
+}
+
+pub struct GameOfLife {
+    grid_size: (u32, u32),
+    workgroup_size: u32,
+    /// Number of frames between two generations.
+    timestep: u32,
+    frame: u32,
+    paused: bool,
+    step_requested: bool,
+    seed: u32,
+    /// A new grid to upload on the next frame.
+    pending_cells: Option<Vec<u32>>,
+    /// Cells painted with the mouse since the last frame, as `(index, state)`.
+    pending_edits: Vec<(u32, u32)>,
+    pattern_path: String,
+    status: String,
+    /// Most cells the cell buffers can hold on this device.
+    max_cells: u64,
+}
+
+impl GameOfLife {
+    pub fn new_with_render_state(wgpu_render_state: &RenderState) -> Option<Self> {
+        let device = &wgpu_render_state.device;
+        let grid_size = (DEFAULT_GRID_SIZE, DEFAULT_GRID_SIZE);
+        let workgroup_size = WORKGROUP_SIZES[1];
+
+        let simulation = LifeSimulation::new(
+            device,
+            grid_size,
+            workgroup_size,
+            &random_cells(grid_size.0, grid_size.1, 1),
+        );
+
+        // Create the square vertex buffer; the cell state is read per instance from the cell buffer
+        let (square_buffer, vertex_buffer_layouts) = {
+            let square_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("GameOfLife Square Vertex Buffer"),
+                contents: bytemuck::cast_slice(SQUARE_VERTICES),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+
+            let vertex_buffer_layouts = [
+                wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<u32>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &[wgpu::VertexAttribute {
+                        format: wgpu::VertexFormat::Uint32,
+                        offset: 0,
+                        shader_location: 0,
+                    }],
+                },
+                wgpu::VertexBufferLayout {
+                    array_stride: 2 * std::mem::size_of::<u32>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &[wgpu::VertexAttribute {
+                        format: wgpu::VertexFormat::Uint32x2,
+                        offset: 0,
+                        shader_location: 1,
+                    }],
+                },
+            ];
+
+            (square_buffer, vertex_buffer_layouts)
+        };
+
+        let (size_bind_group_layout, size_bind_group) = {
+            let bind_group_layout =
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("GameOfLife Size Bind Group Layout"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }],
+                });
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("GameOfLife Size Bind Group"),
+                layout: &bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: simulation.size_buffer().as_entire_binding(),
+                }],
+            });
+
+            (bind_group_layout, bind_group)
+        };
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("GameOfLife Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./shader.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("GameOfLife Pipeline Layout"),
+            bind_group_layouts: &[&size_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("GameOfLife Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &vertex_buffer_layouts,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu_render_state.target_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        // Because the graphics pipeline must have the same lifetime as the egui render pass,
+        // instead of storing the pipeline in our struct, we insert it into the
+        // `paint_callback_resources` type map, which is stored alongside the render pass.
+        wgpu_render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(AppRenderResources {
+                simulation,
+                pipeline,
+                square_buffer,
+                size_bind_group,
+            });
+
+        Some(Self {
+            grid_size,
+            workgroup_size,
+            timestep: 4,
+            frame: 0,
+            paused: false,
+            step_requested: false,
+            seed: 1,
+            pending_cells: None,
+            pending_edits: Vec::new(),
+            pattern_path: String::new(),
+            status: String::new(),
+            max_cells: {
+                let limits = device.limits();
+                let max_bytes = limits
+                    .max_buffer_size
+                    .min(limits.max_storage_buffer_binding_size.into());
+                max_bytes / std::mem::size_of::<u32>() as u64
+            },
+        })
+    }
+
+    fn load_pattern(&mut self, text: &str) {
+        // Leave room for a dead border around the pattern
+        match Pattern::parse(text, MAX_GRID_SIZE - 2) {
+            Ok(pattern) => {
+                // Grow the grid if the pattern does not fit
+                let grid_size = (
+                    self.grid_size.0.max(pattern.width + 2),
+                    self.grid_size.1.max(pattern.height + 2),
+                );
+                if u64::from(grid_size.0) * u64::from(grid_size.1) > self.max_cells {
+                    self.status = format!(
+                        "Failed to load pattern: a {}x{} grid has more cells than the {} buffers hold on this device",
+                        grid_size.0, grid_size.1, self.max_cells
+                    );
+                    return;
+                }
+                self.grid_size = grid_size;
+                self.pending_cells = Some(pattern.to_cells(self.grid_size.0, self.grid_size.1));
+                self.status = format!(
+                    "Loaded {} ({}x{}, {} cells)",
+                    pattern.name.as_deref().unwrap_or("pattern"),
+                    pattern.width,
+                    pattern.height,
+                    pattern.cells.len()
+                );
+            }
+            Err(err) => self.status = format!("Failed to load pattern: {err}"),
+        }
+    }
+}
+
+impl eframe::App for GameOfLife {
+    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        // Patterns can also be dropped onto the window
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        for file in dropped {
+            let text = match (&file.bytes, &file.path) {
+                (Some(bytes), _) => Ok(String::from_utf8_lossy(bytes).into_owned()),
+                (None, Some(path)) => std::fs::read_to_string(path).map_err(|e| e.to_string()),
+                (None, None) => continue,
+            };
+            match text {
+                Ok(text) => self.load_pattern(&text),
+                Err(err) => self.status = format!("Failed to read {}: {err}", file.name),
+            }
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                        self.custom_painting(ui);
+                    });
+                    ui.vertical(|ui| self.controls(ui));
+                });
+            });
+        });
+        // This is needed to keep the simulation running. It tells eframe to call update() again on the next event loop iteration.
+        ctx.request_repaint();
+    }
+}
+
+impl GameOfLife {
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("game_of_life_controls")
+            .num_columns(2)
+            .show(ui, |ui| {
+                let mut grid_size = self.grid_size;
+                ui.label("width");
+                ui.add(egui::Slider::new(&mut grid_size.0, 16..=MAX_GRID_SIZE).logarithmic(true));
+                ui.end_row();
+                ui.label("height");
+                ui.add(egui::Slider::new(&mut grid_size.1, 16..=MAX_GRID_SIZE).logarithmic(true));
+                ui.end_row();
+                if grid_size != self.grid_size {
+                    self.grid_size = grid_size;
+                    self.pending_cells = Some(random_cells(grid_size.0, grid_size.1, self.seed));
+                }
+
+                ui.label("workgroup size");
+                ui.horizontal(|ui| {
+                    for size in WORKGROUP_SIZES {
+                        ui.selectable_value(
+                            &mut self.workgroup_size,
+                            size,
+                            format!("{size}x{size}"),
+                        );
+                    }
+                });
+                ui.end_row();
+                ui.label("timestep");
+                ui.add(egui::Slider::new(&mut self.timestep, 1..=60).text("frames"));
+                ui.end_row();
+            });
+
+        ui.horizontal(|ui| {
+            let label = if self.paused { "Resume" } else { "Pause" };
+            if ui.button(label).clicked() {
+                self.paused = !self.paused;
+            }
+            if ui
+                .add_enabled(self.paused, egui::Button::new("Step"))
+                .clicked()
+            {
+                self.step_requested = true;
+            }
+            if ui.button("Randomize").clicked() {
+                self.seed = self.seed.wrapping_add(1);
+                self.pending_cells =
+                    Some(random_cells(self.grid_size.0, self.grid_size.1, self.seed));
+            }
+            if ui.button("Clear").clicked() {
+                self.pending_cells = Some(vec![0; (self.grid_size.0 * self.grid_size.1) as usize]);
+            }
+        });
+
+        ui.separator();
+        ui.label("Patterns");
+        ui.horizontal_wrapped(|ui| {
+            for (name, rle) in PATTERNS {
+                if ui.button(name).clicked() {
+                    self.load_pattern(rle);
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.pattern_path)
+                .on_hover_text("Path to an .rle file");
+            if ui.button("Load").clicked() {
+                match std::fs::read_to_string(&self.pattern_path) {
+                    Ok(text) => self.load_pattern(&text),
+                    Err(err) => {
+                        self.status = format!("Failed to read {}: {err}", self.pattern_path)
+                    }
+                }
+            }
+        });
+        ui.label("Drop an .rle file onto the window to load it.");
+        ui.label("Left drag to draw cells, right drag to erase them.");
+        if !self.status.is_empty() {
+            ui.label(&self.status);
+        }
+    }
+
+    fn custom_painting(&mut self, ui: &mut egui::Ui) {
+        let (rect, response) = ui.allocate_exact_size(
+            egui::Vec2::new(CANVAS.0, CANVAS.1),
+            egui::Sense::click_and_drag(),
+        );
+
+        if let Some(pos) = response.interact_pointer_pos() {
+            let state = if response.dragged_by(egui::PointerButton::Secondary)
+                || response.clicked_by(egui::PointerButton::Secondary)
+            {
+                Some(0)
+            } else if response.dragged_by(egui::PointerButton::Primary)
+                || response.clicked_by(egui::PointerButton::Primary)
+            {
+                Some(1)
+            } else {
+                None
+            };
+
+            if let (Some(state), true) = (state, rect.contains(pos)) {
+                let uv = (pos - rect.min) / rect.size();
+                let x = ((uv.x * self.grid_size.0 as f32) as u32).min(self.grid_size.0 - 1);
+                let y = ((uv.y * self.grid_size.1 as f32) as u32).min(self.grid_size.1 - 1);
+                self.pending_edits.push((y * self.grid_size.0 + x, state));
+            }
+        }
+
+        let mut steps = 0;
+        if self.paused {
+            steps += u32::from(std::mem::take(&mut self.step_requested));
+        } else {
+            self.frame += 1;
+            if self.frame >= self.timestep {
+                self.frame = 0;
+                steps += 1;
+            }
+        }
+
+        ui.painter().add(paint_callback(
+            rect,
+            CustomPaintCallback {
+                grid_size: self.grid_size,
+                workgroup_size: self.workgroup_size,
+                steps,
+                cells: self.pending_cells.take(),
+                edits: std::mem::take(&mut self.pending_edits),
+            },
+        ));
+    }
+}
+
+struct CustomPaintCallback {
+    grid_size: (u32, u32),
+    workgroup_size: u32,
+    steps: u32,
+    cells: Option<Vec<u32>>,
+    edits: Vec<(u32, u32)>,
+}
+
+impl egui_wgpu::CallbackTrait for CustomPaintCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let profiler = GpuProfiler::get(callback_resources);
+        let stats = FrameStats::get(callback_resources);
+        let resources: &mut AppRenderResources = callback_resources.get_mut().unwrap();
+        let simulation = &mut resources.simulation;
+
+        if simulation.workgroup_size() != self.workgroup_size {
+            simulation.set_workgroup_size(device, self.workgroup_size);
+        }
+        if let Some(cells) = &self.cells {
+            simulation.reset(device, queue, &stats, self.grid_size, cells);
+        }
+        simulation.write_cells(queue, &stats, &self.edits);
+        for _ in 0..self.steps {
+            simulation.step(egui_encoder, &profiler);
+        }
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: eframe::egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
+        let resources: &AppRenderResources = callback_resources.get().unwrap();
+        let (width, height) = resources.simulation.size();
+        render_pass.set_pipeline(&resources.pipeline);
+        render_pass.set_bind_group(0, &resources.size_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, resources.simulation.current_buffer().slice(..));
+        render_pass.set_vertex_buffer(1, resources.square_buffer.slice(..));
+        render_pass.draw(0..4, 0..width * height);
+    }
+}
+
+struct AppRenderResources {
+    pub simulation: LifeSimulation,
+    pub pipeline: wgpu::RenderPipeline,
+    pub square_buffer: wgpu::Buffer,
+    pub size_bind_group: wgpu::BindGroup,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless;
+
+    /// CPU reference implementation of one generation of `compute.wgsl`.
+    fn cpu_step(width: u32, height: u32, cells: &[u32]) -> Vec<u32> {
+        let cell = |x: u32, y: u32| cells[((y % height) * width + (x % width)) as usize];
+
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let (left, right) = (x + width - 1, x + 1);
+                let (up, down) = (y + height - 1, y + 1);
+                let n = cell(left, up)
+                    + cell(x, up)
+                    + cell(right, up)
+                    + cell(left, y)
+                    + cell(right, y)
+                    + cell(left, down)
+                    + cell(x, down)
+                    + cell(right, down);
+                u32::from(n == 3 || (n == 2 && cell(x, y) == 1))
+            })
+            .collect()
+    }
+
+    fn pattern(name: &str, width: u32, height: u32) -> Vec<u32> {
+        let (_, rle) = PATTERNS.iter().find(|(n, _)| *n == name).unwrap();
+        Pattern::parse(rle, MAX_GRID_SIZE)
+            .unwrap()
+            .to_cells(width, height)
+    }
+
+    fn cpu_generations(width: u32, height: u32, cells: &[u32], generations: usize) -> Vec<u32> {
+        (0..generations).fold(cells.to_vec(), |cells, _| cpu_step(width, height, &cells))
+    }
+
+    #[test]
+    fn cpu_reference_oscillators_and_gliders() {
+        let blinker = pattern("Blinker", 8, 8);
+        assert_ne!(cpu_generations(8, 8, &blinker, 1), blinker);
+        assert_eq!(cpu_generations(8, 8, &blinker, 2), blinker);
+
+        let pulsar = pattern("Pulsar", 17, 17);
+        assert_ne!(cpu_generations(17, 17, &pulsar, 1), pulsar);
+        assert_eq!(cpu_generations(17, 17, &pulsar, 3), pulsar);
+
+        // A glider moves one cell down and right every 4 generations, wrapping around the grid
+        let glider = pattern("Glider", 16, 16);
+        let moved = cpu_generations(16, 16, &glider, 4 * 16);
+        assert_eq!(moved, glider);
+        let shifted: Vec<u32> = (0..16 * 16)
+            .map(|i| glider[(((i / 16 + 15) % 16) * 16 + (i % 16 + 15) % 16) as usize])
+            .collect();
+        assert_eq!(cpu_generations(16, 16, &glider, 4), shifted);
+    }
+
+    #[test]
+    fn gpu_generations_match_cpu_reference() {
+        let Some((device, queue)) = headless::request_device() else {
+            eprintln!("no wgpu adapter available, skipping");
+            return;
+        };
+
+        const GENERATIONS: usize = 12;
+        let (width, height) = (37, 29);
+        let grids = [
+            random_cells(width, height, 7),
+            pattern("Glider", width, height),
+            pattern("Pulsar", width, height),
+            pattern("Gosper glider gun", width, height),
+        ];
+
+        for workgroup_size in WORKGROUP_SIZES {
+            for cells in &grids {
+                let mut simulation =
+                    LifeSimulation::new(&device, (width, height), workgroup_size, cells);
+                let mut encoder =
+                    device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+                for _ in 0..GENERATIONS {
+                    simulation.step(&mut encoder, &GpuProfiler::default());
+                }
+                queue.submit(Some(encoder.finish()));
+
+                let bytes = headless::read_buffer(
+                    &device,
+                    &queue,
+                    simulation.current_buffer(),
+                    std::mem::size_of_val(cells.as_slice()) as wgpu::BufferAddress,
+                );
+                let actual: &[u32] = bytemuck::cast_slice(&bytes);
+                let expected = cpu_generations(width, height, cells, GENERATIONS);
+                assert_eq!(actual, expected, "workgroup size {workgroup_size}");
+            }
+        }
+    }
+}
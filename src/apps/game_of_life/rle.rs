@@ -0,0 +1,146 @@
+/// A Game of Life pattern decoded from the run length encoded (RLE) format.
+/// See <https://conwaylife.com/wiki/Run_Length_Encoded>.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pattern {
+    pub name: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    /// Coordinates of the live cells, `(column, row)` with the origin at the top left.
+    pub cells: Vec<(u32, u32)>,
+}
+
+impl Pattern {
+    /// Parse a pattern from RLE text. Any state other than `b` is treated as alive, so
+    /// multi-state patterns degrade to their live cells. Patterns whose header is wider or
+    /// taller than `max_size` are rejected before their cells are decoded.
+    pub fn parse(text: &str, max_size: u32) -> Result<Self, String> {
+        let mut name = None;
+        let mut header = None;
+        let mut data = String::new();
+
+        for line in text.lines().map(str::trim) {
+            if let Some(comment) = line.strip_prefix('#') {
+                if let Some(n) = comment.strip_prefix('N') {
+                    name = Some(n.trim().to_string());
+                }
+            } else if header.is_none() && line.starts_with('x') {
+                header = Some(Self::parse_header(line)?);
+            } else {
+                data.push_str(line);
+            }
+        }
+
+        let (width, height) = header.ok_or("missing `x = .., y = ..` header")?;
+        if width > max_size || height > max_size {
+            return Err(format!(
+                "the pattern is {width}x{height}, larger than the maximum of {max_size}x{max_size}"
+            ));
+        }
+        // Runs are checked against the header, which also bounds the number of cells
+        let end_of_run = |start: u32, count: u32, size: u32, axis: &str| {
+            start
+                .checked_add(count)
+                .filter(|&end| end <= size)
+                .ok_or_else(|| format!("a run goes past the header {axis} of {size}"))
+        };
+
+        let mut cells = Vec::new();
+        let (mut x, mut y) = (0u32, 0u32);
+        let mut run: Option<u32> = None;
+        for c in data.chars() {
+            match c {
+                '0'..='9' => {
+                    let digit = c.to_digit(10).unwrap();
+                    run = Some(
+                        run.unwrap_or(0)
+                            .checked_mul(10)
+                            .and_then(|run| run.checked_add(digit))
+                            .ok_or("run length overflows")?,
+                    );
+                }
+                '!' => break,
+                '$' => {
+                    // Trailing empty rows may end past the last row, as long as no cell does
+                    y = y.saturating_add(run.take().unwrap_or(1));
+                    x = 0;
+                }
+                'b' | '.' => x = end_of_run(x, run.take().unwrap_or(1), width, "x")?,
+                c if c.is_ascii_alphabetic() => {
+                    end_of_run(y, 1, height, "y")?;
+                    let end = end_of_run(x, run.take().unwrap_or(1), width, "x")?;
+                    cells.extend((x..end).map(|x| (x, y)));
+                    x = end;
+                }
+                c if c.is_whitespace() => {}
+                c => return Err(format!("unexpected character `{c}` in pattern data")),
+            }
+        }
+
+        Ok(Self {
+            name,
+            width,
+            height,
+            cells,
+        })
+    }
+
+    fn parse_header(line: &str) -> Result<(u32, u32), String> {
+        let mut width = None;
+        let mut height = None;
+        for field in line.split(',') {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| format!("malformed header field `{field}`"))?;
+            let parse = || {
+                value
+                    .trim()
+                    .parse::<u32>()
+                    .map_err(|e| format!("invalid header value `{}`: {e}", value.trim()))
+            };
+            match key.trim() {
+                "x" => width = Some(parse()?),
+                "y" => height = Some(parse()?),
+                _ => {}
+            }
+        }
+        Ok((
+            width.ok_or("missing width")?,
+            height.ok_or("missing height")?,
+        ))
+    }
+
+    /// Draw the pattern into a `grid_width` x `grid_height` grid of cells, centered and
+    /// wrapping around the edges like the simulation does.
+    pub fn to_cells(&self, grid_width: u32, grid_height: u32) -> Vec<u32> {
+        let mut grid = vec![0u32; (grid_width * grid_height) as usize];
+        let offset_x = grid_width.saturating_sub(self.width) / 2;
+        let offset_y = grid_height.saturating_sub(self.height) / 2;
+        for (x, y) in &self.cells {
+            let x = (x + offset_x) % grid_width;
+            let y = (y + offset_y) % grid_height;
+            grid[(y * grid_width + x) as usize] = 1;
+        }
+        grid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_are_checked_against_the_header() {
+        let pattern = Pattern::parse("#N Test\nx = 3, y = 2\n2bo$3o!", 16).unwrap();
+        assert_eq!(pattern.name.as_deref(), Some("Test"));
+        assert_eq!((pattern.width, pattern.height), (3, 2));
+        assert_eq!(pattern.cells, [(2, 0), (0, 1), (1, 1), (2, 1)]);
+
+        // Wider or taller than the header
+        assert!(Pattern::parse("x = 3, y = 2\n4o!", 16).is_err());
+        assert!(Pattern::parse("x = 3, y = 2\no2$o!", 16).is_err());
+        assert!(Pattern::parse("x = 3, y = 2\n99999999o!", 16).is_err());
+        // Runs that overflow, and headers larger than the maximum
+        assert!(Pattern::parse("x = 3, y = 2\n99999999999o!", 16).is_err());
+        assert!(Pattern::parse("x = 17, y = 2\no!", 16).is_err());
+    }
+}
@@ -0,0 +1,31 @@
+struct VertexInput {
+    @builtin(instance_index) instance: u32,
+    @location(0) cell: u32,
+    @location(1) pos: vec2u,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) cell: f32,
+}
+
+@binding(0) @group(0) var<uniform> size: vec2u;
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    let w = size.x;
+    let h = size.y;
+    let x = f32(in.instance % w + in.pos.x) / f32(w);
+    let y = f32(in.instance / w + in.pos.y) / f32(h);
+
+    var output: VertexOutput;
+    // Row 0 is at the top, so patterns appear the way they are written
+    output.position = vec4(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    output.cell = f32(in.cell);
+    return output;
+}
+
+@fragment
+fn fs_main(@location(0) cell: f32) -> @location(0) vec4f {
+    return vec4(cell, cell, cell, 1.0);
+}
@@ -3,6 +3,8 @@ use eframe::{
     egui_wgpu::{self, RenderState},
 };
 
+use crate::stats::{paint_callback, FrameStats};
+
 const CANVAS_SIZE: f32 = 600.0;
 
 pub struct HelloTriangle();
@@ -82,10 +84,8 @@ impl HelloTriangle {
     fn custom_painting(&self, ui: &mut egui::Ui) {
         let (rect, _response) =
             ui.allocate_exact_size(egui::Vec2::splat(CANVAS_SIZE), egui::Sense::click());
-        ui.painter().add(egui_wgpu::Callback::new_paint_callback(
-            rect,
-            CustomPaintCallback(),
-        ));
+        ui.painter()
+            .add(paint_callback(rect, CustomPaintCallback()));
     }
 }
 
@@ -109,6 +109,7 @@ impl egui_wgpu::CallbackTrait for CustomPaintCallback {
         render_pass: &mut wgpu::RenderPass<'a>,
         callback_resources: &'a egui_wgpu::CallbackResources,
     ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
         let resources: &AppRenderResources = callback_resources.get().unwrap();
         render_pass.set_pipeline(&resources.pipeline);
         render_pass.draw(0..3, 0..1);
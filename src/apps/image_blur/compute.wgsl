@@ -0,0 +1,84 @@
+struct Params {
+    // Number of taps, always odd
+    filter_size: u32,
+    // Output texels per workgroup along the blur direction
+    block_size: u32,
+    // Gaussian weights rather than a box filter
+    gaussian: u32,
+}
+
+struct Direction {
+    // 0 blurs along rows, 1 along columns
+    flip: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(1) @binding(0) var input_texture: texture_2d<f32>;
+@group(1) @binding(1) var output_texture: texture_storage_2d<rgba8unorm, write>;
+@group(1) @binding(2) var<uniform> direction: Direction;
+
+const TILE_SIZE = 128u;
+// Rows blurred by every workgroup
+const BATCH = 4u;
+
+// Each workgroup loads a tile of 4 rows of 128 texels, of which the `filter_size - 1` texels
+// at the borders only feed their neighbours
+var<workgroup> tile: array<array<vec4f, TILE_SIZE>, BATCH>;
+
+fn weight(tap: u32) -> f32 {
+    if (params.gaussian == 0u) {
+        return 1.0;
+    }
+    // The filter spans about 3 standard deviations on each side
+    let sigma = f32(params.filter_size) / 6.0;
+    let x = f32(tap) - f32(params.filter_size / 2u);
+    return exp(-x * x / (2.0 * sigma * sigma));
+}
+
+@compute @workgroup_size(32, 1, 1)
+fn main(
+    @builtin(workgroup_id) workgroup_id: vec3u,
+    @builtin(local_invocation_id) local_id: vec3u,
+) {
+    let filter_offset = params.filter_size / 2u;
+    let dimensions = vec2i(textureDimensions(input_texture));
+    // Coordinates along and across the blur direction
+    let base_index = vec2i(workgroup_id.xy * vec2(params.block_size, BATCH) + local_id.xy * vec2(BATCH, 1u))
+        - vec2(i32(filter_offset), 0);
+
+    for (var r = 0u; r < BATCH; r++) {
+        for (var c = 0u; c < BATCH; c++) {
+            var load_index = base_index + vec2(i32(c), i32(r));
+            if (direction.flip != 0u) {
+                load_index = load_index.yx;
+            }
+            // Texels outside of the image repeat the edge
+            load_index = clamp(load_index, vec2(0), dimensions - 1);
+            tile[r][BATCH * local_id.x + c] = textureLoad(input_texture, load_index, 0);
+        }
+    }
+
+    workgroupBarrier();
+
+    for (var r = 0u; r < BATCH; r++) {
+        for (var c = 0u; c < BATCH; c++) {
+            var write_index = base_index + vec2(i32(c), i32(r));
+            if (direction.flip != 0u) {
+                write_index = write_index.yx;
+            }
+
+            let center = BATCH * local_id.x + c;
+            if (center >= filter_offset && center < TILE_SIZE - filter_offset
+                && all(write_index < dimensions)) {
+                var sum = vec4(0.0);
+                var total_weight = 0.0;
+                for (var tap = 0u; tap < params.filter_size; tap++) {
+                    let w = weight(tap);
+                    sum += tile[r][center + tap - filter_offset] * w;
+                    total_weight += w;
+                }
+                textureStore(output_texture, write_index, sum / total_weight);
+            }
+        }
+    }
+}
@@ -0,0 +1,711 @@
+use eframe::{
+    egui,
+    egui_wgpu::{self, RenderState},
+};
+use image::RgbaImage;
+use std::sync::Arc;
+use wgpu::util::DeviceExt;
+
+use crate::profiler::GpuProfiler;
+use crate::stats::{paint_callback, FrameStats};
+
+const CANVAS: (f32, f32) = (600.0, 600.0);
+/// Must match `TILE_SIZE` in `compute.wgsl`.
+const TILE_SIZE: u32 = 128;
+/// Must match `BATCH` in `compute.wgsl`.
+const BATCH: u32 = 4;
+/// The largest filter that still leaves most of a tile to write.
+const MAX_FILTER_SIZE: u32 = 33;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FilterKind {
+    Box,
+    Gaussian,
+}
+
+impl FilterKind {
+    const ALL: [Self; 2] = [Self::Box, Self::Gaussian];
+}
+
+impl std::fmt::Display for FilterKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Box => "Box",
+            Self::Gaussian => "Gaussian",
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BlurSettings {
+    pub filter: FilterKind,
+    /// Number of taps along each direction, odd and at most `MAX_FILTER_SIZE`.
+    pub filter_size: u32,
+    /// How many times the horizontal and vertical passes are applied.
+    pub iterations: u32,
+}
+
+impl Default for BlurSettings {
+    fn default() -> Self {
+        Self {
+            filter: FilterKind::Gaussian,
+            filter_size: 15,
+            iterations: 2,
+        }
+    }
+}
+
+impl BlurSettings {
+    fn block_size(&self) -> u32 {
+        TILE_SIZE - (self.filter_size - 1)
+    }
+}
+
+/// Laid out to match `Params` in `compute.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Params {
+    filter_size: u32,
+    block_size: u32,
+    gaussian: u32,
+    _padding: u32,
+}
+
+/// Separable blur of an image with compute shaders, ping-ponging between two textures.
+pub struct BlurFilter {
+    pipeline: wgpu::ComputePipeline,
+    params_buffer: wgpu::Buffer,
+    params_bind_group: wgpu::BindGroup,
+    textures: [wgpu::Texture; 2],
+    // Source to texture 0 along rows, texture 0 to 1 along columns, texture 1 to 0 along rows
+    pass_bind_groups: [wgpu::BindGroup; 3],
+}
+
+impl BlurFilter {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, image: &RgbaImage) -> Self {
+        let size = wgpu::Extent3d {
+            width: image.width(),
+            height: image.height(),
+            depth_or_array_layers: 1,
+        };
+
+        // Storage textures can't be sRGB, so the blur averages the encoded values
+        let source_texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("ImageBlur Source Texture"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            image,
+        );
+
+        let textures = [0, 1].map(|i| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(&format!("ImageBlur Texture {i}")),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::STORAGE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            })
+        });
+
+        let params_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("ImageBlur Params Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+
+        let pass_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("ImageBlur Pass Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: wgpu::TextureFormat::Rgba8Unorm,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("ImageBlur Params Buffer"),
+            size: std::mem::size_of::<Params>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let params_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ImageBlur Params Bind Group"),
+            layout: &params_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            }],
+        });
+
+        // Padded to 16 bytes, the smallest uniform binding every backend accepts
+        let direction_buffers = [0u32, 1].map(|flip| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("ImageBlur Direction Buffer {flip}")),
+                contents: bytemuck::cast_slice(&[flip, 0, 0, 0]),
+                usage: wgpu::BufferUsages::UNIFORM,
+            })
+        });
+
+        let source_view = source_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let views = textures
+            .each_ref()
+            .map(|texture| texture.create_view(&wgpu::TextureViewDescriptor::default()));
+        let passes = [
+            (&source_view, &views[0], 0),
+            (&views[0], &views[1], 1),
+            (&views[1], &views[0], 0),
+        ];
+        let pass_bind_groups = passes.map(|(input, output, flip)| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("ImageBlur Pass Bind Group"),
+                layout: &pass_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(input),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(output),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: direction_buffers[flip].as_entire_binding(),
+                    },
+                ],
+            })
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("ImageBlur Compute Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./compute.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("ImageBlur Compute Pipeline Layout"),
+            bind_group_layouts: &[&params_bind_group_layout, &pass_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("ImageBlur Compute Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "main",
+        });
+
+        Self {
+            pipeline,
+            params_buffer,
+            params_bind_group,
+            textures,
+            pass_bind_groups,
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.textures[0].width(), self.textures[0].height())
+    }
+
+    /// The texture holding the result once `run` has been submitted.
+    pub fn output_texture(&self) -> &wgpu::Texture {
+        &self.textures[1]
+    }
+
+    pub fn run(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        settings: &BlurSettings,
+        profiler: &GpuProfiler,
+        stats: &FrameStats,
+    ) {
+        let block_size = settings.block_size();
+        stats.write_buffer(
+            queue,
+            &self.params_buffer,
+            0,
+            bytemuck::bytes_of(&Params {
+                filter_size: settings.filter_size,
+                block_size,
+                gaussian: (settings.filter == FilterKind::Gaussian) as u32,
+                _padding: 0,
+            }),
+        );
+
+        let (width, height) = self.size();
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("ImageBlur Compute Pass"),
+            timestamp_writes: profiler.compute_pass_writes("ImageBlur Compute Pass"),
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.params_bind_group, &[]);
+
+        for iteration in 0..settings.iterations {
+            let horizontal = if iteration == 0 { 0 } else { 2 };
+            pass.set_bind_group(1, &self.pass_bind_groups[horizontal], &[]);
+            pass.dispatch_workgroups(width.div_ceil(block_size), height.div_ceil(BATCH), 1);
+
+            pass.set_bind_group(1, &self.pass_bind_groups[1], &[]);
+            pass.dispatch_workgroups(height.div_ceil(block_size), width.div_ceil(BATCH), 1);
+        }
+    }
+}
+
+pub struct ImageBlur {
+    settings: BlurSettings,
+    image_size: (u32, u32),
+    max_dimension: u32,
+    pending_image: Option<Arc<RgbaImage>>,
+    status: String,
+}
+
+impl ImageBlur {
+    pub fn new_with_render_state(wgpu_render_state: &RenderState) -> Option<Self> {
+        let device = &wgpu_render_state.device;
+        let queue = &wgpu_render_state.queue;
+
+        let image =
+            image::load_from_memory(include_bytes!("../textured_cube/assets/happy-tree.png"))
+                .unwrap()
+                .to_rgba8();
+        let filter = BlurFilter::new(device, queue, &image);
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("ImageBlur Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("ImageBlur Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("ImageBlur Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./shader.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("ImageBlur Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("ImageBlur Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: wgpu_render_state.target_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let bind_group = create_bind_group(device, &bind_group_layout, &sampler, &filter);
+        let image_size = filter.size();
+
+        // Because the graphics pipeline must have the same lifetime as the egui render pass,
+        // instead of storing the pipeline in our struct, we insert it into the
+        // `paint_callback_resources` type map, which is stored alongside the render pass.
+        wgpu_render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(AppRenderResources {
+                filter,
+                pipeline,
+                bind_group_layout,
+                sampler,
+                bind_group,
+                blurred_with: None,
+            });
+
+        Some(Self {
+            settings: BlurSettings::default(),
+            image_size,
+            max_dimension: device.limits().max_texture_dimension_2d,
+            pending_image: None,
+            status: String::new(),
+        })
+    }
+
+    fn load_image(&mut self, bytes: &[u8], name: &str) {
+        match image::load_from_memory(bytes) {
+            Ok(image) => {
+                let (width, height) = (image.width(), image.height());
+                if width.max(height) > self.max_dimension {
+                    self.status = format!(
+                        "{name} is {width}x{height}, but 2D textures are limited to {} per side",
+                        self.max_dimension
+                    );
+                    return;
+                }
+                let image = image.to_rgba8();
+                self.status = format!("Loaded {name} ({width}x{height})");
+                self.image_size = image.dimensions();
+                self.pending_image = Some(Arc::new(image));
+            }
+            Err(err) => self.status = format!("Failed to load {name}: {err}"),
+        }
+    }
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    filter: &BlurFilter,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("ImageBlur Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(
+                    &filter
+                        .output_texture()
+                        .create_view(&wgpu::TextureViewDescriptor::default()),
+                ),
+            },
+        ],
+    })
+}
+
+impl eframe::App for ImageBlur {
+    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        // Images can also be dropped onto the window
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        for file in dropped {
+            let bytes = match (&file.bytes, &file.path) {
+                (Some(bytes), _) => Ok(bytes.to_vec()),
+                (None, Some(path)) => std::fs::read(path).map_err(|e| e.to_string()),
+                (None, None) => continue,
+            };
+            match bytes {
+                Ok(bytes) => self.load_image(&bytes, &file.name),
+                Err(err) => self.status = format!("Failed to read {}: {err}", file.name),
+            }
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                        self.custom_painting(ui);
+                    });
+                    ui.vertical(|ui| self.controls(ui));
+                });
+            });
+        });
+    }
+}
+
+impl ImageBlur {
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("image_blur_controls")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("filter");
+                egui::ComboBox::from_id_source("image_blur_filter")
+                    .selected_text(self.settings.filter.to_string())
+                    .show_ui(ui, |ui| {
+                        for filter in FilterKind::ALL {
+                            ui.selectable_value(
+                                &mut self.settings.filter,
+                                filter,
+                                filter.to_string(),
+                            );
+                        }
+                    });
+                ui.end_row();
+                ui.label("filter size");
+                ui.add(
+                    egui::Slider::new(&mut self.settings.filter_size, 1..=MAX_FILTER_SIZE)
+                        .step_by(2.0),
+                );
+                ui.end_row();
+                ui.label("iterations");
+                ui.add(egui::Slider::new(&mut self.settings.iterations, 1..=10));
+                ui.end_row();
+            });
+        ui.label("Drop an image onto the window to blur it instead.");
+        if !self.status.is_empty() {
+            ui.label(&self.status);
+        }
+    }
+
+    fn custom_painting(&mut self, ui: &mut egui::Ui) {
+        // Fit the image in the canvas, keeping its aspect ratio
+        let (width, height) = (self.image_size.0 as f32, self.image_size.1 as f32);
+        let scale = (CANVAS.0 / width).min(CANVAS.1 / height);
+        let (rect, _response) = ui.allocate_exact_size(
+            egui::Vec2::new(width * scale, height * scale),
+            egui::Sense::hover(),
+        );
+
+        ui.painter().add(paint_callback(
+            rect,
+            CustomPaintCallback {
+                settings: self.settings,
+                image: self.pending_image.take(),
+            },
+        ));
+    }
+}
+
+struct CustomPaintCallback {
+    settings: BlurSettings,
+    image: Option<Arc<RgbaImage>>,
+}
+
+impl egui_wgpu::CallbackTrait for CustomPaintCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let profiler = GpuProfiler::get(callback_resources);
+        let stats = FrameStats::get(callback_resources);
+        let resources: &mut AppRenderResources = callback_resources.get_mut().unwrap();
+        if let Some(image) = &self.image {
+            resources.filter = BlurFilter::new(device, queue, image);
+            resources.bind_group = create_bind_group(
+                device,
+                &resources.bind_group_layout,
+                &resources.sampler,
+                &resources.filter,
+            );
+            resources.blurred_with = None;
+        }
+
+        // The image only needs blurring again when something changed
+        if resources.blurred_with != Some(self.settings) {
+            resources
+                .filter
+                .run(queue, egui_encoder, &self.settings, &profiler, &stats);
+            resources.blurred_with = Some(self.settings);
+        }
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: eframe::egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
+        let resources: &AppRenderResources = callback_resources.get().unwrap();
+        render_pass.set_pipeline(&resources.pipeline);
+        render_pass.set_bind_group(0, &resources.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+struct AppRenderResources {
+    pub filter: BlurFilter,
+    pub pipeline: wgpu::RenderPipeline,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub sampler: wgpu::Sampler,
+    pub bind_group: wgpu::BindGroup,
+    pub blurred_with: Option<BlurSettings>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless;
+
+    /// The weights of `weight` in `compute.wgsl`.
+    fn filter_weights(settings: &BlurSettings) -> Vec<f32> {
+        let sigma = settings.filter_size as f32 / 6.0;
+        (0..settings.filter_size)
+            .map(|tap| match settings.filter {
+                FilterKind::Box => 1.0,
+                FilterKind::Gaussian => {
+                    let x = tap as f32 - (settings.filter_size / 2) as f32;
+                    (-x * x / (2.0 * sigma * sigma)).exp()
+                }
+            })
+            .collect()
+    }
+
+    /// CPU reference implementation of `compute.wgsl`, rounding to 8 bits after every pass like the
+    /// intermediate textures do.
+    fn cpu_blur(image: &RgbaImage, settings: &BlurSettings) -> RgbaImage {
+        let weights = filter_weights(settings);
+        let total_weight: f32 = weights.iter().sum();
+        let offset = (settings.filter_size / 2) as i32;
+        let (width, height) = image.dimensions();
+
+        let pass = |input: &RgbaImage, step: (i32, i32)| {
+            RgbaImage::from_fn(width, height, |x, y| {
+                let mut sum = [0.0f32; 4];
+                for (tap, weight) in weights.iter().enumerate() {
+                    let d = tap as i32 - offset;
+                    let sx = (x as i32 + d * step.0).clamp(0, width as i32 - 1);
+                    let sy = (y as i32 + d * step.1).clamp(0, height as i32 - 1);
+                    let texel = input.get_pixel(sx as u32, sy as u32);
+                    for (s, &c) in sum.iter_mut().zip(texel.0.iter()) {
+                        *s += c as f32 / 255.0 * weight;
+                    }
+                }
+                image::Rgba(sum.map(|s| (s / total_weight * 255.0).round() as u8))
+            })
+        };
+
+        let mut output = image.clone();
+        for _ in 0..settings.iterations {
+            output = pass(&output, (1, 0));
+            output = pass(&output, (0, 1));
+        }
+        output
+    }
+
+    #[test]
+    fn gpu_blur_matches_cpu_reference() {
+        let Some((device, queue)) = headless::request_device() else {
+            eprintln!("no wgpu adapter available, skipping");
+            return;
+        };
+
+        // Neither dimension is a multiple of the block size, and the image spans several tiles
+        let mut state = 1u32;
+        let image = RgbaImage::from_fn(192, 141, |_, _| {
+            image::Rgba([0; 4].map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state >> 24) as u8
+            }))
+        });
+        let filter = BlurFilter::new(&device, &queue, &image);
+
+        for filter_kind in FilterKind::ALL {
+            for (filter_size, iterations) in [(1, 1), (7, 1), (MAX_FILTER_SIZE, 1), (9, 3)] {
+                let settings = BlurSettings {
+                    filter: filter_kind,
+                    filter_size,
+                    iterations,
+                };
+                let mut encoder =
+                    device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+                filter.run(
+                    &queue,
+                    &mut encoder,
+                    &settings,
+                    &GpuProfiler::default(),
+                    &FrameStats::default(),
+                );
+                queue.submit(Some(encoder.finish()));
+
+                let actual = headless::read_texture(&device, &queue, filter.output_texture());
+                let expected = cpu_blur(&image, &settings);
+                // Rounding to 8 bits may differ by one step after every pass
+                let max_difference = actual
+                    .iter()
+                    .zip(expected.as_raw())
+                    .map(|(a, e)| a.abs_diff(*e))
+                    .max()
+                    .unwrap();
+                assert!(
+                    max_difference <= 1,
+                    "{settings:?} differs by up to {max_difference}"
+                );
+            }
+        }
+    }
+}
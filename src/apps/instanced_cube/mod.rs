@@ -6,7 +6,9 @@ use glam::{Mat4, Quat, Vec3};
 use std::f32::consts::PI;
 use wgpu::util::DeviceExt;
 
+use crate::debug_view::{self, DebugView, DebugViewPipelines};
 use crate::meshes::cube;
+use crate::stats::{paint_callback, FrameStats};
 
 const CANVAS: (f32, f32) = (600.0, 600.0);
 const X_COUNT: usize = 4;
@@ -18,6 +20,8 @@ const UNIFORM_BUFFER_SIZE: usize = NUM_INSTANCES * MATRIX_SIZE;
 const STEP: f32 = 4.0;
 
 static mut MVP_MATRIXS: [Mat4; NUM_INSTANCES] = [Mat4::IDENTITY; NUM_INSTANCES];
+/// Written after the model-view-projection matrices, for the normals of the debug view.
+static mut MODEL_MATRIXS: [Mat4; NUM_INSTANCES] = [Mat4::IDENTITY; NUM_INSTANCES];
 
 fn update_mvp_matrix(start_time: std::time::Instant) {
     let now = std::time::Instant::now()
@@ -41,14 +45,15 @@ fn update_mvp_matrix(start_time: std::time::Instant) {
                     1.0,
                 ),
                 Vec3::new(
-                    STEP * (x as f32 - X_COUNT as f32 / 2.0 + 0.5) as f32,
-                    STEP * (y as f32 - Y_COUNT as f32 / 2.0 + 0.5) as f32,
+                    STEP * (x as f32 - X_COUNT as f32 / 2.0 + 0.5),
+                    STEP * (y as f32 - Y_COUNT as f32 / 2.0 + 0.5),
                     0.0,
                 ),
             );
 
             unsafe {
                 MVP_MATRIXS[index] = projection_matrix * view_matrix * model_matrix;
+                MODEL_MATRIXS[index] = model_matrix;
             }
         }
     }
@@ -129,9 +134,17 @@ impl InstancedCube {
             (buffer, bind_group_layout, bind_group)
         };
 
+        let normal_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("InstancedCube Normal Buffer"),
+            contents: bytemuck::cast_slice(cube::NORMALS),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("InstancedCube Shader Module"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("./shader.wgsl").into()),
+            source: wgpu::ShaderSource::Wgsl(
+                debug_view::shader(include_str!("./shader.wgsl"), 1).into(),
+            ),
         });
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -140,13 +153,13 @@ impl InstancedCube {
             push_constant_ranges: &[],
         });
 
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        let pipeline_descriptor = wgpu::RenderPipelineDescriptor {
             label: Some("InstancedCube Render Pipeline"),
             layout: Some(&pipeline_layout),
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: "vs_main",
-                buffers: &[vertex_buffer_layout],
+                buffers: &[vertex_buffer_layout, cube::NORMAL_BUFFER_LAYOUT],
             },
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
@@ -176,7 +189,11 @@ impl InstancedCube {
                 alpha_to_coverage_enabled: false,
             },
             multiview: None,
-        });
+        };
+        let pipeline = device.create_render_pipeline(&pipeline_descriptor);
+        // The same pipeline with the fragment stage of the debug view selected in the side panel
+        let debug_view =
+            DebugViewPipelines::new(device, &pipeline_descriptor, &[&mvp_bind_group_layout]);
 
         // Because the graphics pipeline must have the same lifetime as the egui render pass,
         // instead of storing the pipeline in our struct, we insert it into the
@@ -188,7 +205,9 @@ impl InstancedCube {
             .insert(AppRenderResources {
                 start_time: std::time::Instant::now(),
                 pipeline,
+                debug_view,
                 vertex_buffer,
+                normal_buffer,
                 mvp_buffer,
                 mvp_bind_group,
             });
@@ -215,10 +234,8 @@ impl InstancedCube {
     fn custom_painting(&self, ui: &mut egui::Ui) {
         let (rect, _response) =
             ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::click());
-        ui.painter().add(egui_wgpu::Callback::new_paint_callback(
-            rect,
-            CustomPaintCallback(),
-        ));
+        ui.painter()
+            .add(paint_callback(rect, CustomPaintCallback()));
     }
 }
 
@@ -233,15 +250,26 @@ impl egui_wgpu::CallbackTrait for CustomPaintCallback {
         _egui_encoder: &mut wgpu::CommandEncoder,
         callback_resources: &mut egui_wgpu::CallbackResources,
     ) -> Vec<wgpu::CommandBuffer> {
+        let stats = FrameStats::get(callback_resources);
         let resources: &AppRenderResources = callback_resources.get().unwrap();
         update_mvp_matrix(resources.start_time);
         unsafe {
-            queue.write_buffer(
+            stats.write_buffer(
+                queue,
                 &resources.mvp_buffer,
                 0,
                 bytemuck::cast_slice(&[MVP_MATRIXS]),
             );
+            stats.write_buffer(
+                queue,
+                &resources.mvp_buffer,
+                (NUM_INSTANCES * std::mem::size_of::<Mat4>()) as wgpu::BufferAddress,
+                bytemuck::cast_slice(&[MODEL_MATRIXS]),
+            );
         }
+        resources
+            .debug_view
+            .prepare(queue, &stats, &DebugView::settings(callback_resources));
         Vec::new()
     }
 
@@ -251,10 +279,16 @@ impl egui_wgpu::CallbackTrait for CustomPaintCallback {
         render_pass: &mut wgpu::RenderPass<'a>,
         callback_resources: &'a egui_wgpu::CallbackResources,
     ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
         let resources: &AppRenderResources = callback_resources.get().unwrap();
-        render_pass.set_pipeline(&resources.pipeline);
+        resources.debug_view.set_pipeline(
+            &mut render_pass,
+            &resources.pipeline,
+            &DebugView::settings(callback_resources),
+        );
         render_pass.set_bind_group(0, &resources.mvp_bind_group, &[]);
         render_pass.set_vertex_buffer(0, resources.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, resources.normal_buffer.slice(..));
         render_pass.draw(0..cube::VERTEX_COUNT, 0..NUM_INSTANCES as u32);
     }
 }
@@ -262,7 +296,9 @@ impl egui_wgpu::CallbackTrait for CustomPaintCallback {
 struct AppRenderResources {
     pub start_time: std::time::Instant,
     pub pipeline: wgpu::RenderPipeline,
+    pub debug_view: DebugViewPipelines,
     pub vertex_buffer: wgpu::Buffer,
+    pub normal_buffer: wgpu::Buffer,
     pub mvp_buffer: wgpu::Buffer,
     pub mvp_bind_group: wgpu::BindGroup,
 }
@@ -1,18 +1,25 @@
 struct VertexInput {
+    @builtin(vertex_index) vertex_index: u32,
     @builtin(instance_index) instance_index: u32,
     @location(0) position: vec4f,
     @location(1) color: vec4f,
     @location(2) uv: vec2f,
+    @location(3) normal: vec3f,
 }
 
 struct VertexOutput {
     @builtin(position) position: vec4f,
     @location(0) uv: vec2f,
     @location(1) frag_position: vec4f,
+    // Only read by the debug view fragment shaders appended to this one
+    @location(2) barycentric: vec3f,
+    @location(3) normal: vec3f,
+    @location(4) color: vec4f,
 }
 
 struct Uniforms {
     mvpMatrixArray: array<mat4x4f, 16>,
+    modelMatrixArray: array<mat4x4f, 16>,
 }
 
 @binding(0) @group(0) var<uniform> uniforms: Uniforms;
@@ -24,6 +31,10 @@ fn vs_main(
     output.position = uniforms.mvpMatrixArray[model.instance_index] * model.position;
     output.uv = model.uv;
     output.frag_position = 0.5 * (model.position + vec4(1.0, 1.0, 1.0, 1.0));
+    output.barycentric = debug_barycentric(model.vertex_index);
+    // The model matrices only rotate and translate, so they also turn the normals to world space
+    output.normal = (uniforms.modelMatrixArray[model.instance_index] * vec4(model.normal, 0.0)).xyz;
+    output.color = model.color;
     return output;
 }
 
@@ -1,7 +1,27 @@
+pub mod a_buffer;
+pub mod animometer;
+pub mod bitonic_sort;
+pub mod blending;
+pub mod compute_boids;
+pub mod cornell;
 pub mod cubemap;
 pub mod custom3d;
+pub mod deferred_rendering;
+pub mod game_of_life;
 pub mod hello_triangle;
+pub mod image_blur;
 pub mod instanced_cube;
+pub mod multiple_canvases;
+pub mod normal_map;
+pub mod occlusion_query;
+pub mod particles;
+pub mod pipeline_statistics;
+pub mod points;
 pub mod rotating_cube;
+pub mod shadow_mapping;
+pub mod skinned_mesh;
+pub mod stencil;
+pub mod text_rendering_msdf;
 pub mod textured_cube;
 pub mod two_cubes;
+pub mod volume_rendering_texture_3d;
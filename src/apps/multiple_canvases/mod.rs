@@ -0,0 +1,390 @@
+use eframe::{
+    egui,
+    egui_wgpu::{self, RenderState},
+};
+use glam::{Mat4, Vec3};
+use std::f32::consts::PI;
+use wgpu::util::DeviceExt;
+
+use crate::canvas::PerCanvas;
+use crate::meshes::mesh::{Mesh, Vertex};
+use crate::offscreen::{self, OffscreenTarget};
+use crate::profiler::GpuProfiler;
+use crate::stats::{paint_callback, FrameStats};
+
+const CANVAS: (f32, f32) = (290.0, 290.0);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CameraView {
+    Front,
+    Top,
+    Right,
+    Orbit,
+}
+
+impl CameraView {
+    const ALL: [Self; 4] = [Self::Front, Self::Top, Self::Right, Self::Orbit];
+}
+
+impl std::fmt::Display for CameraView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Front => "Front",
+            Self::Top => "Top",
+            Self::Right => "Right",
+            Self::Orbit => "Orbit",
+        })
+    }
+}
+
+/// Laid out to match `Uniforms` in `shader.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    view_proj: [[f32; 4]; 4],
+    model: [[f32; 4]; 4],
+}
+
+/// The camera of one canvas. Its id is both the id of the canvas widget and the key of its
+/// render target and uniforms in [`PerCanvas`].
+struct CubeCanvas {
+    id: egui::Id,
+    view: CameraView,
+    camera_yaw: f32,
+    camera_pitch: f32,
+}
+
+impl CubeCanvas {
+    fn view_proj(&self) -> Mat4 {
+        // The fixed views are orthographic, like the views of a drawing
+        let orthographic = Mat4::orthographic_rh(-2.0, 2.0, -2.0, 2.0, 0.1, 100.0);
+        match self.view {
+            CameraView::Front => {
+                orthographic * Mat4::look_at_rh(Vec3::Z * 5.0, Vec3::ZERO, Vec3::Y)
+            }
+            CameraView::Top => {
+                orthographic * Mat4::look_at_rh(Vec3::Y * 5.0, Vec3::ZERO, Vec3::NEG_Z)
+            }
+            CameraView::Right => {
+                orthographic * Mat4::look_at_rh(Vec3::X * 5.0, Vec3::ZERO, Vec3::Y)
+            }
+            CameraView::Orbit => {
+                let projection =
+                    Mat4::perspective_rh((2.0 * PI) / 5.0, CANVAS.0 / CANVAS.1, 0.1, 100.0);
+                let eye = Mat4::from_rotation_y(self.camera_yaw)
+                    * Mat4::from_rotation_x(-self.camera_pitch)
+                    * Vec3::new(0.0, 0.0, 5.0).extend(1.0);
+                projection * Mat4::look_at_rh(eye.truncate(), Vec3::ZERO, Vec3::Y)
+            }
+        }
+    }
+}
+
+pub struct MultipleCanvases {
+    canvases: Vec<CubeCanvas>,
+    rotate: bool,
+    angle: f32,
+}
+
+impl MultipleCanvases {
+    pub fn new_with_render_state(wgpu_render_state: &RenderState) -> Option<Self> {
+        let device = &wgpu_render_state.device;
+
+        let mesh = Mesh::create_box(2.0, 2.0, 2.0);
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("MultipleCanvases Vertex Buffer"),
+            contents: bytemuck::cast_slice(&mesh.vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("MultipleCanvases Index Buffer"),
+            contents: bytemuck::cast_slice(&mesh.indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("MultipleCanvases Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("MultipleCanvases Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./shader.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("MultipleCanvases Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("MultipleCanvases Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu_render_state.target_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: offscreen::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        // Because the graphics pipeline must have the same lifetime as the egui render pass,
+        // instead of storing the pipeline in our struct, we insert it into the
+        // `paint_callback_resources` type map, which is stored alongside the render pass.
+        // The pipeline and the cube are shared, while every canvas gets its own render target
+        // and uniforms the first time it is drawn.
+        wgpu_render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(AppRenderResources {
+                color_format: wgpu_render_state.target_format,
+                pipeline,
+                bind_group_layout,
+                vertex_buffer,
+                index_buffer,
+                index_count: mesh.indices.len() as u32,
+                canvases: PerCanvas::default(),
+            });
+
+        let canvases = CameraView::ALL
+            .into_iter()
+            .enumerate()
+            .map(|(i, view)| CubeCanvas {
+                id: egui::Id::new(("multiple_canvases_canvas", i)),
+                view,
+                camera_yaw: 0.6,
+                camera_pitch: 0.4,
+            })
+            .collect();
+
+        Some(Self {
+            canvases,
+            rotate: true,
+            angle: 0.0,
+        })
+    }
+}
+
+impl eframe::App for MultipleCanvases {
+    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        if self.rotate {
+            self.angle += ctx.input(|i| i.stable_dt) * 0.5;
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    egui::Grid::new("multiple_canvases_grid")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            for (i, canvas) in self.canvases.iter_mut().enumerate() {
+                                ui.vertical(|ui| {
+                                    egui::ComboBox::from_id_source(canvas.id.with("view"))
+                                        .selected_text(canvas.view.to_string())
+                                        .show_ui(ui, |ui| {
+                                            for view in CameraView::ALL {
+                                                ui.selectable_value(
+                                                    &mut canvas.view,
+                                                    view,
+                                                    view.to_string(),
+                                                );
+                                            }
+                                        });
+                                    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                                        Self::custom_painting(ui, canvas, self.angle);
+                                    });
+                                });
+                                if i % 2 == 1 {
+                                    ui.end_row();
+                                }
+                            }
+                        });
+                    ui.vertical(|ui| self.controls(ui));
+                });
+            });
+        });
+        // This is needed to animate the cube. It tells eframe to call update() again on the next event loop iteration.
+        ctx.request_repaint();
+    }
+}
+
+impl MultipleCanvases {
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("multiple_canvases_controls")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("rotate");
+                ui.checkbox(&mut self.rotate, "");
+                ui.end_row();
+            });
+        ui.label("Every canvas is its own paint callback, with its own render target and camera.");
+        ui.label("Drag an orbit view to move its camera.");
+    }
+
+    fn custom_painting(ui: &mut egui::Ui, canvas: &mut CubeCanvas, angle: f32) {
+        let (rect, _) =
+            ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::hover());
+        // Interact with the id of the canvas, so that its drag state follows the canvas
+        let response = ui.interact(rect, canvas.id, egui::Sense::drag());
+
+        if canvas.view == CameraView::Orbit {
+            let drag = response.drag_delta();
+            canvas.camera_yaw -= drag.x * 0.01;
+            canvas.camera_pitch = (canvas.camera_pitch + drag.y * 0.01).clamp(-1.5, 1.5);
+        }
+        let model = Mat4::from_rotation_y(angle) * Mat4::from_rotation_x(angle * 0.6);
+
+        ui.painter().add(paint_callback(
+            rect,
+            CustomPaintCallback {
+                id: canvas.id,
+                frame_nr: ui.ctx().frame_nr(),
+                size: offscreen::size_in_pixels(ui, rect),
+                uniforms: Uniforms {
+                    view_proj: canvas.view_proj().to_cols_array_2d(),
+                    model: model.to_cols_array_2d(),
+                },
+            },
+        ));
+    }
+}
+
+struct CustomPaintCallback {
+    id: egui::Id,
+    frame_nr: u64,
+    size: (u32, u32),
+    uniforms: Uniforms,
+}
+
+impl egui_wgpu::CallbackTrait for CustomPaintCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let profiler = GpuProfiler::get(callback_resources);
+        let stats = FrameStats::get(callback_resources);
+        let resources: &mut AppRenderResources = callback_resources.get_mut().unwrap();
+        let (color_format, layout) = (resources.color_format, &resources.bind_group_layout);
+        let canvas = resources.canvases.prepare(self.id, self.frame_nr, || {
+            CanvasResources::new(device, color_format, layout, self.size)
+        });
+        canvas.target.resize(device, self.size);
+        stats.write_buffer(
+            queue,
+            &canvas.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&self.uniforms),
+        );
+
+        let mut render_pass = stats.pass(canvas.target.begin_pass(
+            egui_encoder,
+            wgpu::Color {
+                r: 0.1,
+                g: 0.1,
+                b: 0.12,
+                a: 1.0,
+            },
+            "MultipleCanvases Render Pass",
+            &profiler,
+        ));
+        render_pass.set_pipeline(&resources.pipeline);
+        render_pass.set_bind_group(0, &canvas.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, resources.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(resources.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..resources.index_count, 0, 0..1);
+        drop(render_pass);
+
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: eframe::egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
+        let resources: &AppRenderResources = callback_resources.get().unwrap();
+        // Every canvas is prepared before it is painted
+        let canvas = resources.canvases.get(self.id).unwrap();
+        canvas.target.blit(&mut render_pass);
+    }
+}
+
+/// The render target and camera uniforms of one canvas.
+struct CanvasResources {
+    target: OffscreenTarget,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl CanvasResources {
+    fn new(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        layout: &wgpu::BindGroupLayout,
+        size: (u32, u32),
+    ) -> Self {
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("MultipleCanvases Uniform Buffer"),
+            size: std::mem::size_of::<Uniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("MultipleCanvases Bind Group"),
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+        Self {
+            target: OffscreenTarget::new(device, color_format, Some(offscreen::DEPTH_FORMAT), size),
+            uniform_buffer,
+            bind_group,
+        }
+    }
+}
+
+struct AppRenderResources {
+    pub color_format: wgpu::TextureFormat,
+    pub pipeline: wgpu::RenderPipeline,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub index_count: u32,
+    pub canvases: PerCanvas<CanvasResources>,
+}
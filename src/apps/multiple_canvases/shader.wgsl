@@ -0,0 +1,29 @@
+struct Uniforms {
+    view_proj: mat4x4f,
+    model: mat4x4f,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) normal: vec3f,
+    @location(1) color: vec3f,
+}
+
+@vertex
+fn vs_main(@location(0) position: vec3f, @location(1) normal: vec3f) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = uniforms.view_proj * uniforms.model * vec4f(position, 1.0);
+    out.normal = (uniforms.model * vec4f(normal, 0.0)).xyz;
+    // A color per face, so every camera shows which side it is looking at
+    out.color = abs(normal) * 0.7 + select(vec3f(0.0), vec3f(0.3), normal.x + normal.y + normal.z < 0.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let light = normalize(vec3f(0.3, 0.8, 1.0));
+    let diffuse = max(dot(normalize(in.normal), light), 0.0);
+    return vec4f(in.color * (0.4 + 0.6 * diffuse), 1.0);
+}
@@ -0,0 +1,554 @@
+mod textures;
+
+use eframe::{
+    egui,
+    egui_wgpu::{self, RenderState},
+};
+use glam::{Mat4, Vec3};
+use std::f32::consts::PI;
+use textures::Material;
+use wgpu::util::DeviceExt;
+
+use crate::{
+    meshes::mesh::{Mesh, Vertex},
+    offscreen::{self, OffscreenTarget},
+    profiler::GpuProfiler,
+    stats::{paint_callback, FrameStats},
+};
+
+const CANVAS: (f32, f32) = (600.0, 600.0);
+const TEXTURE_SIZE: u32 = 512;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Diffuse,
+    NormalMap,
+    Parallax,
+    SteepParallax,
+    ParallaxOcclusion,
+    NormalTexture,
+    HeightTexture,
+}
+
+impl Mode {
+    const ALL: [Self; 7] = [
+        Self::Diffuse,
+        Self::NormalMap,
+        Self::Parallax,
+        Self::SteepParallax,
+        Self::ParallaxOcclusion,
+        Self::NormalTexture,
+        Self::HeightTexture,
+    ];
+
+    /// Matches the `MODE_*` constants in `shader.wgsl`.
+    fn shader_value(self) -> u32 {
+        self as u32
+    }
+}
+
+impl std::fmt::Display for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Diffuse => write!(f, "Diffuse only"),
+            Self::NormalMap => write!(f, "Normal mapping"),
+            Self::Parallax => write!(f, "Parallax mapping"),
+            Self::SteepParallax => write!(f, "Steep parallax mapping"),
+            Self::ParallaxOcclusion => write!(f, "Parallax occlusion mapping"),
+            Self::NormalTexture => write!(f, "Normal texture"),
+            Self::HeightTexture => write!(f, "Height texture"),
+        }
+    }
+}
+
+const MATERIALS: [&str; 3] = ["Bricks", "Studs", "Happy tree"];
+
+/// Laid out to match `Uniforms` in `shader.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    projection_matrix: [[f32; 4]; 4],
+    view_matrix: [[f32; 4]; 4],
+    model_matrix: [[f32; 4]; 4],
+    light_position: [f32; 4],
+    view_position: [f32; 4],
+    mode: u32,
+    light_intensity: f32,
+    depth_scale: f32,
+    depth_layers: f32,
+}
+
+pub struct NormalMap {
+    start_time: std::time::Instant,
+    mode: Mode,
+    material: usize,
+    light_position: Vec3,
+    light_intensity: f32,
+    depth_scale: f32,
+    depth_layers: f32,
+    rotate_box: bool,
+    box_angle: f32,
+    camera_yaw: f32,
+    camera_pitch: f32,
+    camera_distance: f32,
+}
+
+impl NormalMap {
+    pub fn new_with_render_state(wgpu_render_state: &RenderState) -> Option<Self> {
+        let device = &wgpu_render_state.device;
+        let queue = &wgpu_render_state.queue;
+
+        let mesh = Mesh::create_box(1.0, 1.0, 1.0);
+
+        // Create the vertex and index buffers
+        let (vertex_buffer, index_buffer) = {
+            let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("NormalMap Vertex Buffer"),
+                contents: bytemuck::cast_slice(&mesh.vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+
+            let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("NormalMap Index Buffer"),
+                contents: bytemuck::cast_slice(&mesh.indices),
+                usage: wgpu::BufferUsages::INDEX,
+            });
+
+            (vertex_buffer, index_buffer)
+        };
+
+        // Create the uniform buffer and bind group
+        let (uniform_buffer, uniform_bind_group_layout, uniform_bind_group) = {
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("NormalMap Uniform Buffer"),
+                size: std::mem::size_of::<Uniforms>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+            let bind_group_layout =
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("NormalMap Uniform Bind Group Layout"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }],
+                });
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("NormalMap Uniform Bind Group"),
+                layout: &bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+            });
+
+            (buffer, bind_group_layout, bind_group)
+        };
+
+        // Create one bind group with the diffuse, normal and height maps per material
+        let (material_bind_group_layout, material_bind_groups) = {
+            let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            };
+            let bind_group_layout =
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("NormalMap Material Bind Group Layout"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                            count: None,
+                        },
+                        texture_entry(1),
+                        texture_entry(2),
+                        texture_entry(3),
+                    ],
+                });
+
+            let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("NormalMap Sampler"),
+                address_mode_u: wgpu::AddressMode::Repeat,
+                address_mode_v: wgpu::AddressMode::Repeat,
+                address_mode_w: wgpu::AddressMode::Repeat,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Nearest,
+                ..Default::default()
+            });
+
+            let happy_tree =
+                image::load_from_memory(include_bytes!("../textured_cube/assets/happy-tree.png"))
+                    .unwrap()
+                    .to_rgba8();
+
+            let materials = [
+                Material::bricks(TEXTURE_SIZE),
+                Material::studs(TEXTURE_SIZE),
+                Material::from_image(&happy_tree, TEXTURE_SIZE),
+            ];
+
+            let bind_groups = materials
+                .iter()
+                .zip(MATERIALS)
+                .map(|(material, name)| {
+                    let [diffuse, normal, height] = [
+                        (&material.diffuse, "Diffuse"),
+                        (&material.normal, "Normal"),
+                        (&material.height, "Height"),
+                    ]
+                    .map(|(data, kind)| {
+                        device
+                            .create_texture_with_data(
+                                queue,
+                                &wgpu::TextureDescriptor {
+                                    label: Some(&format!("NormalMap {name} {kind} Texture")),
+                                    size: wgpu::Extent3d {
+                                        width: material.size,
+                                        height: material.size,
+                                        depth_or_array_layers: 1,
+                                    },
+                                    mip_level_count: 1,
+                                    sample_count: 1,
+                                    dimension: wgpu::TextureDimension::D2,
+                                    format: wgpu::TextureFormat::Rgba8Unorm,
+                                    usage: wgpu::TextureUsages::TEXTURE_BINDING
+                                        | wgpu::TextureUsages::COPY_DST,
+                                    view_formats: &[],
+                                },
+                                wgpu::util::TextureDataOrder::LayerMajor,
+                                data,
+                            )
+                            .create_view(&wgpu::TextureViewDescriptor::default())
+                    });
+
+                    device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: Some(&format!("NormalMap {name} Bind Group")),
+                        layout: &bind_group_layout,
+                        entries: &[
+                            wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: wgpu::BindingResource::Sampler(&sampler),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 1,
+                                resource: wgpu::BindingResource::TextureView(&diffuse),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 2,
+                                resource: wgpu::BindingResource::TextureView(&normal),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 3,
+                                resource: wgpu::BindingResource::TextureView(&height),
+                            },
+                        ],
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            (bind_group_layout, bind_groups)
+        };
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("NormalMap Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./shader.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("NormalMap Pipeline Layout"),
+            bind_group_layouts: &[&uniform_bind_group_layout, &material_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("NormalMap Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu_render_state.target_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: offscreen::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let target = OffscreenTarget::new(
+            device,
+            wgpu_render_state.target_format,
+            Some(offscreen::DEPTH_FORMAT),
+            (CANVAS.0 as u32, CANVAS.1 as u32),
+        );
+
+        // Because the graphics pipeline must have the same lifetime as the egui render pass,
+        // instead of storing the pipeline in our struct, we insert it into the
+        // `paint_callback_resources` type map, which is stored alongside the render pass.
+        wgpu_render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(AppRenderResources {
+                target,
+                pipeline,
+                vertex_buffer,
+                index_buffer,
+                index_count: mesh.indices.len() as u32,
+                uniform_buffer,
+                uniform_bind_group,
+                material_bind_groups,
+            });
+
+        Some(Self {
+            start_time: std::time::Instant::now(),
+            mode: Mode::ParallaxOcclusion,
+            material: 0,
+            light_position: Vec3::new(1.7, 0.7, 1.9),
+            light_intensity: 1.5,
+            depth_scale: 0.05,
+            depth_layers: 16.0,
+            rotate_box: true,
+            box_angle: 0.0,
+            camera_yaw: 0.0,
+            camera_pitch: 0.3,
+            camera_distance: 2.2,
+        })
+    }
+
+    fn uniforms(&self, aspect_ratio: f32) -> Uniforms {
+        let projection_matrix = Mat4::perspective_rh((2.0 * PI) / 5.0, aspect_ratio, 0.1, 10.0);
+
+        let view_position = Vec3::new(
+            self.camera_distance * self.camera_pitch.cos() * self.camera_yaw.sin(),
+            self.camera_distance * self.camera_pitch.sin(),
+            self.camera_distance * self.camera_pitch.cos() * self.camera_yaw.cos(),
+        );
+        let view_matrix = Mat4::look_at_rh(view_position, Vec3::ZERO, Vec3::Y);
+        let model_matrix = Mat4::from_rotation_y(self.box_angle);
+
+        // The shader works in model space, so bring the camera and light there
+        let world_to_model = model_matrix.inverse();
+
+        Uniforms {
+            projection_matrix: projection_matrix.to_cols_array_2d(),
+            view_matrix: view_matrix.to_cols_array_2d(),
+            model_matrix: model_matrix.to_cols_array_2d(),
+            light_position: world_to_model
+                .transform_point3(self.light_position)
+                .extend(1.0)
+                .into(),
+            view_position: world_to_model
+                .transform_point3(view_position)
+                .extend(1.0)
+                .into(),
+            mode: self.mode.shader_value(),
+            light_intensity: self.light_intensity,
+            depth_scale: self.depth_scale,
+            depth_layers: self.depth_layers,
+        }
+    }
+}
+
+impl eframe::App for NormalMap {
+    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                        self.custom_painting(ui);
+                    });
+                    ui.vertical(|ui| self.controls(ui));
+                });
+            });
+        });
+        // This is needed to animate the box. It tells eframe to call update() again on the next event loop iteration.
+        ctx.request_repaint();
+    }
+}
+
+impl NormalMap {
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("normal_map_controls")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("mode");
+                egui::ComboBox::from_id_source("normal_map_mode")
+                    .selected_text(self.mode.to_string())
+                    .show_ui(ui, |ui| {
+                        for mode in Mode::ALL {
+                            ui.selectable_value(&mut self.mode, mode, mode.to_string());
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("material");
+                egui::ComboBox::from_id_source("normal_map_material")
+                    .selected_text(MATERIALS[self.material])
+                    .show_ui(ui, |ui| {
+                        for (i, name) in MATERIALS.iter().enumerate() {
+                            ui.selectable_value(&mut self.material, i, *name);
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("light x");
+                ui.add(egui::Slider::new(&mut self.light_position.x, -5.0..=5.0));
+                ui.end_row();
+                ui.label("light y");
+                ui.add(egui::Slider::new(&mut self.light_position.y, -5.0..=5.0));
+                ui.end_row();
+                ui.label("light z");
+                ui.add(egui::Slider::new(&mut self.light_position.z, -5.0..=5.0));
+                ui.end_row();
+                ui.label("light intensity");
+                ui.add(egui::Slider::new(&mut self.light_intensity, 0.0..=5.0));
+                ui.end_row();
+                ui.label("depth scale");
+                ui.add(egui::Slider::new(&mut self.depth_scale, 0.0..=0.15));
+                ui.end_row();
+                ui.label("depth layers");
+                ui.add(egui::Slider::new(&mut self.depth_layers, 1.0..=64.0));
+                ui.end_row();
+                ui.label("rotate box");
+                ui.checkbox(&mut self.rotate_box, "");
+                ui.end_row();
+            });
+        ui.label("Drag to orbit the camera, scroll to zoom.");
+    }
+
+    fn custom_painting(&mut self, ui: &mut egui::Ui) {
+        let (rect, response) =
+            ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::drag());
+
+        let now = self.start_time.elapsed().as_secs_f32();
+        if self.rotate_box {
+            self.box_angle = now * 0.3;
+        }
+        let drag = response.drag_delta();
+        self.camera_yaw -= drag.x * 0.01;
+        self.camera_pitch = (self.camera_pitch + drag.y * 0.01).clamp(-1.5, 1.5);
+        if response.hovered() {
+            let scroll = ui.input(|i| i.smooth_scroll_delta.y);
+            self.camera_distance = (self.camera_distance - scroll * 0.005).clamp(1.2, 6.0);
+        }
+
+        ui.painter().add(paint_callback(
+            rect,
+            CustomPaintCallback {
+                size: offscreen::size_in_pixels(ui, rect),
+                uniforms: self.uniforms(CANVAS.0 / CANVAS.1),
+                material: self.material,
+            },
+        ));
+    }
+}
+
+struct CustomPaintCallback {
+    size: (u32, u32),
+    uniforms: Uniforms,
+    material: usize,
+}
+
+impl egui_wgpu::CallbackTrait for CustomPaintCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let profiler = GpuProfiler::get(callback_resources);
+        let stats = FrameStats::get(callback_resources);
+        let resources: &mut AppRenderResources = callback_resources.get_mut().unwrap();
+        resources.target.resize(device, self.size);
+        stats.write_buffer(
+            queue,
+            &resources.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&self.uniforms),
+        );
+
+        let mut render_pass = stats.pass(resources.target.begin_pass(
+            egui_encoder,
+            wgpu::Color {
+                r: 0.1,
+                g: 0.1,
+                b: 0.12,
+                a: 1.0,
+            },
+            "NormalMap Render Pass",
+            &profiler,
+        ));
+        render_pass.set_pipeline(&resources.pipeline);
+        render_pass.set_bind_group(0, &resources.uniform_bind_group, &[]);
+        render_pass.set_bind_group(1, &resources.material_bind_groups[self.material], &[]);
+        render_pass.set_vertex_buffer(0, resources.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(resources.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..resources.index_count, 0, 0..1);
+        drop(render_pass);
+
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: eframe::egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
+        let resources: &AppRenderResources = callback_resources.get().unwrap();
+        resources.target.blit(&mut render_pass);
+    }
+}
+
+struct AppRenderResources {
+    pub target: OffscreenTarget,
+    pub pipeline: wgpu::RenderPipeline,
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub index_count: u32,
+    pub uniform_buffer: wgpu::Buffer,
+    pub uniform_bind_group: wgpu::BindGroup,
+    pub material_bind_groups: Vec<wgpu::BindGroup>,
+}
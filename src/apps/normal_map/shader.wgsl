@@ -0,0 +1,154 @@
+const MODE_DIFFUSE = 0u;
+const MODE_NORMAL_MAP = 1u;
+const MODE_PARALLAX = 2u;
+const MODE_STEEP_PARALLAX = 3u;
+const MODE_PARALLAX_OCCLUSION = 4u;
+const MODE_NORMAL_TEXTURE = 5u;
+const MODE_HEIGHT_TEXTURE = 6u;
+
+struct Uniforms {
+    projection_matrix: mat4x4f,
+    view_matrix: mat4x4f,
+    model_matrix: mat4x4f,
+    // Model space positions, so they can be moved into tangent space per vertex
+    light_position: vec4f,
+    view_position: vec4f,
+    mode: u32,
+    light_intensity: f32,
+    depth_scale: f32,
+    depth_layers: f32,
+}
+
+struct VertexInput {
+    @location(0) position: vec3f,
+    @location(1) normal: vec3f,
+    @location(2) uv: vec2f,
+    @location(3) tangent: vec3f,
+    @location(4) bitangent: vec3f,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) uv: vec2f,
+    // Everything below is in tangent space
+    @location(1) frag_position: vec3f,
+    @location(2) view_position: vec3f,
+    @location(3) light_position: vec3f,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(1) @binding(0) var texture_sampler: sampler;
+@group(1) @binding(1) var diffuse_texture: texture_2d<f32>;
+@group(1) @binding(2) var normal_texture: texture_2d<f32>;
+@group(1) @binding(3) var height_texture: texture_2d<f32>;
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    // The rows of this matrix are the tangent space axes, so it maps model space to tangent space
+    let tbn = transpose(mat3x3f(
+        normalize(in.tangent),
+        normalize(in.bitangent),
+        normalize(in.normal),
+    ));
+
+    var output: VertexOutput;
+    output.position = uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix * vec4(in.position, 1.0);
+    output.uv = in.uv;
+    output.frag_position = tbn * in.position;
+    output.view_position = tbn * uniforms.view_position.xyz;
+    output.light_position = tbn * uniforms.light_position.xyz;
+    return output;
+}
+
+// The height texture stores heights, parallax mapping works with depths below the surface
+fn sample_depth(uv: vec2f) -> f32 {
+    return 1.0 - textureSampleLevel(height_texture, texture_sampler, uv, 0.0).r;
+}
+
+// Offset the uv along the view direction proportionally to the depth at the original uv
+fn parallax(uv: vec2f, view_dir: vec3f) -> vec2f {
+    let depth = sample_depth(uv);
+    return uv - view_dir.xy / view_dir.z * depth * uniforms.depth_scale;
+}
+
+// March through depth layers until the ray goes below the surface. Returns the uv at the hit
+// and, in `z`, how far between the last two layers the surface was crossed.
+fn steep_parallax(uv: vec2f, view_dir: vec3f) -> vec3f {
+    // Use more layers at grazing angles, where the ray travels further across the texture
+    let layers = mix(uniforms.depth_layers, uniforms.depth_layers * 0.25, abs(view_dir.z));
+    let layer_depth = 1.0 / layers;
+    let delta_uv = view_dir.xy / view_dir.z * uniforms.depth_scale / layers;
+
+    var current_uv = uv;
+    var current_layer_depth = 0.0;
+    var current_depth = sample_depth(current_uv);
+    var previous_depth = current_depth;
+    for (var i = 0; i < 256 && current_layer_depth < current_depth; i++) {
+        current_uv -= delta_uv;
+        current_layer_depth += layer_depth;
+        previous_depth = current_depth;
+        current_depth = sample_depth(current_uv);
+    }
+
+    // Where the surface crosses the segment between the previous and the current layer
+    let after = current_depth - current_layer_depth;
+    let before = previous_depth - (current_layer_depth - layer_depth);
+    let weight = select(after / (after - before), 0.0, abs(after - before) < 1e-5);
+    return vec3(current_uv, weight);
+}
+
+fn parallax_uv(uv: vec2f, view_dir: vec3f) -> vec2f {
+    switch uniforms.mode {
+        case MODE_PARALLAX: {
+            return parallax(uv, view_dir);
+        }
+        case MODE_STEEP_PARALLAX: {
+            return steep_parallax(uv, view_dir).xy;
+        }
+        case MODE_PARALLAX_OCCLUSION: {
+            // Interpolate between the last two layers instead of snapping to one of them
+            let hit = steep_parallax(uv, view_dir);
+            let layers = mix(uniforms.depth_layers, uniforms.depth_layers * 0.25, abs(view_dir.z));
+            let delta_uv = view_dir.xy / view_dir.z * uniforms.depth_scale / layers;
+            return hit.xy + delta_uv * hit.z;
+        }
+        default: {
+            return uv;
+        }
+    }
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let view_dir = normalize(in.view_position - in.frag_position);
+    let uv = parallax_uv(in.uv, view_dir);
+
+    let diffuse = textureSampleLevel(diffuse_texture, texture_sampler, uv, 0.0).rgb;
+    let normal_sample = textureSampleLevel(normal_texture, texture_sampler, uv, 0.0).rgb;
+
+    switch uniforms.mode {
+        case MODE_NORMAL_TEXTURE: {
+            return vec4(normal_sample, 1.0);
+        }
+        case MODE_HEIGHT_TEXTURE: {
+            return vec4(vec3(1.0 - sample_depth(uv)), 1.0);
+        }
+        default: {}
+    }
+
+    // Without a normal map the surface normal in tangent space is simply +z
+    var normal = vec3(0.0, 0.0, 1.0);
+    if (uniforms.mode != MODE_DIFFUSE) {
+        normal = normalize(normal_sample * 2.0 - 1.0);
+    }
+
+    let light_vector = in.light_position - in.frag_position;
+    let light_dir = normalize(light_vector);
+    let attenuation = uniforms.light_intensity / (1.0 + 0.05 * dot(light_vector, light_vector));
+    let half_dir = normalize(light_dir + view_dir);
+
+    let ambient = 0.15 * diffuse;
+    let lambert = max(dot(normal, light_dir), 0.0) * diffuse;
+    let specular = pow(max(dot(normal, half_dir), 0.0), 32.0) * vec3(0.3);
+    return vec4(ambient + (lambert + specular) * attenuation, 1.0);
+}
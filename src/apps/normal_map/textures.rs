@@ -0,0 +1,146 @@
+//! Diffuse, height and normal maps for the sample. The repo has no authored material textures,
+//! so they are generated procedurally, or derived from an image's luminance.
+
+use crate::random::Random;
+
+/// RGBA8 images of the same size describing one material.
+pub struct Material {
+    pub size: u32,
+    pub diffuse: Vec<u8>,
+    /// Height in the red channel, 1 being the highest.
+    pub height: Vec<u8>,
+    /// Tangent space normal with x along increasing u and y along increasing v.
+    pub normal: Vec<u8>,
+}
+
+impl Material {
+    fn new(size: u32, diffuse: Vec<[f32; 3]>, heights: Vec<f32>, bumpiness: f32) -> Self {
+        let to_u8 = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+        let normal = normals_from_heights(size, &heights, bumpiness);
+        Self {
+            size,
+            diffuse: diffuse
+                .iter()
+                .flat_map(|[r, g, b]| [to_u8(*r), to_u8(*g), to_u8(*b), 255])
+                .collect(),
+            height: heights
+                .iter()
+                .flat_map(|h| [to_u8(*h), to_u8(*h), to_u8(*h), 255])
+                .collect(),
+            normal: normal
+                .iter()
+                .flat_map(|n| {
+                    [n[0], n[1], n[2]]
+                        .map(|c| to_u8(c * 0.5 + 0.5))
+                        .into_iter()
+                        .chain([255])
+                })
+                .collect(),
+        }
+    }
+
+    /// Staggered rows of bricks separated by recessed mortar.
+    pub fn bricks(size: u32) -> Self {
+        const ROWS: u32 = 8;
+        const COLUMNS: u32 = 4;
+        const MORTAR: f32 = 0.06;
+
+        let mut random = Random::new(7);
+        let tints: Vec<f32> = (0..ROWS * COLUMNS * 2)
+            .map(|_| 0.8 + 0.2 * random.next_f32())
+            .collect();
+        let mut noise = Random::new(11);
+
+        let mut diffuse = Vec::with_capacity((size * size) as usize);
+        let mut heights = Vec::with_capacity((size * size) as usize);
+        for y in 0..size {
+            for x in 0..size {
+                let v = y as f32 / size as f32 * ROWS as f32;
+                let row = v.floor() as u32;
+                let offset = if row.is_multiple_of(2) { 0.0 } else { 0.5 };
+                let u = x as f32 / size as f32 * COLUMNS as f32 + offset;
+                let column = u.floor() as u32 % (COLUMNS * 2);
+
+                // Distance to the closest mortar line, in brick heights
+                let edge = (v.fract().min(1.0 - v.fract()))
+                    .min(u.fract().min(1.0 - u.fract()) * ROWS as f32 / COLUMNS as f32 / 2.0);
+                let grain = noise.next_f32() * 0.05;
+
+                if edge < MORTAR {
+                    diffuse.push([0.55 + grain, 0.53 + grain, 0.5 + grain]);
+                    heights.push(0.1 + grain);
+                } else {
+                    let tint = tints[(row * COLUMNS * 2 + column) as usize % tints.len()];
+                    // Round the brick edges off a little
+                    let bevel = ((edge - MORTAR) / 0.08).min(1.0);
+                    diffuse.push([
+                        0.62 * tint + grain,
+                        0.25 * tint + grain,
+                        0.18 * tint + grain,
+                    ]);
+                    heights.push(0.4 + 0.6 * bevel.sqrt() - grain);
+                }
+            }
+        }
+
+        Self::new(size, diffuse, heights, 4.0)
+    }
+
+    /// A grid of round studs, which shows off parallax at grazing angles.
+    pub fn studs(size: u32) -> Self {
+        const COUNT: f32 = 6.0;
+
+        let mut diffuse = Vec::with_capacity((size * size) as usize);
+        let mut heights = Vec::with_capacity((size * size) as usize);
+        for y in 0..size {
+            for x in 0..size {
+                let u = (x as f32 / size as f32 * COUNT).fract() - 0.5;
+                let v = (y as f32 / size as f32 * COUNT).fract() - 0.5;
+                let r = (u * u + v * v).sqrt() / 0.4;
+                let height = if r < 1.0 { (1.0 - r * r).sqrt() } else { 0.0 };
+                heights.push(height);
+                diffuse.push(if r < 1.0 {
+                    [0.2 + 0.5 * height, 0.45 + 0.3 * height, 0.8]
+                } else {
+                    [0.85, 0.8, 0.7]
+                });
+            }
+        }
+
+        Self::new(size, diffuse, heights, 8.0)
+    }
+
+    /// Use an image as the diffuse map and its luminance as the height map.
+    pub fn from_image(image: &image::RgbaImage, size: u32) -> Self {
+        let image =
+            image::imageops::resize(image, size, size, image::imageops::FilterType::Triangle);
+
+        let diffuse: Vec<[f32; 3]> = image
+            .pixels()
+            .map(|p| [p[0], p[1], p[2]].map(|c| c as f32 / 255.0))
+            .collect();
+        let heights = diffuse
+            .iter()
+            .map(|[r, g, b]| 0.2126 * r + 0.7152 * g + 0.0722 * b)
+            .collect();
+
+        Self::new(size, diffuse, heights, 3.0)
+    }
+}
+
+/// Derive tangent space normals from a height field with central differences, wrapping around
+/// the edges so tiled textures stay seamless.
+fn normals_from_heights(size: u32, heights: &[f32], bumpiness: f32) -> Vec<[f32; 3]> {
+    let height = |x: u32, y: u32| heights[((y % size) * size + (x % size)) as usize];
+
+    (0..size)
+        .flat_map(|y| (0..size).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let dx = height(x + 1, y) - height(x + size - 1, y);
+            let dy = height(x, y + 1) - height(x, y + size - 1);
+            let normal = glam::Vec3::new(-dx * bumpiness, -dy * bumpiness, 1.0).normalize();
+            normal.into()
+        })
+        .collect()
+}
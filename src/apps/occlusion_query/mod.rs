@@ -0,0 +1,555 @@
+use eframe::{
+    egui,
+    egui_wgpu::{self, RenderState},
+};
+use glam::{Mat4, Vec3};
+use std::f32::consts::PI;
+use wgpu::util::DeviceExt;
+
+use crate::meshes::mesh::{Mesh, Vertex};
+use crate::offscreen::{self, OffscreenTarget};
+use crate::profiler::GpuProfiler;
+use crate::readback::Readback;
+use crate::stats::{paint_callback, CountedPass, FrameStats};
+
+const CANVAS: (f32, f32) = (600.0, 600.0);
+const COLUMNS: usize = 6;
+const ROWS: usize = 4;
+const CUBE_COUNT: usize = COLUMNS * ROWS;
+const OCCLUDER_COUNT: usize = 4;
+/// Occlusion queries resolve to one `u64` each.
+const RESULT_SIZE: wgpu::BufferAddress = std::mem::size_of::<u64>() as wgpu::BufferAddress;
+
+const OCCLUDED_TINT: [f32; 4] = [0.9, 0.15, 0.1, 1.0];
+const OCCLUDER_COLOR: [f32; 4] = [0.55, 0.55, 0.6, 1.0];
+/// How much of the cubes shows through the occluders, with the debug tint.
+const HIDDEN_OPACITY: f64 = 0.5;
+
+/// Laid out to match `Uniforms` in `shader.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    view_proj: [[f32; 4]; 4],
+}
+
+/// A unit box placed and stretched in world space, read as per-instance vertex attributes.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Instance {
+    offset: [f32; 3],
+    scale: [f32; 3],
+    color: [f32; 4],
+}
+
+impl Instance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![5 => Float32x3, 6 => Float32x3, 7 => Float32x4];
+    const SIZE: wgpu::BufferAddress = std::mem::size_of::<Self>() as wgpu::BufferAddress;
+
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: Self::SIZE,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// The cubes, in a grid behind the occluders, each with its own color.
+fn cubes() -> impl Iterator<Item = Instance> {
+    (0..CUBE_COUNT).map(|i| {
+        let (column, row) = ((i % COLUMNS) as f32, (i / COLUMNS) as f32);
+        let hue = i as f32 / CUBE_COUNT as f32 * 2.0 * PI;
+        Instance {
+            offset: [
+                (column - (COLUMNS - 1) as f32 / 2.0) * 1.6,
+                (row - (ROWS - 1) as f32 / 2.0) * 1.6,
+                -2.0,
+            ],
+            scale: [1.0; 3],
+            color: [
+                0.55 + 0.4 * hue.cos(),
+                0.55 + 0.4 * (hue - 2.0 * PI / 3.0).cos(),
+                0.55 + 0.4 * (hue + 2.0 * PI / 3.0).cos(),
+                1.0,
+            ],
+        }
+    })
+}
+
+/// Three walls sliding sideways and a bar sliding up and down, in front of the cubes.
+fn occluders(time: f32) -> [Instance; OCCLUDER_COUNT] {
+    let wall = |x: f32, speed: f32, phase: f32| Instance {
+        offset: [x + 2.5 * (time * speed + phase).sin(), 0.0, 1.5],
+        scale: [1.4, 8.0, 0.3],
+        color: OCCLUDER_COLOR,
+    };
+    [
+        wall(-3.5, 0.7, 0.0),
+        wall(0.0, 0.5, 2.0),
+        wall(3.5, 0.9, 4.0),
+        Instance {
+            offset: [0.0, 2.5 * (time * 0.6).sin(), 3.0],
+            scale: [12.0, 1.2, 0.3],
+            color: OCCLUDER_COLOR,
+        },
+    ]
+}
+
+/// Whether each query let any samples pass, from the resolved query results.
+fn visibility(results: &[u8]) -> Vec<bool> {
+    results
+        .chunks_exact(RESULT_SIZE as usize)
+        .map(|result| bytemuck::pod_read_unaligned::<u64>(result) != 0)
+        .collect()
+}
+
+pub struct OcclusionQuery {
+    time: f32,
+    animate: bool,
+    debug_tint: bool,
+    camera_yaw: f32,
+    camera_pitch: f32,
+    visible: Option<Vec<bool>>,
+}
+
+impl OcclusionQuery {
+    pub fn new_with_render_state(wgpu_render_state: &RenderState) -> Option<Self> {
+        let device = &wgpu_render_state.device;
+
+        let mesh = Mesh::create_box(1.0, 1.0, 1.0);
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("OcclusionQuery Vertex Buffer"),
+            contents: bytemuck::cast_slice(&mesh.vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("OcclusionQuery Index Buffer"),
+            contents: bytemuck::cast_slice(&mesh.indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        // The cubes first, then the occluders
+        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("OcclusionQuery Instance Buffer"),
+            size: Instance::SIZE * (CUBE_COUNT + OCCLUDER_COUNT) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("OcclusionQuery Uniform Buffer"),
+            size: std::mem::size_of::<Uniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("OcclusionQuery Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("OcclusionQuery Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("OcclusionQuery Query Set"),
+            ty: wgpu::QueryType::Occlusion,
+            count: CUBE_COUNT as u32,
+        });
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("OcclusionQuery Resolve Buffer"),
+            size: RESULT_SIZE * CUBE_COUNT as u64,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = Readback::new(
+            device,
+            resolve_buffer.size(),
+            "OcclusionQuery Readback Buffer",
+        );
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("OcclusionQuery Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./shader.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("OcclusionQuery Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        // The pipelines only differ in how they blend and test depth
+        let create_pipeline = |label: &str,
+                               blend: Option<wgpu::BlendState>,
+                               depth_write_enabled: bool,
+                               depth_compare: wgpu::CompareFunction| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[Vertex::layout(), Instance::layout()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: wgpu_render_state.target_format,
+                        blend,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: offscreen::DEPTH_FORMAT,
+                    depth_write_enabled,
+                    depth_compare,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let opaque_pipeline = create_pipeline(
+            "OcclusionQuery Opaque Pipeline",
+            None,
+            true,
+            wgpu::CompareFunction::Less,
+        );
+        // Draws only what is behind the occluders, mixed in by the blend constant
+        let hidden_pipeline = create_pipeline(
+            "OcclusionQuery Hidden Pipeline",
+            Some(wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Constant,
+                    dst_factor: wgpu::BlendFactor::OneMinusConstant,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::OVER,
+            }),
+            false,
+            wgpu::CompareFunction::Greater,
+        );
+
+        let target = OffscreenTarget::new(
+            device,
+            wgpu_render_state.target_format,
+            Some(offscreen::DEPTH_FORMAT),
+            (CANVAS.0 as u32, CANVAS.1 as u32),
+        );
+
+        // Because the graphics pipeline must have the same lifetime as the egui render pass,
+        // instead of storing the pipeline in our struct, we insert it into the
+        // `paint_callback_resources` type map, which is stored alongside the render pass.
+        wgpu_render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(AppRenderResources {
+                target,
+                opaque_pipeline,
+                hidden_pipeline,
+                vertex_buffer,
+                index_buffer,
+                index_count: mesh.indices.len() as u32,
+                instance_buffer,
+                uniform_buffer,
+                bind_group,
+                query_set,
+                resolve_buffer,
+                readback,
+                visible: None,
+            });
+
+        Some(Self {
+            time: 0.0,
+            animate: true,
+            debug_tint: true,
+            camera_yaw: 0.3,
+            camera_pitch: 0.2,
+            visible: None,
+        })
+    }
+}
+
+impl eframe::App for OcclusionQuery {
+    fn update(&mut self, ctx: &eframe::egui::Context, frame: &mut eframe::Frame) {
+        if let Some(wgpu_render_state) = frame.wgpu_render_state() {
+            let renderer = wgpu_render_state.renderer.read();
+            if let Some(resources) = renderer.callback_resources.get::<AppRenderResources>() {
+                self.visible.clone_from(&resources.visible);
+            }
+        }
+        if self.animate {
+            self.time += ctx.input(|i| i.stable_dt);
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                        self.custom_painting(ui);
+                    });
+                    ui.vertical(|ui| self.controls(ui));
+                });
+            });
+        });
+        // This is needed to animate the occluders. It tells eframe to call update() again on the next event loop iteration.
+        ctx.request_repaint();
+    }
+}
+
+impl OcclusionQuery {
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("occlusion_query_controls")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("animate");
+                ui.checkbox(&mut self.animate, "");
+                ui.end_row();
+                ui.label("debug tint").on_hover_text(
+                    "Show the cubes through the occluders, the occluded ones in red",
+                );
+                ui.checkbox(&mut self.debug_tint, "");
+                ui.end_row();
+                ui.label("visible");
+                if let Some(visible) = &self.visible {
+                    let count = visible.iter().filter(|&&visible| visible).count();
+                    ui.label(format!("{count} of {CUBE_COUNT} cubes"));
+                }
+                ui.end_row();
+            });
+        ui.label("Every cube is drawn inside its own occlusion query. The results are read back");
+        ui.label("without waiting for the GPU, so they trail the picture by a few frames.");
+        ui.label("Drag to orbit the camera.");
+    }
+
+    fn custom_painting(&mut self, ui: &mut egui::Ui) {
+        let (rect, response) =
+            ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::drag());
+
+        let drag = response.drag_delta();
+        self.camera_yaw -= drag.x * 0.01;
+        self.camera_pitch = (self.camera_pitch + drag.y * 0.01).clamp(-1.5, 1.5);
+
+        let size = offscreen::size_in_pixels(ui, rect);
+        let projection =
+            Mat4::perspective_rh((2.0 * PI) / 5.0, size.0 as f32 / size.1 as f32, 0.1, 100.0);
+        let eye = Mat4::from_rotation_y(self.camera_yaw)
+            * Mat4::from_rotation_x(-self.camera_pitch)
+            * Vec3::new(0.0, 0.0, 11.0).extend(1.0);
+        let view = Mat4::look_at_rh(eye.truncate(), Vec3::ZERO, Vec3::Y);
+
+        ui.painter().add(paint_callback(
+            rect,
+            CustomPaintCallback {
+                size,
+                uniforms: Uniforms {
+                    view_proj: (projection * view).to_cols_array_2d(),
+                },
+                time: self.time,
+                debug_tint: self.debug_tint,
+            },
+        ));
+    }
+}
+
+struct CustomPaintCallback {
+    size: (u32, u32),
+    uniforms: Uniforms,
+    time: f32,
+    debug_tint: bool,
+}
+
+impl egui_wgpu::CallbackTrait for CustomPaintCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let profiler = GpuProfiler::get(callback_resources);
+        let stats = FrameStats::get(callback_resources);
+        let resources: &mut AppRenderResources = callback_resources.get_mut().unwrap();
+        resources.target.resize(device, self.size);
+        if let Some(data) = resources.readback.poll(device) {
+            resources.visible = Some(visibility(&data));
+        }
+
+        stats.write_buffer(
+            queue,
+            &resources.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&self.uniforms),
+        );
+        let mut instances: Vec<Instance> = cubes().collect();
+        if let (true, Some(visible)) = (self.debug_tint, &resources.visible) {
+            for (cube, &visible) in instances.iter_mut().zip(visible) {
+                if !visible {
+                    cube.color = OCCLUDED_TINT;
+                }
+            }
+        }
+        instances.extend(occluders(self.time));
+        stats.write_buffer(
+            queue,
+            &resources.instance_buffer,
+            0,
+            bytemuck::cast_slice(&instances),
+        );
+
+        let mut render_pass =
+            stats.pass(egui_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("OcclusionQuery Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: resources.target.color_view(),
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.1,
+                            g: 0.1,
+                            b: 0.12,
+                            a: 1.0,
+                        }),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: resources.target.depth_view().unwrap(),
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: profiler.render_pass_writes("OcclusionQuery Render Pass"),
+                occlusion_query_set: Some(&resources.query_set),
+            }));
+        render_pass.set_bind_group(0, &resources.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, resources.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(resources.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+
+        // The occluders go first, so that the queries of the cubes test against their depth
+        render_pass.set_pipeline(&resources.opaque_pipeline);
+        for i in 0..OCCLUDER_COUNT {
+            resources.draw_object(&mut render_pass, CUBE_COUNT + i);
+        }
+        for i in 0..CUBE_COUNT {
+            render_pass.begin_occlusion_query(i as u32);
+            resources.draw_object(&mut render_pass, i);
+            render_pass.end_occlusion_query();
+        }
+
+        if self.debug_tint {
+            // Let the cubes show through the occluders
+            render_pass.set_pipeline(&resources.hidden_pipeline);
+            render_pass.set_blend_constant(wgpu::Color {
+                r: HIDDEN_OPACITY,
+                g: HIDDEN_OPACITY,
+                b: HIDDEN_OPACITY,
+                a: HIDDEN_OPACITY,
+            });
+            for i in 0..CUBE_COUNT {
+                resources.draw_object(&mut render_pass, i);
+            }
+        }
+        drop(render_pass);
+
+        egui_encoder.resolve_query_set(
+            &resources.query_set,
+            0..CUBE_COUNT as u32,
+            &resources.resolve_buffer,
+            0,
+        );
+        resources.readback.copy_from(
+            egui_encoder,
+            &resources.resolve_buffer,
+            resources.readback.size(),
+        );
+
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: eframe::egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
+        let resources: &AppRenderResources = callback_resources.get().unwrap();
+        resources.target.blit(&mut render_pass);
+    }
+}
+
+struct AppRenderResources {
+    pub target: OffscreenTarget,
+    pub opaque_pipeline: wgpu::RenderPipeline,
+    pub hidden_pipeline: wgpu::RenderPipeline,
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub index_count: u32,
+    pub instance_buffer: wgpu::Buffer,
+    pub uniform_buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+    pub query_set: wgpu::QuerySet,
+    pub resolve_buffer: wgpu::Buffer,
+    pub readback: Readback,
+    /// Which cubes passed their query, in the last frame that was read back.
+    pub visible: Option<Vec<bool>>,
+}
+
+impl AppRenderResources {
+    /// Draw the box at `index` in the instance buffer.
+    fn draw_object<'a>(&'a self, render_pass: &mut CountedPass<'_, 'a>, index: usize) {
+        let offset = index as u64 * Instance::SIZE;
+        render_pass.set_vertex_buffer(
+            1,
+            self.instance_buffer.slice(offset..offset + Instance::SIZE),
+        );
+        render_pass.draw_indexed(0..self.index_count, 0, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn any_passed_sample_makes_a_cube_visible() {
+        let results: Vec<u8> = bytemuck::cast_slice(&[0u64, 1, 0, 4096]).to_vec();
+        assert_eq!(visibility(&results), [false, true, false, true]);
+    }
+
+    #[test]
+    fn cubes_stay_behind_the_occluders() {
+        let occluders = occluders(1.0);
+        let nearest_cube = cubes()
+            .map(|cube| cube.offset[2] + cube.scale[2] / 2.0)
+            .fold(f32::MIN, f32::max);
+        for occluder in occluders {
+            assert!(occluder.offset[2] - occluder.scale[2] / 2.0 > nearest_cube);
+        }
+    }
+}
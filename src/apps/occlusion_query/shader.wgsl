@@ -0,0 +1,33 @@
+struct Uniforms {
+    view_proj: mat4x4f,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) normal: vec3f,
+    @location(1) color: vec4f,
+}
+
+@vertex
+fn vs_main(
+    @location(0) position: vec3f,
+    @location(1) normal: vec3f,
+    @location(5) offset: vec3f,
+    @location(6) scale: vec3f,
+    @location(7) color: vec4f,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = uniforms.view_proj * vec4f(position * scale + offset, 1.0);
+    out.normal = normal;
+    out.color = color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let light = normalize(vec3f(0.4, 0.7, 1.0));
+    let diffuse = max(dot(normalize(in.normal), light), 0.0);
+    return vec4f(in.color.rgb * (0.35 + 0.65 * diffuse), in.color.a);
+}
@@ -0,0 +1,113 @@
+struct SimParams {
+    delta_time: f32,
+    gravity: f32,
+    lifetime: f32,
+    speed: f32,
+    emitter_position: vec3f,
+    // How far from straight up particles may be emitted, 0 is a thin jet
+    spread: f32,
+    emitter_size: f32,
+    spawn_from_texture: u32,
+    texture_width: u32,
+    texture_height: u32,
+    color: vec4f,
+    seed: u32,
+}
+
+struct Particle {
+    position: vec3f,
+    // Seconds left to live, the particle is spawned again once it reaches 0
+    lifetime: f32,
+    color: vec4f,
+    velocity: vec3f,
+}
+
+@group(0) @binding(0) var<uniform> params: SimParams;
+@group(0) @binding(1) var<storage, read_write> particles: array<Particle>;
+// Cumulative spawn probability of every texel, row by row, ending with 1
+@group(0) @binding(2) var<storage, read> spawn_distribution: array<f32>;
+@group(0) @binding(3) var spawn_texture: texture_2d<f32>;
+
+var<private> rng_state: u32;
+
+// PCG hash, see "Hash Functions for GPU Rendering" (Jarzynski and Olano)
+fn pcg(value: u32) -> u32 {
+    let state = value * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn rand() -> f32 {
+    rng_state = pcg(rng_state);
+    return f32(rng_state >> 8u) / 16777216.0;
+}
+
+fn rand_in_sphere() -> vec3f {
+    // Rejection sampling terminates quickly, but bound it anyway
+    for (var i = 0; i < 8; i++) {
+        let p = vec3(rand(), rand(), rand()) * 2.0 - 1.0;
+        if (dot(p, p) <= 1.0) {
+            return p;
+        }
+    }
+    return vec3(0.0);
+}
+
+// Index of the first texel whose cumulative probability reaches `value`
+fn sample_distribution(value: f32) -> u32 {
+    var low = 0u;
+    var high = arrayLength(&spawn_distribution) - 1u;
+    while (low < high) {
+        let middle = (low + high) / 2u;
+        if (spawn_distribution[middle] < value) {
+            low = middle + 1u;
+        } else {
+            high = middle;
+        }
+    }
+    return low;
+}
+
+fn spawn() -> Particle {
+    var particle: Particle;
+    particle.lifetime = params.lifetime * (0.5 + rand());
+
+    if (params.spawn_from_texture != 0u) {
+        // Lay the texture out on a vertical square around the emitter, spawning more particles
+        // where the texture is more opaque, in the color of the texel
+        let texel = sample_distribution(rand());
+        let x = texel % params.texture_width;
+        let y = texel / params.texture_width;
+        let uv = (vec2(f32(x), f32(y)) + vec2(rand(), rand()))
+            / vec2(f32(params.texture_width), f32(params.texture_height));
+        particle.position = params.emitter_position
+            + vec3(uv.x - 0.5, 0.5 - uv.y, 0.0) * params.emitter_size;
+        particle.color = textureLoad(spawn_texture, vec2(x, y), 0);
+        particle.velocity = rand_in_sphere() * params.speed * params.spread * 0.2;
+    } else {
+        particle.position = params.emitter_position + rand_in_sphere() * params.emitter_size * 0.5;
+        particle.color = params.color;
+        let direction = normalize(vec3(0.0, 1.0, 0.0) + rand_in_sphere() * params.spread);
+        particle.velocity = direction * params.speed * (0.8 + 0.4 * rand());
+    }
+    return particle;
+}
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) global_id: vec3u) {
+    let index = global_id.x;
+    if (index >= arrayLength(&particles)) {
+        return;
+    }
+    rng_state = pcg(index ^ pcg(params.seed));
+
+    var particle = particles[index];
+    particle.lifetime -= params.delta_time;
+    if (particle.lifetime <= 0.0) {
+        particle = spawn();
+    } else {
+        particle.velocity.y -= params.gravity * params.delta_time;
+        particle.position += particle.velocity * params.delta_time;
+    }
+    particles[index] = particle;
+}
@@ -0,0 +1,673 @@
+use eframe::{
+    egui,
+    egui_wgpu::{self, RenderState},
+};
+use glam::{Mat4, Vec3};
+use std::f32::consts::PI;
+use wgpu::util::DeviceExt;
+
+use crate::profiler::GpuProfiler;
+use crate::random::Random;
+use crate::stats::{paint_callback, FrameStats};
+
+const CANVAS: (f32, f32) = (600.0, 600.0);
+const WORKGROUP_SIZE: u32 = 64;
+const DEFAULT_NUM_PARTICLES: u32 = 20000;
+
+/// Corners of the quad drawn for every particle, as two triangles.
+#[rustfmt::skip]
+const QUAD_VERTICES: &[f32] = &[
+    -1.0, -1.0, 1.0, -1.0, -1.0, 1.0,
+    -1.0, 1.0, 1.0, -1.0, 1.0, 1.0,
+];
+
+/// Emitter parameters, laid out to match `SimParams` in `compute.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SimParams {
+    pub delta_time: f32,
+    pub gravity: f32,
+    pub lifetime: f32,
+    pub speed: f32,
+    pub emitter_position: [f32; 3],
+    pub spread: f32,
+    pub emitter_size: f32,
+    pub spawn_from_texture: u32,
+    pub texture_width: u32,
+    pub texture_height: u32,
+    pub color: [f32; 4],
+    pub seed: u32,
+    pub _padding: [u32; 3],
+}
+
+impl Default for SimParams {
+    fn default() -> Self {
+        Self {
+            delta_time: 0.0,
+            gravity: 4.0,
+            lifetime: 2.5,
+            speed: 5.0,
+            emitter_position: [0.0, 0.0, 0.0],
+            spread: 0.25,
+            emitter_size: 0.3,
+            spawn_from_texture: 0,
+            texture_width: 1,
+            texture_height: 1,
+            color: [1.0, 0.45, 0.15, 1.0],
+            seed: 0,
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// A single particle, laid out to match `Particle` in `compute.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Particle {
+    pub position: [f32; 3],
+    pub lifetime: f32,
+    pub color: [f32; 4],
+    pub velocity: [f32; 3],
+    pub _padding: f32,
+}
+
+/// Laid out to match `RenderParams` in `particle.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct RenderParams {
+    view_proj: [[f32; 4]; 4],
+    camera_right: [f32; 4],
+    camera_up: [f32; 4],
+    particle_size: f32,
+    _padding: [f32; 3],
+}
+
+/// Invisible particles with random remaining lifetimes, so that they are spawned gradually
+/// rather than all in the first frame.
+fn init_particles(count: u32, lifetime: f32) -> Vec<Particle> {
+    let mut random = Random::new(0x5eed);
+    (0..count)
+        .map(|_| Particle {
+            lifetime: random.next_f32() * lifetime,
+            ..bytemuck::Zeroable::zeroed()
+        })
+        .collect()
+}
+
+/// The cumulative spawn probability of every pixel, row by row, proportional to its alpha.
+/// Fully opaque images spawn particles evenly.
+fn spawn_distribution(image: &image::RgbaImage) -> Vec<f32> {
+    let mut total = 0.0;
+    let mut distribution: Vec<f32> = image
+        .pixels()
+        .map(|pixel| {
+            total += pixel[3] as f32 / 255.0;
+            total
+        })
+        .collect();
+    if total > 0.0 {
+        distribution.iter_mut().for_each(|value| *value /= total);
+    }
+    distribution
+}
+
+/// The compute half of the sample: a particle buffer updated in place, since every particle
+/// only depends on itself.
+pub struct ParticleSimulation {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    params_buffer: wgpu::Buffer,
+    distribution_buffer: wgpu::Buffer,
+    texture_view: wgpu::TextureView,
+    particle_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    num_particles: u32,
+}
+
+impl ParticleSimulation {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        particles: &[Particle],
+        spawn_image: &image::RgbaImage,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Particles Compute Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./compute.wgsl").into()),
+        });
+
+        let storage_entry = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Particles Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage_entry(1, false),
+                storage_entry(2, true),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Particles Compute Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Particles Compute Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "main",
+        });
+
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Particles Params Buffer"),
+            contents: bytemuck::bytes_of(&SimParams::default()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let distribution_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Particles Spawn Distribution Buffer"),
+            contents: bytemuck::cast_slice(&spawn_distribution(spawn_image)),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let texture_view = device
+            .create_texture_with_data(
+                queue,
+                &wgpu::TextureDescriptor {
+                    label: Some("Particles Spawn Texture"),
+                    size: wgpu::Extent3d {
+                        width: spawn_image.width(),
+                        height: spawn_image.height(),
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::Rgba8Unorm,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                    view_formats: &[],
+                },
+                wgpu::util::TextureDataOrder::LayerMajor,
+                spawn_image,
+            )
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let (particle_buffer, bind_group) = Self::create_particle_buffer(
+            device,
+            &bind_group_layout,
+            &params_buffer,
+            &distribution_buffer,
+            &texture_view,
+            particles,
+        );
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            params_buffer,
+            distribution_buffer,
+            texture_view,
+            particle_buffer,
+            bind_group,
+            num_particles: particles.len() as u32,
+        }
+    }
+
+    fn create_particle_buffer(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        params_buffer: &wgpu::Buffer,
+        distribution_buffer: &wgpu::Buffer,
+        texture_view: &wgpu::TextureView,
+        particles: &[Particle],
+    ) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let particle_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Particles Particle Buffer"),
+            contents: bytemuck::cast_slice(particles),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Particles Bind Group"),
+            layout: bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: particle_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: distribution_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(texture_view),
+                },
+            ],
+        });
+
+        (particle_buffer, bind_group)
+    }
+
+    /// Replace the simulated particles, e.g. after the particle count changed.
+    pub fn reset(&mut self, device: &wgpu::Device, particles: &[Particle]) {
+        let (particle_buffer, bind_group) = Self::create_particle_buffer(
+            device,
+            &self.bind_group_layout,
+            &self.params_buffer,
+            &self.distribution_buffer,
+            &self.texture_view,
+            particles,
+        );
+        self.particle_buffer = particle_buffer;
+        self.bind_group = bind_group;
+        self.num_particles = particles.len() as u32;
+    }
+
+    pub fn num_particles(&self) -> u32 {
+        self.num_particles
+    }
+
+    pub fn particle_buffer(&self) -> &wgpu::Buffer {
+        &self.particle_buffer
+    }
+
+    pub fn write_params(&self, queue: &wgpu::Queue, stats: &FrameStats, params: &SimParams) {
+        stats.write_buffer(queue, &self.params_buffer, 0, bytemuck::bytes_of(params));
+    }
+
+    /// Record one simulation step into `encoder`.
+    pub fn step(&mut self, encoder: &mut wgpu::CommandEncoder, profiler: &GpuProfiler) {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Particles Compute Pass"),
+            timestamp_writes: profiler.compute_pass_writes("Particles Compute Pass"),
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.dispatch_workgroups(self.num_particles.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+}
+
+pub struct Particles {
+    params: SimParams,
+    num_particles: u32,
+    particle_size: f32,
+    frame: u32,
+    camera_yaw: f32,
+    camera_pitch: f32,
+}
+
+impl Particles {
+    pub fn new_with_render_state(wgpu_render_state: &RenderState) -> Option<Self> {
+        let device = &wgpu_render_state.device;
+        let queue = &wgpu_render_state.queue;
+
+        let spawn_image =
+            image::load_from_memory(include_bytes!("../textured_cube/assets/happy-tree.png"))
+                .unwrap()
+                .to_rgba8();
+        let params = SimParams {
+            texture_width: spawn_image.width(),
+            texture_height: spawn_image.height(),
+            ..Default::default()
+        };
+
+        let simulation = ParticleSimulation::new(
+            device,
+            queue,
+            &init_particles(DEFAULT_NUM_PARTICLES, params.lifetime),
+            &spawn_image,
+        );
+
+        let quad_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Particles Quad Vertex Buffer"),
+            contents: bytemuck::cast_slice(QUAD_VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let render_params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particles Render Params Buffer"),
+            size: std::mem::size_of::<RenderParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Particles Render Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Particles Render Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: render_params_buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Particles Render Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./particle.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Particles Render Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        // Particles are added on top of each other, so they do not need to be sorted
+        let additive = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Particles Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[
+                    wgpu::VertexBufferLayout {
+                        array_stride: std::mem::size_of::<Particle>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Instance,
+                        attributes: &wgpu::vertex_attr_array![
+                            0 => Float32x3,
+                            1 => Float32,
+                            2 => Float32x4,
+                        ],
+                    },
+                    wgpu::VertexBufferLayout {
+                        array_stride: 2 * std::mem::size_of::<f32>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &wgpu::vertex_attr_array![3 => Float32x2],
+                    },
+                ],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: wgpu_render_state.target_format,
+                    blend: Some(wgpu::BlendState {
+                        color: additive,
+                        alpha: additive,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        // Because the graphics pipeline must have the same lifetime as the egui render pass,
+        // instead of storing the pipeline in our struct, we insert it into the
+        // `paint_callback_resources` type map, which is stored alongside the render pass.
+        wgpu_render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(AppRenderResources {
+                simulation,
+                pipeline,
+                bind_group,
+                render_params_buffer,
+                quad_vertex_buffer,
+            });
+
+        Some(Self {
+            params,
+            num_particles: DEFAULT_NUM_PARTICLES,
+            particle_size: 0.08,
+            frame: 0,
+            camera_yaw: 0.0,
+            camera_pitch: 0.15,
+        })
+    }
+
+    fn render_params(&self) -> RenderParams {
+        let target = Vec3::new(0.0, 1.5, 0.0);
+        let camera_position = target
+            + 7.0
+                * Vec3::new(
+                    self.camera_pitch.cos() * self.camera_yaw.sin(),
+                    self.camera_pitch.sin(),
+                    self.camera_pitch.cos() * self.camera_yaw.cos(),
+                );
+        let view_matrix = Mat4::look_at_rh(camera_position, target, Vec3::Y);
+        let projection_matrix =
+            Mat4::perspective_rh((2.0 * PI) / 5.0, CANVAS.0 / CANVAS.1, 0.1, 100.0);
+
+        // The rows of the view matrix are the camera axes in world space
+        RenderParams {
+            view_proj: (projection_matrix * view_matrix).to_cols_array_2d(),
+            camera_right: view_matrix.row(0).truncate().extend(0.0).into(),
+            camera_up: view_matrix.row(1).truncate().extend(0.0).into(),
+            particle_size: self.particle_size,
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+impl eframe::App for Particles {
+    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                        self.custom_painting(ui);
+                    });
+                    ui.vertical(|ui| self.controls(ui));
+                });
+            });
+        });
+        // This is needed to keep the simulation running. It tells eframe to call update() again on the next event loop iteration.
+        ctx.request_repaint();
+    }
+}
+
+impl Particles {
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("particles_controls")
+            .num_columns(2)
+            .show(ui, |ui| {
+                let params = &mut self.params;
+                ui.label("particles");
+                ui.add(egui::Slider::new(&mut self.num_particles, 1..=200000).logarithmic(true));
+                ui.end_row();
+                ui.label("spawn from texture");
+                let mut from_texture = params.spawn_from_texture != 0;
+                ui.checkbox(&mut from_texture, "");
+                params.spawn_from_texture = from_texture as u32;
+                ui.end_row();
+                ui.label("lifetime");
+                ui.add(egui::Slider::new(&mut params.lifetime, 0.1..=10.0));
+                ui.end_row();
+                ui.label("speed");
+                ui.add(egui::Slider::new(&mut params.speed, 0.0..=10.0));
+                ui.end_row();
+                ui.label("spread");
+                ui.add(egui::Slider::new(&mut params.spread, 0.0..=2.0));
+                ui.end_row();
+                ui.label("gravity");
+                ui.add(egui::Slider::new(&mut params.gravity, -5.0..=10.0));
+                ui.end_row();
+                ui.label("emitter size");
+                ui.add(egui::Slider::new(&mut params.emitter_size, 0.0..=5.0));
+                ui.end_row();
+                ui.label("emitter x");
+                ui.add(egui::Slider::new(
+                    &mut params.emitter_position[0],
+                    -3.0..=3.0,
+                ));
+                ui.end_row();
+                ui.label("emitter y");
+                ui.add(egui::Slider::new(
+                    &mut params.emitter_position[1],
+                    -3.0..=3.0,
+                ));
+                ui.end_row();
+                ui.label("emitter z");
+                ui.add(egui::Slider::new(
+                    &mut params.emitter_position[2],
+                    -3.0..=3.0,
+                ));
+                ui.end_row();
+                ui.label("color");
+                ui.color_edit_button_rgba_unmultiplied(&mut params.color);
+                ui.end_row();
+                ui.label("particle size");
+                ui.add(egui::Slider::new(&mut self.particle_size, 0.01..=0.5));
+                ui.end_row();
+            });
+        if ui.button("Reset").clicked() {
+            *self = Self {
+                params: SimParams {
+                    texture_width: self.params.texture_width,
+                    texture_height: self.params.texture_height,
+                    ..Default::default()
+                },
+                num_particles: DEFAULT_NUM_PARTICLES,
+                particle_size: 0.08,
+                ..*self
+            };
+        }
+        ui.label("Drag to orbit the camera.");
+    }
+
+    fn custom_painting(&mut self, ui: &mut egui::Ui) {
+        let (rect, response) =
+            ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::drag());
+        let drag = response.drag_delta();
+        self.camera_yaw -= drag.x * 0.01;
+        self.camera_pitch = (self.camera_pitch + drag.y * 0.01).clamp(-1.5, 1.5);
+
+        self.frame = self.frame.wrapping_add(1);
+        let params = SimParams {
+            delta_time: ui.input(|i| i.stable_dt).min(0.1),
+            seed: self.frame,
+            ..self.params
+        };
+
+        ui.painter().rect_filled(rect, 0.0, egui::Color32::BLACK);
+        ui.painter().add(paint_callback(
+            rect,
+            CustomPaintCallback {
+                params,
+                render_params: self.render_params(),
+                num_particles: self.num_particles,
+            },
+        ));
+    }
+}
+
+struct CustomPaintCallback {
+    params: SimParams,
+    render_params: RenderParams,
+    num_particles: u32,
+}
+
+impl egui_wgpu::CallbackTrait for CustomPaintCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let profiler = GpuProfiler::get(callback_resources);
+        let stats = FrameStats::get(callback_resources);
+        let resources: &mut AppRenderResources = callback_resources.get_mut().unwrap();
+        if resources.simulation.num_particles() != self.num_particles {
+            resources.simulation.reset(
+                device,
+                &init_particles(self.num_particles, self.params.lifetime),
+            );
+        }
+        resources
+            .simulation
+            .write_params(queue, &stats, &self.params);
+        resources.simulation.step(egui_encoder, &profiler);
+        stats.write_buffer(
+            queue,
+            &resources.render_params_buffer,
+            0,
+            bytemuck::bytes_of(&self.render_params),
+        );
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: eframe::egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
+        let resources: &AppRenderResources = callback_resources.get().unwrap();
+        render_pass.set_pipeline(&resources.pipeline);
+        render_pass.set_bind_group(0, &resources.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, resources.simulation.particle_buffer().slice(..));
+        render_pass.set_vertex_buffer(1, resources.quad_vertex_buffer.slice(..));
+        render_pass.draw(
+            0..(QUAD_VERTICES.len() / 2) as u32,
+            0..resources.simulation.num_particles(),
+        );
+    }
+}
+
+struct AppRenderResources {
+    pub simulation: ParticleSimulation,
+    pub pipeline: wgpu::RenderPipeline,
+    pub bind_group: wgpu::BindGroup,
+    pub render_params_buffer: wgpu::Buffer,
+    pub quad_vertex_buffer: wgpu::Buffer,
+}
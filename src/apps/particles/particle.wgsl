@@ -0,0 +1,46 @@
+struct RenderParams {
+    view_proj: mat4x4f,
+    // Camera axes in world space, so quads can be turned to face the camera
+    camera_right: vec4f,
+    camera_up: vec4f,
+    particle_size: f32,
+}
+
+struct VertexInput {
+    // Per particle
+    @location(0) position: vec3f,
+    @location(1) lifetime: f32,
+    @location(2) color: vec4f,
+    // Per quad corner
+    @location(3) corner: vec2f,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) color: vec4f,
+    @location(1) corner: vec2f,
+}
+
+@group(0) @binding(0) var<uniform> params: RenderParams;
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    let offset = (params.camera_right.xyz * in.corner.x + params.camera_up.xyz * in.corner.y)
+        * params.particle_size * 0.5;
+
+    var output: VertexOutput;
+    output.position = params.view_proj * vec4(in.position + offset, 1.0);
+    // Fade out during the last half second
+    output.color = vec4(in.color.rgb, in.color.a * clamp(in.lifetime * 2.0, 0.0, 1.0));
+    output.corner = in.corner;
+    return output;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    // A soft round sprite
+    let falloff = max(1.0 - length(in.corner), 0.0);
+    let alpha = in.color.a * falloff * falloff;
+    // Premultiplied, for additive blending
+    return vec4(in.color.rgb * alpha, alpha);
+}
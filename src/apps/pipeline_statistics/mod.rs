@@ -0,0 +1,517 @@
+use eframe::{
+    egui,
+    egui_wgpu::{self, RenderState},
+};
+use glam::{Mat4, Vec3};
+use std::f32::consts::PI;
+use wgpu::util::DeviceExt;
+
+use crate::meshes::cube;
+use crate::offscreen::{self, OffscreenTarget};
+use crate::profiler::GpuProfiler;
+use crate::readback::Readback;
+use crate::stats::{paint_callback, FrameStats};
+
+const CANVAS: (f32, f32) = (600.0, 600.0);
+const MAX_INSTANCES: u32 = 4096;
+/// Distance between the centers of neighboring cubes, which are two units wide.
+const SPACING: f32 = 3.0;
+const STATISTICS: wgpu::PipelineStatisticsTypes =
+    wgpu::PipelineStatisticsTypes::VERTEX_SHADER_INVOCATIONS
+        .union(wgpu::PipelineStatisticsTypes::CLIPPER_INVOCATIONS)
+        .union(wgpu::PipelineStatisticsTypes::CLIPPER_PRIMITIVES_OUT)
+        .union(wgpu::PipelineStatisticsTypes::FRAGMENT_SHADER_INVOCATIONS);
+/// The query resolves to one `u64` per statistic.
+const RESULT_SIZE: wgpu::BufferAddress = 4 * std::mem::size_of::<u64>() as wgpu::BufferAddress;
+
+/// Laid out to match `Uniforms` in `shader.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    view_proj: [[f32; 4]; 4],
+    time: f32,
+    side: u32,
+    spacing: f32,
+    _padding: f32,
+}
+
+/// Which faces the rasterizer discards.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CullMode {
+    None,
+    Front,
+    Back,
+}
+
+impl CullMode {
+    const ALL: [Self; 3] = [Self::None, Self::Front, Self::Back];
+
+    fn face(self) -> Option<wgpu::Face> {
+        match self {
+            Self::None => None,
+            Self::Front => Some(wgpu::Face::Front),
+            Self::Back => Some(wgpu::Face::Back),
+        }
+    }
+}
+
+impl std::fmt::Display for CullMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Front => write!(f, "front"),
+            Self::Back => write!(f, "back"),
+        }
+    }
+}
+
+/// The counters of one pipeline statistics query, in the order of [`STATISTICS`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Statistics {
+    vertex_shader_invocations: u64,
+    clipper_invocations: u64,
+    clipper_primitives_out: u64,
+    fragment_shader_invocations: u64,
+}
+
+impl Statistics {
+    /// Read the resolved query, whose statistics are ordered by their bit in the flags.
+    fn from_results(results: &[u8]) -> Self {
+        let value = |i: usize| bytemuck::pod_read_unaligned::<u64>(&results[i * 8..i * 8 + 8]);
+        Self {
+            vertex_shader_invocations: value(0),
+            clipper_invocations: value(1),
+            clipper_primitives_out: value(2),
+            fragment_shader_invocations: value(3),
+        }
+    }
+}
+
+/// Cubes along each side of the smallest lattice holding `instances` cubes.
+fn lattice_side(instances: u32) -> u32 {
+    let mut side = 1;
+    while side * side * side < instances {
+        side += 1;
+    }
+    side
+}
+
+/// The query and where its results go, on adapters that support pipeline statistics.
+struct StatisticsQuery {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback: Readback,
+}
+
+impl StatisticsQuery {
+    fn new(device: &wgpu::Device) -> Option<Self> {
+        if !device
+            .features()
+            .contains(wgpu::Features::PIPELINE_STATISTICS_QUERY)
+        {
+            return None;
+        }
+        Some(Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("PipelineStatistics Query Set"),
+                ty: wgpu::QueryType::PipelineStatistics(STATISTICS),
+                count: 1,
+            }),
+            resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("PipelineStatistics Resolve Buffer"),
+                size: RESULT_SIZE,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback: Readback::new(device, RESULT_SIZE, "PipelineStatistics Readback Buffer"),
+        })
+    }
+}
+
+pub struct PipelineStatistics {
+    time: f32,
+    animate: bool,
+    instances: u32,
+    cull_mode: CullMode,
+    camera_yaw: f32,
+    camera_pitch: f32,
+    supported: bool,
+    statistics: Option<Statistics>,
+}
+
+impl PipelineStatistics {
+    pub fn new_with_render_state(wgpu_render_state: &RenderState) -> Option<Self> {
+        let device = &wgpu_render_state.device;
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("PipelineStatistics Vertex Buffer"),
+            contents: bytemuck::cast_slice(cube::VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        // Positions and colors, skipping the uvs
+        let vertex_buffer_layouts = [wgpu::VertexBufferLayout {
+            array_stride: 10 * std::mem::size_of::<f32>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4],
+        }];
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("PipelineStatistics Uniform Buffer"),
+            size: std::mem::size_of::<Uniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("PipelineStatistics Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("PipelineStatistics Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("PipelineStatistics Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./shader.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("PipelineStatistics Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        // One pipeline per cull mode, which is fixed at pipeline creation
+        let pipelines = CullMode::ALL.map(|cull_mode| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(&format!("PipelineStatistics Render Pipeline ({cull_mode})")),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &vertex_buffer_layouts,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu_render_state.target_format.into())],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: cull_mode.face(),
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: offscreen::DEPTH_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        });
+
+        let target = OffscreenTarget::new(
+            device,
+            wgpu_render_state.target_format,
+            Some(offscreen::DEPTH_FORMAT),
+            (CANVAS.0 as u32, CANVAS.1 as u32),
+        );
+        let query = StatisticsQuery::new(device);
+        let supported = query.is_some();
+
+        // Because the graphics pipeline must have the same lifetime as the egui render pass,
+        // instead of storing the pipeline in our struct, we insert it into the
+        // `paint_callback_resources` type map, which is stored alongside the render pass.
+        wgpu_render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(AppRenderResources {
+                target,
+                pipelines,
+                vertex_buffer,
+                uniform_buffer,
+                bind_group,
+                query,
+                statistics: None,
+            });
+
+        Some(Self {
+            time: 0.0,
+            animate: true,
+            instances: 64,
+            cull_mode: CullMode::None,
+            camera_yaw: 0.5,
+            camera_pitch: 0.4,
+            supported,
+            statistics: None,
+        })
+    }
+}
+
+impl eframe::App for PipelineStatistics {
+    fn update(&mut self, ctx: &eframe::egui::Context, frame: &mut eframe::Frame) {
+        if let Some(wgpu_render_state) = frame.wgpu_render_state() {
+            let renderer = wgpu_render_state.renderer.read();
+            if let Some(resources) = renderer.callback_resources.get::<AppRenderResources>() {
+                self.statistics = resources.statistics;
+            }
+        }
+        if self.animate {
+            self.time += ctx.input(|i| i.stable_dt);
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                        self.custom_painting(ui);
+                    });
+                    ui.vertical(|ui| self.controls(ui));
+                });
+            });
+        });
+        // This is needed to animate the cubes. It tells eframe to call update() again on the next event loop iteration.
+        ctx.request_repaint();
+    }
+}
+
+impl PipelineStatistics {
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("pipeline_statistics_controls")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("instances");
+                ui.add(egui::Slider::new(&mut self.instances, 1..=MAX_INSTANCES).logarithmic(true));
+                ui.end_row();
+                ui.label("cull mode");
+                egui::ComboBox::from_id_source("pipeline_statistics_cull_mode")
+                    .selected_text(self.cull_mode.to_string())
+                    .show_ui(ui, |ui| {
+                        for cull_mode in CullMode::ALL {
+                            ui.selectable_value(
+                                &mut self.cull_mode,
+                                cull_mode,
+                                cull_mode.to_string(),
+                            );
+                        }
+                    });
+                ui.end_row();
+                ui.label("animate");
+                ui.checkbox(&mut self.animate, "");
+                ui.end_row();
+            });
+        ui.separator();
+
+        egui::Grid::new("pipeline_statistics_results")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("vertices submitted");
+                ui.label((self.instances * cube::VERTEX_COUNT).to_string());
+                ui.end_row();
+                ui.label("triangles submitted");
+                ui.label((self.instances * cube::VERTEX_COUNT / 3).to_string());
+                ui.end_row();
+                if let Some(statistics) = self.statistics {
+                    ui.label("vertex shader invocations");
+                    ui.label(statistics.vertex_shader_invocations.to_string());
+                    ui.end_row();
+                    ui.label("clipper invocations");
+                    ui.label(statistics.clipper_invocations.to_string());
+                    ui.end_row();
+                    ui.label("clipper primitives out");
+                    ui.label(statistics.clipper_primitives_out.to_string());
+                    ui.end_row();
+                    ui.label("fragment shader invocations");
+                    ui.label(statistics.fragment_shader_invocations.to_string());
+                    ui.end_row();
+                }
+            });
+        if !self.supported {
+            ui.label("Pipeline statistics queries are not supported by this adapter.");
+        }
+        ui.label("Back face culling drops the faces turned away from the camera, about half of");
+        ui.label("the triangles of closed meshes, before they reach the fragment shader. Front");
+        ui.label("face culling shows the inside of the cubes instead.");
+        ui.label("Drag to orbit the camera.");
+    }
+
+    fn custom_painting(&mut self, ui: &mut egui::Ui) {
+        let (rect, response) =
+            ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::drag());
+
+        let drag = response.drag_delta();
+        self.camera_yaw -= drag.x * 0.01;
+        self.camera_pitch = (self.camera_pitch + drag.y * 0.01).clamp(-1.5, 1.5);
+
+        // Back off far enough to see the whole lattice
+        let side = lattice_side(self.instances);
+        let distance = side as f32 * SPACING * 1.3 + 4.0;
+        let size = offscreen::size_in_pixels(ui, rect);
+        let projection = Mat4::perspective_rh(
+            (2.0 * PI) / 5.0,
+            size.0 as f32 / size.1 as f32,
+            0.1,
+            distance * 3.0,
+        );
+        let eye = Mat4::from_rotation_y(self.camera_yaw)
+            * Mat4::from_rotation_x(-self.camera_pitch)
+            * Vec3::new(0.0, 0.0, distance).extend(1.0);
+        let view = Mat4::look_at_rh(eye.truncate(), Vec3::ZERO, Vec3::Y);
+
+        ui.painter().add(paint_callback(
+            rect,
+            CustomPaintCallback {
+                size,
+                uniforms: Uniforms {
+                    view_proj: (projection * view).to_cols_array_2d(),
+                    time: self.time,
+                    side,
+                    spacing: SPACING,
+                    _padding: 0.0,
+                },
+                instances: self.instances,
+                cull_mode: self.cull_mode,
+            },
+        ));
+    }
+}
+
+struct CustomPaintCallback {
+    size: (u32, u32),
+    uniforms: Uniforms,
+    instances: u32,
+    cull_mode: CullMode,
+}
+
+impl egui_wgpu::CallbackTrait for CustomPaintCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let profiler = GpuProfiler::get(callback_resources);
+        let stats = FrameStats::get(callback_resources);
+        let resources: &mut AppRenderResources = callback_resources.get_mut().unwrap();
+        resources.target.resize(device, self.size);
+        if let Some(data) = resources
+            .query
+            .as_mut()
+            .and_then(|query| query.readback.poll(device))
+        {
+            resources.statistics = Some(Statistics::from_results(&data));
+        }
+
+        stats.write_buffer(
+            queue,
+            &resources.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&self.uniforms),
+        );
+
+        let mut render_pass = stats.pass(resources.target.begin_pass(
+            egui_encoder,
+            wgpu::Color {
+                r: 0.1,
+                g: 0.1,
+                b: 0.12,
+                a: 1.0,
+            },
+            "PipelineStatistics Render Pass",
+            &profiler,
+        ));
+        let cull_mode = CullMode::ALL
+            .iter()
+            .position(|&cull_mode| cull_mode == self.cull_mode)
+            .unwrap();
+        render_pass.set_pipeline(&resources.pipelines[cull_mode]);
+        render_pass.set_bind_group(0, &resources.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, resources.vertex_buffer.slice(..));
+        if let Some(query) = &resources.query {
+            render_pass.begin_pipeline_statistics_query(&query.query_set, 0);
+        }
+        render_pass.draw(0..cube::VERTEX_COUNT, 0..self.instances);
+        if resources.query.is_some() {
+            render_pass.end_pipeline_statistics_query();
+        }
+        drop(render_pass);
+
+        if let Some(query) = &mut resources.query {
+            egui_encoder.resolve_query_set(&query.query_set, 0..1, &query.resolve_buffer, 0);
+            query
+                .readback
+                .copy_from(egui_encoder, &query.resolve_buffer, RESULT_SIZE);
+        }
+
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: eframe::egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
+        let resources: &AppRenderResources = callback_resources.get().unwrap();
+        resources.target.blit(&mut render_pass);
+    }
+}
+
+struct AppRenderResources {
+    pub target: OffscreenTarget,
+    /// Indexed like [`CullMode::ALL`].
+    pub pipelines: [wgpu::RenderPipeline; 3],
+    pub vertex_buffer: wgpu::Buffer,
+    pub uniform_buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+    pub query: Option<StatisticsQuery>,
+    /// The statistics of the last frame that was read back.
+    pub statistics: Option<Statistics>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_statistics_in_flag_order() {
+        let results: Vec<u8> = bytemuck::cast_slice(&[360u64, 120, 60, 5000]).to_vec();
+        assert_eq!(
+            Statistics::from_results(&results),
+            Statistics {
+                vertex_shader_invocations: 360,
+                clipper_invocations: 120,
+                clipper_primitives_out: 60,
+                fragment_shader_invocations: 5000,
+            }
+        );
+    }
+
+    #[test]
+    fn lattice_holds_every_instance() {
+        assert_eq!(lattice_side(1), 1);
+        assert_eq!(lattice_side(8), 2);
+        assert_eq!(lattice_side(9), 3);
+        assert_eq!(lattice_side(MAX_INSTANCES), 16);
+    }
+}
@@ -0,0 +1,54 @@
+struct Uniforms {
+    view_proj: mat4x4f,
+    time: f32,
+    // Cubes along each side of the lattice
+    side: u32,
+    spacing: f32,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+
+struct VertexInput {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec4f,
+    @location(1) color: vec4f,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) color: vec4f,
+}
+
+fn rotation_x(angle: f32) -> mat3x3f {
+    let c = cos(angle);
+    let s = sin(angle);
+    return mat3x3f(1.0, 0.0, 0.0, 0.0, c, s, 0.0, -s, c);
+}
+
+fn rotation_y(angle: f32) -> mat3x3f {
+    let c = cos(angle);
+    let s = sin(angle);
+    return mat3x3f(c, 0.0, -s, 0.0, 1.0, 0.0, s, 0.0, c);
+}
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    let side = uniforms.side;
+    let index = in.instance_index;
+    let cell = vec3f(f32(index % side), f32(index / side % side), f32(index / (side * side)));
+    let offset = (cell - vec3f(f32(side - 1u) * 0.5)) * uniforms.spacing;
+
+    // Every cube spins at its own pace
+    let angle = uniforms.time * (0.5 + f32(index % 7u) * 0.1) + f32(index);
+    let position = rotation_y(angle) * rotation_x(angle * 0.7) * in.position.xyz;
+
+    var out: VertexOutput;
+    out.position = uniforms.view_proj * vec4f(position + offset, 1.0);
+    out.color = in.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return in.color;
+}
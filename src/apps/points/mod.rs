@@ -0,0 +1,407 @@
+use eframe::{
+    egui,
+    egui_wgpu::{self, RenderState},
+};
+use glam::{Mat4, Vec3};
+use std::f32::consts::PI;
+use wgpu::util::DeviceExt;
+
+use crate::offscreen::{self, OffscreenTarget};
+use crate::profiler::GpuProfiler;
+use crate::stats::{paint_callback, FrameStats};
+
+const CANVAS: (f32, f32) = (600.0, 600.0);
+const DEFAULT_NUM_POINTS: u32 = 1000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SizeUnit {
+    Pixels,
+    World,
+}
+
+/// Laid out to match `Uniforms` in `shader.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    model_view: [[f32; 4]; 4],
+    projection: [[f32; 4]; 4],
+    resolution: [f32; 2],
+    size: f32,
+    size_in_pixels: u32,
+    textured: u32,
+    _padding: [u32; 3],
+}
+
+/// `count` points evenly spread over the unit sphere, placed along a spiral turning by the
+/// golden angle.
+fn fibonacci_sphere(count: u32) -> Vec<[f32; 3]> {
+    let golden_angle = PI * (3.0 - 5f32.sqrt());
+    (0..count)
+        .map(|i| {
+            let y = 1.0 - (i as f32 + 0.5) / count as f32 * 2.0;
+            let radius = (1.0 - y * y).sqrt();
+            let theta = golden_angle * i as f32;
+            [theta.cos() * radius, y, theta.sin() * radius]
+        })
+        .collect()
+}
+
+pub struct Points {
+    start_time: std::time::Instant,
+    num_points: u32,
+    size_unit: SizeUnit,
+    size_in_pixels: f32,
+    size_in_world: f32,
+    textured: bool,
+    rotate: bool,
+    angle: f32,
+}
+
+impl Points {
+    pub fn new_with_render_state(wgpu_render_state: &RenderState) -> Option<Self> {
+        let device = &wgpu_render_state.device;
+        let queue = &wgpu_render_state.queue;
+
+        let vertex_buffer = create_vertex_buffer(device, DEFAULT_NUM_POINTS);
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Points Uniform Buffer"),
+            size: std::mem::size_of::<Uniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let texture_view = {
+            let image =
+                image::load_from_memory(include_bytes!("../textured_cube/assets/happy-tree.png"))
+                    .unwrap()
+                    .to_rgba8();
+            device
+                .create_texture_with_data(
+                    queue,
+                    &wgpu::TextureDescriptor {
+                        label: Some("Points Texture"),
+                        size: wgpu::Extent3d {
+                            width: image.width(),
+                            height: image.height(),
+                            depth_or_array_layers: 1,
+                        },
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                        view_formats: &[],
+                    },
+                    wgpu::util::TextureDataOrder::LayerMajor,
+                    &image,
+                )
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Points Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Points Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Points Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Points Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./shader.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Points Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Points Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                // One point per instance, the quad corners come from the vertex index
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: 3 * std::mem::size_of::<f32>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu_render_state.target_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: offscreen::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let target = OffscreenTarget::new(
+            device,
+            wgpu_render_state.target_format,
+            Some(offscreen::DEPTH_FORMAT),
+            (CANVAS.0 as u32, CANVAS.1 as u32),
+        );
+
+        // Because the graphics pipeline must have the same lifetime as the egui render pass,
+        // instead of storing the pipeline in our struct, we insert it into the
+        // `paint_callback_resources` type map, which is stored alongside the render pass.
+        wgpu_render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(AppRenderResources {
+                target,
+                pipeline,
+                bind_group,
+                uniform_buffer,
+                vertex_buffer,
+                num_points: DEFAULT_NUM_POINTS,
+            });
+
+        Some(Self {
+            start_time: std::time::Instant::now(),
+            num_points: DEFAULT_NUM_POINTS,
+            size_unit: SizeUnit::Pixels,
+            size_in_pixels: 10.0,
+            size_in_world: 0.05,
+            textured: false,
+            rotate: true,
+            angle: 0.0,
+        })
+    }
+
+    fn uniforms(&self, size: (u32, u32)) -> Uniforms {
+        let projection = Mat4::perspective_rh((2.0 * PI) / 5.0, CANVAS.0 / CANVAS.1, 0.1, 50.0);
+        let view = Mat4::look_at_rh(Vec3::new(0.0, 0.0, 3.0), Vec3::ZERO, Vec3::Y);
+        let model = Mat4::from_rotation_y(self.angle) * Mat4::from_rotation_x(self.angle * 0.3);
+
+        // Point sizes in pixels are physical pixels, so scale them with the display
+        let pixels_per_point = size.0 as f32 / CANVAS.0;
+        let (size_value, size_in_pixels) = match self.size_unit {
+            SizeUnit::Pixels => (self.size_in_pixels * pixels_per_point, 1),
+            SizeUnit::World => (self.size_in_world, 0),
+        };
+
+        Uniforms {
+            model_view: (view * model).to_cols_array_2d(),
+            projection: projection.to_cols_array_2d(),
+            resolution: [size.0 as f32, size.1 as f32],
+            size: size_value,
+            size_in_pixels,
+            textured: self.textured as u32,
+            _padding: [0; 3],
+        }
+    }
+}
+
+fn create_vertex_buffer(device: &wgpu::Device, num_points: u32) -> wgpu::Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Points Vertex Buffer"),
+        contents: bytemuck::cast_slice(&fibonacci_sphere(num_points)),
+        usage: wgpu::BufferUsages::VERTEX,
+    })
+}
+
+impl eframe::App for Points {
+    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                        self.custom_painting(ui);
+                    });
+                    ui.vertical(|ui| self.controls(ui));
+                });
+            });
+        });
+        // This is needed to animate the sphere. It tells eframe to call update() again on the next event loop iteration.
+        ctx.request_repaint();
+    }
+}
+
+impl Points {
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("points_controls")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("points");
+                ui.add(egui::Slider::new(&mut self.num_points, 10..=100000).logarithmic(true));
+                ui.end_row();
+                ui.label("size unit");
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.size_unit, SizeUnit::Pixels, "Pixels");
+                    ui.selectable_value(&mut self.size_unit, SizeUnit::World, "World");
+                });
+                ui.end_row();
+                ui.label("size");
+                match self.size_unit {
+                    SizeUnit::Pixels => {
+                        ui.add(egui::Slider::new(&mut self.size_in_pixels, 1.0..=64.0))
+                    }
+                    SizeUnit::World => ui.add(
+                        egui::Slider::new(&mut self.size_in_world, 0.001..=0.5).logarithmic(true),
+                    ),
+                };
+                ui.end_row();
+                ui.label("textured");
+                ui.checkbox(&mut self.textured, "");
+                ui.end_row();
+                ui.label("rotate");
+                ui.checkbox(&mut self.rotate, "");
+                ui.end_row();
+            });
+    }
+
+    fn custom_painting(&mut self, ui: &mut egui::Ui) {
+        let (rect, _response) =
+            ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::click());
+
+        if self.rotate {
+            self.angle = self.start_time.elapsed().as_secs_f32() * 0.5;
+        }
+
+        let size = offscreen::size_in_pixels(ui, rect);
+        ui.painter().add(paint_callback(
+            rect,
+            CustomPaintCallback {
+                size,
+                uniforms: self.uniforms(size),
+                num_points: self.num_points,
+            },
+        ));
+    }
+}
+
+struct CustomPaintCallback {
+    size: (u32, u32),
+    uniforms: Uniforms,
+    num_points: u32,
+}
+
+impl egui_wgpu::CallbackTrait for CustomPaintCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let profiler = GpuProfiler::get(callback_resources);
+        let stats = FrameStats::get(callback_resources);
+        let resources: &mut AppRenderResources = callback_resources.get_mut().unwrap();
+        resources.target.resize(device, self.size);
+        if resources.num_points != self.num_points {
+            resources.vertex_buffer = create_vertex_buffer(device, self.num_points);
+            resources.num_points = self.num_points;
+        }
+        stats.write_buffer(
+            queue,
+            &resources.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&self.uniforms),
+        );
+
+        let mut render_pass = stats.pass(resources.target.begin_pass(
+            egui_encoder,
+            wgpu::Color {
+                r: 0.05,
+                g: 0.05,
+                b: 0.08,
+                a: 1.0,
+            },
+            "Points Render Pass",
+            &profiler,
+        ));
+        render_pass.set_pipeline(&resources.pipeline);
+        render_pass.set_bind_group(0, &resources.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, resources.vertex_buffer.slice(..));
+        render_pass.draw(0..6, 0..resources.num_points);
+        drop(render_pass);
+
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: eframe::egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
+        let resources: &AppRenderResources = callback_resources.get().unwrap();
+        resources.target.blit(&mut render_pass);
+    }
+}
+
+struct AppRenderResources {
+    pub target: OffscreenTarget,
+    pub pipeline: wgpu::RenderPipeline,
+    pub bind_group: wgpu::BindGroup,
+    pub uniform_buffer: wgpu::Buffer,
+    pub vertex_buffer: wgpu::Buffer,
+    pub num_points: u32,
+}
@@ -0,0 +1,70 @@
+struct Uniforms {
+    model_view: mat4x4f,
+    projection: mat4x4f,
+    // Viewport size in pixels
+    resolution: vec2f,
+    size: f32,
+    // Whether `size` is in pixels rather than in world units
+    size_in_pixels: u32,
+    textured: u32,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) color: vec3f,
+    // Position within the quad, from -1 to 1
+    @location(1) corner: vec2f,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var point_sampler: sampler;
+@group(0) @binding(2) var point_texture: texture_2d<f32>;
+
+// wgpu has no point size, so every point is drawn as an instanced quad
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    @location(0) position: vec3f,
+) -> VertexOutput {
+    var corners = array<vec2f, 6>(
+        vec2(-1.0, -1.0), vec2(1.0, -1.0), vec2(-1.0, 1.0),
+        vec2(-1.0, 1.0), vec2(1.0, -1.0), vec2(1.0, 1.0),
+    );
+    let corner = corners[vertex_index];
+
+    let view_position = uniforms.model_view * vec4(position, 1.0);
+    var clip_position: vec4f;
+    if (uniforms.size_in_pixels != 0u) {
+        // Offset in clip space, scaled by w so the size survives the perspective divide
+        clip_position = uniforms.projection * view_position;
+        clip_position += vec4(corner * uniforms.size / uniforms.resolution * clip_position.w, 0.0, 0.0);
+    } else {
+        // Offset in view space, so the quad faces the camera and shrinks with distance
+        clip_position = uniforms.projection * (view_position + vec4(corner * uniforms.size * 0.5, 0.0, 0.0));
+    }
+
+    var output: VertexOutput;
+    output.position = clip_position;
+    output.color = position * 0.5 + 0.5;
+    output.corner = corner;
+    return output;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    if (uniforms.textured != 0u) {
+        let uv = in.corner * vec2(0.5, -0.5) + 0.5;
+        let color = textureSample(point_texture, point_sampler, uv);
+        // Cut out the transparent parts, as points are drawn without blending
+        if (color.a < 0.5) {
+            discard;
+        }
+        return color;
+    }
+
+    // Round points
+    if (length(in.corner) > 1.0) {
+        discard;
+    }
+    return vec4(in.color, 1.0);
+}
@@ -6,20 +6,24 @@ use glam::{Mat4, Quat, Vec3};
 use std::f32::consts::PI;
 use wgpu::util::DeviceExt;
 
+use crate::debug_view::{self, DebugView, DebugViewPipelines};
 use crate::meshes::cube;
+use crate::stats::{paint_callback, FrameStats};
 
 const CANVAS: (f32, f32) = (600.0, 600.0);
 
-fn get_mvp_matrix(start_time: std::time::Instant) -> Mat4 {
+fn get_model_matrix(start_time: std::time::Instant) -> Mat4 {
     let now = std::time::Instant::now()
         .duration_since(start_time)
         .as_secs_f32();
 
-    let model_matrix = Mat4::from_rotation_translation(
+    Mat4::from_rotation_translation(
         Quat::from_axis_angle(Vec3::new(now.sin(), now.cos(), 0.0), 1.0),
         Vec3::ZERO,
-    );
+    )
+}
 
+fn get_mvp_matrix(model_matrix: Mat4) -> Mat4 {
     let view_matrix = Mat4::from_translation(Vec3::new(0.0, 0.0, -4.0));
 
     let projection_matrix = Mat4::perspective_rh((2.0 * PI) / 5.0, CANVAS.0 / CANVAS.1, 1.0, 100.0);
@@ -102,9 +106,17 @@ impl RotatingCube {
             (buffer, bind_group_layout, bind_group)
         };
 
+        let normal_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("RotatingCube Normal Buffer"),
+            contents: bytemuck::cast_slice(cube::NORMALS),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("RotatingCube Shader Module"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("./shader.wgsl").into()),
+            source: wgpu::ShaderSource::Wgsl(
+                debug_view::shader(include_str!("./shader.wgsl"), 1).into(),
+            ),
         });
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -113,13 +125,13 @@ impl RotatingCube {
             push_constant_ranges: &[],
         });
 
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        let pipeline_descriptor = wgpu::RenderPipelineDescriptor {
             label: Some("RotatingCube Render Pipeline"),
             layout: Some(&pipeline_layout),
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: "vs_main",
-                buffers: &[vertex_buffer_layout],
+                buffers: &[vertex_buffer_layout, cube::NORMAL_BUFFER_LAYOUT],
             },
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
@@ -149,7 +161,11 @@ impl RotatingCube {
                 alpha_to_coverage_enabled: false,
             },
             multiview: None,
-        });
+        };
+        let pipeline = device.create_render_pipeline(&pipeline_descriptor);
+        // The same pipeline with the fragment stage of the debug view selected in the side panel
+        let debug_view =
+            DebugViewPipelines::new(device, &pipeline_descriptor, &[&mvp_bind_group_layout]);
 
         // Because the graphics pipeline must have the same lifetime as the egui render pass,
         // instead of storing the pipeline in our struct, we insert it into the
@@ -161,7 +177,9 @@ impl RotatingCube {
             .insert(AppRenderResources {
                 start_time: std::time::Instant::now(),
                 pipeline,
+                debug_view,
                 vertex_buffer,
+                normal_buffer,
                 mvp_buffer,
                 mvp_bind_group,
             });
@@ -188,10 +206,8 @@ impl RotatingCube {
     fn custom_painting(&self, ui: &mut egui::Ui) {
         let (rect, _response) =
             ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::click());
-        ui.painter().add(egui_wgpu::Callback::new_paint_callback(
-            rect,
-            CustomPaintCallback(),
-        ));
+        ui.painter()
+            .add(paint_callback(rect, CustomPaintCallback()));
     }
 }
 
@@ -206,12 +222,19 @@ impl egui_wgpu::CallbackTrait for CustomPaintCallback {
         _egui_encoder: &mut wgpu::CommandEncoder,
         callback_resources: &mut egui_wgpu::CallbackResources,
     ) -> Vec<wgpu::CommandBuffer> {
+        let stats = FrameStats::get(callback_resources);
         let resources: &AppRenderResources = callback_resources.get().unwrap();
-        queue.write_buffer(
+        let model_matrix = get_model_matrix(resources.start_time);
+        stats.write_buffer(
+            queue,
             &resources.mvp_buffer,
             0,
-            bytemuck::cast_slice(&[get_mvp_matrix(resources.start_time)]),
+            // The model matrix follows, for the normals of the debug view
+            bytemuck::cast_slice(&[get_mvp_matrix(model_matrix), model_matrix]),
         );
+        resources
+            .debug_view
+            .prepare(queue, &stats, &DebugView::settings(callback_resources));
         Vec::new()
     }
 
@@ -221,10 +244,16 @@ impl egui_wgpu::CallbackTrait for CustomPaintCallback {
         render_pass: &mut wgpu::RenderPass<'a>,
         callback_resources: &'a egui_wgpu::CallbackResources,
     ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
         let resources: &AppRenderResources = callback_resources.get().unwrap();
-        render_pass.set_pipeline(&resources.pipeline);
+        resources.debug_view.set_pipeline(
+            &mut render_pass,
+            &resources.pipeline,
+            &DebugView::settings(callback_resources),
+        );
         render_pass.set_bind_group(0, &resources.mvp_bind_group, &[]);
         render_pass.set_vertex_buffer(0, resources.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, resources.normal_buffer.slice(..));
         render_pass.draw(0..cube::VERTEX_COUNT, 0..1);
     }
 }
@@ -232,7 +261,9 @@ impl egui_wgpu::CallbackTrait for CustomPaintCallback {
 struct AppRenderResources {
     pub start_time: std::time::Instant,
     pub pipeline: wgpu::RenderPipeline,
+    pub debug_view: DebugViewPipelines,
     pub vertex_buffer: wgpu::Buffer,
+    pub normal_buffer: wgpu::Buffer,
     pub mvp_buffer: wgpu::Buffer,
     pub mvp_bind_group: wgpu::BindGroup,
 }
@@ -0,0 +1,852 @@
+use eframe::{
+    egui,
+    egui_wgpu::{self, RenderState},
+};
+use glam::{Mat4, Quat, Vec3};
+use std::f32::consts::PI;
+use wgpu::util::DeviceExt;
+
+use crate::{
+    meshes::mesh::{Mesh, Vertex},
+    offscreen::{self, OffscreenTarget},
+    profiler::GpuProfiler,
+    stats::{paint_callback, FrameStats},
+};
+
+const CANVAS: (f32, f32) = (600.0, 600.0);
+const SHADOW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+const SHADOW_MAP_SIZES: [u32; 5] = [256, 512, 1024, 2048, 4096];
+/// Radius of a sphere around the origin containing the whole scene, which the light's
+/// orthographic projection has to cover.
+const SCENE_RADIUS: f32 = 5.0;
+const OBJECT_COUNT: usize = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum View {
+    Scene,
+    ShadowMap,
+}
+
+impl std::fmt::Display for View {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Scene => write!(f, "Scene"),
+            Self::ShadowMap => write!(f, "Shadow map"),
+        }
+    }
+}
+
+/// Laid out to match `Uniforms` in `shader.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    light_view_proj: [[f32; 4]; 4],
+    camera_view_proj: [[f32; 4]; 4],
+    light_direction: [f32; 4],
+    pcf_radius: i32,
+    texel_size: f32,
+    viewport_size: [u32; 2],
+}
+
+/// Every object in the scene is the same box, scaled and placed by its instance.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Instance {
+    model_matrix: [[f32; 4]; 4],
+    color: [f32; 4],
+}
+
+impl Instance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+        5 => Float32x4,
+        6 => Float32x4,
+        7 => Float32x4,
+        8 => Float32x4,
+        9 => Float32x4,
+    ];
+
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+
+    fn new(translation: Vec3, rotation: Quat, scale: Vec3, color: [f32; 3]) -> Self {
+        Self {
+            model_matrix: Mat4::from_scale_rotation_translation(scale, rotation, translation)
+                .to_cols_array_2d(),
+            color: [color[0], color[1], color[2], 1.0],
+        }
+    }
+}
+
+/// The settings baked into the shadow pipeline and shadow map, which are recreated when they
+/// change.
+#[derive(Clone, Copy, PartialEq)]
+struct ShadowSettings {
+    /// Constant depth bias, in units of the smallest depth difference the format can represent.
+    depth_bias: i32,
+    depth_bias_slope_scale: f32,
+    map_size: u32,
+}
+
+pub struct ShadowMapping {
+    view: View,
+    settings: ShadowSettings,
+    pcf_radius: i32,
+    light_elevation: f32,
+    light_azimuth: f32,
+    rotate_light: bool,
+    animate_objects: bool,
+    time: f32,
+    camera_yaw: f32,
+    camera_pitch: f32,
+}
+
+impl ShadowMapping {
+    pub fn new_with_render_state(wgpu_render_state: &RenderState) -> Option<Self> {
+        let device = &wgpu_render_state.device;
+
+        let mesh = Mesh::create_box(1.0, 1.0, 1.0);
+
+        // Create the vertex, index and instance buffers
+        let (vertex_buffer, index_buffer, instance_buffer) = {
+            let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("ShadowMapping Vertex Buffer"),
+                contents: bytemuck::cast_slice(&mesh.vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+
+            let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("ShadowMapping Index Buffer"),
+                contents: bytemuck::cast_slice(&mesh.indices),
+                usage: wgpu::BufferUsages::INDEX,
+            });
+
+            let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("ShadowMapping Instance Buffer"),
+                size: (std::mem::size_of::<Instance>() * OBJECT_COUNT) as u64,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+            (vertex_buffer, index_buffer, instance_buffer)
+        };
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("ShadowMapping Uniform Buffer"),
+            size: std::mem::size_of::<Uniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let uniform_entry = wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        // The shadow pass renders into the shadow map, so it cannot have it bound as well
+        let (shadow_bind_group_layout, shadow_bind_group) = {
+            let bind_group_layout =
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("ShadowMapping Shadow Bind Group Layout"),
+                    entries: &[uniform_entry],
+                });
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("ShadowMapping Shadow Bind Group"),
+                layout: &bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                }],
+            });
+
+            (bind_group_layout, bind_group)
+        };
+
+        let scene_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("ShadowMapping Scene Bind Group Layout"),
+                entries: &[
+                    uniform_entry,
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Depth,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                        count: None,
+                    },
+                ],
+            });
+
+        let shadow_map_display_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("ShadowMapping Shadow Map Display Bind Group Layout"),
+                entries: &[
+                    uniform_entry,
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        // Not every backend can load from depth textures, but all of them can
+                        // read depth as unfilterable floats
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        // Linear filtering makes the comparison sampler return the fraction of the 4 closest
+        // texels that pass, which is PCF in hardware
+        let comparison_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("ShadowMapping Comparison Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("ShadowMapping Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./shader.wgsl").into()),
+        });
+
+        let shadow_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("ShadowMapping Shadow Pipeline Layout"),
+                bind_group_layouts: &[&shadow_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let scene_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("ShadowMapping Scene Pipeline Layout"),
+                bind_group_layouts: &[&scene_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let scene_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("ShadowMapping Scene Pipeline"),
+            layout: Some(&scene_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::layout(), Instance::layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu_render_state.target_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: offscreen::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let shadow_map_display_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("ShadowMapping Shadow Map Display Pipeline Layout"),
+                bind_group_layouts: &[&shadow_map_display_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let shadow_map_display_pipeline =
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("ShadowMapping Shadow Map Display Pipeline"),
+                layout: Some(&shadow_map_display_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_fullscreen",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_shadow_map",
+                    targets: &[Some(wgpu_render_state.target_format.into())],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: offscreen::DEPTH_FORMAT,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::Always,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            });
+
+        let settings = ShadowSettings {
+            depth_bias: 4000,
+            depth_bias_slope_scale: 2.0,
+            map_size: 1024,
+        };
+
+        let shadow_pipeline =
+            create_shadow_pipeline(device, &shadow_pipeline_layout, &shader, settings);
+        let shadow_map = ShadowMap::new(
+            device,
+            settings.map_size,
+            &scene_bind_group_layout,
+            &shadow_map_display_bind_group_layout,
+            &uniform_buffer,
+            &comparison_sampler,
+        );
+
+        let target = OffscreenTarget::new(
+            device,
+            wgpu_render_state.target_format,
+            Some(offscreen::DEPTH_FORMAT),
+            (CANVAS.0 as u32, CANVAS.1 as u32),
+        );
+
+        // Because the graphics pipeline must have the same lifetime as the egui render pass,
+        // instead of storing the pipeline in our struct, we insert it into the
+        // `paint_callback_resources` type map, which is stored alongside the render pass.
+        wgpu_render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(AppRenderResources {
+                target,
+                shader,
+                settings,
+                shadow_pipeline_layout,
+                shadow_pipeline,
+                scene_pipeline,
+                shadow_map_display_pipeline,
+                scene_bind_group_layout,
+                shadow_map_display_bind_group_layout,
+                comparison_sampler,
+                shadow_map,
+                shadow_bind_group,
+                uniform_buffer,
+                vertex_buffer,
+                index_buffer,
+                instance_buffer,
+                index_count: mesh.indices.len() as u32,
+            });
+
+        Some(Self {
+            view: View::Scene,
+            settings,
+            pcf_radius: 1,
+            light_elevation: 0.9,
+            light_azimuth: 0.6,
+            rotate_light: true,
+            animate_objects: true,
+            time: 0.0,
+            camera_yaw: 0.5,
+            camera_pitch: 0.5,
+        })
+    }
+
+    fn instances(&self) -> [Instance; OBJECT_COUNT] {
+        let t = self.time;
+        let mut instances = [Instance::new(
+            Vec3::new(0.0, -0.1, 0.0),
+            Quat::IDENTITY,
+            Vec3::new(8.0, 0.2, 8.0),
+            [0.8, 0.8, 0.78],
+        ); OBJECT_COUNT];
+
+        // A tall pillar, so there is a long shadow falling across the other objects
+        instances[1] = Instance::new(
+            Vec3::new(-1.6, 1.25, -1.2),
+            Quat::IDENTITY,
+            Vec3::new(0.5, 2.5, 0.5),
+            [0.9, 0.85, 0.6],
+        );
+        // A slab floating above the center, spinning
+        instances[2] = Instance::new(
+            Vec3::new(0.0, 1.3, 0.0),
+            Quat::from_rotation_y(t * 0.5) * Quat::from_rotation_x(0.3),
+            Vec3::new(1.4, 0.15, 0.8),
+            [0.3, 0.6, 0.9],
+        );
+        // Cubes circling around it
+        for (i, instance) in instances[3..].iter_mut().enumerate() {
+            let angle = t * 0.4 + i as f32 * 2.0 * PI / (OBJECT_COUNT - 3) as f32;
+            let hue = i as f32 / (OBJECT_COUNT - 3) as f32;
+            *instance = Instance::new(
+                Vec3::new(
+                    2.2 * angle.cos(),
+                    0.35 + 0.2 * (t + i as f32).sin().abs(),
+                    2.2 * angle.sin(),
+                ),
+                Quat::from_rotation_y(-angle) * Quat::from_rotation_z(t * (0.5 + hue)),
+                Vec3::splat(0.5),
+                [0.9 - 0.6 * hue, 0.3 + 0.4 * hue, 0.3 + 0.5 * hue],
+            );
+        }
+
+        instances
+    }
+
+    fn uniforms(&self, viewport_size: (u32, u32)) -> Uniforms {
+        let light_direction = Vec3::new(
+            self.light_elevation.cos() * self.light_azimuth.cos(),
+            self.light_elevation.sin(),
+            self.light_elevation.cos() * self.light_azimuth.sin(),
+        );
+        // A directional light has parallel rays, so an orthographic projection around the
+        // scene, looking along the light direction
+        let light_view = Mat4::look_at_rh(
+            light_direction * SCENE_RADIUS * 2.0,
+            Vec3::ZERO,
+            if light_direction.y.abs() > 0.99 {
+                Vec3::Z
+            } else {
+                Vec3::Y
+            },
+        );
+        let light_projection = Mat4::orthographic_rh(
+            -SCENE_RADIUS,
+            SCENE_RADIUS,
+            -SCENE_RADIUS,
+            SCENE_RADIUS,
+            SCENE_RADIUS,
+            SCENE_RADIUS * 3.0,
+        );
+
+        let camera_position = Vec3::new(
+            7.0 * self.camera_pitch.cos() * self.camera_yaw.sin(),
+            7.0 * self.camera_pitch.sin(),
+            7.0 * self.camera_pitch.cos() * self.camera_yaw.cos(),
+        );
+        let camera_view = Mat4::look_at_rh(camera_position, Vec3::new(0.0, 0.5, 0.0), Vec3::Y);
+        let camera_projection =
+            Mat4::perspective_rh((2.0 * PI) / 5.0, CANVAS.0 / CANVAS.1, 1.0, 100.0);
+
+        Uniforms {
+            light_view_proj: (light_projection * light_view).to_cols_array_2d(),
+            camera_view_proj: (camera_projection * camera_view).to_cols_array_2d(),
+            light_direction: light_direction.extend(0.0).into(),
+            pcf_radius: self.pcf_radius,
+            texel_size: 1.0 / self.settings.map_size as f32,
+            viewport_size: [viewport_size.0, viewport_size.1],
+        }
+    }
+}
+
+impl eframe::App for ShadowMapping {
+    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                        self.custom_painting(ui);
+                    });
+                    ui.vertical(|ui| self.controls(ui));
+                });
+            });
+        });
+        // This is needed to animate the scene. It tells eframe to call update() again on the next event loop iteration.
+        ctx.request_repaint();
+    }
+}
+
+impl ShadowMapping {
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("shadow_mapping_controls")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("view");
+                egui::ComboBox::from_id_source("shadow_mapping_view")
+                    .selected_text(self.view.to_string())
+                    .show_ui(ui, |ui| {
+                        for view in [View::Scene, View::ShadowMap] {
+                            ui.selectable_value(&mut self.view, view, view.to_string());
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("shadow map size");
+                egui::ComboBox::from_id_source("shadow_mapping_size")
+                    .selected_text(self.settings.map_size.to_string())
+                    .show_ui(ui, |ui| {
+                        for size in SHADOW_MAP_SIZES {
+                            ui.selectable_value(
+                                &mut self.settings.map_size,
+                                size,
+                                size.to_string(),
+                            );
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("depth bias");
+                ui.add(
+                    egui::Slider::new(&mut self.settings.depth_bias, 0..=100000).logarithmic(true),
+                );
+                ui.end_row();
+                ui.label("slope scale");
+                ui.add(egui::Slider::new(
+                    &mut self.settings.depth_bias_slope_scale,
+                    0.0..=10.0,
+                ));
+                ui.end_row();
+
+                ui.label("PCF kernel");
+                egui::ComboBox::from_id_source("shadow_mapping_pcf")
+                    .selected_text(pcf_name(self.pcf_radius))
+                    .show_ui(ui, |ui| {
+                        for radius in 0..=3 {
+                            ui.selectable_value(&mut self.pcf_radius, radius, pcf_name(radius));
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("light elevation");
+                ui.add(egui::Slider::new(&mut self.light_elevation, 0.2..=1.5));
+                ui.end_row();
+                ui.label("light azimuth");
+                ui.add(egui::Slider::new(&mut self.light_azimuth, 0.0..=2.0 * PI));
+                ui.end_row();
+                ui.label("rotate light");
+                ui.checkbox(&mut self.rotate_light, "");
+                ui.end_row();
+                ui.label("animate objects");
+                ui.checkbox(&mut self.animate_objects, "");
+                ui.end_row();
+            });
+        ui.label("Drag to orbit the camera.");
+    }
+
+    fn custom_painting(&mut self, ui: &mut egui::Ui) {
+        let (rect, response) =
+            ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::drag());
+
+        let dt = ui.input(|i| i.stable_dt).min(0.1);
+        if self.animate_objects {
+            self.time += dt;
+        }
+        if self.rotate_light {
+            self.light_azimuth = (self.light_azimuth + dt * 0.2).rem_euclid(2.0 * PI);
+        }
+        let drag = response.drag_delta();
+        self.camera_yaw -= drag.x * 0.01;
+        self.camera_pitch = (self.camera_pitch + drag.y * 0.01).clamp(0.05, 1.5);
+
+        let size = offscreen::size_in_pixels(ui, rect);
+        ui.painter().add(paint_callback(
+            rect,
+            CustomPaintCallback {
+                size,
+                view: self.view,
+                settings: self.settings,
+                uniforms: self.uniforms(size),
+                instances: self.instances(),
+            },
+        ));
+    }
+}
+
+fn pcf_name(radius: i32) -> String {
+    match radius {
+        0 => "Off".to_owned(),
+        radius => format!("{0}x{0}", 2 * radius + 1),
+    }
+}
+
+fn create_shadow_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    settings: ShadowSettings,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("ShadowMapping Shadow Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_shadow",
+            buffers: &[Vertex::layout(), Instance::layout()],
+        },
+        // Only depth is written
+        fragment: None,
+        primitive: wgpu::PrimitiveState {
+            cull_mode: Some(wgpu::Face::Back),
+            ..Default::default()
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: SHADOW_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            // Push the stored depths away from the light, so surfaces do not shadow themselves
+            // (shadow acne). Too much bias detaches shadows from their casters (peter panning).
+            bias: wgpu::DepthBiasState {
+                constant: settings.depth_bias,
+                slope_scale: settings.depth_bias_slope_scale,
+                clamp: 0.0,
+            },
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+/// The shadow map texture, with bind groups to sample it from the scene and to display it.
+struct ShadowMap {
+    view: wgpu::TextureView,
+    scene_bind_group: wgpu::BindGroup,
+    display_bind_group: wgpu::BindGroup,
+}
+
+impl ShadowMap {
+    fn new(
+        device: &wgpu::Device,
+        size: u32,
+        scene_bind_group_layout: &wgpu::BindGroupLayout,
+        display_bind_group_layout: &wgpu::BindGroupLayout,
+        uniform_buffer: &wgpu::Buffer,
+        comparison_sampler: &wgpu::Sampler,
+    ) -> Self {
+        let view = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("ShadowMapping Shadow Map"),
+                size: wgpu::Extent3d {
+                    width: size,
+                    height: size,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: SHADOW_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let scene_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ShadowMapping Scene Bind Group"),
+            layout: scene_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(comparison_sampler),
+                },
+            ],
+        });
+
+        let display_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ShadowMapping Shadow Map Display Bind Group"),
+            layout: display_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+            ],
+        });
+
+        Self {
+            view,
+            scene_bind_group,
+            display_bind_group,
+        }
+    }
+}
+
+struct CustomPaintCallback {
+    size: (u32, u32),
+    view: View,
+    settings: ShadowSettings,
+    uniforms: Uniforms,
+    instances: [Instance; OBJECT_COUNT],
+}
+
+impl egui_wgpu::CallbackTrait for CustomPaintCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let profiler = GpuProfiler::get(callback_resources);
+        let stats = FrameStats::get(callback_resources);
+        let resources: &mut AppRenderResources = callback_resources.get_mut().unwrap();
+        resources.target.resize(device, self.size);
+        resources.update_settings(device, self.settings);
+
+        stats.write_buffer(
+            queue,
+            &resources.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&self.uniforms),
+        );
+        stats.write_buffer(
+            queue,
+            &resources.instance_buffer,
+            0,
+            bytemuck::cast_slice(&self.instances),
+        );
+
+        // Render the scene's depth from the light
+        {
+            let mut shadow_pass =
+                stats.pass(egui_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("ShadowMapping Shadow Pass"),
+                    color_attachments: &[],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &resources.shadow_map.view,
+                        depth_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(1.0),
+                            store: wgpu::StoreOp::Store,
+                        }),
+                        stencil_ops: None,
+                    }),
+                    timestamp_writes: profiler.render_pass_writes("ShadowMapping Shadow Pass"),
+                    occlusion_query_set: None,
+                }));
+            shadow_pass.set_pipeline(&resources.shadow_pipeline);
+            shadow_pass.set_bind_group(0, &resources.shadow_bind_group, &[]);
+            shadow_pass.set_vertex_buffer(0, resources.vertex_buffer.slice(..));
+            shadow_pass.set_vertex_buffer(1, resources.instance_buffer.slice(..));
+            shadow_pass
+                .set_index_buffer(resources.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            shadow_pass.draw_indexed(0..resources.index_count, 0, 0..OBJECT_COUNT as u32);
+        }
+
+        // Then render it from the camera, or show the shadow map itself
+        let mut render_pass = stats.pass(resources.target.begin_pass(
+            egui_encoder,
+            wgpu::Color {
+                r: 0.45,
+                g: 0.6,
+                b: 0.75,
+                a: 1.0,
+            },
+            "ShadowMapping Scene Pass",
+            &profiler,
+        ));
+        match self.view {
+            View::Scene => {
+                render_pass.set_pipeline(&resources.scene_pipeline);
+                render_pass.set_bind_group(0, &resources.shadow_map.scene_bind_group, &[]);
+                render_pass.set_vertex_buffer(0, resources.vertex_buffer.slice(..));
+                render_pass.set_vertex_buffer(1, resources.instance_buffer.slice(..));
+                render_pass
+                    .set_index_buffer(resources.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed(0..resources.index_count, 0, 0..OBJECT_COUNT as u32);
+            }
+            View::ShadowMap => {
+                render_pass.set_pipeline(&resources.shadow_map_display_pipeline);
+                render_pass.set_bind_group(0, &resources.shadow_map.display_bind_group, &[]);
+                render_pass.draw(0..3, 0..1);
+            }
+        }
+        drop(render_pass);
+
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: eframe::egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
+        let resources: &AppRenderResources = callback_resources.get().unwrap();
+        resources.target.blit(&mut render_pass);
+    }
+}
+
+struct AppRenderResources {
+    pub target: OffscreenTarget,
+    pub shader: wgpu::ShaderModule,
+    pub settings: ShadowSettings,
+    pub shadow_pipeline_layout: wgpu::PipelineLayout,
+    pub shadow_pipeline: wgpu::RenderPipeline,
+    pub scene_pipeline: wgpu::RenderPipeline,
+    pub shadow_map_display_pipeline: wgpu::RenderPipeline,
+    pub scene_bind_group_layout: wgpu::BindGroupLayout,
+    pub shadow_map_display_bind_group_layout: wgpu::BindGroupLayout,
+    pub comparison_sampler: wgpu::Sampler,
+    pub shadow_map: ShadowMap,
+    pub shadow_bind_group: wgpu::BindGroup,
+    pub uniform_buffer: wgpu::Buffer,
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub instance_buffer: wgpu::Buffer,
+    pub index_count: u32,
+}
+
+impl AppRenderResources {
+    /// The depth bias is part of the pipeline state and the shadow map size of the texture, so
+    /// changing either means recreating them.
+    fn update_settings(&mut self, device: &wgpu::Device, settings: ShadowSettings) {
+        if settings.depth_bias != self.settings.depth_bias
+            || settings.depth_bias_slope_scale != self.settings.depth_bias_slope_scale
+        {
+            self.shadow_pipeline = create_shadow_pipeline(
+                device,
+                &self.shadow_pipeline_layout,
+                &self.shader,
+                settings,
+            );
+        }
+        if settings.map_size != self.settings.map_size {
+            self.shadow_map = ShadowMap::new(
+                device,
+                settings.map_size,
+                &self.scene_bind_group_layout,
+                &self.shadow_map_display_bind_group_layout,
+                &self.uniform_buffer,
+                &self.comparison_sampler,
+            );
+        }
+        self.settings = settings;
+    }
+}
@@ -0,0 +1,111 @@
+struct Uniforms {
+    light_view_proj: mat4x4f,
+    camera_view_proj: mat4x4f,
+    // Points towards the light
+    light_direction: vec4f,
+    // 0 samples the shadow map once, N averages a (2N + 1) x (2N + 1) kernel
+    pcf_radius: i32,
+    texel_size: f32,
+    viewport_size: vec2u,
+}
+
+struct VertexInput {
+    @location(0) position: vec3f,
+    @location(1) normal: vec3f,
+}
+
+struct InstanceInput {
+    @location(5) model_0: vec4f,
+    @location(6) model_1: vec4f,
+    @location(7) model_2: vec4f,
+    @location(8) model_3: vec4f,
+    @location(9) color: vec4f,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) normal: vec3f,
+    @location(1) color: vec3f,
+    @location(2) shadow_position: vec3f,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var shadow_map: texture_depth_2d;
+@group(0) @binding(2) var shadow_sampler: sampler_comparison;
+// The same shadow map, viewed as plain values rather than compared against
+@group(0) @binding(3) var shadow_map_values: texture_2d<f32>;
+
+fn model_matrix(instance: InstanceInput) -> mat4x4f {
+    return mat4x4f(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+}
+
+// Depth only pass from the light's point of view
+@vertex
+fn vs_shadow(vertex: VertexInput, instance: InstanceInput) -> @builtin(position) vec4f {
+    return uniforms.light_view_proj * model_matrix(instance) * vec4(vertex.position, 1.0);
+}
+
+@vertex
+fn vs_main(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let world_position = model_matrix(instance) * vec4(vertex.position, 1.0);
+
+    // From light clip space to shadow map uv, y pointing down in texture space
+    let light_position = uniforms.light_view_proj * world_position;
+    let shadow_uv = light_position.xy * vec2(0.5, -0.5) + vec2(0.5);
+
+    var output: VertexOutput;
+    output.position = uniforms.camera_view_proj * world_position;
+    // Only boxes scaled along their axes are drawn, so the model matrix maps their normals fine
+    output.normal = normalize((model_matrix(instance) * vec4(vertex.normal, 0.0)).xyz);
+    output.color = instance.color.rgb;
+    output.shadow_position = vec3(shadow_uv, light_position.z);
+    return output;
+}
+
+// Fraction of the fragment that is lit, 1 outside of the shadow map
+fn visibility(shadow_position: vec3f) -> f32 {
+    var lit = 0.0;
+    for (var y = -uniforms.pcf_radius; y <= uniforms.pcf_radius; y++) {
+        for (var x = -uniforms.pcf_radius; x <= uniforms.pcf_radius; x++) {
+            let offset = vec2f(f32(x), f32(y)) * uniforms.texel_size;
+            // The comparison sampler filters linearly, so every tap already blends 2x2 texels
+            lit += textureSampleCompareLevel(
+                shadow_map,
+                shadow_sampler,
+                shadow_position.xy + offset,
+                shadow_position.z,
+            );
+        }
+    }
+    let taps = f32((2 * uniforms.pcf_radius + 1) * (2 * uniforms.pcf_radius + 1));
+
+    let outside = any(shadow_position.xy < vec2(0.0)) || any(shadow_position.xy > vec2(1.0))
+        || shadow_position.z > 1.0;
+    return select(lit / taps, 1.0, outside);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let lambert = max(dot(normalize(in.normal), uniforms.light_direction.xyz), 0.0);
+    let ambient = 0.25;
+    let light = ambient + (1.0 - ambient) * lambert * visibility(in.shadow_position);
+    return vec4(in.color * light, 1.0);
+}
+
+// Full screen triangle showing the shadow map itself
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4f {
+    let uv = vec2f(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_shadow_map(@builtin(position) position: vec4f) -> @location(0) vec4f {
+    // Stretch the shadow map over the viewport, which has the same size as the
+    // offscreen target
+    let size = vec2f(textureDimensions(shadow_map_values));
+    let viewport_size = vec2f(uniforms.viewport_size);
+    let texel = vec2i(position.xy / viewport_size * size);
+    let depth = textureLoad(shadow_map_values, texel, 0).r;
+    return vec4(vec3(depth), 1.0);
+}
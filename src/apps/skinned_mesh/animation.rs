@@ -0,0 +1,288 @@
+//! Keyframe animation of node transforms, sampled on the CPU.
+//!
+//! Mirrors the glTF animation model: each channel animates the translation, rotation or scale
+//! of one node from a list of keyframe times and values. See
+//! <https://registry.khronos.org/glTF/specs/2.0/glTF-2.0.html#animations>.
+
+use glam::{Mat4, Quat, Vec3};
+
+/// A node transform split into translation, rotation and scale, which is what channels animate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            translation: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+        }
+    }
+}
+
+impl Transform {
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Values are interpolated linearly, rotations with a spherical linear interpolation.
+    Linear,
+    /// Each value is held until the next keyframe.
+    Step,
+}
+
+/// The values of a channel, one per keyframe.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Keyframes {
+    Translation(Vec<Vec3>),
+    Rotation(Vec<Quat>),
+    Scale(Vec<Vec3>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Channel {
+    /// Index of the animated node.
+    pub node: usize,
+    pub interpolation: Interpolation,
+    /// Keyframe times in seconds, in increasing order.
+    pub times: Vec<f32>,
+    pub keyframes: Keyframes,
+}
+
+/// The keyframes surrounding `time` and how far `time` is between them. Times before the first
+/// or after the last keyframe clamp to it.
+fn keyframe_span(times: &[f32], time: f32) -> (usize, usize, f32) {
+    let next = times.partition_point(|&t| t <= time);
+    if next == 0 {
+        return (0, 0, 0.0);
+    }
+    if next == times.len() {
+        return (next - 1, next - 1, 0.0);
+    }
+    let previous = next - 1;
+    let duration = times[next] - times[previous];
+    (previous, next, (time - times[previous]) / duration)
+}
+
+impl Keyframes {
+    fn count(&self) -> usize {
+        match self {
+            Self::Translation(values) | Self::Scale(values) => values.len(),
+            Self::Rotation(values) => values.len(),
+        }
+    }
+}
+
+impl Channel {
+    /// A channel with one value per keyframe time, as [`Self::apply`] needs at least one.
+    pub fn new(
+        node: usize,
+        interpolation: Interpolation,
+        times: Vec<f32>,
+        keyframes: Keyframes,
+    ) -> Result<Self, String> {
+        if times.is_empty() {
+            return Err(format!("a channel of node {node} has no keyframes"));
+        }
+        if keyframes.count() != times.len() {
+            return Err(format!(
+                "a channel of node {node} has {} keyframe times but {} values",
+                times.len(),
+                keyframes.count()
+            ));
+        }
+        Ok(Self {
+            node,
+            interpolation,
+            times,
+            keyframes,
+        })
+    }
+
+    /// Set the property this channel animates in `transform` to its value at `time`.
+    pub fn apply(&self, time: f32, transform: &mut Transform) {
+        let (previous, next, t) = keyframe_span(&self.times, time);
+        let t = match self.interpolation {
+            Interpolation::Linear => t,
+            Interpolation::Step => 0.0,
+        };
+        match &self.keyframes {
+            Keyframes::Translation(values) => {
+                transform.translation = values[previous].lerp(values[next], t);
+            }
+            Keyframes::Rotation(values) => {
+                // `slerp` takes the shortest path, whichever sign the quaternions have
+                transform.rotation = values[previous].slerp(values[next], t).normalize();
+            }
+            Keyframes::Scale(values) => {
+                transform.scale = values[previous].lerp(values[next], t);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Animation {
+    pub name: String,
+    pub channels: Vec<Channel>,
+    /// Time of the last keyframe of any channel.
+    pub duration: f32,
+}
+
+impl Animation {
+    pub fn new(name: String, channels: Vec<Channel>) -> Self {
+        let duration = channels
+            .iter()
+            .filter_map(|channel| channel.times.last().copied())
+            .fold(0.0, f32::max);
+        Self {
+            name,
+            channels,
+            duration,
+        }
+    }
+
+    /// Apply every channel at `time`, looping over the duration of the animation, to the node
+    /// transforms. Nodes no channel targets keep their transform.
+    pub fn sample(&self, time: f32, transforms: &mut [Transform]) {
+        let time = if self.duration > 0.0 {
+            time.rem_euclid(self.duration)
+        } else {
+            0.0
+        };
+        for channel in &self.channels {
+            if let Some(transform) = transforms.get_mut(channel.node) {
+                channel.apply(time, transform);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn channel(interpolation: Interpolation, keyframes: Keyframes) -> Channel {
+        Channel {
+            node: 0,
+            interpolation,
+            times: vec![1.0, 2.0, 4.0],
+            keyframes,
+        }
+    }
+
+    fn translations() -> Keyframes {
+        Keyframes::Translation(vec![
+            Vec3::ZERO,
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(2.0, 4.0, 0.0),
+        ])
+    }
+
+    fn sample(channel: &Channel, time: f32) -> Transform {
+        let mut transform = Transform::default();
+        channel.apply(time, &mut transform);
+        transform
+    }
+
+    #[test]
+    fn linear_translation_interpolates_between_keyframes() {
+        let channel = channel(Interpolation::Linear, translations());
+        assert_eq!(sample(&channel, 1.0).translation, Vec3::ZERO);
+        assert_eq!(sample(&channel, 1.5).translation, Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(sample(&channel, 2.0).translation, Vec3::new(2.0, 0.0, 0.0));
+        assert_eq!(sample(&channel, 3.0).translation, Vec3::new(2.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn times_outside_the_keyframes_clamp() {
+        let channel = channel(Interpolation::Linear, translations());
+        assert_eq!(sample(&channel, 0.0).translation, Vec3::ZERO);
+        assert_eq!(sample(&channel, 10.0).translation, Vec3::new(2.0, 4.0, 0.0));
+    }
+
+    #[test]
+    fn step_holds_the_previous_keyframe() {
+        let channel = channel(Interpolation::Step, translations());
+        assert_eq!(sample(&channel, 1.0).translation, Vec3::ZERO);
+        assert_eq!(sample(&channel, 1.99).translation, Vec3::ZERO);
+        assert_eq!(sample(&channel, 2.0).translation, Vec3::new(2.0, 0.0, 0.0));
+        assert_eq!(sample(&channel, 3.9).translation, Vec3::new(2.0, 0.0, 0.0));
+        assert_eq!(sample(&channel, 4.0).translation, Vec3::new(2.0, 4.0, 0.0));
+    }
+
+    #[test]
+    fn linear_rotation_slerps_along_the_shortest_path() {
+        let quarter_turn = Quat::from_rotation_z(FRAC_PI_2);
+        // The same rotations as identity and a quarter turn, with flipped signs
+        let channel = channel(
+            Interpolation::Linear,
+            Keyframes::Rotation(vec![-Quat::IDENTITY, quarter_turn, -quarter_turn]),
+        );
+        let halfway = sample(&channel, 1.5).rotation;
+        assert!(halfway.angle_between(Quat::from_rotation_z(FRAC_PI_2 / 2.0)) < 1e-5);
+        assert!(halfway.is_normalized());
+        assert!(sample(&channel, 3.0).rotation.angle_between(quarter_turn) < 1e-5);
+    }
+
+    #[test]
+    fn channels_only_touch_their_property() {
+        let channel = channel(
+            Interpolation::Linear,
+            Keyframes::Scale(vec![Vec3::ONE, Vec3::splat(3.0), Vec3::ONE]),
+        );
+        let mut transform = Transform {
+            translation: Vec3::new(1.0, 2.0, 3.0),
+            ..Default::default()
+        };
+        channel.apply(1.5, &mut transform);
+        assert_eq!(transform.scale, Vec3::splat(2.0));
+        assert_eq!(transform.translation, Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(transform.rotation, Quat::IDENTITY);
+    }
+
+    #[test]
+    fn channels_need_a_value_per_keyframe() {
+        let new =
+            |times: Vec<f32>, keyframes| Channel::new(0, Interpolation::Linear, times, keyframes);
+        assert!(new(vec![1.0, 2.0, 4.0], translations()).is_ok());
+        assert!(new(Vec::new(), Keyframes::Scale(Vec::new())).is_err());
+        assert!(new(vec![1.0, 2.0, 4.0, 5.0], translations()).is_err());
+        assert!(new(vec![1.0], Keyframes::Rotation(vec![Quat::IDENTITY; 2])).is_err());
+    }
+
+    #[test]
+    fn animation_loops_over_its_duration() {
+        let animation = Animation::new(
+            "Test".to_string(),
+            vec![
+                channel(Interpolation::Linear, translations()),
+                Channel {
+                    node: 1,
+                    interpolation: Interpolation::Step,
+                    times: vec![0.0, 2.5],
+                    keyframes: Keyframes::Scale(vec![Vec3::ONE, Vec3::splat(2.0)]),
+                },
+            ],
+        );
+        assert_eq!(animation.duration, 4.0);
+
+        let mut transforms = [Transform::default(); 3];
+        animation.sample(4.0 + 1.5, &mut transforms);
+        assert_eq!(transforms[0].translation, Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(transforms[1].scale, Vec3::ONE);
+        assert_eq!(transforms[2], Transform::default());
+
+        animation.sample(-1.0, &mut transforms);
+        assert_eq!(transforms[0].translation, Vec3::new(2.0, 2.0, 0.0));
+        assert_eq!(transforms[1].scale, Vec3::splat(2.0));
+    }
+}
@@ -0,0 +1,582 @@
+mod animation;
+mod model;
+
+use eframe::{
+    egui,
+    egui_wgpu::{self, RenderState},
+};
+use glam::{Mat4, Vec3};
+use std::{f32::consts::PI, sync::Arc};
+use wgpu::util::DeviceExt;
+
+use crate::offscreen::{self, OffscreenTarget};
+use crate::profiler::GpuProfiler;
+use crate::stats::{paint_callback, FrameStats};
+use model::{SkinnedModel, SkinnedVertex};
+
+const CANVAS: (f32, f32) = (600.0, 600.0);
+/// Joints are marked with crosses this large, relative to the radius of the model.
+const JOINT_MARKER_SIZE: f32 = 0.04;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Shading {
+    Lit,
+    JointWeights,
+}
+
+impl Shading {
+    const ALL: [Self; 2] = [Self::Lit, Self::JointWeights];
+}
+
+impl std::fmt::Display for Shading {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Lit => "Lit",
+            Self::JointWeights => "Joint weights",
+        })
+    }
+}
+
+/// Laid out to match `Uniforms` in `shader.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    view_proj: [[f32; 4]; 4],
+    base_color: [f32; 4],
+    skeleton_color: [f32; 4],
+    shading: u32,
+    _padding: [u32; 3],
+}
+
+/// The bones of the skeleton as a line list, followed by a small cross at every joint.
+fn skeleton_lines(model: &SkinnedModel, world: &[Mat4]) -> Vec<[f32; 3]> {
+    let marker = model.bounds.1 * JOINT_MARKER_SIZE;
+    let bones = model.bones(world).into_iter().flatten();
+    let markers = model.joints.iter().flat_map(|&joint| {
+        let center = world[joint].transform_point3(Vec3::ZERO);
+        [Vec3::X, Vec3::Y, Vec3::Z]
+            .into_iter()
+            .flat_map(move |axis| [center - axis * marker, center + axis * marker])
+    });
+    bones.chain(markers).map(|p| p.to_array()).collect()
+}
+
+pub struct SkinnedMesh {
+    model: Arc<SkinnedModel>,
+    /// A model loaded since the last frame, whose buffers still have to be created.
+    pending_model: Option<Arc<SkinnedModel>>,
+    animation: usize,
+    playing: bool,
+    speed: f32,
+    time: f32,
+    last_frame: std::time::Instant,
+    shading: Shading,
+    show_mesh: bool,
+    show_skeleton: bool,
+    camera_yaw: f32,
+    camera_pitch: f32,
+    status: String,
+}
+
+impl SkinnedMesh {
+    pub fn new_with_render_state(wgpu_render_state: &RenderState) -> Option<Self> {
+        let device = &wgpu_render_state.device;
+
+        let model = Arc::new(SkinnedModel::load(include_bytes!("assets/tentacle.glb")).ok()?);
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("SkinnedMesh Uniform Buffer"),
+            size: std::mem::size_of::<Uniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("SkinnedMesh Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("SkinnedMesh Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./shader.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("SkinnedMesh Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("SkinnedMesh Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[SkinnedVertex::layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu_render_state.target_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: offscreen::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        // The skeleton is drawn over the mesh, so it shows through it
+        let skeleton_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("SkinnedMesh Skeleton Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_skeleton",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: 3 * std::mem::size_of::<f32>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_skeleton",
+                targets: &[Some(wgpu_render_state.target_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: offscreen::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let model_buffers = ModelBuffers::new(device, &bind_group_layout, &uniform_buffer, &model);
+
+        let target = OffscreenTarget::new(
+            device,
+            wgpu_render_state.target_format,
+            Some(offscreen::DEPTH_FORMAT),
+            (CANVAS.0 as u32, CANVAS.1 as u32),
+        );
+
+        // Because the graphics pipeline must have the same lifetime as the egui render pass,
+        // instead of storing the pipeline in our struct, we insert it into the
+        // `paint_callback_resources` type map, which is stored alongside the render pass.
+        wgpu_render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(AppRenderResources {
+                target,
+                pipeline,
+                skeleton_pipeline,
+                bind_group_layout,
+                uniform_buffer,
+                model_buffers,
+            });
+
+        Some(Self {
+            status: describe(&model, "tentacle.glb"),
+            model,
+            pending_model: None,
+            animation: 0,
+            playing: true,
+            speed: 1.0,
+            time: 0.0,
+            last_frame: std::time::Instant::now(),
+            shading: Shading::Lit,
+            show_mesh: true,
+            show_skeleton: true,
+            camera_yaw: 0.0,
+            camera_pitch: 0.2,
+        })
+    }
+
+    fn load_model(&mut self, bytes: &[u8], name: &str) {
+        match SkinnedModel::load(bytes) {
+            Ok(model) => {
+                self.status = describe(&model, name);
+                let model = Arc::new(model);
+                self.model = model.clone();
+                self.pending_model = Some(model);
+                self.animation = 0;
+                self.time = 0.0;
+            }
+            Err(err) => self.status = format!("Failed to load {name}: {err}"),
+        }
+    }
+
+    fn uniforms(&self) -> Uniforms {
+        let (center, radius) = self.model.bounds;
+        let projection = Mat4::perspective_rh(
+            (2.0 * PI) / 5.0,
+            CANVAS.0 / CANVAS.1,
+            radius * 0.05,
+            radius * 10.0,
+        );
+        let eye = Mat4::from_rotation_y(self.camera_yaw)
+            * Mat4::from_rotation_x(-self.camera_pitch)
+            * Vec3::new(0.0, 0.0, radius * 1.8).extend(1.0);
+        let view = Mat4::look_at_rh(center + eye.truncate(), center, Vec3::Y);
+
+        Uniforms {
+            view_proj: (projection * view).to_cols_array_2d(),
+            base_color: self.model.base_color,
+            skeleton_color: [1.0, 0.85, 0.2, 1.0],
+            shading: match self.shading {
+                Shading::Lit => 0,
+                Shading::JointWeights => 1,
+            },
+            _padding: [0; 3],
+        }
+    }
+}
+
+fn describe(model: &SkinnedModel, name: &str) -> String {
+    format!(
+        "{name}: {} vertices, {} joints, {} animations",
+        model.vertices.len(),
+        model.joints.len(),
+        model.animations.len()
+    )
+}
+
+/// The buffers of a model, recreated when another model is loaded.
+struct ModelBuffers {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    index_count: u32,
+    joint_buffer: wgpu::Buffer,
+    skeleton_buffer: wgpu::Buffer,
+    skeleton_vertex_count: u32,
+    bind_group: wgpu::BindGroup,
+}
+
+impl ModelBuffers {
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        uniform_buffer: &wgpu::Buffer,
+        model: &SkinnedModel,
+    ) -> Self {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("SkinnedMesh Vertex Buffer"),
+            contents: bytemuck::cast_slice(&model.vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("SkinnedMesh Index Buffer"),
+            contents: bytemuck::cast_slice(&model.indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let joint_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("SkinnedMesh Joint Matrix Buffer"),
+            size: (model.joints.len().max(1) * std::mem::size_of::<Mat4>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // The number of lines only depends on the skeleton, not on its pose
+        let rest_world = model.world_matrices(&model.rest_pose());
+        let skeleton_vertex_count = skeleton_lines(model, &rest_world).len() as u32;
+        let skeleton_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("SkinnedMesh Skeleton Vertex Buffer"),
+            size: (skeleton_vertex_count.max(1) as usize * std::mem::size_of::<[f32; 3]>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("SkinnedMesh Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: joint_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self {
+            vertex_buffer,
+            index_buffer,
+            index_count: model.indices.len() as u32,
+            joint_buffer,
+            skeleton_buffer,
+            skeleton_vertex_count,
+            bind_group,
+        }
+    }
+}
+
+impl eframe::App for SkinnedMesh {
+    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        // Models can also be dropped onto the window
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        for file in dropped {
+            let bytes = match (&file.bytes, &file.path) {
+                (Some(bytes), _) => Ok(bytes.to_vec()),
+                (None, Some(path)) => std::fs::read(path).map_err(|e| e.to_string()),
+                (None, None) => continue,
+            };
+            match bytes {
+                Ok(bytes) => self.load_model(&bytes, &file.name),
+                Err(err) => self.status = format!("Failed to read {}: {err}", file.name),
+            }
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                        self.custom_painting(ui);
+                    });
+                    ui.vertical(|ui| self.controls(ui));
+                });
+            });
+        });
+        // This is needed to play the animation. It tells eframe to call update() again on the next event loop iteration.
+        ctx.request_repaint();
+    }
+}
+
+impl SkinnedMesh {
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        let duration = self
+            .model
+            .animations
+            .get(self.animation)
+            .map_or(0.0, |animation| animation.duration);
+
+        egui::Grid::new("skinned_mesh_controls")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("animation");
+                egui::ComboBox::from_id_source("skinned_mesh_animation")
+                    .selected_text(match self.model.animations.get(self.animation) {
+                        Some(animation) => animation.name.as_str(),
+                        None => "None",
+                    })
+                    .show_ui(ui, |ui| {
+                        for (i, animation) in self.model.animations.iter().enumerate() {
+                            ui.selectable_value(&mut self.animation, i, &animation.name);
+                        }
+                    });
+                ui.end_row();
+                ui.label("play");
+                ui.checkbox(&mut self.playing, "");
+                ui.end_row();
+                ui.label("speed");
+                ui.add(egui::Slider::new(&mut self.speed, 0.0..=2.0));
+                ui.end_row();
+                ui.label("time");
+                ui.add_enabled(
+                    duration > 0.0,
+                    egui::Slider::new(&mut self.time, 0.0..=duration).suffix(" s"),
+                );
+                ui.end_row();
+                ui.label("shading");
+                egui::ComboBox::from_id_source("skinned_mesh_shading")
+                    .selected_text(self.shading.to_string())
+                    .show_ui(ui, |ui| {
+                        for shading in Shading::ALL {
+                            ui.selectable_value(&mut self.shading, shading, shading.to_string());
+                        }
+                    });
+                ui.end_row();
+                ui.label("mesh");
+                ui.checkbox(&mut self.show_mesh, "");
+                ui.end_row();
+                ui.label("skeleton");
+                ui.checkbox(&mut self.show_skeleton, "");
+                ui.end_row();
+            });
+
+        ui.label(&self.status);
+        ui.label("Drop a skinned .glb or .gltf file to load it.");
+        ui.label("Drag to orbit the camera.");
+    }
+
+    fn custom_painting(&mut self, ui: &mut egui::Ui) {
+        let (rect, response) =
+            ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::drag());
+
+        let drag = response.drag_delta();
+        self.camera_yaw -= drag.x * 0.01;
+        self.camera_pitch = (self.camera_pitch + drag.y * 0.01).clamp(-1.5, 1.5);
+
+        let now = std::time::Instant::now();
+        let mut pose = self.model.rest_pose();
+        if let Some(animation) = self.model.animations.get(self.animation) {
+            if self.playing {
+                self.time += (now - self.last_frame).as_secs_f32() * self.speed;
+            }
+            if animation.duration > 0.0 {
+                self.time = self.time.rem_euclid(animation.duration);
+            }
+            animation.sample(self.time, &mut pose);
+        }
+        self.last_frame = now;
+
+        let world = self.model.world_matrices(&pose);
+        let joint_matrices = self.model.joint_matrices(&world);
+
+        ui.painter().add(paint_callback(
+            rect,
+            CustomPaintCallback {
+                size: offscreen::size_in_pixels(ui, rect),
+                uniforms: self.uniforms(),
+                joint_matrices: joint_matrices.iter().map(Mat4::to_cols_array_2d).collect(),
+                skeleton: skeleton_lines(&self.model, &world),
+                show_mesh: self.show_mesh,
+                show_skeleton: self.show_skeleton,
+                model: self.pending_model.take(),
+            },
+        ));
+    }
+}
+
+struct CustomPaintCallback {
+    size: (u32, u32),
+    uniforms: Uniforms,
+    joint_matrices: Vec<[[f32; 4]; 4]>,
+    skeleton: Vec<[f32; 3]>,
+    show_mesh: bool,
+    show_skeleton: bool,
+    model: Option<Arc<SkinnedModel>>,
+}
+
+impl egui_wgpu::CallbackTrait for CustomPaintCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let profiler = GpuProfiler::get(callback_resources);
+        let stats = FrameStats::get(callback_resources);
+        let resources: &mut AppRenderResources = callback_resources.get_mut().unwrap();
+        resources.target.resize(device, self.size);
+        if let Some(model) = &self.model {
+            resources.model_buffers = ModelBuffers::new(
+                device,
+                &resources.bind_group_layout,
+                &resources.uniform_buffer,
+                model,
+            );
+        }
+        let buffers = &resources.model_buffers;
+
+        stats.write_buffer(
+            queue,
+            &resources.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&self.uniforms),
+        );
+        stats.write_buffer(
+            queue,
+            &buffers.joint_buffer,
+            0,
+            bytemuck::cast_slice(&self.joint_matrices),
+        );
+        stats.write_buffer(
+            queue,
+            &buffers.skeleton_buffer,
+            0,
+            bytemuck::cast_slice(&self.skeleton),
+        );
+
+        let mut render_pass = stats.pass(resources.target.begin_pass(
+            egui_encoder,
+            wgpu::Color {
+                r: 0.1,
+                g: 0.1,
+                b: 0.12,
+                a: 1.0,
+            },
+            "SkinnedMesh Render Pass",
+            &profiler,
+        ));
+        render_pass.set_bind_group(0, &buffers.bind_group, &[]);
+        if self.show_mesh {
+            render_pass.set_pipeline(&resources.pipeline);
+            render_pass.set_vertex_buffer(0, buffers.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(buffers.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..buffers.index_count, 0, 0..1);
+        }
+        if self.show_skeleton && buffers.skeleton_vertex_count > 0 {
+            render_pass.set_pipeline(&resources.skeleton_pipeline);
+            render_pass.set_vertex_buffer(0, buffers.skeleton_buffer.slice(..));
+            render_pass.draw(0..buffers.skeleton_vertex_count, 0..1);
+        }
+        drop(render_pass);
+
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: eframe::egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
+        let resources: &AppRenderResources = callback_resources.get().unwrap();
+        resources.target.blit(&mut render_pass);
+    }
+}
+
+struct AppRenderResources {
+    pub target: OffscreenTarget,
+    pub pipeline: wgpu::RenderPipeline,
+    pub skeleton_pipeline: wgpu::RenderPipeline,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub uniform_buffer: wgpu::Buffer,
+    pub model_buffers: ModelBuffers,
+}
@@ -0,0 +1,348 @@
+//! Loading a skinned mesh with its skeleton and animations from glTF.
+
+use glam::{Mat4, Quat, Vec3, Vec4};
+
+use super::animation::{Animation, Channel, Interpolation, Keyframes, Transform};
+
+/// A vertex influenced by up to four joints.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SkinnedVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    /// Indices into the joints of the skin.
+    pub joints: [u32; 4],
+    /// Sum to 1.
+    pub weights: [f32; 4],
+}
+
+impl SkinnedVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 4] = wgpu::vertex_attr_array![
+        0 => Float32x3,
+        1 => Float32x3,
+        2 => Uint32x4,
+        3 => Float32x4,
+    ];
+
+    /// Position at location 0, normal at 1, joints at 2 and weights at 3.
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+pub struct Node {
+    pub parent: Option<usize>,
+    /// The transform of the node when no animation targets it.
+    pub rest: Transform,
+}
+
+/// The first skinned mesh of a glTF scene, with the node hierarchy its skeleton lives in.
+pub struct SkinnedModel {
+    pub vertices: Vec<SkinnedVertex>,
+    pub indices: Vec<u32>,
+    pub base_color: [f32; 4],
+    pub nodes: Vec<Node>,
+    /// Node indices ordered so that parents come before their children.
+    order: Vec<usize>,
+    /// The nodes acting as joints, indexed by [`SkinnedVertex::joints`].
+    pub joints: Vec<usize>,
+    /// Transform the mesh from its bind pose into the space of each joint.
+    pub inverse_bind_matrices: Vec<Mat4>,
+    pub animations: Vec<Animation>,
+    /// Center and radius of a sphere around the mesh in its rest pose.
+    pub bounds: (Vec3, f32),
+}
+
+impl SkinnedModel {
+    /// Load a `.glb`, or a `.gltf` with its buffers embedded. Primitives other than triangle
+    /// lists are skipped, and cubic spline channels are played back linearly.
+    pub fn load(bytes: &[u8]) -> Result<Self, String> {
+        let (document, buffers, _images) = gltf::import_slice(bytes).map_err(|e| e.to_string())?;
+        let get_buffer = |buffer: gltf::Buffer| Some(&*buffers[buffer.index()]);
+
+        let mut nodes: Vec<Node> = document
+            .nodes()
+            .map(|node| {
+                let (translation, rotation, scale) = node.transform().decomposed();
+                Node {
+                    parent: None,
+                    rest: Transform {
+                        translation: Vec3::from(translation),
+                        rotation: Quat::from_array(rotation),
+                        scale: Vec3::from(scale),
+                    },
+                }
+            })
+            .collect();
+        for node in document.nodes() {
+            for child in node.children() {
+                nodes[child.index()].parent = Some(node.index());
+            }
+        }
+
+        let (mesh, skin) = document
+            .nodes()
+            .find_map(|node| Some((node.mesh()?, node.skin()?)))
+            .ok_or("no skinned mesh found")?;
+
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let mut missing_normals = false;
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                continue;
+            }
+            let reader = primitive.reader(get_buffer);
+            let positions: Vec<[f32; 3]> = reader
+                .read_positions()
+                .ok_or("primitive without positions")?
+                .collect();
+            let joints: Vec<[u16; 4]> = reader
+                .read_joints(0)
+                .ok_or("primitive without joints")?
+                .into_u16()
+                .collect();
+            let weights: Vec<[f32; 4]> = reader
+                .read_weights(0)
+                .ok_or("primitive without weights")?
+                .into_f32()
+                .collect();
+            let normals: Vec<[f32; 3]> = match reader.read_normals() {
+                Some(normals) => normals.collect(),
+                None => {
+                    missing_normals = true;
+                    vec![[0.0; 3]; positions.len()]
+                }
+            };
+
+            let base = vertices.len() as u32;
+            for (((position, normal), joints), weights) in
+                positions.into_iter().zip(normals).zip(joints).zip(weights)
+            {
+                let weights = Vec4::from(weights);
+                let sum = weights.element_sum();
+                vertices.push(SkinnedVertex {
+                    position,
+                    normal,
+                    joints: joints.map(u32::from),
+                    weights: if sum > 0.0 { weights / sum } else { Vec4::X }.into(),
+                });
+            }
+            match reader.read_indices() {
+                Some(read) => indices.extend(read.into_u32().map(|i| base + i)),
+                None => indices.extend(base..vertices.len() as u32),
+            }
+        }
+        if indices.is_empty() {
+            return Err("the skinned mesh has no triangles".to_string());
+        }
+        let base_color = mesh
+            .primitives()
+            .next()
+            .map(|primitive| {
+                primitive
+                    .material()
+                    .pbr_metallic_roughness()
+                    .base_color_factor()
+            })
+            .unwrap_or([1.0; 4]);
+
+        let joints: Vec<usize> = skin.joints().map(|node| node.index()).collect();
+        let inverse_bind_matrices = match skin.reader(get_buffer).read_inverse_bind_matrices() {
+            Some(matrices) => matrices.map(|m| Mat4::from_cols_array_2d(&m)).collect(),
+            None => vec![Mat4::IDENTITY; joints.len()],
+        };
+
+        let animations = document
+            .animations()
+            .enumerate()
+            .map(|(i, animation)| {
+                let name = match animation.name() {
+                    Some(name) => name.to_string(),
+                    None => format!("Animation {i}"),
+                };
+                let channels = animation
+                    .channels()
+                    .filter_map(|channel| read_channel(&channel, get_buffer).transpose())
+                    .collect::<Result<_, _>>()
+                    .map_err(|err| format!("{name}: {err}"))?;
+                Ok(Animation::new(name, channels))
+            })
+            .collect::<Result<_, String>>()?;
+
+        let order = parents_first(&nodes);
+        let mut model = Self {
+            vertices,
+            indices,
+            base_color,
+            nodes,
+            order,
+            joints,
+            inverse_bind_matrices,
+            animations,
+            bounds: (Vec3::ZERO, 1.0),
+        };
+        if missing_normals {
+            model.compute_normals();
+        }
+        model.bounds = model.compute_bounds();
+        Ok(model)
+    }
+
+    pub fn rest_pose(&self) -> Vec<Transform> {
+        self.nodes.iter().map(|node| node.rest).collect()
+    }
+
+    /// The transform of every node into the scene, from the local transforms of all nodes.
+    pub fn world_matrices(&self, local: &[Transform]) -> Vec<Mat4> {
+        let mut world = vec![Mat4::IDENTITY; self.nodes.len()];
+        for &i in &self.order {
+            let parent = self.nodes[i].parent.map_or(Mat4::IDENTITY, |p| world[p]);
+            world[i] = parent * local[i].matrix();
+        }
+        world
+    }
+
+    /// One matrix per joint, taking bind pose vertices to where the joint has moved them.
+    pub fn joint_matrices(&self, world: &[Mat4]) -> Vec<Mat4> {
+        self.joints
+            .iter()
+            .zip(&self.inverse_bind_matrices)
+            .map(|(&joint, inverse_bind)| world[joint] * *inverse_bind)
+            .collect()
+    }
+
+    /// Line segments from each joint to the joints it parents.
+    pub fn bones(&self, world: &[Mat4]) -> Vec<[Vec3; 2]> {
+        self.joints
+            .iter()
+            .filter_map(|&joint| {
+                let parent = self.nodes[joint].parent?;
+                self.joints.contains(&parent).then(|| {
+                    [
+                        world[parent].transform_point3(Vec3::ZERO),
+                        world[joint].transform_point3(Vec3::ZERO),
+                    ]
+                })
+            })
+            .collect()
+    }
+
+    /// Smooth normals for meshes that come without any, averaging the faces around each vertex.
+    fn compute_normals(&mut self) {
+        let mut normals = vec![Vec3::ZERO; self.vertices.len()];
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] =
+                [0, 1, 2].map(|i| Vec3::from(self.vertices[triangle[i] as usize].position));
+            // Not normalized, so larger faces weigh more
+            let normal = (b - a).cross(c - a);
+            for &i in triangle {
+                normals[i as usize] += normal;
+            }
+        }
+        for (vertex, normal) in self.vertices.iter_mut().zip(normals) {
+            vertex.normal = normal.normalize_or_zero().into();
+        }
+    }
+
+    fn compute_bounds(&self) -> (Vec3, f32) {
+        let joint_matrices = self.joint_matrices(&self.world_matrices(&self.rest_pose()));
+        let positions: Vec<Vec3> = self
+            .vertices
+            .iter()
+            .map(|vertex| {
+                let position = Vec3::from(vertex.position);
+                vertex
+                    .joints
+                    .iter()
+                    .zip(vertex.weights)
+                    .filter_map(|(&joint, weight)| {
+                        let matrix = joint_matrices.get(joint as usize)?;
+                        Some(matrix.transform_point3(position) * weight)
+                    })
+                    .sum()
+            })
+            .collect();
+
+        let min = positions.iter().copied().fold(Vec3::INFINITY, Vec3::min);
+        let max = positions
+            .iter()
+            .copied()
+            .fold(Vec3::NEG_INFINITY, Vec3::max);
+        let center = (min + max) / 2.0;
+        let radius = positions
+            .iter()
+            .map(|position| position.distance(center))
+            .fold(0.0, f32::max);
+        (center, radius.max(f32::EPSILON))
+    }
+}
+
+/// The channel, `None` for the ones this sample does not play, or an error if its keyframes
+/// are malformed.
+fn read_channel<'a, 's>(
+    channel: &gltf::animation::Channel<'a>,
+    get_buffer: impl Clone + Fn(gltf::Buffer<'a>) -> Option<&'s [u8]>,
+) -> Result<Option<Channel>, String> {
+    use gltf::animation::util::ReadOutputs;
+
+    let reader = channel.reader(get_buffer);
+    let (Some(inputs), Some(outputs)) = (reader.read_inputs(), reader.read_outputs()) else {
+        return Ok(None);
+    };
+    let times: Vec<f32> = inputs.collect();
+
+    let (interpolation, cubic_spline) = match channel.sampler().interpolation() {
+        gltf::animation::Interpolation::Linear => (Interpolation::Linear, false),
+        gltf::animation::Interpolation::Step => (Interpolation::Step, false),
+        gltf::animation::Interpolation::CubicSpline => (Interpolation::Linear, true),
+    };
+    let keyframes = match outputs {
+        ReadOutputs::Translations(values) => {
+            Keyframes::Translation(keyframe_values(values.map(Vec3::from), cubic_spline))
+        }
+        ReadOutputs::Rotations(values) => Keyframes::Rotation(keyframe_values(
+            values.into_f32().map(Quat::from_array),
+            cubic_spline,
+        )),
+        ReadOutputs::Scales(values) => {
+            Keyframes::Scale(keyframe_values(values.map(Vec3::from), cubic_spline))
+        }
+        ReadOutputs::MorphTargetWeights(_) => return Ok(None),
+    };
+
+    Channel::new(
+        channel.target().node().index(),
+        interpolation,
+        times,
+        keyframes,
+    )
+    .map(Some)
+}
+
+/// Cubic spline keyframes come as in-tangent, value and out-tangent, keep the values only.
+fn keyframe_values<T>(values: impl Iterator<Item = T>, cubic_spline: bool) -> Vec<T> {
+    if cubic_spline {
+        values.skip(1).step_by(3).collect()
+    } else {
+        values.collect()
+    }
+}
+
+/// Node indices in an order where every parent comes before its children.
+fn parents_first(nodes: &[Node]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..nodes.len())
+        .filter(|&i| nodes[i].parent.is_none())
+        .collect();
+    let mut next = 0;
+    while next < order.len() {
+        let parent = order[next];
+        order.extend((0..nodes.len()).filter(|&i| nodes[i].parent == Some(parent)));
+        next += 1;
+    }
+    order
+}
@@ -0,0 +1,65 @@
+struct Uniforms {
+    view_proj: mat4x4f,
+    base_color: vec4f,
+    skeleton_color: vec4f,
+    // 0: lit, 1: joint weights
+    shading: u32,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+// Written every frame from the sampled animation
+@group(0) @binding(1) var<storage, read> joint_matrices: array<mat4x4f>;
+
+struct VertexInput {
+    @location(0) position: vec3f,
+    @location(1) normal: vec3f,
+    @location(2) joints: vec4u,
+    @location(3) weights: vec4f,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) normal: vec3f,
+    @location(1) weight_color: vec3f,
+}
+
+// A distinct color for each joint, cycling through the hues
+fn joint_color(joint: u32) -> vec3f {
+    let hue = fract(f32(joint) * 0.618034);
+    return clamp(abs(fract(hue + vec3f(0.0, 2.0, 1.0) / 3.0) * 6.0 - 3.0) - 1.0, vec3f(0.0), vec3f(1.0));
+}
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    let skin = in.weights.x * joint_matrices[in.joints.x]
+        + in.weights.y * joint_matrices[in.joints.y]
+        + in.weights.z * joint_matrices[in.joints.z]
+        + in.weights.w * joint_matrices[in.joints.w];
+
+    var out: VertexOutput;
+    out.position = uniforms.view_proj * skin * vec4f(in.position, 1.0);
+    out.normal = (skin * vec4f(in.normal, 0.0)).xyz;
+    out.weight_color = in.weights.x * joint_color(in.joints.x)
+        + in.weights.y * joint_color(in.joints.y)
+        + in.weights.z * joint_color(in.joints.z)
+        + in.weights.w * joint_color(in.joints.w);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let color = select(uniforms.base_color.rgb, in.weight_color, uniforms.shading == 1u);
+    let light = normalize(vec3f(0.4, 1.0, 0.6));
+    let diffuse = max(dot(normalize(in.normal), light), 0.0);
+    return vec4f(color * (0.25 + 0.75 * diffuse), 1.0);
+}
+
+@vertex
+fn vs_skeleton(@location(0) position: vec3f) -> @builtin(position) vec4f {
+    return uniforms.view_proj * vec4f(position, 1.0);
+}
+
+@fragment
+fn fs_skeleton() -> @location(0) vec4f {
+    return uniforms.skeleton_color;
+}
@@ -0,0 +1,827 @@
+use eframe::{
+    egui,
+    egui_wgpu::{self, RenderState},
+};
+use glam::{Mat4, Vec3};
+use std::f32::consts::{PI, TAU};
+use std::ops::Range;
+use wgpu::util::DeviceExt;
+
+use crate::meshes::mesh::{Mesh, Vertex};
+use crate::offscreen::{self, OffscreenTarget};
+use crate::profiler::GpuProfiler;
+use crate::stats::{paint_callback, CountedPass, FrameStats};
+
+const CANVAS: (f32, f32) = (600.0, 600.0);
+const DEPTH_STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth24PlusStencil8;
+/// A box has six faces, each marking the stencil with its own value and showing its own scene.
+const FACE_COUNT: u32 = 6;
+/// Indices per face of `Mesh::create_box`, which lists the faces one after another.
+const FACE_INDICES: u32 = 6;
+const MAX_BOXES: usize = 64;
+const MAX_KNOTS: usize = 8;
+const CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.1,
+    g: 0.1,
+    b: 0.12,
+    a: 1.0,
+};
+const FLOOR_COLOR: [f32; 4] = [0.45, 0.45, 0.5, 1.0];
+const FRAME_COLOR: [f32; 4] = [0.8, 0.75, 0.6, 1.0];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Portal,
+    Outline,
+}
+
+impl Mode {
+    const ALL: [Self; 2] = [Self::Portal, Self::Outline];
+
+    fn camera_distance(self) -> f32 {
+        match self {
+            Self::Portal => 5.0,
+            Self::Outline => 7.0,
+        }
+    }
+}
+
+impl std::fmt::Display for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Portal => write!(f, "Portal cube"),
+            Self::Outline => write!(f, "Outlines"),
+        }
+    }
+}
+
+/// Laid out to match `Uniforms` in `shader.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    view_proj: [[f32; 4]; 4],
+    outline_color: [f32; 4],
+    outline_width: f32,
+    _padding: [f32; 3],
+}
+
+/// A mesh placed in world space, read as per-instance vertex attributes.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct Instance {
+    model: [[f32; 4]; 4],
+    color: [f32; 4],
+}
+
+impl Instance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+        5 => Float32x4,
+        6 => Float32x4,
+        7 => Float32x4,
+        8 => Float32x4,
+        9 => Float32x4,
+    ];
+    const SIZE: wgpu::BufferAddress = std::mem::size_of::<Self>() as wgpu::BufferAddress;
+
+    fn new(model: Mat4, color: [f32; 4]) -> Self {
+        Self {
+            model: model.to_cols_array_2d(),
+            color,
+        }
+    }
+
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: Self::SIZE,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Instances drawn together, as ranges of the box and the knot instances of a [`Frame`].
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Batch {
+    boxes: Range<u32>,
+    knots: Range<u32>,
+}
+
+/// Everything drawn in a frame.
+#[derive(Default)]
+struct Frame {
+    boxes: Vec<Instance>,
+    knots: Vec<Instance>,
+    /// Drawn as usual.
+    plain: Batch,
+    /// The cube whose faces mark the stencil, and the scene seen through each face.
+    portal: Option<(Batch, Vec<Batch>)>,
+    /// The objects that get an outline.
+    outlined: Batch,
+}
+
+impl Frame {
+    fn push(
+        &mut self,
+        boxes: impl IntoIterator<Item = Instance>,
+        knots: impl IntoIterator<Item = Instance>,
+    ) -> Batch {
+        let (box_start, knot_start) = (self.boxes.len() as u32, self.knots.len() as u32);
+        self.boxes.extend(boxes);
+        self.knots.extend(knots);
+        Batch {
+            boxes: box_start..self.boxes.len() as u32,
+            knots: knot_start..self.knots.len() as u32,
+        }
+    }
+}
+
+fn color(hue: f32, base: f32, amount: f32) -> [f32; 4] {
+    [
+        base + amount * hue.cos(),
+        base + amount * (hue - TAU / 3.0).cos(),
+        base + amount * (hue + TAU / 3.0).cos(),
+        1.0,
+    ]
+}
+
+fn floor() -> Instance {
+    Instance::new(
+        Mat4::from_scale_rotation_translation(
+            Vec3::new(10.0, 0.2, 10.0),
+            Default::default(),
+            Vec3::new(0.0, -2.1, 0.0),
+        ),
+        FLOOR_COLOR,
+    )
+}
+
+/// The scene behind face `face` of the portal cube, in the space of the cube: a room with
+/// walls of its own color, and either a spinning knot or a ring of orbiting cubes.
+fn scene(face: u32, time: f32) -> (Vec<Instance>, Vec<Instance>) {
+    let hue = face as f32 / FACE_COUNT as f32 * TAU;
+    // Scaling by a negative factor turns the box inside out, so its back faces are the walls
+    let room = Instance::new(Mat4::from_scale(Vec3::splat(-1.99)), color(hue, 0.7, 0.25));
+    let object_color = color(hue + PI, 0.55, 0.4);
+    if face.is_multiple_of(2) {
+        let axis = [Vec3::Y, Vec3::X, Vec3::new(1.0, 1.0, 0.0).normalize()][face as usize / 2];
+        let knot = Instance::new(
+            Mat4::from_axis_angle(axis, time * 0.8) * Mat4::from_scale(Vec3::splat(0.4)),
+            object_color,
+        );
+        (vec![room], vec![knot])
+    } else {
+        let count = face + 2;
+        let cubes = (0..count).map(|i| {
+            let angle = time + i as f32 / count as f32 * TAU;
+            Instance::new(
+                Mat4::from_translation(Vec3::new(
+                    0.55 * angle.cos(),
+                    0.3 * (time * 2.0 + i as f32).sin(),
+                    0.55 * angle.sin(),
+                )) * Mat4::from_rotation_y(-angle)
+                    * Mat4::from_scale(Vec3::splat(0.3)),
+                object_color,
+            )
+        });
+        (std::iter::once(room).chain(cubes).collect(), Vec::new())
+    }
+}
+
+fn portal_frame(time: f32) -> Frame {
+    let cube = Mat4::from_rotation_y(time * 0.3) * Mat4::from_rotation_x(0.35);
+    let mut frame = Frame::default();
+
+    // Bars along the twelve edges of the cube, so that its outline shows from the outside
+    let edges = (0..3).flat_map(|axis| {
+        let (along, u, v) = (
+            Vec3::AXES[axis],
+            Vec3::AXES[(axis + 1) % 3],
+            Vec3::AXES[(axis + 2) % 3],
+        );
+        [(-1.0, -1.0), (-1.0, 1.0), (1.0, -1.0), (1.0, 1.0)].map(|(a, b)| {
+            Instance::new(
+                cube * Mat4::from_scale_rotation_translation(
+                    along * 2.08 + (u + v) * 0.08,
+                    Default::default(),
+                    u * a + v * b,
+                ),
+                FRAME_COLOR,
+            )
+        })
+    });
+    frame.plain = frame.push(std::iter::once(floor()).chain(edges), []);
+
+    let portal = frame.push(
+        [Instance::new(
+            cube * Mat4::from_scale(Vec3::splat(2.0)),
+            [1.0; 4],
+        )],
+        [],
+    );
+    let scenes = (0..FACE_COUNT)
+        .map(|face| {
+            let (boxes, knots) = scene(face, time);
+            let place = |instance: Instance| {
+                Instance::new(
+                    cube * Mat4::from_cols_array_2d(&instance.model),
+                    instance.color,
+                )
+            };
+            frame.push(boxes.into_iter().map(place), knots.into_iter().map(place))
+        })
+        .collect();
+    frame.portal = Some((portal, scenes));
+    frame
+}
+
+fn outline_frame(time: f32) -> Frame {
+    let mut frame = Frame::default();
+    // Between the camera and the middle knot, to hide part of it
+    let wall = Instance::new(
+        Mat4::from_scale_rotation_translation(
+            Vec3::new(1.2, 3.6, 0.2),
+            glam::Quat::from_rotation_y(0.8),
+            Vec3::new(1.1, -0.2, 1.1),
+        ),
+        FLOOR_COLOR,
+    );
+    frame.plain = frame.push([floor(), wall], []);
+
+    let knots = [Vec3::Y, Vec3::X, Vec3::Z]
+        .into_iter()
+        .enumerate()
+        .map(|(i, axis)| {
+            let x = (i as f32 - 1.0) * 2.6;
+            Instance::new(
+                Mat4::from_translation(Vec3::new(x, 0.0, 0.0))
+                    * Mat4::from_axis_angle(axis, time * 0.6 + i as f32)
+                    * Mat4::from_scale(Vec3::splat(0.6)),
+                color(i as f32 * 2.0, 0.55, 0.4),
+            )
+        });
+    frame.outlined = frame.push([], knots);
+    frame
+}
+
+/// A mesh uploaded to the GPU.
+struct GpuMesh {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    index_count: u32,
+}
+
+impl GpuMesh {
+    fn new(device: &wgpu::Device, mesh: &Mesh, label: &str) -> Self {
+        Self {
+            vertex_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("{label} Vertex Buffer")),
+                contents: bytemuck::cast_slice(&mesh.vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+            index_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("{label} Index Buffer")),
+                contents: bytemuck::cast_slice(&mesh.indices),
+                usage: wgpu::BufferUsages::INDEX,
+            }),
+            index_count: mesh.indices.len() as u32,
+        }
+    }
+
+    /// Draw the `indices` of the mesh for the `instances` in `instance_buffer`.
+    fn draw<'a>(
+        &'a self,
+        render_pass: &mut CountedPass<'_, 'a>,
+        instance_buffer: &'a wgpu::Buffer,
+        indices: Range<u32>,
+        instances: Range<u32>,
+    ) {
+        if instances.is_empty() {
+            return;
+        }
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(indices, 0, instances);
+    }
+}
+
+pub struct Stencil {
+    mode: Mode,
+    time: f32,
+    animate: bool,
+    show_stencil: bool,
+    outline_color: egui::Color32,
+    outline_width: f32,
+    outline_through: bool,
+    camera_yaw: f32,
+    camera_pitch: f32,
+}
+
+impl Stencil {
+    pub fn new_with_render_state(wgpu_render_state: &RenderState) -> Option<Self> {
+        let device = &wgpu_render_state.device;
+
+        let cube = GpuMesh::new(device, &Mesh::create_box(1.0, 1.0, 1.0), "Stencil Box");
+        let knot = GpuMesh::new(
+            device,
+            &Mesh::create_torus_knot(1.0, 0.3, 128, 16, 2, 3),
+            "Stencil Knot",
+        );
+        let create_instance_buffer = |label: &str, count: usize| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: Instance::SIZE * count as u64,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        let box_instance_buffer = create_instance_buffer("Stencil Box Instance Buffer", MAX_BOXES);
+        let knot_instance_buffer =
+            create_instance_buffer("Stencil Knot Instance Buffer", MAX_KNOTS);
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Stencil Uniform Buffer"),
+            size: std::mem::size_of::<Uniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Stencil Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Stencil Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Stencil Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./shader.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Stencil Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        // The pipelines differ in their stencil state, and in how they test depth and write
+        // colors to go with it
+        let create_pipeline = |label: &str,
+                               entry_points: (&str, &str),
+                               write_mask: wgpu::ColorWrites,
+                               depth_write_enabled: bool,
+                               depth_compare: wgpu::CompareFunction,
+                               stencil: wgpu::StencilFaceState| {
+            // The stencil view draws a translucent triangle over the whole target, without
+            // vertex buffers
+            let stencil_view = entry_points.0 == "vs_stencil";
+            let meshes = [Vertex::layout(), Instance::layout()];
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: entry_points.0,
+                    buffers: if stencil_view { &[] } else { &meshes },
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: entry_points.1,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: wgpu_render_state.target_format,
+                        blend: stencil_view.then_some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DEPTH_STENCIL_FORMAT,
+                    depth_write_enabled,
+                    depth_compare,
+                    stencil: wgpu::StencilState {
+                        front: stencil,
+                        back: stencil,
+                        read_mask: 0xff,
+                        write_mask: 0xff,
+                    },
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let stencil_state = |compare, pass_op, depth_fail_op| wgpu::StencilFaceState {
+            compare,
+            fail_op: wgpu::StencilOperation::Keep,
+            depth_fail_op,
+            pass_op,
+        };
+        use wgpu::{CompareFunction as Compare, StencilOperation as Op};
+        let lit = ("vs_main", "fs_main");
+        let opaque_pipeline = create_pipeline(
+            "Stencil Opaque Pipeline",
+            lit,
+            wgpu::ColorWrites::ALL,
+            true,
+            Compare::Less,
+            wgpu::StencilFaceState::IGNORE,
+        );
+        // Writes the value of each visible face into the stencil, and nothing else
+        let portal_pipeline = create_pipeline(
+            "Stencil Portal Pipeline",
+            lit,
+            wgpu::ColorWrites::empty(),
+            false,
+            Compare::Less,
+            stencil_state(Compare::Always, Op::Replace, Op::Keep),
+        );
+        // Draws only where the stencil holds the value of the face the scene belongs to
+        let scene_pipeline = create_pipeline(
+            "Stencil Scene Pipeline",
+            lit,
+            wgpu::ColorWrites::ALL,
+            true,
+            Compare::Less,
+            stencil_state(Compare::Equal, Op::Keep, Op::Keep),
+        );
+        // Marks the outlined objects, including where something is in front of them, so that
+        // their outline stays a band around them when it is drawn through other objects
+        let mark_pipeline = create_pipeline(
+            "Stencil Mark Pipeline",
+            lit,
+            wgpu::ColorWrites::ALL,
+            true,
+            Compare::Less,
+            stencil_state(Compare::Always, Op::Replace, Op::Replace),
+        );
+        let create_outline_pipeline = |label, depth_compare| {
+            create_pipeline(
+                label,
+                ("vs_outline", "fs_flat"),
+                wgpu::ColorWrites::ALL,
+                false,
+                depth_compare,
+                stencil_state(Compare::NotEqual, Op::Keep, Op::Keep),
+            )
+        };
+        let outline_pipeline = create_outline_pipeline("Stencil Outline Pipeline", Compare::Less);
+        let outline_through_pipeline =
+            create_outline_pipeline("Stencil Outline Through Pipeline", Compare::Always);
+        let stencil_view_pipeline = create_pipeline(
+            "Stencil View Pipeline",
+            ("vs_stencil", "fs_flat"),
+            wgpu::ColorWrites::ALL,
+            false,
+            Compare::Always,
+            stencil_state(Compare::Equal, Op::Keep, Op::Keep),
+        );
+
+        let target = OffscreenTarget::new(
+            device,
+            wgpu_render_state.target_format,
+            Some(DEPTH_STENCIL_FORMAT),
+            (CANVAS.0 as u32, CANVAS.1 as u32),
+        );
+
+        // Because the graphics pipeline must have the same lifetime as the egui render pass,
+        // instead of storing the pipeline in our struct, we insert it into the
+        // `paint_callback_resources` type map, which is stored alongside the render pass.
+        wgpu_render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(AppRenderResources {
+                target,
+                opaque_pipeline,
+                portal_pipeline,
+                scene_pipeline,
+                mark_pipeline,
+                outline_pipeline,
+                outline_through_pipeline,
+                stencil_view_pipeline,
+                cube,
+                knot,
+                box_instance_buffer,
+                knot_instance_buffer,
+                uniform_buffer,
+                bind_group,
+            });
+
+        Some(Self {
+            mode: Mode::Portal,
+            time: 0.0,
+            animate: true,
+            show_stencil: false,
+            outline_color: egui::Color32::from_rgb(255, 160, 30),
+            outline_width: 0.06,
+            outline_through: true,
+            camera_yaw: 0.8,
+            camera_pitch: 0.45,
+        })
+    }
+}
+
+impl eframe::App for Stencil {
+    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        if self.animate {
+            self.time += ctx.input(|i| i.stable_dt);
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                        self.custom_painting(ui);
+                    });
+                    ui.vertical(|ui| self.controls(ui));
+                });
+            });
+        });
+        // This is needed to animate the scenes. It tells eframe to call update() again on the next event loop iteration.
+        ctx.request_repaint();
+    }
+}
+
+impl Stencil {
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("stencil_controls")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("mode");
+                egui::ComboBox::from_id_source("stencil_mode")
+                    .selected_text(self.mode.to_string())
+                    .show_ui(ui, |ui| {
+                        for mode in Mode::ALL {
+                            ui.selectable_value(&mut self.mode, mode, mode.to_string());
+                        }
+                    });
+                ui.end_row();
+                ui.label("animate");
+                ui.checkbox(&mut self.animate, "");
+                ui.end_row();
+                ui.label("show stencil")
+                    .on_hover_text("Tint every pixel with a color for its stencil value");
+                ui.checkbox(&mut self.show_stencil, "");
+                ui.end_row();
+                if self.mode == Mode::Outline {
+                    ui.label("outline color");
+                    ui.color_edit_button_srgba(&mut self.outline_color);
+                    ui.end_row();
+                    ui.label("outline width");
+                    ui.add(egui::Slider::new(&mut self.outline_width, 0.01..=0.2));
+                    ui.end_row();
+                    ui.label("through objects")
+                        .on_hover_text("Draw the outlines without testing their depth");
+                    ui.checkbox(&mut self.outline_through, "");
+                    ui.end_row();
+                }
+            });
+        match self.mode {
+            Mode::Portal => {
+                ui.label("Each face of the cube writes its own value into the stencil buffer,");
+                ui.label("without writing colors or depth. The scene behind each face is then");
+                ui.label("drawn with a stencil test for the value of that face.");
+            }
+            Mode::Outline => {
+                ui.label("The knots set the stencil to 1 wherever they cover the target. They");
+                ui.label("are then drawn again, pushed out along their normals, where the");
+                ui.label("stencil is not 1, which leaves only a band around them.");
+            }
+        }
+        ui.label("Drag to orbit the camera.");
+    }
+
+    fn custom_painting(&mut self, ui: &mut egui::Ui) {
+        let (rect, response) =
+            ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::drag());
+
+        let drag = response.drag_delta();
+        self.camera_yaw -= drag.x * 0.01;
+        self.camera_pitch = (self.camera_pitch + drag.y * 0.01).clamp(-1.5, 1.5);
+
+        let size = offscreen::size_in_pixels(ui, rect);
+        let projection =
+            Mat4::perspective_rh((2.0 * PI) / 5.0, size.0 as f32 / size.1 as f32, 0.1, 100.0);
+        let eye = Mat4::from_rotation_y(self.camera_yaw)
+            * Mat4::from_rotation_x(-self.camera_pitch)
+            * Vec3::new(0.0, 0.0, self.mode.camera_distance()).extend(1.0);
+        let view = Mat4::look_at_rh(eye.truncate(), Vec3::ZERO, Vec3::Y);
+
+        ui.painter().add(paint_callback(
+            rect,
+            CustomPaintCallback {
+                size,
+                uniforms: Uniforms {
+                    view_proj: (projection * view).to_cols_array_2d(),
+                    outline_color: self.outline_color.to_normalized_gamma_f32(),
+                    outline_width: self.outline_width,
+                    _padding: [0.0; 3],
+                },
+                mode: self.mode,
+                time: self.time,
+                show_stencil: self.show_stencil,
+                outline_through: self.outline_through,
+            },
+        ));
+    }
+}
+
+struct CustomPaintCallback {
+    size: (u32, u32),
+    uniforms: Uniforms,
+    mode: Mode,
+    time: f32,
+    show_stencil: bool,
+    outline_through: bool,
+}
+
+impl egui_wgpu::CallbackTrait for CustomPaintCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let profiler = GpuProfiler::get(callback_resources);
+        let stats = FrameStats::get(callback_resources);
+        let resources: &mut AppRenderResources = callback_resources.get_mut().unwrap();
+        resources.target.resize(device, self.size);
+
+        let frame = match self.mode {
+            Mode::Portal => portal_frame(self.time),
+            Mode::Outline => outline_frame(self.time),
+        };
+        stats.write_buffer(
+            queue,
+            &resources.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&self.uniforms),
+        );
+        for (buffer, instances) in [
+            (&resources.box_instance_buffer, &frame.boxes),
+            (&resources.knot_instance_buffer, &frame.knots),
+        ] {
+            if !instances.is_empty() {
+                stats.write_buffer(queue, buffer, 0, bytemuck::cast_slice(instances));
+            }
+        }
+
+        let mut render_pass = stats.pass(resources.target.begin_pass(
+            egui_encoder,
+            CLEAR_COLOR,
+            "Stencil Render Pass",
+            &profiler,
+        ));
+        render_pass.set_bind_group(0, &resources.bind_group, &[]);
+
+        render_pass.set_pipeline(&resources.opaque_pipeline);
+        resources.draw_batch(&mut render_pass, &frame.plain);
+
+        if let Some((portal, scenes)) = &frame.portal {
+            render_pass.set_pipeline(&resources.portal_pipeline);
+            for face in 0..FACE_COUNT {
+                render_pass.set_stencil_reference(face + 1);
+                resources.cube.draw(
+                    &mut render_pass,
+                    &resources.box_instance_buffer,
+                    face * FACE_INDICES..(face + 1) * FACE_INDICES,
+                    portal.boxes.clone(),
+                );
+            }
+            render_pass.set_pipeline(&resources.scene_pipeline);
+            for (face, scene) in scenes.iter().enumerate() {
+                render_pass.set_stencil_reference(face as u32 + 1);
+                resources.draw_batch(&mut render_pass, scene);
+            }
+        }
+
+        if frame.outlined != Batch::default() {
+            render_pass.set_stencil_reference(1);
+            render_pass.set_pipeline(&resources.mark_pipeline);
+            resources.draw_batch(&mut render_pass, &frame.outlined);
+            render_pass.set_pipeline(if self.outline_through {
+                &resources.outline_through_pipeline
+            } else {
+                &resources.outline_pipeline
+            });
+            resources.draw_batch(&mut render_pass, &frame.outlined);
+        }
+
+        if self.show_stencil {
+            render_pass.set_pipeline(&resources.stencil_view_pipeline);
+            for value in 1..=FACE_COUNT {
+                render_pass.set_stencil_reference(value);
+                render_pass.draw(0..3, value..value + 1);
+            }
+        }
+        drop(render_pass);
+
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: eframe::egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
+        let resources: &AppRenderResources = callback_resources.get().unwrap();
+        resources.target.blit(&mut render_pass);
+    }
+}
+
+struct AppRenderResources {
+    pub target: OffscreenTarget,
+    pub opaque_pipeline: wgpu::RenderPipeline,
+    pub portal_pipeline: wgpu::RenderPipeline,
+    pub scene_pipeline: wgpu::RenderPipeline,
+    pub mark_pipeline: wgpu::RenderPipeline,
+    pub outline_pipeline: wgpu::RenderPipeline,
+    pub outline_through_pipeline: wgpu::RenderPipeline,
+    pub stencil_view_pipeline: wgpu::RenderPipeline,
+    pub cube: GpuMesh,
+    pub knot: GpuMesh,
+    pub box_instance_buffer: wgpu::Buffer,
+    pub knot_instance_buffer: wgpu::Buffer,
+    pub uniform_buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl AppRenderResources {
+    /// Draw the boxes and the knots of `batch` with the current pipeline.
+    fn draw_batch<'a>(&'a self, render_pass: &mut CountedPass<'_, 'a>, batch: &Batch) {
+        self.cube.draw(
+            render_pass,
+            &self.box_instance_buffer,
+            0..self.cube.index_count,
+            batch.boxes.clone(),
+        );
+        self.knot.draw(
+            render_pass,
+            &self.knot_instance_buffer,
+            0..self.knot.index_count,
+            batch.knots.clone(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_fit_the_instance_buffers() {
+        for frame in [portal_frame(1.0), outline_frame(1.0)] {
+            assert!(frame.boxes.len() <= MAX_BOXES);
+            assert!(frame.knots.len() <= MAX_KNOTS);
+        }
+        let (portal, scenes) = portal_frame(1.0).portal.unwrap();
+        assert_eq!(portal.boxes.len(), 1);
+        assert_eq!(scenes.len(), FACE_COUNT as usize);
+    }
+
+    #[test]
+    fn scenes_stay_inside_the_portal_cube() {
+        let mesh = Mesh::create_torus_knot(1.0, 0.3, 128, 16, 2, 3);
+        for face in 0..FACE_COUNT {
+            let (boxes, knots) = scene(face, 1.0);
+            // Skip the room, which is the inside of the cube
+            let corners = boxes[1..].iter().flat_map(|instance| {
+                let model = Mat4::from_cols_array_2d(&instance.model);
+                Mesh::create_box(1.0, 1.0, 1.0)
+                    .vertices
+                    .into_iter()
+                    .map(move |vertex| model.transform_point3(vertex.position.into()))
+            });
+            let knot_vertices = knots.iter().flat_map(|instance| {
+                let model = Mat4::from_cols_array_2d(&instance.model);
+                mesh.vertices
+                    .iter()
+                    .map(move |vertex| model.transform_point3(vertex.position.into()))
+            });
+            for position in corners.chain(knot_vertices) {
+                assert!(position.abs().max_element() < 1.0, "{face}: {position}");
+            }
+        }
+    }
+}
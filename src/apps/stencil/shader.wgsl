@@ -0,0 +1,86 @@
+struct Uniforms {
+    view_proj: mat4x4f,
+    outline_color: vec4f,
+    // How far the outline reaches out of the objects, in world units
+    outline_width: f32,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+
+struct VertexInput {
+    @location(0) position: vec3f,
+    @location(1) normal: vec3f,
+    @location(5) model_0: vec4f,
+    @location(6) model_1: vec4f,
+    @location(7) model_2: vec4f,
+    @location(8) model_3: vec4f,
+    @location(9) color: vec4f,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) normal: vec3f,
+    @location(1) color: vec4f,
+}
+
+fn world_normal(in: VertexInput) -> vec3f {
+    // Rooms are boxes scaled by a negative factor, which turns their normals inwards
+    return normalize((mat4x4f(in.model_0, in.model_1, in.model_2, in.model_3) * vec4f(in.normal, 0.0)).xyz);
+}
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    let model = mat4x4f(in.model_0, in.model_1, in.model_2, in.model_3);
+    var out: VertexOutput;
+    out.position = uniforms.view_proj * model * vec4f(in.position, 1.0);
+    out.normal = world_normal(in);
+    out.color = in.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let light = normalize(vec3f(0.4, 0.7, 1.0));
+    let diffuse = max(dot(normalize(in.normal), light), 0.0);
+    return vec4f(in.color.rgb * (0.35 + 0.65 * diffuse), in.color.a);
+}
+
+// The objects pushed out along their normals, so they cover a band around themselves
+@vertex
+fn vs_outline(in: VertexInput) -> VertexOutput {
+    let model = mat4x4f(in.model_0, in.model_1, in.model_2, in.model_3);
+    let normal = world_normal(in);
+    let position = (model * vec4f(in.position, 1.0)).xyz + normal * uniforms.outline_width;
+    var out: VertexOutput;
+    out.position = uniforms.view_proj * vec4f(position, 1.0);
+    out.normal = normal;
+    out.color = uniforms.outline_color;
+    return out;
+}
+
+// Unlit, for the outlines and the stencil view
+@fragment
+fn fs_flat(in: VertexOutput) -> @location(0) vec4f {
+    return in.color;
+}
+
+// A triangle covering the whole target, drawn once per stencil value with that value as
+// instance index
+@vertex
+fn vs_stencil(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,
+) -> VertexOutput {
+    let uv = vec2f(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    let hue = f32(instance_index) / 6.0 * 6.2831853;
+    var out: VertexOutput;
+    out.position = vec4f(uv * 2.0 - 1.0, 0.0, 1.0);
+    out.normal = vec3f(0.0, 0.0, 1.0);
+    out.color = vec4f(
+        0.5 + 0.5 * cos(hue),
+        0.5 + 0.5 * cos(hue - 2.0943951),
+        0.5 + 0.5 * cos(hue + 2.0943951),
+        0.6,
+    );
+    return out;
+}
@@ -0,0 +1,441 @@
+use eframe::{
+    egui,
+    egui_wgpu::{self, RenderState},
+};
+use glam::{Mat4, Quat, Vec3};
+use std::f32::consts::{FRAC_PI_2, PI};
+use wgpu::util::DeviceExt;
+
+use crate::meshes::mesh::{Mesh, Vertex};
+use crate::offscreen::{self, OffscreenTarget};
+use crate::profiler::GpuProfiler;
+use crate::stats::{paint_callback, FrameStats};
+use crate::text::{MsdfFont, MsdfText, MsdfTextRenderer, TextOptions};
+
+const CANVAS: (f32, f32) = (600.0, 600.0);
+/// Size of an atlas texel on the faces of the unit cube.
+const FACE_PIXEL_SCALE: f32 = 1.0 / 96.0;
+/// Labels larger than this are scaled down to fit on their face, leaving a margin.
+const FACE_TEXT_SIZE: f32 = 1.8;
+const PARAGRAPH: &str = "Multi-channel signed distance fields
+keep the corners of glyphs sharp
+when text is magnified or seen at an angle.
+
+Kerning pulls pairs like AV, To, Wa and Ty
+closer together than their advances alone.";
+
+/// The faces of the cube: the rotation turning +Z towards the face, and the default label.
+const FACES: [(Vec3, f32, &str); 6] = [
+    (Vec3::Y, 0.0, "Front"),
+    (Vec3::Y, PI, "Back"),
+    (Vec3::Y, FRAC_PI_2, "Right"),
+    (Vec3::Y, -FRAC_PI_2, "Left"),
+    (Vec3::X, -FRAC_PI_2, "Top"),
+    (Vec3::X, FRAC_PI_2, "Bottom"),
+];
+
+/// Laid out to match `Uniforms` in `shader.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    view_proj: [[f32; 4]; 4],
+    model: [[f32; 4]; 4],
+}
+
+/// What the texts are laid out from, so they are only recreated when it changes.
+#[derive(Clone, PartialEq)]
+struct TextSettings {
+    front: String,
+    kerning: bool,
+}
+
+pub struct TextRenderingMsdf {
+    start_time: std::time::Instant,
+    settings: TextSettings,
+    text_color: egui::Rgba,
+    rotate: bool,
+    angle: f32,
+    camera_yaw: f32,
+    camera_pitch: f32,
+}
+
+impl TextRenderingMsdf {
+    pub fn new_with_render_state(wgpu_render_state: &RenderState) -> Option<Self> {
+        let device = &wgpu_render_state.device;
+        let queue = &wgpu_render_state.queue;
+
+        let mesh = Mesh::create_box(2.0, 2.0, 2.0);
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("TextRenderingMsdf Vertex Buffer"),
+            contents: bytemuck::cast_slice(&mesh.vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("TextRenderingMsdf Index Buffer"),
+            contents: bytemuck::cast_slice(&mesh.indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("TextRenderingMsdf Uniform Buffer"),
+            size: std::mem::size_of::<Uniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("TextRenderingMsdf Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("TextRenderingMsdf Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("TextRenderingMsdf Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./shader.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("TextRenderingMsdf Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("TextRenderingMsdf Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu_render_state.target_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: offscreen::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let text_renderer = MsdfTextRenderer::new(
+            device,
+            wgpu_render_state.target_format,
+            Some(offscreen::DEPTH_FORMAT),
+        );
+        let font = text_renderer.default_font(device, queue);
+        let settings = TextSettings {
+            front: FACES[0].2.to_string(),
+            kerning: true,
+        };
+        let texts = create_texts(device, &text_renderer, &font, &settings);
+
+        let target = OffscreenTarget::new(
+            device,
+            wgpu_render_state.target_format,
+            Some(offscreen::DEPTH_FORMAT),
+            (CANVAS.0 as u32, CANVAS.1 as u32),
+        );
+
+        // Because the graphics pipeline must have the same lifetime as the egui render pass,
+        // instead of storing the pipeline in our struct, we insert it into the
+        // `paint_callback_resources` type map, which is stored alongside the render pass.
+        wgpu_render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(AppRenderResources {
+                target,
+                pipeline,
+                bind_group,
+                uniform_buffer,
+                vertex_buffer,
+                index_buffer,
+                index_count: mesh.indices.len() as u32,
+                text_renderer,
+                font,
+                texts,
+                settings: settings.clone(),
+            });
+
+        Some(Self {
+            start_time: std::time::Instant::now(),
+            settings,
+            text_color: egui::Rgba::WHITE,
+            rotate: true,
+            angle: 0.0,
+            camera_yaw: 0.0,
+            camera_pitch: 0.0,
+        })
+    }
+}
+
+/// The label of every face, in the order of [`FACES`], and a paragraph behind the cube.
+struct Texts {
+    faces: Vec<MsdfText>,
+    paragraph: MsdfText,
+}
+
+fn create_texts(
+    device: &wgpu::Device,
+    renderer: &MsdfTextRenderer,
+    font: &MsdfFont,
+    settings: &TextSettings,
+) -> Texts {
+    let options = TextOptions {
+        centered: true,
+        pixel_scale: FACE_PIXEL_SCALE,
+        kerning: settings.kerning,
+    };
+    let faces = FACES
+        .iter()
+        .enumerate()
+        .map(|(i, (_, _, label))| {
+            let label = if i == 0 {
+                settings.front.as_str()
+            } else {
+                label
+            };
+            renderer.create_text(device, font, label, &options)
+        })
+        .collect();
+    let paragraph = renderer.create_text(
+        device,
+        font,
+        PARAGRAPH,
+        &TextOptions {
+            pixel_scale: 1.0 / 160.0,
+            ..options
+        },
+    );
+    Texts { faces, paragraph }
+}
+
+impl eframe::App for TextRenderingMsdf {
+    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                        self.custom_painting(ui);
+                    });
+                    ui.vertical(|ui| self.controls(ui));
+                });
+            });
+        });
+        // This is needed to animate the cube. It tells eframe to call update() again on the next event loop iteration.
+        ctx.request_repaint();
+    }
+}
+
+impl TextRenderingMsdf {
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("text_rendering_msdf_controls")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("front text");
+                ui.add(egui::TextEdit::singleline(&mut self.settings.front).desired_width(120.0));
+                ui.end_row();
+                ui.label("kerning");
+                ui.checkbox(&mut self.settings.kerning, "");
+                ui.end_row();
+                ui.label("text color");
+                egui::color_picker::color_edit_button_rgba(
+                    ui,
+                    &mut self.text_color,
+                    egui::color_picker::Alpha::OnlyBlend,
+                );
+                ui.end_row();
+                ui.label("rotate");
+                ui.checkbox(&mut self.rotate, "");
+                ui.end_row();
+            });
+        ui.label("Drag to orbit the camera.");
+    }
+
+    fn custom_painting(&mut self, ui: &mut egui::Ui) {
+        let (rect, response) =
+            ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::drag());
+
+        if self.rotate {
+            self.angle = self.start_time.elapsed().as_secs_f32() * 0.4;
+        }
+        let drag = response.drag_delta();
+        self.camera_yaw -= drag.x * 0.01;
+        self.camera_pitch = (self.camera_pitch + drag.y * 0.01).clamp(-1.5, 1.5);
+
+        let projection = Mat4::perspective_rh((2.0 * PI) / 5.0, CANVAS.0 / CANVAS.1, 0.1, 100.0);
+        let eye = Mat4::from_rotation_y(self.camera_yaw)
+            * Mat4::from_rotation_x(-self.camera_pitch)
+            * Vec3::new(0.0, 0.0, 5.0).extend(1.0);
+        let view = Mat4::look_at_rh(eye.truncate(), Vec3::ZERO, Vec3::Y);
+        let model = Mat4::from_rotation_y(self.angle) * Mat4::from_rotation_x(self.angle * 0.6);
+
+        // Each label sits just above its face, so it wins the depth test against the cube
+        let face_transforms = FACES.map(|(axis, angle, _)| {
+            model
+                * Mat4::from_quat(Quat::from_axis_angle(axis, angle))
+                * Mat4::from_translation(Vec3::Z * 1.002)
+        });
+        let paragraph_transform = Mat4::from_translation(Vec3::new(0.0, 0.0, -3.0));
+
+        ui.painter().add(paint_callback(
+            rect,
+            CustomPaintCallback {
+                size: offscreen::size_in_pixels(ui, rect),
+                view_proj: projection * view,
+                uniforms: Uniforms {
+                    view_proj: (projection * view).to_cols_array_2d(),
+                    model: model.to_cols_array_2d(),
+                },
+                face_transforms,
+                paragraph_transform,
+                text_color: self.text_color.to_array(),
+                settings: self.settings.clone(),
+            },
+        ));
+    }
+}
+
+struct CustomPaintCallback {
+    size: (u32, u32),
+    view_proj: Mat4,
+    uniforms: Uniforms,
+    face_transforms: [Mat4; 6],
+    paragraph_transform: Mat4,
+    text_color: [f32; 4],
+    settings: TextSettings,
+}
+
+impl egui_wgpu::CallbackTrait for CustomPaintCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let profiler = GpuProfiler::get(callback_resources);
+        let stats = FrameStats::get(callback_resources);
+        let resources: &mut AppRenderResources = callback_resources.get_mut().unwrap();
+        resources.target.resize(device, self.size);
+        if resources.settings != self.settings {
+            resources.texts = create_texts(
+                device,
+                &resources.text_renderer,
+                &resources.font,
+                &self.settings,
+            );
+            resources.settings = self.settings.clone();
+        }
+
+        stats.write_buffer(
+            queue,
+            &resources.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&self.uniforms),
+        );
+        resources
+            .text_renderer
+            .update_camera(queue, &stats, self.view_proj);
+        for (text, transform) in resources.texts.faces.iter().zip(self.face_transforms) {
+            // Shrink labels that would not fit on their face
+            let fit = (FACE_TEXT_SIZE / text.width.max(text.height)).min(1.0);
+            text.update(
+                queue,
+                &stats,
+                transform * Mat4::from_scale(Vec3::splat(fit)),
+                self.text_color,
+            );
+        }
+        let paragraph_color = [0.85, 0.85, 0.9, 1.0];
+        resources
+            .texts
+            .paragraph
+            .update(queue, &stats, self.paragraph_transform, paragraph_color);
+
+        let mut render_pass = stats.pass(resources.target.begin_pass(
+            egui_encoder,
+            wgpu::Color {
+                r: 0.1,
+                g: 0.1,
+                b: 0.12,
+                a: 1.0,
+            },
+            "TextRenderingMsdf Render Pass",
+            &profiler,
+        ));
+        render_pass.set_pipeline(&resources.pipeline);
+        render_pass.set_bind_group(0, &resources.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, resources.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(resources.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..resources.index_count, 0, 0..1);
+
+        // Text is blended, so it goes after everything opaque
+        resources.text_renderer.draw(
+            &mut render_pass,
+            &resources.font,
+            resources
+                .texts
+                .faces
+                .iter()
+                .chain(std::iter::once(&resources.texts.paragraph)),
+        );
+        drop(render_pass);
+
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: eframe::egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
+        let resources: &AppRenderResources = callback_resources.get().unwrap();
+        resources.target.blit(&mut render_pass);
+    }
+}
+
+struct AppRenderResources {
+    pub target: OffscreenTarget,
+    pub pipeline: wgpu::RenderPipeline,
+    pub bind_group: wgpu::BindGroup,
+    pub uniform_buffer: wgpu::Buffer,
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub index_count: u32,
+    pub text_renderer: MsdfTextRenderer,
+    pub font: MsdfFont,
+    pub texts: Texts,
+    pub settings: TextSettings,
+}
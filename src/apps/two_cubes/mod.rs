@@ -6,11 +6,13 @@ use glam::{Mat4, Quat, Vec3};
 use std::{f32::consts::PI, num::NonZeroU64};
 use wgpu::{util::DeviceExt, BufferBinding};
 
+use crate::debug_view::{self, DebugView, DebugViewPipelines};
 use crate::meshes::cube;
+use crate::stats::{paint_callback, FrameStats};
 
 const CANVAS: (f32, f32) = (600.0, 600.0);
 
-fn get_mvp_matrix(start_time: std::time::Instant) -> (Mat4, Mat4) {
+fn get_model_matrix(start_time: std::time::Instant) -> (Mat4, Mat4) {
     let now = std::time::Instant::now()
         .duration_since(start_time)
         .as_secs_f32();
@@ -25,6 +27,10 @@ fn get_mvp_matrix(start_time: std::time::Instant) -> (Mat4, Mat4) {
         Vec3::new(2.0, 0.0, 0.0),
     );
 
+    (model_matrix_0, model_matrix_1)
+}
+
+fn get_mvp_matrix((model_matrix_0, model_matrix_1): (Mat4, Mat4)) -> (Mat4, Mat4) {
     let view_matrix = Mat4::from_translation(Vec3::new(0.0, 0.0, -7.0));
 
     let projection_matrix = Mat4::perspective_rh((2.0 * PI) / 5.0, CANVAS.0 / CANVAS.1, 1.0, 100.0);
@@ -103,7 +109,7 @@ impl TwoCubes {
                 resource: wgpu::BindingResource::Buffer(BufferBinding {
                     buffer: &mvp_buffer,
                     offset: 0,
-                    size: Some(NonZeroU64::new(std::mem::size_of::<Mat4>() as u64).unwrap()),
+                    size: Some(NonZeroU64::new(2 * std::mem::size_of::<Mat4>() as u64).unwrap()),
                 }),
             }],
         });
@@ -116,14 +122,22 @@ impl TwoCubes {
                 resource: wgpu::BindingResource::Buffer(BufferBinding {
                     buffer: &mvp_buffer,
                     offset: 256,
-                    size: Some(NonZeroU64::new(std::mem::size_of::<Mat4>() as u64).unwrap()),
+                    size: Some(NonZeroU64::new(2 * std::mem::size_of::<Mat4>() as u64).unwrap()),
                 }),
             }],
         });
 
+        let normal_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("TwoCubes Normal Buffer"),
+            contents: bytemuck::cast_slice(cube::NORMALS),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("TwoCubes Shader Module"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("./shader.wgsl").into()),
+            source: wgpu::ShaderSource::Wgsl(
+                debug_view::shader(include_str!("./shader.wgsl"), 1).into(),
+            ),
         });
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -132,13 +146,13 @@ impl TwoCubes {
             push_constant_ranges: &[],
         });
 
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        let pipeline_descriptor = wgpu::RenderPipelineDescriptor {
             label: Some("TwoCubes Render Pipeline"),
             layout: Some(&pipeline_layout),
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: "vs_main",
-                buffers: &[vertices_buffer_layout],
+                buffers: &[vertices_buffer_layout, cube::NORMAL_BUFFER_LAYOUT],
             },
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
@@ -168,7 +182,11 @@ impl TwoCubes {
                 alpha_to_coverage_enabled: false,
             },
             multiview: None,
-        });
+        };
+        let pipeline = device.create_render_pipeline(&pipeline_descriptor);
+        // The same pipeline with the fragment stage of the debug view selected in the side panel
+        let debug_view =
+            DebugViewPipelines::new(device, &pipeline_descriptor, &[&bind_group_layout]);
 
         // Because the graphics pipeline must have the same lifetime as the egui render pass,
         // instead of storing the pipeline in our struct, we insert it into the
@@ -180,7 +198,9 @@ impl TwoCubes {
             .insert(AppRenderResources {
                 start_time: std::time::Instant::now(),
                 pipeline,
+                debug_view,
                 vertices_buffer,
+                normal_buffer,
                 mvp_buffer,
                 mvp_bind_group_0,
                 mvp_bind_group_1,
@@ -208,10 +228,8 @@ impl TwoCubes {
     fn custom_painting(&self, ui: &mut egui::Ui) {
         let (rect, _response) =
             ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::click());
-        ui.painter().add(egui_wgpu::Callback::new_paint_callback(
-            rect,
-            CustomPaintCallback(),
-        ));
+        ui.painter()
+            .add(paint_callback(rect, CustomPaintCallback()));
     }
 }
 
@@ -226,17 +244,26 @@ impl egui_wgpu::CallbackTrait for CustomPaintCallback {
         _egui_encoder: &mut wgpu::CommandEncoder,
         callback_resources: &mut egui_wgpu::CallbackResources,
     ) -> Vec<wgpu::CommandBuffer> {
+        let stats = FrameStats::get(callback_resources);
         let resources: &AppRenderResources = callback_resources.get().unwrap();
-        queue.write_buffer(
+        let model_matrix = get_model_matrix(resources.start_time);
+        let mvp_matrix = get_mvp_matrix(model_matrix);
+        // The model matrices follow, for the normals of the debug view
+        stats.write_buffer(
+            queue,
             &resources.mvp_buffer,
             0,
-            bytemuck::cast_slice(&[get_mvp_matrix(resources.start_time).0]),
+            bytemuck::cast_slice(&[mvp_matrix.0, model_matrix.0]),
         );
-        queue.write_buffer(
+        stats.write_buffer(
+            queue,
             &resources.mvp_buffer,
             256,
-            bytemuck::cast_slice(&[get_mvp_matrix(resources.start_time).1]),
+            bytemuck::cast_slice(&[mvp_matrix.1, model_matrix.1]),
         );
+        resources
+            .debug_view
+            .prepare(queue, &stats, &DebugView::settings(callback_resources));
         Vec::new()
     }
 
@@ -246,9 +273,15 @@ impl egui_wgpu::CallbackTrait for CustomPaintCallback {
         render_pass: &mut wgpu::RenderPass<'a>,
         callback_resources: &'a egui_wgpu::CallbackResources,
     ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
         let resources: &AppRenderResources = callback_resources.get().unwrap();
-        render_pass.set_pipeline(&resources.pipeline);
+        resources.debug_view.set_pipeline(
+            &mut render_pass,
+            &resources.pipeline,
+            &DebugView::settings(callback_resources),
+        );
         render_pass.set_vertex_buffer(0, resources.vertices_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, resources.normal_buffer.slice(..));
         render_pass.set_bind_group(0, &resources.mvp_bind_group_0, &[]);
         render_pass.draw(0..cube::VERTEX_COUNT, 0..1);
         render_pass.set_bind_group(0, &resources.mvp_bind_group_1, &[]);
@@ -259,7 +292,9 @@ impl egui_wgpu::CallbackTrait for CustomPaintCallback {
 struct AppRenderResources {
     pub start_time: std::time::Instant,
     pub pipeline: wgpu::RenderPipeline,
+    pub debug_view: DebugViewPipelines,
     pub vertices_buffer: wgpu::Buffer,
+    pub normal_buffer: wgpu::Buffer,
     pub mvp_buffer: wgpu::Buffer,
     pub mvp_bind_group_0: wgpu::BindGroup,
     pub mvp_bind_group_1: wgpu::BindGroup,
@@ -0,0 +1,624 @@
+mod transfer_function;
+mod volume;
+
+use eframe::{
+    egui,
+    egui_wgpu::{self, RenderState},
+};
+use glam::{Mat4, Vec3};
+use std::f32::consts::PI;
+use std::sync::Arc;
+use wgpu::util::DeviceExt;
+
+use transfer_function::{ControlPoint, Preset, TransferFunction};
+use volume::Volume;
+
+use crate::stats::{paint_callback, FrameStats};
+
+const CANVAS: (f32, f32) = (600.0, 600.0);
+/// Voxels along each side of the generated phantom.
+const PHANTOM_SIZE: u32 = 128;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RenderMode {
+    Composite,
+    MaximumIntensity,
+}
+
+impl RenderMode {
+    const ALL: [Self; 2] = [Self::Composite, Self::MaximumIntensity];
+}
+
+impl std::fmt::Display for RenderMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Composite => "Composite",
+            Self::MaximumIntensity => "Maximum intensity",
+        })
+    }
+}
+
+/// Laid out to match `Uniforms` in `shader.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    inverse_view_proj: [[f32; 4]; 4],
+    eye: [f32; 3],
+    step_count: u32,
+    extent: [f32; 3],
+    mode: u32,
+    clip_min: [f32; 3],
+    density: f32,
+    clip_max: [f32; 3],
+    _padding: f32,
+}
+
+pub struct VolumeRenderingTexture3D {
+    start_time: std::time::Instant,
+    mode: RenderMode,
+    step_count: u32,
+    density: f32,
+    preset: Preset,
+    transfer_function: TransferFunction,
+    selected_point: usize,
+    clip_min: [f32; 3],
+    clip_max: [f32; 3],
+    rotate: bool,
+    angle: f32,
+    camera_yaw: f32,
+    camera_pitch: f32,
+    extent: Vec3,
+    max_dimension: u32,
+    pending_volume: Option<Arc<Volume>>,
+    status: String,
+}
+
+impl VolumeRenderingTexture3D {
+    pub fn new_with_render_state(wgpu_render_state: &RenderState) -> Option<Self> {
+        let device = &wgpu_render_state.device;
+        let queue = &wgpu_render_state.queue;
+
+        let volume = Volume::shepp_logan(PHANTOM_SIZE);
+        let volume_texture = create_volume_texture(device, queue, &volume);
+
+        let preset = Preset::Tissue;
+        let transfer_function = preset.transfer_function();
+        let transfer_function_texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("VolumeRenderingTexture3D Transfer Function Texture"),
+                size: wgpu::Extent3d {
+                    width: transfer_function::RESOLUTION as u32,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            bytemuck::cast_slice(&transfer_function.texels()),
+        );
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("VolumeRenderingTexture3D Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("VolumeRenderingTexture3D Uniform Buffer"),
+            size: std::mem::size_of::<Uniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("VolumeRenderingTexture3D Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D3,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("VolumeRenderingTexture3D Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./shader.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("VolumeRenderingTexture3D Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        // Rays that miss the volume are discarded, so the volume is blended over the canvas
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("VolumeRenderingTexture3D Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: wgpu_render_state.target_format,
+                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let bind_group = create_bind_group(
+            device,
+            &bind_group_layout,
+            &uniform_buffer,
+            &volume_texture,
+            &transfer_function_texture,
+            &sampler,
+        );
+
+        // Because the graphics pipeline must have the same lifetime as the egui render pass,
+        // instead of storing the pipeline in our struct, we insert it into the
+        // `paint_callback_resources` type map, which is stored alongside the render pass.
+        wgpu_render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(AppRenderResources {
+                pipeline,
+                bind_group_layout,
+                bind_group,
+                uniform_buffer,
+                sampler,
+                transfer_function_texture,
+                transfer_function: transfer_function.clone(),
+            });
+
+        Some(Self {
+            start_time: std::time::Instant::now(),
+            mode: RenderMode::Composite,
+            step_count: 256,
+            density: 1.0,
+            preset,
+            transfer_function,
+            selected_point: 0,
+            clip_min: [0.0; 3],
+            clip_max: [1.0; 3],
+            rotate: true,
+            angle: 0.0,
+            camera_yaw: 0.0,
+            camera_pitch: 0.3,
+            extent: volume.extent(),
+            max_dimension: device.limits().max_texture_dimension_3d,
+            pending_volume: None,
+            status: String::new(),
+        })
+    }
+
+    fn load_volume(&mut self, bytes: &[u8], name: &str) {
+        match Volume::load_raw(bytes, name) {
+            Ok(volume) => {
+                let (width, height, depth) = volume.size;
+                if width.max(height).max(depth) > self.max_dimension {
+                    self.status = format!(
+                        "{name} is {width}x{height}x{depth}, but 3D textures are limited to {} per side",
+                        self.max_dimension
+                    );
+                    return;
+                }
+                self.status = format!("Loaded {name} ({width}x{height}x{depth})");
+                self.extent = volume.extent();
+                self.pending_volume = Some(Arc::new(volume));
+            }
+            Err(err) => self.status = format!("Failed to load {name}: {err}"),
+        }
+    }
+}
+
+fn create_volume_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    volume: &Volume,
+) -> wgpu::Texture {
+    device.create_texture_with_data(
+        queue,
+        &wgpu::TextureDescriptor {
+            label: Some("VolumeRenderingTexture3D Volume Texture"),
+            size: wgpu::Extent3d {
+                width: volume.size.0,
+                height: volume.size.1,
+                depth_or_array_layers: volume.size.2,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        },
+        wgpu::util::TextureDataOrder::LayerMajor,
+        &volume.data,
+    )
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buffer: &wgpu::Buffer,
+    volume_texture: &wgpu::Texture,
+    transfer_function_texture: &wgpu::Texture,
+    sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("VolumeRenderingTexture3D Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(
+                    &volume_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(
+                    &transfer_function_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    })
+}
+
+impl eframe::App for VolumeRenderingTexture3D {
+    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        // Volumes can also be dropped onto the window
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        for file in dropped {
+            let bytes = match (&file.bytes, &file.path) {
+                (Some(bytes), _) => Ok(bytes.to_vec()),
+                (None, Some(path)) => std::fs::read(path).map_err(|e| e.to_string()),
+                (None, None) => continue,
+            };
+            match bytes {
+                Ok(bytes) => self.load_volume(&bytes, &file.name),
+                Err(err) => self.status = format!("Failed to read {}: {err}", file.name),
+            }
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    egui::Frame::canvas(ui.style()).show(ui, |ui| {
+                        self.custom_painting(ui);
+                    });
+                    ui.vertical(|ui| self.controls(ui));
+                });
+            });
+        });
+        // This is needed to animate the volume. It tells eframe to call update() again on the next event loop iteration.
+        ctx.request_repaint();
+    }
+}
+
+impl VolumeRenderingTexture3D {
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("volume_rendering_texture_3d_controls")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("mode");
+                egui::ComboBox::from_id_source("volume_rendering_texture_3d_mode")
+                    .selected_text(self.mode.to_string())
+                    .show_ui(ui, |ui| {
+                        for mode in RenderMode::ALL {
+                            ui.selectable_value(&mut self.mode, mode, mode.to_string());
+                        }
+                    });
+                ui.end_row();
+                ui.label("steps");
+                ui.add(egui::Slider::new(&mut self.step_count, 16..=1024).logarithmic(true));
+                ui.end_row();
+                ui.label("density");
+                ui.add(egui::Slider::new(&mut self.density, 0.05..=4.0).logarithmic(true));
+                ui.end_row();
+                ui.label("preset");
+                egui::ComboBox::from_id_source("volume_rendering_texture_3d_preset")
+                    .selected_text(self.preset.to_string())
+                    .show_ui(ui, |ui| {
+                        for preset in Preset::ALL {
+                            if ui
+                                .selectable_label(self.preset == preset, preset.to_string())
+                                .clicked()
+                            {
+                                self.preset = preset;
+                                self.transfer_function = preset.transfer_function();
+                                self.selected_point = 0;
+                            }
+                        }
+                    });
+                ui.end_row();
+                for (axis, name) in ["clip x", "clip y", "clip z"].iter().enumerate() {
+                    ui.label(*name);
+                    ui.horizontal(|ui| {
+                        ui.add(egui::Slider::new(&mut self.clip_min[axis], 0.0..=1.0));
+                        ui.add(egui::Slider::new(&mut self.clip_max[axis], 0.0..=1.0));
+                    });
+                    self.clip_max[axis] = self.clip_max[axis].max(self.clip_min[axis]);
+                    ui.end_row();
+                }
+                ui.label("rotate");
+                ui.checkbox(&mut self.rotate, "");
+                ui.end_row();
+            });
+
+        ui.separator();
+        ui.label("transfer function");
+        self.transfer_function_editor(ui);
+        if let Some(point) = self.transfer_function.points.get_mut(self.selected_point) {
+            ui.horizontal(|ui| {
+                ui.label("point color");
+                egui::color_picker::color_edit_button_srgb(ui, &mut point.color);
+            });
+        }
+        ui.label("Drag points to change their value and opacity, double-click to add one and right-click to remove one.");
+        ui.label("Drag to orbit the camera.");
+        ui.label("Drop a raw 8-bit volume named like name_WxHxD.raw onto the window to render it instead.");
+        if !self.status.is_empty() {
+            ui.label(&self.status);
+        }
+    }
+
+    /// The colors of the transfer function as a gradient, with its opacity as a curve on top
+    /// and its control points as handles.
+    fn transfer_function_editor(&mut self, ui: &mut egui::Ui) {
+        let (rect, response) =
+            ui.allocate_exact_size(egui::Vec2::new(256.0, 96.0), egui::Sense::click());
+        let painter = ui.painter_at(rect);
+
+        let texels = self.transfer_function.texels();
+        let x = |value: f32| rect.left() + value * rect.width();
+        let y = |opacity: f32| rect.bottom() - opacity * rect.height();
+        let step = 1.0 / (texels.len() - 1) as f32;
+
+        let mut gradient = egui::Mesh::default();
+        for (i, [r, g, b, _]) in texels.iter().enumerate() {
+            let color = egui::Color32::from_rgb(*r, *g, *b);
+            gradient.colored_vertex(egui::pos2(x(i as f32 * step), rect.top()), color);
+            gradient.colored_vertex(egui::pos2(x(i as f32 * step), rect.bottom()), color);
+            if i > 0 {
+                let k = 2 * i as u32;
+                gradient.add_triangle(k - 2, k - 1, k);
+                gradient.add_triangle(k - 1, k, k + 1);
+            }
+        }
+        painter.add(gradient);
+        let opacity = texels
+            .iter()
+            .enumerate()
+            .map(|(i, texel)| egui::pos2(x(i as f32 * step), y(texel[3] as f32 / 255.0)))
+            .collect();
+        painter.add(egui::Shape::line(
+            opacity,
+            egui::Stroke::new(1.5, egui::Color32::WHITE),
+        ));
+
+        let mut removed = None;
+        for (i, point) in self.transfer_function.points.iter_mut().enumerate() {
+            let center = egui::pos2(x(point.value), y(point.opacity));
+            let handle = ui.interact(
+                egui::Rect::from_center_size(center, egui::Vec2::splat(12.0)),
+                response.id.with(i),
+                egui::Sense::click_and_drag(),
+            );
+            if handle.clicked() || handle.dragged() {
+                self.selected_point = i;
+            }
+            if handle.dragged() {
+                let delta = handle.drag_delta();
+                point.value = (point.value + delta.x / rect.width()).clamp(0.0, 1.0);
+                point.opacity = (point.opacity - delta.y / rect.height()).clamp(0.0, 1.0);
+            }
+            if handle.secondary_clicked() {
+                removed = Some(i);
+            }
+
+            let [r, g, b] = point.color;
+            let outline = if i == self.selected_point {
+                egui::Stroke::new(2.0, egui::Color32::YELLOW)
+            } else {
+                egui::Stroke::new(1.0, egui::Color32::BLACK)
+            };
+            painter.circle(center, 5.0, egui::Color32::from_rgb(r, g, b), outline);
+        }
+
+        // Keep at least two points, so there is always something to interpolate
+        if let (Some(i), true) = (removed, self.transfer_function.points.len() > 2) {
+            self.transfer_function.points.remove(i);
+            self.selected_point = self
+                .selected_point
+                .min(self.transfer_function.points.len() - 1);
+        }
+        if let (true, Some(position)) = (response.double_clicked(), response.interact_pointer_pos())
+        {
+            let value = ((position.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+            let [r, g, b, _] = self.transfer_function.sample(value);
+            self.transfer_function.points.push(ControlPoint {
+                value,
+                color: [r, g, b],
+                opacity: ((rect.bottom() - position.y) / rect.height()).clamp(0.0, 1.0),
+            });
+            self.selected_point = self.transfer_function.points.len() - 1;
+        }
+    }
+
+    fn custom_painting(&mut self, ui: &mut egui::Ui) {
+        let (rect, response) =
+            ui.allocate_exact_size(egui::Vec2::new(CANVAS.0, CANVAS.1), egui::Sense::drag());
+
+        if self.rotate {
+            self.angle = self.start_time.elapsed().as_secs_f32() * 0.3;
+        }
+        let drag = response.drag_delta();
+        self.camera_yaw -= drag.x * 0.01;
+        self.camera_pitch = (self.camera_pitch + drag.y * 0.01).clamp(-1.5, 1.5);
+
+        let projection = Mat4::perspective_rh((2.0 * PI) / 5.0, CANVAS.0 / CANVAS.1, 0.1, 100.0);
+        let eye = (Mat4::from_rotation_y(self.camera_yaw + self.angle)
+            * Mat4::from_rotation_x(-self.camera_pitch)
+            * Vec3::new(0.0, 0.0, 1.3).extend(1.0))
+        .truncate();
+        let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+
+        ui.painter().add(paint_callback(
+            rect,
+            CustomPaintCallback {
+                uniforms: Uniforms {
+                    inverse_view_proj: (projection * view).inverse().to_cols_array_2d(),
+                    eye: eye.to_array(),
+                    step_count: self.step_count,
+                    extent: self.extent.to_array(),
+                    mode: match self.mode {
+                        RenderMode::Composite => 0,
+                        RenderMode::MaximumIntensity => 1,
+                    },
+                    clip_min: self.clip_min,
+                    density: self.density,
+                    clip_max: self.clip_max,
+                    _padding: 0.0,
+                },
+                transfer_function: self.transfer_function.clone(),
+                volume: self.pending_volume.take(),
+            },
+        ));
+    }
+}
+
+struct CustomPaintCallback {
+    uniforms: Uniforms,
+    transfer_function: TransferFunction,
+    volume: Option<Arc<Volume>>,
+}
+
+impl egui_wgpu::CallbackTrait for CustomPaintCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        _egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let stats = FrameStats::get(callback_resources);
+        let resources: &mut AppRenderResources = callback_resources.get_mut().unwrap();
+        if let Some(volume) = &self.volume {
+            let volume_texture = create_volume_texture(device, queue, volume);
+            resources.bind_group = create_bind_group(
+                device,
+                &resources.bind_group_layout,
+                &resources.uniform_buffer,
+                &volume_texture,
+                &resources.transfer_function_texture,
+                &resources.sampler,
+            );
+        }
+
+        // The transfer function only needs uploading again when it was edited
+        if resources.transfer_function != self.transfer_function {
+            queue.write_texture(
+                resources.transfer_function_texture.as_image_copy(),
+                bytemuck::cast_slice(&self.transfer_function.texels()),
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(transfer_function::RESOLUTION as u32 * 4),
+                    rows_per_image: None,
+                },
+                resources.transfer_function_texture.size(),
+            );
+            resources.transfer_function = self.transfer_function.clone();
+        }
+
+        stats.write_buffer(
+            queue,
+            &resources.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&self.uniforms),
+        );
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: eframe::egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        let mut render_pass = FrameStats::get(callback_resources).pass(render_pass);
+        let resources: &AppRenderResources = callback_resources.get().unwrap();
+        render_pass.set_pipeline(&resources.pipeline);
+        render_pass.set_bind_group(0, &resources.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+struct AppRenderResources {
+    pub pipeline: wgpu::RenderPipeline,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+    pub uniform_buffer: wgpu::Buffer,
+    pub sampler: wgpu::Sampler,
+    pub transfer_function_texture: wgpu::Texture,
+    pub transfer_function: TransferFunction,
+}
@@ -0,0 +1,97 @@
+struct Uniforms {
+    inverse_view_proj: mat4x4f,
+    eye: vec3f,
+    // Samples along the diagonal of the volume
+    step_count: u32,
+    // Extent of the volume, centered on the origin
+    extent: vec3f,
+    // 0 for compositing, 1 for maximum intensity projection
+    mode: u32,
+    // The clipping box, from 0 to 1 along each axis of the volume
+    clip_min: vec3f,
+    density: f32,
+    clip_max: vec3f,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var volume: texture_3d<f32>;
+@group(0) @binding(2) var transfer_function: texture_2d<f32>;
+@group(0) @binding(3) var linear_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) ndc: vec2f,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    // A triangle covering the whole viewport
+    let uv = vec2f(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    var out: VertexOutput;
+    out.position = vec4f(uv * 2.0 - 1.0, 0.0, 1.0);
+    out.ndc = out.position.xy;
+    return out;
+}
+
+fn classify(value: f32) -> vec4f {
+    return textureSampleLevel(transfer_function, linear_sampler, vec2f(value, 0.5), 0.0);
+}
+
+fn sample_volume(position: vec3f) -> f32 {
+    // Texture rows go down and slices come towards the viewer
+    let uvw = position / uniforms.extent + 0.5;
+    return textureSampleLevel(volume, linear_sampler, vec3f(uvw.x, 1.0 - uvw.y, uvw.z), 0.0).r;
+}
+
+// Breaks up the banding of evenly spaced samples
+fn jitter(pixel: vec2f) -> f32 {
+    return fract(sin(dot(pixel, vec2f(12.9898, 78.233))) * 43758.5453);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let far = uniforms.inverse_view_proj * vec4f(in.ndc, 1.0, 1.0);
+    let direction = normalize(far.xyz / far.w - uniforms.eye);
+
+    // Where the ray enters and leaves the clipped volume
+    let box_min = (uniforms.clip_min - 0.5) * uniforms.extent;
+    let box_max = (uniforms.clip_max - 0.5) * uniforms.extent;
+    let t0 = (box_min - uniforms.eye) / direction;
+    let t1 = (box_max - uniforms.eye) / direction;
+    let t_near = max(max(min(t0.x, t1.x), min(t0.y, t1.y)), max(min(t0.z, t1.z), 0.0));
+    let t_far = min(min(max(t0.x, t1.x), max(t0.y, t1.y)), max(t0.z, t1.z));
+    if t_near >= t_far {
+        discard;
+    }
+
+    let step = length(uniforms.extent) / f32(uniforms.step_count);
+    var t = t_near + step * jitter(in.position.xy);
+    var color = vec3f(0.0);
+    var alpha = 0.0;
+    var maximum = 0.0;
+    for (var i = 0u; i < uniforms.step_count && t < t_far; i++) {
+        let value = sample_volume(uniforms.eye + direction * t);
+        t += step;
+        if uniforms.mode == 1u {
+            maximum = max(maximum, value);
+            continue;
+        }
+
+        // Opacities are for 256 steps, corrected for the actual step size
+        let sample = classify(value);
+        let sample_alpha = 1.0 - pow(1.0 - sample.a, uniforms.density * 256.0 / f32(uniforms.step_count));
+        color += (1.0 - alpha) * sample_alpha * sample.rgb;
+        alpha += (1.0 - alpha) * sample_alpha;
+        if alpha > 0.99 {
+            break;
+        }
+    }
+
+    if uniforms.mode == 1u {
+        let sample = classify(maximum);
+        alpha = clamp(sample.a * uniforms.density, 0.0, 1.0);
+        color = sample.rgb * alpha;
+    }
+    // Premultiplied
+    return vec4f(color, alpha);
+}
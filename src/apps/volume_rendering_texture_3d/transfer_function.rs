@@ -0,0 +1,148 @@
+//! Mapping voxel values to color and opacity.
+
+/// How many entries the transfer function texture has, one per 8-bit voxel value.
+pub const RESOLUTION: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ControlPoint {
+    /// The voxel value, from 0 to 1.
+    pub value: f32,
+    pub color: [u8; 3],
+    pub opacity: f32,
+}
+
+/// Colors and opacities interpolated linearly between control points, and held constant
+/// before the first and after the last.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransferFunction {
+    pub points: Vec<ControlPoint>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    Grayscale,
+    Tissue,
+    Bone,
+}
+
+impl Preset {
+    pub const ALL: [Self; 3] = [Self::Grayscale, Self::Tissue, Self::Bone];
+
+    pub fn transfer_function(self) -> TransferFunction {
+        let point = |value, color, opacity| ControlPoint {
+            value,
+            color,
+            opacity,
+        };
+        let points = match self {
+            Self::Grayscale => vec![point(0.0, [0, 0, 0], 0.0), point(1.0, [255, 255, 255], 0.2)],
+            Self::Tissue => vec![
+                point(0.0, [0, 0, 0], 0.0),
+                point(0.12, [90, 20, 20], 0.0),
+                point(0.2, [200, 90, 70], 0.015),
+                point(0.3, [250, 190, 110], 0.1),
+                point(0.5, [255, 230, 200], 0.0),
+                point(0.9, [255, 255, 255], 0.0),
+                point(1.0, [255, 255, 255], 0.04),
+            ],
+            Self::Bone => vec![
+                point(0.0, [0, 0, 0], 0.0),
+                point(0.5, [200, 180, 150], 0.0),
+                point(0.9, [230, 220, 200], 0.1),
+                point(1.0, [255, 255, 255], 0.4),
+            ],
+        };
+        TransferFunction { points }
+    }
+}
+
+impl std::fmt::Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Grayscale => "Grayscale",
+            Self::Tissue => "Tissue",
+            Self::Bone => "Bone",
+        })
+    }
+}
+
+impl TransferFunction {
+    /// The color and opacity of `value`, with the opacity scaled to 0..=255 like the color.
+    pub fn sample(&self, value: f32) -> [u8; 4] {
+        let mut points = self.points.clone();
+        points.sort_by(|a, b| a.value.total_cmp(&b.value));
+        let rgba = |p: &ControlPoint| {
+            [
+                p.color[0] as f32,
+                p.color[1] as f32,
+                p.color[2] as f32,
+                p.opacity * 255.0,
+            ]
+        };
+
+        let color = match points.iter().position(|p| p.value > value) {
+            None => points.last().map_or([0.0; 4], rgba),
+            Some(0) => rgba(&points[0]),
+            Some(next) => {
+                let (a, b) = (&points[next - 1], &points[next]);
+                let t = (value - a.value) / (b.value - a.value);
+                let (a, b) = (rgba(a), rgba(b));
+                std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t)
+            }
+        };
+        color.map(|c| c.round().clamp(0.0, 255.0) as u8)
+    }
+
+    /// Texels of the `RESOLUTION`x1 transfer function texture.
+    pub fn texels(&self) -> Vec<[u8; 4]> {
+        (0..RESOLUTION)
+            .map(|i| self.sample(i as f32 / (RESOLUTION - 1) as f32))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_points() -> TransferFunction {
+        TransferFunction {
+            points: vec![
+                ControlPoint {
+                    value: 0.75,
+                    color: [200, 100, 0],
+                    opacity: 1.0,
+                },
+                ControlPoint {
+                    value: 0.25,
+                    color: [0, 100, 200],
+                    opacity: 0.0,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn interpolates_between_points_in_any_order() {
+        let function = two_points();
+        assert_eq!(function.sample(0.25), [0, 100, 200, 0]);
+        assert_eq!(function.sample(0.5), [100, 100, 100, 128]);
+        assert_eq!(function.sample(0.75), [200, 100, 0, 255]);
+    }
+
+    #[test]
+    fn holds_the_end_points() {
+        let function = two_points();
+        assert_eq!(function.sample(0.0), function.sample(0.25));
+        assert_eq!(function.sample(1.0), function.sample(0.75));
+        assert_eq!(TransferFunction { points: Vec::new() }.sample(0.5), [0; 4]);
+    }
+
+    #[test]
+    fn texels_cover_every_voxel_value() {
+        let texels = Preset::Grayscale.transfer_function().texels();
+        assert_eq!(texels.len(), RESOLUTION);
+        assert_eq!(texels[0], [0, 0, 0, 0]);
+        assert_eq!(texels[RESOLUTION - 1], [255, 255, 255, 51]);
+    }
+}
@@ -0,0 +1,217 @@
+//! 8-bit scalar volumes, loaded from raw files or generated.
+
+use glam::Vec3;
+
+/// Voxels stored x fastest, then y from the top row down, then z from back to front.
+pub struct Volume {
+    pub size: (u32, u32, u32),
+    pub data: Vec<u8>,
+}
+
+/// An ellipsoid of the 3D Shepp-Logan phantom: the density it adds, its center, its semi-axes
+/// and its rotation around the vertical axis in degrees.
+struct Ellipsoid {
+    density: f32,
+    center: [f32; 3],
+    axes: [f32; 3],
+    angle: f32,
+}
+
+/// The modified 3D Shepp-Logan head phantom, with higher contrast than the original, in
+/// coordinates where x goes right, y goes from the face to the back of the head and z goes up.
+const SHEPP_LOGAN: [Ellipsoid; 10] = [
+    Ellipsoid {
+        density: 1.0,
+        center: [0.0, 0.0, 0.0],
+        axes: [0.69, 0.92, 0.81],
+        angle: 0.0,
+    },
+    Ellipsoid {
+        density: -0.8,
+        center: [0.0, -0.0184, 0.0],
+        axes: [0.6624, 0.874, 0.78],
+        angle: 0.0,
+    },
+    Ellipsoid {
+        density: -0.2,
+        center: [0.22, 0.0, 0.0],
+        axes: [0.11, 0.31, 0.22],
+        angle: -18.0,
+    },
+    Ellipsoid {
+        density: -0.2,
+        center: [-0.22, 0.0, 0.0],
+        axes: [0.16, 0.41, 0.28],
+        angle: 18.0,
+    },
+    Ellipsoid {
+        density: 0.1,
+        center: [0.0, 0.35, -0.15],
+        axes: [0.21, 0.25, 0.41],
+        angle: 0.0,
+    },
+    Ellipsoid {
+        density: 0.1,
+        center: [0.0, 0.1, 0.25],
+        axes: [0.046, 0.046, 0.05],
+        angle: 0.0,
+    },
+    Ellipsoid {
+        density: 0.1,
+        center: [0.0, -0.1, 0.25],
+        axes: [0.046, 0.046, 0.05],
+        angle: 0.0,
+    },
+    Ellipsoid {
+        density: 0.1,
+        center: [-0.08, -0.605, 0.0],
+        axes: [0.046, 0.023, 0.05],
+        angle: 0.0,
+    },
+    Ellipsoid {
+        density: 0.1,
+        center: [0.0, -0.606, 0.0],
+        axes: [0.023, 0.023, 0.02],
+        angle: 0.0,
+    },
+    Ellipsoid {
+        density: 0.1,
+        center: [0.06, -0.605, 0.0],
+        axes: [0.023, 0.046, 0.02],
+        angle: 0.0,
+    },
+];
+
+impl Volume {
+    /// Load a raw 8-bit volume. Raw files have no header, so the size comes from the file name,
+    /// like `brain_180x216x180.raw`, or the volume has to be a cube.
+    pub fn load_raw(bytes: &[u8], name: &str) -> Result<Self, String> {
+        let size = match size_from_name(name) {
+            Some(size) => size,
+            None => {
+                let side = (bytes.len() as f64).cbrt().round() as u32;
+                if (side as usize).pow(3) != bytes.len() {
+                    return Err(format!(
+                        "{} bytes is not a cube, name the file like volume_WxHxD.raw",
+                        bytes.len()
+                    ));
+                }
+                (side, side, side)
+            }
+        };
+
+        let expected = size.0 as usize * size.1 as usize * size.2 as usize;
+        if expected == 0 || bytes.len() != expected {
+            return Err(format!(
+                "expected {expected} bytes for {}x{}x{} voxels, found {}",
+                size.0,
+                size.1,
+                size.2,
+                bytes.len()
+            ));
+        }
+        Ok(Self {
+            size,
+            data: bytes.to_vec(),
+        })
+    }
+
+    /// The Shepp-Logan phantom sampled on a `side`³ grid, standing upright.
+    pub fn shepp_logan(side: u32) -> Self {
+        let mut data = Vec::with_capacity(side.pow(3) as usize);
+        let coordinate = |i: u32| (i as f32 + 0.5) / side as f32 * 2.0 - 1.0;
+        for z in 0..side {
+            for y in 0..side {
+                for x in 0..side {
+                    // Facing the viewer, with the top of the head up
+                    let point = Vec3::new(coordinate(x), coordinate(z), -coordinate(y));
+                    let density: f32 = SHEPP_LOGAN
+                        .iter()
+                        .filter(|e| e.contains(point))
+                        .map(|e| e.density)
+                        .sum();
+                    data.push((density.clamp(0.0, 1.0) * 255.0).round() as u8);
+                }
+            }
+        }
+        Self {
+            size: (side, side, side),
+            data,
+        }
+    }
+
+    /// The extent of the volume with its longest side 1, assuming cubic voxels.
+    pub fn extent(&self) -> Vec3 {
+        let size = Vec3::new(self.size.0 as f32, self.size.1 as f32, self.size.2 as f32);
+        size / size.max_element()
+    }
+}
+
+impl Ellipsoid {
+    fn contains(&self, point: Vec3) -> bool {
+        let (sin, cos) = self.angle.to_radians().sin_cos();
+        let p = point - Vec3::from(self.center);
+        let rotated = Vec3::new(p.x * cos + p.y * sin, p.y * cos - p.x * sin, p.z);
+        (rotated / Vec3::from(self.axes)).length_squared() <= 1.0
+    }
+}
+
+/// The last `WxHxD` group of numbers in a file name.
+fn size_from_name(name: &str) -> Option<(u32, u32, u32)> {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .rev()
+        .find_map(|part| {
+            let mut numbers = part.split('x').map(|n| n.parse::<u32>().ok());
+            match (
+                numbers.next(),
+                numbers.next(),
+                numbers.next(),
+                numbers.next(),
+            ) {
+                (Some(Some(w)), Some(Some(h)), Some(Some(d)), None) => Some((w, h, d)),
+                _ => None,
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_come_from_the_file_name() {
+        assert_eq!(
+            size_from_name("brain_180x216x180.raw"),
+            Some((180, 216, 180))
+        );
+        assert_eq!(size_from_name("t1_4x2x3_uint8.raw"), Some((4, 2, 3)));
+        assert_eq!(size_from_name("volume.raw"), None);
+        assert_eq!(size_from_name("4x2.raw"), None);
+
+        let volume = Volume::load_raw(&[0; 24], "small_4x2x3.raw").unwrap();
+        assert_eq!(volume.size, (4, 2, 3));
+        assert!(Volume::load_raw(&[0; 23], "small_4x2x3.raw").is_err());
+    }
+
+    #[test]
+    fn unnamed_volumes_must_be_cubes() {
+        assert_eq!(
+            Volume::load_raw(&[0; 27], "volume.raw").unwrap().size,
+            (3, 3, 3)
+        );
+        assert!(Volume::load_raw(&[0; 28], "volume.raw").is_err());
+    }
+
+    #[test]
+    fn phantom_has_skull_brain_and_air() {
+        let volume = Volume::shepp_logan(32);
+        assert_eq!(volume.data.len(), 32 * 32 * 32);
+        let at = |x: usize, y: usize, z: usize| volume.data[(z * 32 + y) * 32 + x];
+        // Outside the head, and in the brain above the ventricles
+        assert_eq!(at(0, 0, 0), 0);
+        assert_eq!(at(16, 10, 16), 51);
+        // The skull is only a voxel or two thick
+        assert!(volume.data.contains(&255));
+        assert_eq!(volume.extent(), Vec3::ONE);
+    }
+}
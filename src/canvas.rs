@@ -0,0 +1,90 @@
+//! Per-canvas state for paint callbacks.
+//!
+//! egui_wgpu keeps callback resources in a type map, with a single slot per Rust type, so a
+//! sample that paints several canvases cannot give each its own buffers or render target by
+//! inserting them directly. Instead it inserts a [`PerCanvas`] once and keeps one value per
+//! canvas inside it, keyed by the id of the canvas widget.
+
+use eframe::egui;
+use std::collections::HashMap;
+
+struct Entry<T> {
+    value: T,
+    /// The egui frame the canvas was last prepared in.
+    frame_nr: u64,
+}
+
+pub struct PerCanvas<T> {
+    entries: HashMap<egui::Id, Entry<T>>,
+    /// The frame stale canvases were last dropped in, so that it happens once per frame.
+    swept_frame_nr: Option<u64>,
+}
+
+impl<T> Default for PerCanvas<T> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            swept_frame_nr: None,
+        }
+    }
+}
+
+impl<T> PerCanvas<T> {
+    /// The state of canvas `id`, created with `create` the first time the canvas is drawn.
+    /// Call from `prepare` with the number of the frame that painted the callback, which is
+    /// [`egui::Context::frame_nr`].
+    ///
+    /// Canvases that were neither prepared in this frame nor in the one before are dropped,
+    /// so the state of canvases that are no longer shown does not pile up.
+    pub fn prepare(&mut self, id: egui::Id, frame_nr: u64, create: impl FnOnce() -> T) -> &mut T {
+        if self.swept_frame_nr != Some(frame_nr) {
+            self.swept_frame_nr = Some(frame_nr);
+            self.entries
+                .retain(|_, entry| entry.frame_nr + 1 >= frame_nr);
+        }
+        let entry = self.entries.entry(id).or_insert_with(|| Entry {
+            value: create(),
+            frame_nr,
+        });
+        entry.frame_nr = frame_nr;
+        &mut entry.value
+    }
+
+    /// The state of canvas `id`, for `paint`, once it was prepared.
+    pub fn get(&self, id: egui::Id) -> Option<&T> {
+        self.entries.get(&id).map(|entry| &entry.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creates_state_once_per_canvas() {
+        let mut canvases = PerCanvas::default();
+        let (a, b) = (egui::Id::new("a"), egui::Id::new("b"));
+
+        *canvases.prepare(a, 0, || 1) += 10;
+        *canvases.prepare(b, 0, || 2) += 20;
+        assert_eq!(canvases.prepare(a, 1, || 0), &mut 11);
+        assert_eq!(canvases.get(b), Some(&22));
+        assert_eq!(canvases.get(egui::Id::new("c")), None);
+    }
+
+    #[test]
+    fn drops_canvases_that_are_no_longer_drawn() {
+        let mut canvases = PerCanvas::default();
+        let (a, b) = (egui::Id::new("a"), egui::Id::new("b"));
+        canvases.prepare(a, 0, || ());
+        canvases.prepare(b, 0, || ());
+
+        // Canvas b could still be prepared later in frame 1
+        canvases.prepare(a, 1, || ());
+        assert_eq!(canvases.entries.len(), 2);
+
+        canvases.prepare(a, 2, || ());
+        assert_eq!(canvases.entries.len(), 1);
+        assert!(canvases.get(b).is_none());
+    }
+}
@@ -0,0 +1,74 @@
+// Appended to the shaders of samples that support debug views, see `src/debug_view/mod.rs`.
+// Their vertex shader outputs a `VertexOutput` with these fields, which every view reads from:
+//   barycentric: vec3f, set to `debug_barycentric(vertex_index)`
+//   normal: vec3f, in world space
+//   uv: vec2f
+//   color: vec4f
+
+struct DebugView {
+    // Line color of the wireframe
+    color: vec4f,
+    // Line width in pixels, only used by the barycentric fallback
+    width: f32,
+    // Distance at which the linear depth view turns black
+    depth_range: f32,
+}
+
+// The group follows the bind groups of the sample, its index is filled in by `debug_view::shader`
+@group(DEBUG_VIEW_GROUP) @binding(0) var<uniform> debug_view: DebugView;
+
+// The barycentric coordinates of a vertex of a non-indexed triangle list: every vertex gets
+// another corner of the triangle, so the coordinates interpolate to zero along its edges.
+fn debug_barycentric(vertex_index: u32) -> vec3f {
+    let corner = vertex_index % 3u;
+    return vec3f(f32(corner == 0u), f32(corner == 1u), f32(corner == 2u));
+}
+
+// Only edges are rasterized with `PolygonMode::Line`
+@fragment
+fn fs_debug_lines(in: VertexOutput) -> @location(0) vec4f {
+    return debug_view.color;
+}
+
+@fragment
+fn fs_debug_wireframe(in: VertexOutput) -> @location(0) vec4f {
+    // Distance to the closest edge of the triangle, in pixels
+    let distance = in.barycentric / max(fwidth(in.barycentric), vec3f(1e-6));
+    let edge = min(min(distance.x, distance.y), distance.z);
+    let half_width = debug_view.width * 0.5;
+    let coverage = 1.0 - smoothstep(half_width - 0.5, half_width + 0.5, edge);
+    // Discarding the inside of the triangles keeps the edges behind them visible
+    if coverage <= 0.0 {
+        discard;
+    }
+    return vec4f(debug_view.color.rgb, debug_view.color.a * coverage);
+}
+
+@fragment
+fn fs_debug_normals(in: VertexOutput) -> @location(0) vec4f {
+    return vec4f(normalize(in.normal) * 0.5 + 0.5, 1.0);
+}
+
+@fragment
+fn fs_debug_uv(in: VertexOutput) -> @location(0) vec4f {
+    return vec4f(fract(in.uv), 0.0, 1.0);
+}
+
+@fragment
+fn fs_debug_color(in: VertexOutput) -> @location(0) vec4f {
+    return in.color;
+}
+
+@fragment
+fn fs_debug_depth(in: VertexOutput) -> @location(0) vec4f {
+    // The w of the fragment position is 1 / w of the clip position, which is the distance
+    // along the view direction for a perspective projection
+    let depth = 1.0 / in.position.w;
+    return vec4f(vec3f(1.0 - saturate(depth / debug_view.depth_range)), 1.0);
+}
+
+// Blended additively without depth test, so every layer drawn makes the pixel brighter
+@fragment
+fn fs_debug_overdraw(in: VertexOutput) -> @location(0) vec4f {
+    return vec4f(0.25, 0.1, 0.04, 1.0);
+}
@@ -0,0 +1,537 @@
+//! Debug views of the mesh samples, selected from the side panel of the main app: wireframe,
+//! normals, UVs, vertex colors, linear depth and overdraw.
+//!
+//! The main app inserts a [`DebugView`] into the callback resources. A sample appends the WGSL
+//! of [`shader`] to its own, gives the `VertexOutput` of its vertex shader the fields the views
+//! read, listed in `debug_view.wgsl`, and derives [`DebugViewPipelines`] from the descriptor of
+//! its pipeline. When drawing, [`DebugViewPipelines::set_pipeline`] picks either the pipeline of
+//! the sample or the one swapping its fragment stage for the selected view.
+//!
+//! With `POLYGON_MODE_LINE` the wireframe rasterizes only the edges of the triangles, which are
+//! always one pixel wide. Otherwise, or when asked to, it draws every triangle and keeps the
+//! pixels close to its edges, found with the barycentric coordinates.
+
+use eframe::{egui, egui_wgpu};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::stats::{CountedPass, FrameStats};
+
+/// The sample shader with the WGSL it needs for the debug views appended. `group` is the index
+/// of the debug view bind group, after those of the sample.
+pub fn shader(source: &str, group: u32) -> String {
+    let debug_view =
+        include_str!("debug_view.wgsl").replace("DEBUG_VIEW_GROUP", &group.to_string());
+    format!("{source}\n{debug_view}")
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum View {
+    /// The fragment shader of the sample.
+    Shaded,
+    Wireframe,
+    Normals,
+    Uv,
+    Color,
+    Depth,
+    Overdraw,
+}
+
+impl View {
+    const ALL: [Self; 7] = [
+        Self::Shaded,
+        Self::Wireframe,
+        Self::Normals,
+        Self::Uv,
+        Self::Color,
+        Self::Depth,
+        Self::Overdraw,
+    ];
+
+    /// The fragment entry point of `debug_view.wgsl` drawing this view.
+    fn entry_point(self) -> Option<&'static str> {
+        match self {
+            Self::Shaded => None,
+            Self::Wireframe => Some("fs_debug_wireframe"),
+            Self::Normals => Some("fs_debug_normals"),
+            Self::Uv => Some("fs_debug_uv"),
+            Self::Color => Some("fs_debug_color"),
+            Self::Depth => Some("fs_debug_depth"),
+            Self::Overdraw => Some("fs_debug_overdraw"),
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Shaded => "The sample as it is.",
+            Self::Wireframe => "The edges of every triangle.",
+            Self::Normals => "World space normals mapped from -1..1 to 0..1.",
+            Self::Uv => "Texture coordinates as red and green.",
+            Self::Color => "The colors of the vertices.",
+            Self::Depth => "Distance from the camera, white close to it and black at the range.",
+            Self::Overdraw => {
+                "Every triangle drawn on a pixel makes it brighter, without depth test."
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for View {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Shaded => write!(f, "Shaded"),
+            Self::Wireframe => write!(f, "Wireframe"),
+            Self::Normals => write!(f, "Normals"),
+            Self::Uv => write!(f, "UVs"),
+            Self::Color => write!(f, "Vertex colors"),
+            Self::Depth => write!(f, "Linear depth"),
+            Self::Overdraw => write!(f, "Overdraw"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Settings {
+    pub view: View,
+    /// Use `PolygonMode::Line` for the wireframe when supported rather than the barycentric
+    /// fallback.
+    pub polygon_mode_line: bool,
+    /// Wireframe line width in pixels, only used by the barycentric fallback.
+    pub width: f32,
+    pub color: [u8; 3],
+    /// Distance at which the linear depth view turns black.
+    pub depth_range: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            view: View::Shaded,
+            polygon_mode_line: true,
+            width: 1.5,
+            color: [255, 255, 255],
+            depth_range: 20.0,
+        }
+    }
+}
+
+/// A handle to the debug view settings, cheap to clone.
+#[derive(Clone, Default)]
+pub struct DebugView {
+    settings: Arc<egui::mutex::Mutex<Settings>>,
+    polygon_mode_line: bool,
+}
+
+impl DebugView {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            settings: Default::default(),
+            polygon_mode_line: device
+                .features()
+                .contains(wgpu::Features::POLYGON_MODE_LINE),
+        }
+    }
+
+    /// The settings of the handle the main app inserted, or ones showing the samples as they are.
+    pub fn settings(callback_resources: &egui_wgpu::CallbackResources) -> Settings {
+        callback_resources
+            .get::<Self>()
+            .map(|debug_view| *debug_view.settings.lock())
+            .unwrap_or_default()
+    }
+
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        let mut settings = self.settings.lock();
+        ui.horizontal(|ui| {
+            ui.label("Debug view");
+            egui::ComboBox::from_id_source("debug_view")
+                .selected_text(settings.view.to_string())
+                .show_ui(ui, |ui| {
+                    for view in View::ALL {
+                        ui.selectable_value(&mut settings.view, view, view.to_string())
+                            .on_hover_text(view.description());
+                    }
+                });
+        })
+        .response
+        .on_hover_text("Applies to rotatingCube, twoCubes, texturedCube and instancedCube.");
+
+        ui.indent("debug_view_settings", |ui| match settings.view {
+            View::Wireframe => {
+                ui.add_enabled(
+                    self.polygon_mode_line,
+                    egui::Checkbox::new(&mut settings.polygon_mode_line, "PolygonMode::Line"),
+                )
+                .on_disabled_hover_text("POLYGON_MODE_LINE is not supported by this adapter.");
+                let lines = self.polygon_mode_line && settings.polygon_mode_line;
+                ui.add_enabled(
+                    !lines,
+                    egui::Slider::new(&mut settings.width, 0.5..=8.0).text("line width"),
+                )
+                .on_disabled_hover_text("Lines drawn with PolygonMode::Line are one pixel wide.");
+                ui.horizontal(|ui| {
+                    ui.color_edit_button_srgb(&mut settings.color);
+                    ui.label("line color");
+                });
+            }
+            View::Depth => {
+                ui.add(
+                    egui::Slider::new(&mut settings.depth_range, 1.0..=100.0)
+                        .logarithmic(true)
+                        .text("range"),
+                );
+            }
+            _ => {}
+        });
+    }
+}
+
+/// Laid out to match `DebugView` in `debug_view.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    color: [f32; 4],
+    width: f32,
+    depth_range: f32,
+    _padding: [f32; 2],
+}
+
+impl Uniforms {
+    fn new(settings: &Settings) -> Self {
+        let [r, g, b] = settings.color.map(|channel| channel as f32 / 255.0);
+        Self {
+            color: [r, g, b, 1.0],
+            width: settings.width,
+            depth_range: settings.depth_range,
+            _padding: [0.0; 2],
+        }
+    }
+}
+
+/// The debug view variants of a sample pipeline, drawing its triangles with the same vertex
+/// shader, vertex buffers and depth state.
+pub struct DebugViewPipelines {
+    /// Every view but `Shaded`.
+    views: HashMap<View, wgpu::RenderPipeline>,
+    /// The wireframe with `PolygonMode::Line`, only created when the device supports it.
+    lines: Option<wgpu::RenderPipeline>,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    /// Index of the bind group, after those of the sample.
+    group: u32,
+}
+
+impl DebugViewPipelines {
+    /// `descriptor` describes the pipeline of the sample, whose layout has `bind_group_layouts`.
+    /// Its shader module must have been created from [`shader`], see the module docs.
+    pub fn new(
+        device: &wgpu::Device,
+        descriptor: &wgpu::RenderPipelineDescriptor,
+        bind_group_layouts: &[&wgpu::BindGroupLayout],
+    ) -> Self {
+        let label = descriptor.label.unwrap_or("Sample");
+        let group = bind_group_layouts.len() as u32;
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&format!("{label} Debug View Uniform Buffer")),
+            size: std::mem::size_of::<Uniforms>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(&format!("{label} Debug View Bind Group Layout")),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{label} Debug View Bind Group")),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(&format!("{label} Debug View Pipeline Layout")),
+            bind_group_layouts: &[bind_group_layouts, &[&bind_group_layout]].concat(),
+            push_constant_ranges: &[],
+        });
+
+        let fragment = descriptor
+            .fragment
+            .as_ref()
+            .expect("debug view pipelines need a fragment shader");
+        let create_pipeline = |view: View, entry_point, polygon_mode| {
+            // The antialiased edges of the wireframe fallback blend in, and overdraw adds up
+            // every layer, so it also ignores depth
+            let (blend, depth_stencil) = match view {
+                View::Wireframe => (
+                    Some(wgpu::BlendState::ALPHA_BLENDING),
+                    descriptor.depth_stencil.clone(),
+                ),
+                View::Overdraw => {
+                    let add = wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::One,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    };
+                    let depth_stencil = descriptor.depth_stencil.clone().map(|depth_stencil| {
+                        wgpu::DepthStencilState {
+                            depth_write_enabled: false,
+                            depth_compare: wgpu::CompareFunction::Always,
+                            ..depth_stencil
+                        }
+                    });
+                    (
+                        Some(wgpu::BlendState {
+                            color: add,
+                            alpha: add,
+                        }),
+                        depth_stencil,
+                    )
+                }
+                _ => (None, descriptor.depth_stencil.clone()),
+            };
+            let targets: Vec<_> = fragment
+                .targets
+                .iter()
+                .map(|target| {
+                    target
+                        .clone()
+                        .map(|target| wgpu::ColorTargetState { blend, ..target })
+                })
+                .collect();
+
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(&format!("{label} {view}")),
+                layout: Some(&pipeline_layout),
+                vertex: descriptor.vertex.clone(),
+                fragment: Some(wgpu::FragmentState {
+                    module: fragment.module,
+                    entry_point,
+                    targets: &targets,
+                }),
+                primitive: wgpu::PrimitiveState {
+                    polygon_mode,
+                    ..descriptor.primitive
+                },
+                depth_stencil,
+                multisample: descriptor.multisample,
+                multiview: descriptor.multiview,
+            })
+        };
+
+        let views = View::ALL
+            .into_iter()
+            .filter_map(|view| {
+                let entry_point = view.entry_point()?;
+                Some((
+                    view,
+                    create_pipeline(view, entry_point, wgpu::PolygonMode::Fill),
+                ))
+            })
+            .collect();
+        let lines = device
+            .features()
+            .contains(wgpu::Features::POLYGON_MODE_LINE)
+            .then(|| create_pipeline(View::Wireframe, "fs_debug_lines", wgpu::PolygonMode::Line));
+
+        Self {
+            views,
+            lines,
+            uniform_buffer,
+            bind_group,
+            group,
+        }
+    }
+
+    /// Write the wireframe color and width and the depth range, in `prepare`.
+    pub fn prepare(&self, queue: &wgpu::Queue, stats: &FrameStats, settings: &Settings) {
+        if matches!(settings.view, View::Wireframe | View::Depth) {
+            stats.write_buffer(
+                queue,
+                &self.uniform_buffer,
+                0,
+                bytemuck::bytes_of(&Uniforms::new(settings)),
+            );
+        }
+    }
+
+    /// Set `pipeline`, the one of the sample, or the one drawing the selected view.
+    pub fn set_pipeline<'a>(
+        &'a self,
+        render_pass: &mut CountedPass<'_, 'a>,
+        pipeline: &'a wgpu::RenderPipeline,
+        settings: &Settings,
+    ) {
+        let debug_pipeline = match (&self.lines, settings.view) {
+            (Some(lines), View::Wireframe) if settings.polygon_mode_line => Some(lines),
+            (_, view) => self.views.get(&view),
+        };
+        match debug_pipeline {
+            Some(debug_pipeline) => {
+                render_pass.set_pipeline(debug_pipeline);
+                render_pass.set_bind_group(self.group, &self.bind_group, &[]);
+            }
+            None => render_pass.set_pipeline(pipeline),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: u32 = 32;
+
+    /// Draw `instances` times a triangle covering the lower left half of a black target, at a
+    /// distance of 2 from the camera, with `settings`, and read back the RGB of its pixels.
+    fn draw_triangle(settings: &Settings, instances: u32) -> Option<Vec<[u8; 3]>> {
+        let (device, queue) = crate::headless::request_device()?;
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        let target = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: SIZE,
+                height: SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let source = "
+            struct VertexOutput {
+                @builtin(position) position: vec4f,
+                @location(0) barycentric: vec3f,
+                @location(1) normal: vec3f,
+                @location(2) uv: vec2f,
+                @location(3) color: vec4f,
+            }
+            @vertex fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+                var corners = array(vec2f(-1.0, -1.0), vec2f(1.0, -1.0), vec2f(-1.0, 1.0));
+                return VertexOutput(
+                    vec4f(corners[vertex_index] * 2.0, 0.0, 2.0),
+                    debug_barycentric(vertex_index),
+                    vec3f(0.0, 0.0, 1.0),
+                    corners[vertex_index],
+                    vec4f(1.0),
+                );
+            }
+            @fragment fn fs_main(in: VertexOutput) -> @location(0) vec4f { return in.color; }";
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(super::shader(source, 0).into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&Default::default());
+        let descriptor = wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        };
+        let pipeline = device.create_render_pipeline(&descriptor);
+        let debug_view = DebugViewPipelines::new(&device, &descriptor, &[]);
+
+        let stats = FrameStats::default();
+        debug_view.prepare(&queue, &stats, settings);
+        let mut encoder = device.create_command_encoder(&Default::default());
+        {
+            let mut render_pass =
+                stats.pass(encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &view,
+                        resolve_target: None,
+                        ops: wgpu::Operations::default(),
+                    })],
+                    ..Default::default()
+                }));
+            debug_view.set_pipeline(&mut render_pass, &pipeline, settings);
+            render_pass.draw(0..3, 0..instances);
+        }
+        queue.submit(Some(encoder.finish()));
+
+        let pixels = crate::headless::read_texture(&device, &queue, &target);
+        Some(
+            pixels
+                .chunks_exact(4)
+                .map(|pixel| [pixel[0], pixel[1], pixel[2]])
+                .collect(),
+        )
+    }
+
+    fn pixel(pixels: &[[u8; 3]], x: u32, y: u32) -> [u8; 3] {
+        pixels[(y * SIZE + x) as usize]
+    }
+
+    #[test]
+    fn barycentric_wireframe_keeps_only_the_edges() {
+        let settings = Settings {
+            view: View::Wireframe,
+            polygon_mode_line: false,
+            width: 2.0,
+            color: [255, 0, 0],
+            ..Default::default()
+        };
+        let Some(pixels) = draw_triangle(&settings, 1) else {
+            return;
+        };
+        // On the left edge, inside the triangle, and outside of it
+        assert_eq!(pixel(&pixels, 0, SIZE / 2), [255, 0, 0]);
+        assert_eq!(pixel(&pixels, SIZE / 4, SIZE * 3 / 4), [0, 0, 0]);
+        assert_eq!(pixel(&pixels, SIZE - 2, 1), [0, 0, 0]);
+    }
+
+    #[test]
+    fn depth_and_overdraw_views() {
+        let settings = Settings {
+            view: View::Depth,
+            depth_range: 4.0,
+            ..Default::default()
+        };
+        let Some(pixels) = draw_triangle(&settings, 1) else {
+            return;
+        };
+        // Half of the range away
+        let [gray, ..] = pixel(&pixels, SIZE / 4, SIZE * 3 / 4);
+        assert!(gray.abs_diff(128) <= 1, "{gray}");
+
+        let settings = Settings {
+            view: View::Overdraw,
+            ..Default::default()
+        };
+        let Some(pixels) = draw_triangle(&settings, 2) else {
+            return;
+        };
+        // Two layers of 0.25, 0.1 and 0.04
+        let [r, g, b] = pixel(&pixels, SIZE / 4, SIZE * 3 / 4);
+        assert!(r.abs_diff(128) <= 1 && g.abs_diff(51) <= 1 && b.abs_diff(20) <= 1);
+        assert_eq!(pixel(&pixels, SIZE - 2, 1), [0, 0, 0]);
+    }
+}
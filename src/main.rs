@@ -1,18 +1,79 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
 mod apps;
+mod canvas;
+mod debug_view;
+mod headless;
 mod main_app;
 mod meshes;
+mod offscreen;
+mod profiler;
+mod random;
+mod readback;
+mod stats;
+mod text;
 
 use eframe::egui;
 use main_app::MainApp;
 
+/// Features some samples use when the adapter supports them; samples check
+/// `device.features()` and degrade gracefully otherwise.
+#[cfg(feature = "wgpu")]
+const OPTIONAL_FEATURES: wgpu::Features = wgpu::Features::TIMESTAMP_QUERY
+    .union(wgpu::Features::PIPELINE_STATISTICS_QUERY)
+    .union(wgpu::Features::POLYGON_MODE_LINE);
+
+/// Same as the egui default, except that optional features are enabled and that the GL backend
+/// gets the limits of GLES 3.1 rather than WebGL2, so that compute shaders are available.
+#[cfg(feature = "wgpu")]
+fn wgpu_options() -> eframe::egui_wgpu::WgpuConfiguration {
+    eframe::egui_wgpu::WgpuConfiguration {
+        device_descriptor: std::sync::Arc::new(|adapter| {
+            let base_limits = if adapter.get_info().backend == wgpu::Backend::Gl {
+                wgpu::Limits::downlevel_defaults()
+            } else {
+                wgpu::Limits::default()
+            };
+
+            wgpu::DeviceDescriptor {
+                label: Some("wgpu samples device"),
+                required_features: adapter.features() & OPTIONAL_FEATURES,
+                required_limits: wgpu::Limits {
+                    // When using a depth buffer, we have to be able to create a texture
+                    // large enough for the entire surface, and we want to support 4k+ displays.
+                    max_texture_dimension_2d: 8192,
+                    ..base_limits
+                },
+            }
+        }),
+        ..Default::default()
+    }
+}
+
 fn main() -> Result<(), eframe::Error> {
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
+
+    // `cargo run --release -- animometer [options]` runs the benchmark without a window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "animometer") {
+        let result = apps::animometer::benchmark::BenchmarkOptions::parse(&args[1..])
+            .and_then(apps::animometer::benchmark::run);
+        match result {
+            Ok(result) => println!("{result}"),
+            Err(err) => {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_maximized(true),
         #[cfg(feature = "wgpu")]
         renderer: eframe::Renderer::Wgpu,
+        #[cfg(feature = "wgpu")]
+        wgpu_options: wgpu_options(),
         ..Default::default()
     };
     eframe::run_native(
@@ -1,7 +1,15 @@
 use eframe::egui;
 
+use crate::debug_view::DebugView;
+use crate::profiler::GpuProfiler;
+use crate::stats::FrameStats;
+
 use crate::apps::{
-    cubemap, hello_triangle, instanced_cube, rotating_cube, textured_cube, two_cubes,
+    a_buffer, animometer, bitonic_sort, blending, compute_boids, cornell, cubemap, custom3d,
+    deferred_rendering, game_of_life, hello_triangle, image_blur, instanced_cube,
+    multiple_canvases, normal_map, occlusion_query, particles, pipeline_statistics, points,
+    rotating_cube, shadow_mapping, skinned_mesh, stencil, text_rendering_msdf, textured_cube,
+    two_cubes, volume_rendering_texture_3d,
 };
 
 /// The type of app to run.
@@ -12,6 +20,7 @@ pub enum AppType {
     GPGPUDemos,
     GraphicsTechniques,
     Benchmarks,
+    EguiIntegration,
 }
 
 impl std::fmt::Display for AppType {
@@ -22,16 +31,16 @@ impl std::fmt::Display for AppType {
             Self::GPGPUDemos => write!(f, "GPGPU Demos"),
             Self::GraphicsTechniques => write!(f, "Graphics Techniques"),
             Self::Benchmarks => write!(f, "Benchmarks"),
+            Self::EguiIntegration => write!(f, "egui Integration"),
         }
     }
 }
 
+/// Creates an app from the eframe frame, or `None` if the app is not implemented yet.
+type AppConstructor = fn(&eframe::Frame) -> Option<Box<dyn eframe::App>>;
+
 /// List of apps to run.
-const APPS: [(
-    &str,
-    AppType,
-    fn(&eframe::Frame) -> Option<Box<dyn eframe::App>>,
-); 27] = [
+const APPS: [(&str, AppType, AppConstructor); 33] = [
     // Basic Graphics
     (
         "helloTriangle",
@@ -39,7 +48,7 @@ const APPS: [(
         |frame: &eframe::Frame| {
             Some(Box::new(
                 hello_triangle::HelloTriangle::new_with_render_state(
-                    &frame.wgpu_render_state().unwrap(),
+                    frame.wgpu_render_state().unwrap(),
                 )
                 .unwrap(),
             ))
@@ -56,7 +65,7 @@ const APPS: [(
         |frame: &eframe::Frame| {
             Some(Box::new(
                 rotating_cube::RotatingCube::new_with_render_state(
-                    &frame.wgpu_render_state().unwrap(),
+                    frame.wgpu_render_state().unwrap(),
                 )
                 .unwrap(),
             ))
@@ -67,7 +76,7 @@ const APPS: [(
         AppType::BasicGraphics,
         |frame: &eframe::Frame| {
             Some(Box::new(
-                two_cubes::TwoCubes::new_with_render_state(&frame.wgpu_render_state().unwrap())
+                two_cubes::TwoCubes::new_with_render_state(frame.wgpu_render_state().unwrap())
                     .unwrap(),
             ))
         },
@@ -78,7 +87,7 @@ const APPS: [(
         |frame: &eframe::Frame| {
             Some(Box::new(
                 textured_cube::TexturedCube::new_with_render_state(
-                    &frame.wgpu_render_state().unwrap(),
+                    frame.wgpu_render_state().unwrap(),
                 )
                 .unwrap(),
             ))
@@ -90,7 +99,7 @@ const APPS: [(
         |frame: &eframe::Frame| {
             Some(Box::new(
                 instanced_cube::InstancedCube::new_with_render_state(
-                    &frame.wgpu_render_state().unwrap(),
+                    frame.wgpu_render_state().unwrap(),
                 )
                 .unwrap(),
             ))
@@ -106,7 +115,7 @@ const APPS: [(
         AppType::BasicGraphics,
         |frame: &eframe::Frame| {
             Some(Box::new(
-                cubemap::Cubemap::new_with_render_state(&frame.wgpu_render_state().unwrap())
+                cubemap::Cubemap::new_with_render_state(frame.wgpu_render_state().unwrap())
                     .unwrap(),
             ))
         },
@@ -127,21 +136,84 @@ const APPS: [(
         AppType::WebGPUFeatures,
         |_frame: &eframe::Frame| None,
     ),
+    (
+        "occlusionQuery",
+        AppType::WebGPUFeatures,
+        |frame: &eframe::Frame| {
+            Some(Box::new(
+                occlusion_query::OcclusionQuery::new_with_render_state(
+                    frame.wgpu_render_state().unwrap(),
+                )
+                .unwrap(),
+            ))
+        },
+    ),
+    (
+        "pipelineStatistics",
+        AppType::WebGPUFeatures,
+        |frame: &eframe::Frame| {
+            Some(Box::new(
+                pipeline_statistics::PipelineStatistics::new_with_render_state(
+                    frame.wgpu_render_state().unwrap(),
+                )
+                .unwrap(),
+            ))
+        },
+    ),
+    (
+        "blending",
+        AppType::WebGPUFeatures,
+        |frame: &eframe::Frame| {
+            Some(Box::new(
+                blending::Blending::new_with_render_state(frame.wgpu_render_state().unwrap())
+                    .unwrap(),
+            ))
+        },
+    ),
+    (
+        "stencil",
+        AppType::WebGPUFeatures,
+        |frame: &eframe::Frame| {
+            Some(Box::new(
+                stencil::Stencil::new_with_render_state(frame.wgpu_render_state().unwrap())
+                    .unwrap(),
+            ))
+        },
+    ),
     // GPGPU Demos
     (
-        "computeBoids(WIP)",
+        "computeBoids",
         AppType::GPGPUDemos,
-        |_frame: &eframe::Frame| None,
+        |frame: &eframe::Frame| {
+            Some(Box::new(
+                compute_boids::ComputeBoids::new_with_render_state(
+                    frame.wgpu_render_state().unwrap(),
+                )
+                .unwrap(),
+            ))
+        },
     ),
     (
-        "gameOfLife(WIP)",
+        "gameOfLife",
         AppType::GPGPUDemos,
-        |_frame: &eframe::Frame| None,
+        |frame: &eframe::Frame| {
+            Some(Box::new(
+                game_of_life::GameOfLife::new_with_render_state(frame.wgpu_render_state().unwrap())
+                    .unwrap(),
+            ))
+        },
     ),
     (
-        "bitonicSort(WIP)",
+        "bitonicSort",
         AppType::GPGPUDemos,
-        |_frame: &eframe::Frame| None,
+        |frame: &eframe::Frame| {
+            Some(Box::new(
+                bitonic_sort::BitonicSort::new_with_render_state(
+                    frame.wgpu_render_state().unwrap(),
+                )
+                .unwrap(),
+            ))
+        },
     ),
     // Graphics Techniques
     (
@@ -150,83 +222,204 @@ const APPS: [(
         |_frame: &eframe::Frame| None,
     ),
     (
-        "normalMap(WIP)",
+        "normalMap",
         AppType::GraphicsTechniques,
-        |_frame: &eframe::Frame| None,
+        |frame: &eframe::Frame| {
+            Some(Box::new(
+                normal_map::NormalMap::new_with_render_state(frame.wgpu_render_state().unwrap())
+                    .unwrap(),
+            ))
+        },
     ),
     (
-        "shadowMapping(WIP)",
+        "shadowMapping",
         AppType::GraphicsTechniques,
-        |_frame: &eframe::Frame| None,
+        |frame: &eframe::Frame| {
+            Some(Box::new(
+                shadow_mapping::ShadowMapping::new_with_render_state(
+                    frame.wgpu_render_state().unwrap(),
+                )
+                .unwrap(),
+            ))
+        },
     ),
     (
-        "deferredRendering(WIP)",
+        "deferredRendering",
         AppType::GraphicsTechniques,
-        |_frame: &eframe::Frame| None,
+        |frame: &eframe::Frame| {
+            Some(Box::new(
+                deferred_rendering::DeferredRendering::new_with_render_state(
+                    frame.wgpu_render_state().unwrap(),
+                )
+                .unwrap(),
+            ))
+        },
     ),
     (
-        "particles(WIP)",
+        "particles",
         AppType::GraphicsTechniques,
-        |_frame: &eframe::Frame| None,
+        |frame: &eframe::Frame| {
+            Some(Box::new(
+                particles::Particles::new_with_render_state(frame.wgpu_render_state().unwrap())
+                    .unwrap(),
+            ))
+        },
     ),
     (
-        "points(WIP)",
+        "points",
         AppType::GraphicsTechniques,
-        |_frame: &eframe::Frame| None,
+        |frame: &eframe::Frame| {
+            Some(Box::new(
+                points::Points::new_with_render_state(frame.wgpu_render_state().unwrap()).unwrap(),
+            ))
+        },
     ),
     (
-        "imageBlur(WIP)",
+        "imageBlur",
         AppType::GraphicsTechniques,
-        |_frame: &eframe::Frame| None,
+        |frame: &eframe::Frame| {
+            Some(Box::new(
+                image_blur::ImageBlur::new_with_render_state(frame.wgpu_render_state().unwrap())
+                    .unwrap(),
+            ))
+        },
     ),
     (
-        "cornell(WIP)",
+        "cornell",
         AppType::GraphicsTechniques,
-        |_frame: &eframe::Frame| None,
+        |frame: &eframe::Frame| {
+            Some(Box::new(
+                cornell::Cornell::new_with_render_state(frame.wgpu_render_state().unwrap())
+                    .unwrap(),
+            ))
+        },
     ),
     (
-        "a-buffer(WIP)",
+        "a-buffer",
         AppType::GraphicsTechniques,
-        |_frame: &eframe::Frame| None,
+        |frame: &eframe::Frame| {
+            Some(Box::new(
+                a_buffer::ABuffer::new_with_render_state(frame.wgpu_render_state().unwrap())
+                    .unwrap(),
+            ))
+        },
     ),
     (
-        "skinnedMesh(WIP)",
+        "skinnedMesh",
         AppType::GraphicsTechniques,
-        |_frame: &eframe::Frame| None,
+        |frame: &eframe::Frame| {
+            Some(Box::new(
+                skinned_mesh::SkinnedMesh::new_with_render_state(
+                    frame.wgpu_render_state().unwrap(),
+                )
+                .unwrap(),
+            ))
+        },
     ),
     (
-        "textRenderingMsdf(WIP)",
+        "textRenderingMsdf",
         AppType::GraphicsTechniques,
-        |_frame: &eframe::Frame| None,
+        |frame: &eframe::Frame| {
+            Some(Box::new(
+                text_rendering_msdf::TextRenderingMsdf::new_with_render_state(
+                    frame.wgpu_render_state().unwrap(),
+                )
+                .unwrap(),
+            ))
+        },
     ),
     (
-        "volumeRenderingTexture3D(WIP)",
+        "volumeRenderingTexture3D",
         AppType::GraphicsTechniques,
-        |_frame: &eframe::Frame| None,
+        |frame: &eframe::Frame| {
+            Some(Box::new(
+                volume_rendering_texture_3d::VolumeRenderingTexture3D::new_with_render_state(
+                    frame.wgpu_render_state().unwrap(),
+                )
+                .unwrap(),
+            ))
+        },
     ),
     // Benchmarks
     (
-        "animometer(WIP)",
+        "animometer",
         AppType::Benchmarks,
-        |_frame: &eframe::Frame| None,
+        |frame: &eframe::Frame| {
+            Some(Box::new(
+                animometer::Animometer::new_with_render_state(frame.wgpu_render_state().unwrap())
+                    .unwrap(),
+            ))
+        },
+    ),
+    // egui Integration
+    (
+        "custom3d",
+        AppType::EguiIntegration,
+        |frame: &eframe::Frame| {
+            Some(Box::new(
+                custom3d::Custom3d::new_with_render_state(frame.wgpu_render_state().unwrap())
+                    .unwrap(),
+            ))
+        },
+    ),
+    (
+        "multipleCanvases",
+        AppType::EguiIntegration,
+        |frame: &eframe::Frame| {
+            Some(Box::new(
+                multiple_canvases::MultipleCanvases::new_with_render_state(
+                    frame.wgpu_render_state().unwrap(),
+                )
+                .unwrap(),
+            ))
+        },
     ),
 ];
 
 /// The main app that switches between different apps.
 pub struct MainApp {
     current_app: Option<Box<dyn eframe::App>>,
+    profiler: GpuProfiler,
+    frame_stats: FrameStats,
+    show_frame_stats: bool,
+    debug_view: DebugView,
 }
 
 /// Implement the main app.
 impl MainApp {
-    pub fn new(_cc: &eframe::CreationContext) -> Option<Self> {
-        Some(Self { current_app: None })
+    pub fn new(cc: &eframe::CreationContext) -> Option<Self> {
+        // Samples find the profiler, the frame statistics and the debug view settings next to
+        // their own resources, see `GpuProfiler::get`, `FrameStats::get` and `DebugView::settings`
+        let frame_stats = FrameStats::new();
+        let (profiler, debug_view) = cc
+            .wgpu_render_state
+            .as_ref()
+            .map(|render_state| {
+                let profiler = GpuProfiler::new(&render_state.device, &render_state.queue);
+                let debug_view = DebugView::new(&render_state.device);
+                let mut renderer = render_state.renderer.write();
+                renderer.callback_resources.insert(profiler.clone());
+                renderer.callback_resources.insert(frame_stats.clone());
+                renderer.callback_resources.insert(debug_view.clone());
+                (profiler, debug_view)
+            })
+            .unwrap_or_default();
+
+        Some(Self {
+            current_app: None,
+            profiler,
+            frame_stats,
+            show_frame_stats: false,
+            debug_view,
+        })
     }
 
     fn switch_app(&mut self, app_name: &str, frame: &eframe::Frame) {
         APPS.iter().for_each(|(name, _, app)| {
             if *name == app_name {
                 self.current_app = app(frame);
+                self.profiler.clear();
+                self.frame_stats.clear();
             }
         });
     }
@@ -234,60 +427,96 @@ impl MainApp {
 
 impl eframe::App for MainApp {
     fn update(&mut self, ctx: &eframe::egui::Context, frame: &mut eframe::Frame) {
+        // The callbacks of the previous frame ran after its `update`, so it is complete now
+        let frame_time = ctx.input(|i| i.unstable_dt);
+        self.frame_stats
+            .end_frame(std::time::Duration::from_secs_f32(frame_time));
+
         egui::SidePanel::left("side_panel").show(ctx, |ui| {
             egui::ScrollArea::vertical().show(ui, |ui| {
                 ui.heading("wgpu samples");
+                ui.checkbox(&mut self.show_frame_stats, "Frame stats overlay");
+                self.debug_view.ui(ui);
                 ui.separator();
 
                 ui.heading(AppType::BasicGraphics.to_string());
                 APPS.iter()
-                    .filter(|x| (**x).1 == AppType::BasicGraphics)
+                    .filter(|x| x.1 == AppType::BasicGraphics)
                     .for_each(|(name, _, _)| {
                         if ui.link(*name).clicked() {
-                            self.switch_app(*name, &frame);
+                            self.switch_app(name, frame);
                         }
                     });
 
                 ui.heading(AppType::WebGPUFeatures.to_string());
                 APPS.iter()
-                    .filter(|x| (**x).1 == AppType::WebGPUFeatures)
+                    .filter(|x| x.1 == AppType::WebGPUFeatures)
                     .for_each(|(name, _, _)| {
                         if ui.link(*name).clicked() {
-                            self.switch_app(*name, &frame);
+                            self.switch_app(name, frame);
                         }
                     });
 
                 ui.heading(AppType::GPGPUDemos.to_string());
                 APPS.iter()
-                    .filter(|x| (**x).1 == AppType::GPGPUDemos)
+                    .filter(|x| x.1 == AppType::GPGPUDemos)
                     .for_each(|(name, _, _)| {
                         if ui.link(*name).clicked() {
-                            self.switch_app(*name, &frame);
+                            self.switch_app(name, frame);
                         }
                     });
 
                 ui.heading(AppType::GraphicsTechniques.to_string());
                 APPS.iter()
-                    .filter(|x| (**x).1 == AppType::GraphicsTechniques)
+                    .filter(|x| x.1 == AppType::GraphicsTechniques)
                     .for_each(|(name, _, _)| {
                         if ui.link(*name).clicked() {
-                            self.switch_app(*name, &frame);
+                            self.switch_app(name, frame);
                         }
                     });
 
                 ui.heading(AppType::Benchmarks.to_string());
                 APPS.iter()
-                    .filter(|x| (**x).1 == AppType::Benchmarks)
+                    .filter(|x| x.1 == AppType::Benchmarks)
+                    .for_each(|(name, _, _)| {
+                        if ui.link(*name).clicked() {
+                            self.switch_app(name, frame);
+                        }
+                    });
+
+                ui.heading(AppType::EguiIntegration.to_string());
+                APPS.iter()
+                    .filter(|x| x.1 == AppType::EguiIntegration)
                     .for_each(|(name, _, _)| {
                         if ui.link(*name).clicked() {
-                            self.switch_app(*name, &frame);
+                            self.switch_app(name, frame);
                         }
                     });
             });
         });
 
+        egui::TopBottomPanel::bottom("profiler_panel").show(ctx, |ui| {
+            let shown = egui::CollapsingHeader::new("GPU profiler")
+                .show(ui, |ui| self.profiler.ui(ui))
+                .body_returned
+                .is_some();
+            if !shown {
+                self.profiler.hide();
+            }
+        });
+
+        if self.show_frame_stats {
+            egui::Area::new(egui::Id::new("frame_stats_overlay"))
+                .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-8.0, 8.0))
+                .order(egui::Order::Foreground)
+                .interactable(false)
+                .show(ctx, |ui| {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| self.frame_stats.ui(ui));
+                });
+        }
+
         if let Some(app) = self.current_app.as_mut() {
-            app.update(ctx, frame);
+            self.frame_stats.time_update(|| app.update(ctx, frame));
         }
     }
 }
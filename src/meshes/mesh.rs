@@ -0,0 +1,186 @@
+use glam::{Vec2, Vec3};
+
+/// A vertex with everything lighting techniques need, laid out for a single vertex buffer.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+    pub tangent: [f32; 3],
+    pub bitangent: [f32; 3],
+}
+
+impl Vertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+        0 => Float32x3,
+        1 => Float32x3,
+        2 => Float32x2,
+        3 => Float32x3,
+        4 => Float32x3,
+    ];
+
+    /// Position at location 0, normal at 1, uv at 2, tangent at 3 and bitangent at 4.
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// An indexed triangle list.
+#[derive(Clone, Debug, Default)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+impl Mesh {
+    /// An axis aligned box centered on the origin, with separate vertices per face so each face
+    /// gets flat normals and its own `[0, 1]` uv square.
+    pub fn create_box(width: f32, height: f32, depth: f32) -> Self {
+        let half = Vec3::new(width, height, depth) / 2.0;
+
+        // Normal, then the directions of u and v on the face
+        let faces = [
+            (Vec3::X, Vec3::NEG_Z, Vec3::NEG_Y),
+            (Vec3::NEG_X, Vec3::Z, Vec3::NEG_Y),
+            (Vec3::Y, Vec3::X, Vec3::Z),
+            (Vec3::NEG_Y, Vec3::X, Vec3::NEG_Z),
+            (Vec3::Z, Vec3::X, Vec3::NEG_Y),
+            (Vec3::NEG_Z, Vec3::NEG_X, Vec3::NEG_Y),
+        ];
+
+        let mut mesh = Self::default();
+        for (normal, u_axis, v_axis) in faces {
+            let base = mesh.vertices.len() as u32;
+            for (u, v) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+                let position =
+                    (normal + u_axis * (2.0 * u - 1.0) + v_axis * (2.0 * v - 1.0)) * half;
+                mesh.vertices.push(Vertex {
+                    position: position.into(),
+                    normal: normal.into(),
+                    uv: [u, v],
+                    ..Default::default()
+                });
+            }
+            mesh.indices
+                .extend([base, base + 2, base + 1, base, base + 3, base + 2]);
+        }
+
+        mesh.compute_tangents();
+        mesh
+    }
+
+    /// A tube of radius `tube_radius` following a `(p, q)` torus knot of radius `radius`, which
+    /// winds `p` times around the axis of the torus and `q` times through its hole. u runs along
+    /// the knot and v around the tube.
+    pub fn create_torus_knot(
+        radius: f32,
+        tube_radius: f32,
+        tubular_segments: u32,
+        radial_segments: u32,
+        p: u32,
+        q: u32,
+    ) -> Self {
+        let curve = |u: f32| {
+            let quotient = q as f32 / p as f32 * u;
+            let r = radius * (2.0 + quotient.cos()) * 0.5;
+            Vec3::new(r * u.cos(), r * u.sin(), radius * quotient.sin() * 0.5)
+        };
+
+        let mut mesh = Self::default();
+        for i in 0..=tubular_segments {
+            let u = i as f32 / tubular_segments as f32 * p as f32 * std::f32::consts::TAU;
+            // A frame around the curve, from a point slightly further along it
+            let center = curve(u);
+            let next = curve(u + 0.01);
+            let tangent = next - center;
+            let bitangent = tangent.cross(next + center).normalize();
+            let normal = bitangent.cross(tangent).normalize();
+
+            for j in 0..=radial_segments {
+                let v = j as f32 / radial_segments as f32 * std::f32::consts::TAU;
+                let offset =
+                    normal * (-tube_radius * v.cos()) + bitangent * (tube_radius * v.sin());
+                mesh.vertices.push(Vertex {
+                    position: (center + offset).into(),
+                    normal: offset.normalize().into(),
+                    uv: [
+                        i as f32 / tubular_segments as f32,
+                        j as f32 / radial_segments as f32,
+                    ],
+                    ..Default::default()
+                });
+            }
+        }
+
+        let row = radial_segments + 1;
+        for i in 1..=tubular_segments {
+            for j in 1..=radial_segments {
+                let a = row * (i - 1) + j - 1;
+                let b = row * i + j - 1;
+                let c = row * i + j;
+                let d = row * (i - 1) + j;
+                mesh.indices.extend([a, b, d, b, c, d]);
+            }
+        }
+
+        mesh.compute_tangents();
+        mesh
+    }
+
+    /// Compute per-vertex tangents and bitangents from the positions, normals and uvs, so
+    /// normal maps can be applied to any mesh with uvs.
+    ///
+    /// The tangent follows increasing u and the bitangent increasing v. Contributions of all
+    /// triangles sharing a vertex are summed, then made orthogonal to the normal.
+    pub fn compute_tangents(&mut self) {
+        let mut tangents = vec![Vec3::ZERO; self.vertices.len()];
+        let mut bitangents = vec![Vec3::ZERO; self.vertices.len()];
+
+        for triangle in self.indices.chunks_exact(3) {
+            let [i0, i1, i2] = [0, 1, 2].map(|i| triangle[i] as usize);
+            let [v0, v1, v2] = [i0, i1, i2].map(|i| self.vertices[i]);
+
+            let edge1 = Vec3::from(v1.position) - Vec3::from(v0.position);
+            let edge2 = Vec3::from(v2.position) - Vec3::from(v0.position);
+            let delta_uv1 = Vec2::from(v1.uv) - Vec2::from(v0.uv);
+            let delta_uv2 = Vec2::from(v2.uv) - Vec2::from(v0.uv);
+
+            let determinant = delta_uv1.x * delta_uv2.y - delta_uv2.x * delta_uv1.y;
+            if determinant.abs() < f32::EPSILON {
+                // Degenerate uvs, this triangle cannot tell which way u and v go
+                continue;
+            }
+            let r = 1.0 / determinant;
+            let tangent = (edge1 * delta_uv2.y - edge2 * delta_uv1.y) * r;
+            let bitangent = (edge2 * delta_uv1.x - edge1 * delta_uv2.x) * r;
+
+            for i in [i0, i1, i2] {
+                tangents[i] += tangent;
+                bitangents[i] += bitangent;
+            }
+        }
+
+        for ((vertex, tangent), bitangent) in self.vertices.iter_mut().zip(tangents).zip(bitangents)
+        {
+            let normal = Vec3::from(vertex.normal);
+            // Gram-Schmidt, falling back to any perpendicular direction
+            let tangent = (tangent - normal * normal.dot(tangent))
+                .try_normalize()
+                .unwrap_or_else(|| normal.any_orthonormal_vector());
+            // Keep the handedness of the uv mapping, which may be mirrored
+            let handedness = if normal.cross(tangent).dot(bitangent) < 0.0 {
+                -1.0
+            } else {
+                1.0
+            };
+
+            vertex.tangent = tangent.into();
+            vertex.bitangent = (normal.cross(tangent) * handedness).into();
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! Render targets for samples that need more than egui's render pass offers.
+//!
+//! egui paints every callback into its own render pass, which has a single color attachment
+//! and no depth buffer. Samples that need depth testing or several passes render into an
+//! [`OffscreenTarget`] from `prepare`, using the encoder egui hands them, and then copy the
+//! result into egui's render pass from `paint` with [`OffscreenTarget::blit`].
+
+use crate::profiler::GpuProfiler;
+use crate::stats::CountedPass;
+
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth24Plus;
+
+pub struct OffscreenTarget {
+    color_format: wgpu::TextureFormat,
+    depth_format: Option<wgpu::TextureFormat>,
+    size: (u32, u32),
+    color_texture: wgpu::Texture,
+    color_view: wgpu::TextureView,
+    depth_view: Option<wgpu::TextureView>,
+    sampler: wgpu::Sampler,
+    blit_bind_group_layout: wgpu::BindGroupLayout,
+    blit_bind_group: wgpu::BindGroup,
+    blit_pipeline: wgpu::RenderPipeline,
+}
+
+impl OffscreenTarget {
+    /// Create a target rendering in `color_format`, which should be egui's target format so
+    /// that the blit is a plain copy, with an optional depth attachment.
+    pub fn new(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        depth_format: Option<wgpu::TextureFormat>,
+        size: (u32, u32),
+    ) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Offscreen Sampler"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let blit_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Offscreen Blit Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Offscreen Blit Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./blit.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Offscreen Blit Pipeline Layout"),
+            bind_group_layouts: &[&blit_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let blit_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Offscreen Blit Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(color_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let (color_texture, color_view, depth_view, blit_bind_group) = Self::create_textures(
+            device,
+            color_format,
+            depth_format,
+            size,
+            &blit_bind_group_layout,
+            &sampler,
+        );
+
+        Self {
+            color_format,
+            depth_format,
+            size,
+            color_texture,
+            color_view,
+            depth_view,
+            sampler,
+            blit_bind_group_layout,
+            blit_bind_group,
+            blit_pipeline,
+        }
+    }
+
+    fn create_textures(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        depth_format: Option<wgpu::TextureFormat>,
+        size: (u32, u32),
+        blit_bind_group_layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+    ) -> (
+        wgpu::Texture,
+        wgpu::TextureView,
+        Option<wgpu::TextureView>,
+        wgpu::BindGroup,
+    ) {
+        let extent = wgpu::Extent3d {
+            width: size.0.max(1),
+            height: size.1.max(1),
+            depth_or_array_layers: 1,
+        };
+
+        let color_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Color Texture"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: color_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let color_view = color_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let depth_view = depth_format.map(|format| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("Offscreen Depth Texture"),
+                    size: extent,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        });
+
+        let blit_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Offscreen Blit Bind Group"),
+            layout: blit_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&color_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        });
+
+        (color_texture, color_view, depth_view, blit_bind_group)
+    }
+
+    /// Recreate the textures if the size changed, e.g. when the display scale changes.
+    /// Returns whether the textures were recreated.
+    pub fn resize(&mut self, device: &wgpu::Device, size: (u32, u32)) -> bool {
+        if size == self.size {
+            return false;
+        }
+        let (color_texture, color_view, depth_view, blit_bind_group) = Self::create_textures(
+            device,
+            self.color_format,
+            self.depth_format,
+            size,
+            &self.blit_bind_group_layout,
+            &self.sampler,
+        );
+        self.color_texture = color_texture;
+        self.color_view = color_view;
+        self.depth_view = depth_view;
+        self.blit_bind_group = blit_bind_group;
+        self.size = size;
+        true
+    }
+
+    /// For passes that need other attachments than [`Self::begin_pass`] sets up.
+    pub fn color_view(&self) -> &wgpu::TextureView {
+        &self.color_view
+    }
+
+    /// For binding the depth of an earlier pass, in passes without a depth attachment.
+    pub fn depth_view(&self) -> Option<&wgpu::TextureView> {
+        self.depth_view.as_ref()
+    }
+
+    /// Begin a render pass that clears the color attachment to `clear_color` and, if there is
+    /// one, the depth attachment to 1 and its stencil to 0. The pass is timed by `profiler`
+    /// under `label`.
+    pub fn begin_pass<'a>(
+        &'a self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        clear_color: wgpu::Color,
+        label: &str,
+        profiler: &'a GpuProfiler,
+    ) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.color_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear_color),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: self.depth_view.as_ref().map(|view| {
+                wgpu::RenderPassDepthStencilAttachment {
+                    view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: self
+                        .depth_format
+                        .filter(wgpu::TextureFormat::has_stencil_aspect)
+                        .map(|_| wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(0),
+                            store: wgpu::StoreOp::Store,
+                        }),
+                }
+            }),
+            timestamp_writes: profiler.render_pass_writes(label),
+            occlusion_query_set: None,
+        })
+    }
+
+    /// Draw the color texture over the whole viewport of `render_pass`.
+    pub fn blit<'a>(&'a self, render_pass: &mut CountedPass<'_, 'a>) {
+        render_pass.set_pipeline(&self.blit_pipeline);
+        render_pass.set_bind_group(0, &self.blit_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+/// The size in physical pixels of a canvas `rect` given in egui points.
+pub fn size_in_pixels(ui: &eframe::egui::Ui, rect: eframe::egui::Rect) -> (u32, u32) {
+    let size = rect.size() * ui.ctx().pixels_per_point();
+    (size.x.round() as u32, size.y.round() as u32)
+}
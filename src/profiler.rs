@@ -0,0 +1,380 @@
+//! GPU timing of the passes samples record, shown in the profiler panel of the main app.
+//!
+//! The main app inserts a [`GpuProfiler`] into the callback resources. Samples fetch it in
+//! `prepare` with [`GpuProfiler::get`] and give the passes they begin the timestamp writes it
+//! hands out. Once every callback was prepared, the callback painted by [`GpuProfiler::ui`]
+//! resolves the queries into a [`Readback`], so the times show up a few frames later without
+//! stalling the GPU.
+
+use eframe::{egui, egui_wgpu};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use crate::readback::Readback;
+
+/// Passes that can be timed per frame; later passes are not timed.
+const MAX_PASSES: u32 = 32;
+/// Frames shown in the graph.
+const HISTORY: usize = 240;
+/// Each pass writes a timestamp at its beginning and one at its end, as a `u64` each.
+const PASS_SIZE: wgpu::BufferAddress = 2 * std::mem::size_of::<u64>() as wgpu::BufferAddress;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PassTime {
+    pub label: String,
+    pub milliseconds: f64,
+}
+
+/// The GPU time of every pass, from the timestamps the passes wrote in query order. Passes
+/// with the same label, like the steps of a simulation, are added up.
+fn pass_times(labels: &[String], ticks: &[u64], period: f32) -> Vec<PassTime> {
+    let mut times: Vec<PassTime> = Vec::new();
+    for (label, ticks) in labels.iter().zip(ticks.chunks_exact(2)) {
+        let milliseconds = ticks[1].saturating_sub(ticks[0]) as f64 * period as f64 / 1_000_000.0;
+        match times.iter_mut().find(|time| time.label == *label) {
+            Some(time) => time.milliseconds += milliseconds,
+            None => times.push(PassTime {
+                label: label.clone(),
+                milliseconds,
+            }),
+        }
+    }
+    times
+}
+
+struct State {
+    enabled: bool,
+    /// Labels of the passes given timestamp writes this frame, in query order.
+    passes: Vec<String>,
+    /// How many passes the last frame timed, `None` until a frame ended with the panel open.
+    last_frame_passes: Option<usize>,
+    readback: Readback,
+    /// Labels of the passes timed by the readback in flight.
+    pending: Vec<String>,
+    history: VecDeque<Vec<PassTime>>,
+}
+
+struct Inner {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    /// Nanoseconds per timestamp tick.
+    period: f32,
+    state: egui::mutex::Mutex<State>,
+}
+
+/// A handle to the profiler, cheap to clone. Without timestamp query support it hands out no
+/// timestamp writes, so samples can use it unconditionally.
+#[derive(Clone, Default)]
+pub struct GpuProfiler {
+    inner: Option<Arc<Inner>>,
+}
+
+impl GpuProfiler {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return Self::default();
+        }
+
+        let size = MAX_PASSES as u64 * PASS_SIZE;
+        let inner = Inner {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("Profiler Query Set"),
+                ty: wgpu::QueryType::Timestamp,
+                count: 2 * MAX_PASSES,
+            }),
+            resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Profiler Resolve Buffer"),
+                size,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            period: queue.get_timestamp_period(),
+            state: egui::mutex::Mutex::new(State {
+                enabled: false,
+                passes: Vec::new(),
+                last_frame_passes: None,
+                readback: Readback::new(device, size, "Profiler Readback Buffer"),
+                pending: Vec::new(),
+                history: VecDeque::new(),
+            }),
+        };
+        Self {
+            inner: Some(Arc::new(inner)),
+        }
+    }
+
+    /// The profiler the main app inserted, or one that times nothing, e.g. in tests.
+    pub fn get(callback_resources: &egui_wgpu::CallbackResources) -> Self {
+        callback_resources
+            .get::<Self>()
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Forget the times of the previous sample.
+    pub fn clear(&self) {
+        if let Some(inner) = &self.inner {
+            let mut state = inner.state.lock();
+            state.pending.clear();
+            state.history.clear();
+            state.last_frame_passes = None;
+        }
+    }
+
+    /// Timestamp writes for the next render pass, while the profiler panel is open.
+    pub fn render_pass_writes(&self, label: &str) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        let (query_set, index) = self.next_pass(label)?;
+        Some(wgpu::RenderPassTimestampWrites {
+            query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        })
+    }
+
+    /// Timestamp writes for the next compute pass, while the profiler panel is open.
+    pub fn compute_pass_writes(&self, label: &str) -> Option<wgpu::ComputePassTimestampWrites<'_>> {
+        let (query_set, index) = self.next_pass(label)?;
+        Some(wgpu::ComputePassTimestampWrites {
+            query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        })
+    }
+
+    /// The query set and the index of the first of the two queries of the next pass.
+    fn next_pass(&self, label: &str) -> Option<(&wgpu::QuerySet, u32)> {
+        let inner = self.inner.as_ref()?;
+        let mut state = inner.state.lock();
+        let count = state.passes.len() as u32;
+        if !state.enabled || count == MAX_PASSES {
+            return None;
+        }
+        state.passes.push(label.to_owned());
+        Some((&inner.query_set, 2 * count))
+    }
+
+    /// Pick up the times of an earlier frame, and resolve the queries of this one if the
+    /// readback is free. Called once all callbacks were prepared.
+    fn end_frame(&self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+        let mut state = inner.state.lock();
+        if let Some(data) = state.readback.poll(device) {
+            let times = pass_times(&state.pending, bytemuck::cast_slice(&data), inner.period);
+            if !times.is_empty() {
+                if state.history.len() == HISTORY {
+                    state.history.pop_front();
+                }
+                state.history.push_back(times);
+            }
+        }
+
+        let passes = std::mem::take(&mut state.passes);
+        state.last_frame_passes = Some(passes.len());
+        if !passes.is_empty() && state.readback.is_idle() {
+            encoder.resolve_query_set(
+                &inner.query_set,
+                0..2 * passes.len() as u32,
+                &inner.resolve_buffer,
+                0,
+            );
+            let size = passes.len() as u64 * PASS_SIZE;
+            state
+                .readback
+                .copy_from(encoder, &inner.resolve_buffer, size);
+            state.pending = passes;
+        }
+    }
+
+    /// The profiler panel: a rolling graph of the GPU time of every pass, and their means.
+    /// Passes are only timed while it is shown.
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        let Some(inner) = &self.inner else {
+            ui.label("Timestamp queries are not supported by this adapter.");
+            return;
+        };
+        let (history, last_frame_passes) = {
+            let mut state = inner.state.lock();
+            if !state.enabled {
+                state.enabled = true;
+                state.passes.clear();
+            }
+            (state.history.clone(), state.last_frame_passes)
+        };
+
+        let (rect, _) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width().min(720.0), 120.0),
+            egui::Sense::hover(),
+        );
+        // Resolves the queries in `finish_prepare`, after the sample recorded its passes
+        ui.painter().add(egui_wgpu::Callback::new_paint_callback(
+            rect,
+            ProfilerCallback {
+                profiler: self.clone(),
+            },
+        ));
+
+        let mut labels: Vec<&str> = Vec::new();
+        for pass in history.iter().flatten() {
+            if !labels.contains(&pass.label.as_str()) {
+                labels.push(&pass.label);
+            }
+        }
+        if labels.is_empty() {
+            // egui begins the render pass callbacks paint into, so it cannot be timed
+            let text = if last_frame_passes == Some(0) {
+                "This sample has no timed passes: it only paints into the egui render pass."
+            } else {
+                "The times of the passes of this sample are on the way."
+            };
+            ui.painter().text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                text,
+                egui::FontId::proportional(14.0),
+                ui.visuals().weak_text_color(),
+            );
+            return;
+        }
+
+        let color = |i: usize| egui::epaint::Hsva::new(i as f32 * 0.618 % 1.0, 0.7, 0.9, 1.0);
+        let totals: Vec<f64> = history
+            .iter()
+            .map(|frame| frame.iter().map(|pass| pass.milliseconds).sum())
+            .collect();
+        let max = totals.iter().copied().fold(0.0, f64::max).max(0.001) * 1.1;
+        let point = |frame: usize, milliseconds: f64| {
+            egui::pos2(
+                rect.right()
+                    - (history.len() - 1 - frame) as f32 / (HISTORY - 1) as f32 * rect.width(),
+                rect.bottom() - (milliseconds / max) as f32 * rect.height(),
+            )
+        };
+
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+        let total_line = totals
+            .iter()
+            .enumerate()
+            .map(|(frame, &ms)| point(frame, ms));
+        painter.add(egui::Shape::line(
+            total_line.collect(),
+            egui::Stroke::new(1.5, ui.visuals().strong_text_color()),
+        ));
+        for (i, label) in labels.iter().enumerate() {
+            let line = history.iter().enumerate().filter_map(|(frame, passes)| {
+                let pass = passes.iter().find(|pass| pass.label == *label)?;
+                Some(point(frame, pass.milliseconds))
+            });
+            painter.add(egui::Shape::line(
+                line.collect(),
+                egui::Stroke::new(1.0, color(i)),
+            ));
+        }
+        painter.text(
+            rect.left_top() + egui::vec2(4.0, 2.0),
+            egui::Align2::LEFT_TOP,
+            format!("{max:.3} ms"),
+            egui::FontId::monospace(11.0),
+            ui.visuals().weak_text_color(),
+        );
+
+        let mean = |values: &mut dyn Iterator<Item = f64>| {
+            let (sum, count) = values.fold((0.0, 0), |(sum, count), ms| (sum + ms, count + 1));
+            sum / count.max(1) as f64
+        };
+        egui::Grid::new("gpu_profiler_passes")
+            .num_columns(2)
+            .show(ui, |ui| {
+                for (i, label) in labels.iter().enumerate() {
+                    ui.colored_label(color(i), *label);
+                    let times = history.iter().flatten().filter(|pass| pass.label == *label);
+                    ui.label(format!(
+                        "{:.3} ms",
+                        mean(&mut times.map(|pass| pass.milliseconds))
+                    ));
+                    ui.end_row();
+                }
+                ui.strong("total");
+                ui.strong(format!("{:.3} ms", mean(&mut totals.iter().copied())));
+                ui.end_row();
+            });
+    }
+
+    /// Stop timing passes, as the panel is no longer shown.
+    pub fn hide(&self) {
+        if let Some(inner) = &self.inner {
+            let mut state = inner.state.lock();
+            state.enabled = false;
+            state.passes.clear();
+            state.last_frame_passes = None;
+        }
+    }
+}
+
+struct ProfilerCallback {
+    profiler: GpuProfiler,
+}
+
+impl egui_wgpu::CallbackTrait for ProfilerCallback {
+    fn finish_prepare(
+        &self,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        _callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        self.profiler.end_frame(device, egui_encoder);
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: egui::PaintCallbackInfo,
+        _render_pass: &mut wgpu::RenderPass<'a>,
+        _callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_ticks_to_milliseconds() {
+        let labels = ["Shadow Pass".to_owned(), "Main Pass".to_owned()];
+        let times = pass_times(&labels, &[1000, 3000, 3000, 1_003_000], 2.0);
+        assert_eq!(
+            times,
+            [
+                PassTime {
+                    label: "Shadow Pass".to_owned(),
+                    milliseconds: 0.004,
+                },
+                PassTime {
+                    label: "Main Pass".to_owned(),
+                    milliseconds: 2.0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn adds_up_passes_with_the_same_label() {
+        let labels = ["Step".to_owned(), "Render".to_owned(), "Step".to_owned()];
+        let times = pass_times(&labels, &[0, 10, 10, 40, 40, 60], 1000.0);
+        assert_eq!(times.len(), 2);
+        assert_eq!(times[0].label, "Step");
+        assert!((times[0].milliseconds - 0.03).abs() < 1e-9);
+        assert!((times[1].milliseconds - 0.03).abs() < 1e-9);
+    }
+
+    #[test]
+    fn profiler_without_support_times_nothing() {
+        let profiler = GpuProfiler::default();
+        assert!(profiler.render_pass_writes("Pass").is_none());
+        assert!(profiler.compute_pass_writes("Pass").is_none());
+    }
+}
@@ -0,0 +1,25 @@
+//! A tiny seeded random number generator, so samples can scatter objects reproducibly without
+//! pulling in an extra dependency.
+
+/// xorshift32, see <https://en.wikipedia.org/wiki/Xorshift>.
+#[derive(Clone, Debug)]
+pub struct Random(u32);
+
+impl Random {
+    pub fn new(seed: u32) -> Self {
+        // xorshift gets stuck at zero
+        Self(seed.max(1))
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    /// A float in `[0, 1]`.
+    pub fn next_f32(&mut self) -> f32 {
+        self.next_u32() as f32 / u32::MAX as f32
+    }
+}
@@ -0,0 +1,107 @@
+//! Non-blocking buffer readback for use inside paint callbacks.
+//!
+//! A buffer cannot be mapped while a command buffer that writes it is still waiting to be
+//! submitted, and egui submits the encoder passed to `prepare` only after all callbacks ran.
+//! So a readback takes three frames: the copy is recorded in the first, the map is requested in
+//! the second (after the copy was submitted), and the data is picked up once the map completes.
+
+use std::sync::{
+    atomic::{AtomicU8, Ordering},
+    Arc,
+};
+
+const MAP_PENDING: u8 = 0;
+const MAP_SUCCEEDED: u8 = 1;
+const MAP_FAILED: u8 = 2;
+
+enum State {
+    Idle,
+    Copied,
+    Mapping(Arc<AtomicU8>),
+}
+
+pub struct Readback {
+    buffer: wgpu::Buffer,
+    state: State,
+}
+
+impl Readback {
+    pub fn new(device: &wgpu::Device, size: wgpu::BufferAddress, label: &str) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            buffer,
+            state: State::Idle,
+        }
+    }
+
+    pub fn size(&self) -> wgpu::BufferAddress {
+        self.buffer.size()
+    }
+
+    /// Whether a new copy can be recorded.
+    pub fn is_idle(&self) -> bool {
+        matches!(self.state, State::Idle)
+    }
+
+    /// Record a copy of `size` bytes from `source` into the readback buffer.
+    /// Returns `false` without recording anything if a previous readback is still in flight.
+    pub fn copy_from(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::Buffer,
+        size: wgpu::BufferAddress,
+    ) -> bool {
+        if !self.is_idle() {
+            return false;
+        }
+        encoder.copy_buffer_to_buffer(source, 0, &self.buffer, 0, size);
+        self.state = State::Copied;
+        true
+    }
+
+    /// Advance the readback; call once per frame, in a later frame than [`Self::copy_from`].
+    /// Returns the data once it is available.
+    pub fn poll(&mut self, device: &wgpu::Device) -> Option<Vec<u8>> {
+        match &self.state {
+            State::Idle => None,
+            State::Copied => {
+                let status = Arc::new(AtomicU8::new(MAP_PENDING));
+                let callback_status = status.clone();
+                self.buffer
+                    .slice(..)
+                    .map_async(wgpu::MapMode::Read, move |result| {
+                        let value = if result.is_ok() {
+                            MAP_SUCCEEDED
+                        } else {
+                            MAP_FAILED
+                        };
+                        callback_status.store(value, Ordering::Release);
+                    });
+                self.state = State::Mapping(status);
+                None
+            }
+            State::Mapping(status) => {
+                device.poll(wgpu::Maintain::Poll);
+                match status.load(Ordering::Acquire) {
+                    MAP_SUCCEEDED => {
+                        let data = self.buffer.slice(..).get_mapped_range().to_vec();
+                        self.buffer.unmap();
+                        self.state = State::Idle;
+                        Some(data)
+                    }
+                    MAP_FAILED => {
+                        self.state = State::Idle;
+                        None
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+}
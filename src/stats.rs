@@ -0,0 +1,559 @@
+//! CPU side statistics of the frames samples render, shown in the frame stats overlay of the
+//! main app.
+//!
+//! The main app inserts a [`FrameStats`] into the callback resources. Samples fetch it in
+//! `prepare` and `paint` with [`FrameStats::get`], write their buffers through
+//! [`FrameStats::write_buffer`] and record their draws through the [`CountedPass`] it wraps
+//! their render passes in. Paint callbacks created with [`paint_callback`] time their
+//! `prepare` and `paint`.
+
+use eframe::{egui, egui_wgpu};
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Frames the times are averaged over.
+const HISTORY: usize = 60;
+
+/// What happened on the CPU during one frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Counters {
+    /// Time from the start of this frame to the start of the next one.
+    frame: Duration,
+    /// Time spent in the `update` of the sample.
+    update: Duration,
+    /// Time spent in `prepare` and `finish_prepare` of the paint callbacks.
+    prepare: Duration,
+    /// Time spent in `paint` of the paint callbacks.
+    paint: Duration,
+    draw_calls: u64,
+    /// Vertices of non-indexed draws and indices of indexed ones, times their instances.
+    vertices: u64,
+    /// Bytes written with `queue.write_buffer`.
+    bytes_written: u64,
+}
+
+/// The mean of every time over `history`, and the counts of its last frame.
+fn summary(history: &VecDeque<Counters>) -> Counters {
+    let Some(last) = history.back() else {
+        return Counters::default();
+    };
+    let count = history.len() as u32;
+    let mean = |time: fn(&Counters) -> Duration| history.iter().map(time).sum::<Duration>() / count;
+    Counters {
+        frame: mean(|counters| counters.frame),
+        update: mean(|counters| counters.update),
+        prepare: mean(|counters| counters.prepare),
+        paint: mean(|counters| counters.paint),
+        ..*last
+    }
+}
+
+/// `bytes` with a binary unit, e.g. `1.5 KiB`.
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+struct State {
+    current: Counters,
+    history: VecDeque<Counters>,
+}
+
+/// A handle to the frame statistics, cheap to clone. The default handle counts nothing, so
+/// samples can use it unconditionally, e.g. in tests.
+#[derive(Clone, Default)]
+pub struct FrameStats {
+    state: Option<Arc<egui::mutex::Mutex<State>>>,
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        let state = State {
+            current: Counters::default(),
+            history: VecDeque::new(),
+        };
+        Self {
+            state: Some(Arc::new(egui::mutex::Mutex::new(state))),
+        }
+    }
+
+    /// The statistics the main app inserted, or ones that count nothing.
+    pub fn get(callback_resources: &egui_wgpu::CallbackResources) -> Self {
+        callback_resources
+            .get::<Self>()
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Forget the frames of the previous sample.
+    pub fn clear(&self) {
+        if let Some(state) = &self.state {
+            let mut state = state.lock();
+            state.current = Counters::default();
+            state.history.clear();
+        }
+    }
+
+    fn add(&self, add: impl FnOnce(&mut Counters)) {
+        if let Some(state) = &self.state {
+            add(&mut state.lock().current);
+        }
+    }
+
+    /// `queue.write_buffer`, counting the bytes written.
+    pub fn write_buffer(
+        &self,
+        queue: &wgpu::Queue,
+        buffer: &wgpu::Buffer,
+        offset: wgpu::BufferAddress,
+        data: &[u8],
+    ) {
+        self.add(|counters| counters.bytes_written += data.len() as u64);
+        queue.write_buffer(buffer, offset, data);
+    }
+
+    /// Wrap `render_pass`, either a pass begun by the sample or the one egui paints with, to
+    /// count the draws recorded through it.
+    pub fn pass<'p, 'a>(&self, render_pass: impl Into<PassRef<'p, 'a>>) -> CountedPass<'p, 'a> {
+        CountedPass {
+            render_pass: render_pass.into(),
+            stats: self.clone(),
+            draw_calls: 0,
+            vertices: 0,
+        }
+    }
+
+    /// Run the `update` of a sample, timing it.
+    pub fn time_update(&self, update: impl FnOnce()) {
+        let start = Instant::now();
+        update();
+        let elapsed = start.elapsed();
+        self.add(|counters| counters.update += elapsed);
+    }
+
+    /// Close the frame whose callbacks ran since the last call, `frame_time` after it started.
+    /// Called by the main app at the start of every frame.
+    pub fn end_frame(&self, frame_time: Duration) {
+        if let Some(state) = &self.state {
+            let mut state = state.lock();
+            let mut counters = std::mem::take(&mut state.current);
+            counters.frame = frame_time;
+            if state.history.len() == HISTORY {
+                state.history.pop_front();
+            }
+            state.history.push_back(counters);
+        }
+    }
+
+    /// The overlay contents: mean times over the last frames and the counts of the last one.
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        let Some(state) = &self.state else {
+            return;
+        };
+        let summary = summary(&state.lock().history);
+
+        let milliseconds = |time: Duration| format!("{:.2} ms", time.as_secs_f64() * 1000.0);
+        let fps = 1.0 / summary.frame.as_secs_f64().max(1e-6);
+        egui::Grid::new("frame_stats")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("frame");
+                ui.label(format!("{} ({fps:.0} fps)", milliseconds(summary.frame)));
+                ui.end_row();
+                ui.label("update");
+                ui.label(milliseconds(summary.update));
+                ui.end_row();
+                ui.label("prepare");
+                ui.label(milliseconds(summary.prepare));
+                ui.end_row();
+                ui.label("paint");
+                ui.label(milliseconds(summary.paint));
+                ui.end_row();
+                ui.label("draw calls");
+                ui.label(summary.draw_calls.to_string());
+                ui.end_row();
+                ui.label("vertices");
+                ui.label(summary.vertices.to_string());
+                ui.end_row();
+                ui.label("buffer writes");
+                ui.label(format_bytes(summary.bytes_written));
+                ui.end_row();
+            });
+    }
+}
+
+/// A render pass that a [`CountedPass`] owns or borrows.
+pub enum PassRef<'p, 'a> {
+    Owned(wgpu::RenderPass<'a>),
+    Borrowed(&'p mut wgpu::RenderPass<'a>),
+}
+
+impl<'a> From<wgpu::RenderPass<'a>> for PassRef<'_, 'a> {
+    fn from(render_pass: wgpu::RenderPass<'a>) -> Self {
+        Self::Owned(render_pass)
+    }
+}
+
+impl<'p, 'a> From<&'p mut wgpu::RenderPass<'a>> for PassRef<'p, 'a> {
+    fn from(render_pass: &'p mut wgpu::RenderPass<'a>) -> Self {
+        Self::Borrowed(render_pass)
+    }
+}
+
+/// A render pass that counts the draws recorded through it, adding them to the frame
+/// statistics when dropped. It forwards the state the samples set to the wrapped pass, but does
+/// not hand out the pass itself, so that no draw goes uncounted.
+pub struct CountedPass<'p, 'a> {
+    render_pass: PassRef<'p, 'a>,
+    stats: FrameStats,
+    draw_calls: u64,
+    vertices: u64,
+}
+
+impl<'a> CountedPass<'_, 'a> {
+    pub fn set_pipeline(&mut self, pipeline: &'a wgpu::RenderPipeline) {
+        self.render_pass().set_pipeline(pipeline);
+    }
+
+    pub fn set_bind_group(
+        &mut self,
+        index: u32,
+        bind_group: &'a wgpu::BindGroup,
+        offsets: &[wgpu::DynamicOffset],
+    ) {
+        self.render_pass()
+            .set_bind_group(index, bind_group, offsets);
+    }
+
+    pub fn set_vertex_buffer(&mut self, slot: u32, buffer_slice: wgpu::BufferSlice<'a>) {
+        self.render_pass().set_vertex_buffer(slot, buffer_slice);
+    }
+
+    pub fn set_index_buffer(
+        &mut self,
+        buffer_slice: wgpu::BufferSlice<'a>,
+        index_format: wgpu::IndexFormat,
+    ) {
+        self.render_pass()
+            .set_index_buffer(buffer_slice, index_format);
+    }
+
+    pub fn set_blend_constant(&mut self, color: wgpu::Color) {
+        self.render_pass().set_blend_constant(color);
+    }
+
+    pub fn set_stencil_reference(&mut self, reference: u32) {
+        self.render_pass().set_stencil_reference(reference);
+    }
+
+    pub fn begin_occlusion_query(&mut self, query_index: u32) {
+        self.render_pass().begin_occlusion_query(query_index);
+    }
+
+    pub fn end_occlusion_query(&mut self) {
+        self.render_pass().end_occlusion_query();
+    }
+
+    pub fn begin_pipeline_statistics_query(
+        &mut self,
+        query_set: &'a wgpu::QuerySet,
+        query_index: u32,
+    ) {
+        self.render_pass()
+            .begin_pipeline_statistics_query(query_set, query_index);
+    }
+
+    pub fn end_pipeline_statistics_query(&mut self) {
+        self.render_pass().end_pipeline_statistics_query();
+    }
+
+    pub fn draw(&mut self, vertices: Range<u32>, instances: Range<u32>) {
+        self.count(1, vertices.len() as u64 * instances.len() as u64);
+        self.render_pass().draw(vertices, instances);
+    }
+
+    pub fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>) {
+        self.count(1, indices.len() as u64 * instances.len() as u64);
+        self.render_pass()
+            .draw_indexed(indices, base_vertex, instances);
+    }
+
+    /// Execute `bundle`, which recorded `draw_calls` draws of `vertices` vertices in total;
+    /// a bundle does not tell what it contains.
+    pub fn execute_bundle(
+        &mut self,
+        bundle: &'a wgpu::RenderBundle,
+        draw_calls: u64,
+        vertices: u64,
+    ) {
+        self.count(draw_calls, vertices);
+        self.render_pass().execute_bundles(std::iter::once(bundle));
+    }
+
+    fn count(&mut self, draw_calls: u64, vertices: u64) {
+        self.draw_calls += draw_calls;
+        self.vertices += vertices;
+    }
+
+    fn render_pass(&mut self) -> &mut wgpu::RenderPass<'a> {
+        match &mut self.render_pass {
+            PassRef::Owned(render_pass) => render_pass,
+            PassRef::Borrowed(render_pass) => render_pass,
+        }
+    }
+}
+
+/// Lets code that records into both passes and bundles, like the animometer scene, record into
+/// a counted pass. The vertices of indirect draws are only known to the GPU, so only the calls
+/// are counted.
+impl<'a> wgpu::util::RenderEncoder<'a> for CountedPass<'_, 'a> {
+    fn set_bind_group(
+        &mut self,
+        index: u32,
+        bind_group: &'a wgpu::BindGroup,
+        offsets: &[wgpu::DynamicOffset],
+    ) {
+        self.render_pass()
+            .set_bind_group(index, bind_group, offsets);
+    }
+
+    fn set_pipeline(&mut self, pipeline: &'a wgpu::RenderPipeline) {
+        self.render_pass().set_pipeline(pipeline);
+    }
+
+    fn set_index_buffer(
+        &mut self,
+        buffer_slice: wgpu::BufferSlice<'a>,
+        index_format: wgpu::IndexFormat,
+    ) {
+        self.render_pass()
+            .set_index_buffer(buffer_slice, index_format);
+    }
+
+    fn set_vertex_buffer(&mut self, slot: u32, buffer_slice: wgpu::BufferSlice<'a>) {
+        self.render_pass().set_vertex_buffer(slot, buffer_slice);
+    }
+
+    fn draw(&mut self, vertices: Range<u32>, instances: Range<u32>) {
+        CountedPass::draw(self, vertices, instances);
+    }
+
+    fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>) {
+        CountedPass::draw_indexed(self, indices, base_vertex, instances);
+    }
+
+    fn draw_indirect(
+        &mut self,
+        indirect_buffer: &'a wgpu::Buffer,
+        indirect_offset: wgpu::BufferAddress,
+    ) {
+        self.count(1, 0);
+        self.render_pass()
+            .draw_indirect(indirect_buffer, indirect_offset);
+    }
+
+    fn draw_indexed_indirect(
+        &mut self,
+        indirect_buffer: &'a wgpu::Buffer,
+        indirect_offset: wgpu::BufferAddress,
+    ) {
+        self.count(1, 0);
+        self.render_pass()
+            .draw_indexed_indirect(indirect_buffer, indirect_offset);
+    }
+
+    fn set_push_constants(&mut self, stages: wgpu::ShaderStages, offset: u32, data: &[u8]) {
+        self.render_pass().set_push_constants(stages, offset, data);
+    }
+}
+
+impl Drop for CountedPass<'_, '_> {
+    fn drop(&mut self) {
+        let (draw_calls, vertices) = (self.draw_calls, self.vertices);
+        self.stats.add(|counters| {
+            counters.draw_calls += draw_calls;
+            counters.vertices += vertices;
+        });
+    }
+}
+
+/// The paint callback of a sample, with its `prepare` and `paint` timed.
+pub fn paint_callback(
+    rect: egui::Rect,
+    callback: impl egui_wgpu::CallbackTrait + 'static,
+) -> egui::PaintCallback {
+    egui_wgpu::Callback::new_paint_callback(rect, Timed(callback))
+}
+
+struct Timed<C>(C);
+
+impl<C: egui_wgpu::CallbackTrait> egui_wgpu::CallbackTrait for Timed<C> {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let start = Instant::now();
+        let command_buffers = self.0.prepare(
+            device,
+            queue,
+            screen_descriptor,
+            egui_encoder,
+            callback_resources,
+        );
+        let elapsed = start.elapsed();
+        FrameStats::get(callback_resources).add(|counters| counters.prepare += elapsed);
+        command_buffers
+    }
+
+    fn finish_prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let start = Instant::now();
+        let command_buffers =
+            self.0
+                .finish_prepare(device, queue, egui_encoder, callback_resources);
+        let elapsed = start.elapsed();
+        FrameStats::get(callback_resources).add(|counters| counters.prepare += elapsed);
+        command_buffers
+    }
+
+    fn paint<'a>(
+        &'a self,
+        info: egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        let start = Instant::now();
+        self.0.paint(info, render_pass, callback_resources);
+        let elapsed = start.elapsed();
+        FrameStats::get(callback_resources).add(|counters| counters.paint += elapsed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_averages_times_and_keeps_the_last_counts() {
+        let frame = |milliseconds, draw_calls| Counters {
+            frame: Duration::from_millis(milliseconds),
+            prepare: Duration::from_millis(milliseconds / 4),
+            draw_calls,
+            ..Default::default()
+        };
+        let history = VecDeque::from([frame(16, 10), frame(20, 12)]);
+        let mean = summary(&history);
+        assert_eq!(mean.frame, Duration::from_millis(18));
+        assert_eq!(mean.prepare, Duration::from_micros(4500));
+        assert_eq!(mean.draw_calls, 12);
+        assert_eq!(summary(&VecDeque::new()), Counters::default());
+    }
+
+    #[test]
+    fn formats_bytes_with_binary_units() {
+        assert_eq!(format_bytes(64), "64 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024), "3.0 MiB");
+    }
+
+    #[test]
+    fn counts_draws_and_written_bytes() {
+        let Some((device, queue)) = crate::headless::request_device() else {
+            return;
+        };
+        let stats = FrameStats::new();
+        let target = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: 4,
+                height: 4,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        stats.write_buffer(&queue, &buffer, 0, &[0; 16]);
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(
+                "@vertex fn vs_main() -> @builtin(position) vec4f { return vec4f(0.0); }
+                 @fragment fn fs_main() -> @location(0) vec4f { return vec4f(1.0); }"
+                    .into(),
+            ),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::TextureFormat::Rgba8Unorm.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let mut encoder = device.create_command_encoder(&Default::default());
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations::default(),
+                })],
+                ..Default::default()
+            });
+            let mut render_pass = stats.pass(&mut render_pass);
+            render_pass.set_pipeline(&pipeline);
+            render_pass.draw(0..36, 0..2);
+            render_pass.draw(0..3, 0..100);
+        }
+        queue.submit(Some(encoder.finish()));
+        stats.end_frame(Duration::from_millis(16));
+
+        let summary = summary(&stats.state.as_ref().unwrap().lock().history);
+        assert_eq!(summary.draw_calls, 2);
+        assert_eq!(summary.vertices, 372);
+        assert_eq!(summary.bytes_written, 16);
+        assert_eq!(summary.frame, Duration::from_millis(16));
+    }
+}
@@ -0,0 +1,322 @@
+//! Glyph metrics in the BMFont JSON format written by msdf-bmfont-xml and msdf-atlas-gen, and
+//! laying out strings with them.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Deserialize)]
+struct BmFont {
+    pages: Vec<String>,
+    chars: Vec<BmChar>,
+    common: BmCommon,
+    #[serde(rename = "distanceField")]
+    distance_field: BmDistanceField,
+    #[serde(default)]
+    kernings: Vec<BmKerning>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BmCommon {
+    line_height: f32,
+    scale_w: u32,
+    scale_h: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BmDistanceField {
+    field_type: String,
+    distance_range: f32,
+}
+
+#[derive(Deserialize)]
+struct BmChar {
+    id: u32,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    xoffset: f32,
+    yoffset: f32,
+    xadvance: f32,
+}
+
+#[derive(Deserialize)]
+struct BmKerning {
+    first: u32,
+    second: u32,
+    amount: f32,
+}
+
+/// Where a glyph is in the atlas and how to place it, all in atlas texels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Glyph {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// From the pen position to the left of the glyph.
+    pub x_offset: f32,
+    /// From the top of the line down to the top of the glyph.
+    pub y_offset: f32,
+    /// How far the pen moves after the glyph.
+    pub x_advance: f32,
+}
+
+pub struct FontMetrics {
+    pub line_height: f32,
+    pub atlas_size: (u32, u32),
+    /// The distance, in atlas texels, that the field spans from fully outside to fully inside.
+    pub distance_range: f32,
+    glyphs: HashMap<char, Glyph>,
+    kernings: HashMap<(char, char), f32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextOptions {
+    /// Center every line horizontally, and the block of lines vertically, on the origin.
+    /// Otherwise the origin is the top left of the text.
+    pub centered: bool,
+    /// Size of an atlas texel in text space.
+    pub pixel_scale: f32,
+    pub kerning: bool,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self {
+            centered: false,
+            pixel_scale: 1.0 / 512.0,
+            kerning: true,
+        }
+    }
+}
+
+/// A glyph quad, laid out to match `GlyphInput` in `msdf.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GlyphQuad {
+    /// Left, bottom, right and top in text space, where y goes up.
+    pub rect: [f32; 4],
+    /// The same corners in normalized atlas coordinates, where v goes down.
+    pub uv: [f32; 4],
+}
+
+pub struct TextLayout {
+    pub quads: Vec<GlyphQuad>,
+    /// Size of the text, in text space.
+    pub width: f32,
+    pub height: f32,
+}
+
+impl FontMetrics {
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let font: BmFont = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        if font.distance_field.field_type != "msdf" {
+            return Err(format!(
+                "expected an msdf font, found {}",
+                font.distance_field.field_type
+            ));
+        }
+        if font.pages.len() != 1 {
+            return Err(format!(
+                "expected a single atlas page, found {}",
+                font.pages.len()
+            ));
+        }
+
+        let to_char = |id| char::from_u32(id).ok_or(format!("invalid character id {id}"));
+        let glyphs = font
+            .chars
+            .iter()
+            .map(|c| {
+                let glyph = Glyph {
+                    x: c.x,
+                    y: c.y,
+                    width: c.width,
+                    height: c.height,
+                    x_offset: c.xoffset,
+                    y_offset: c.yoffset,
+                    x_advance: c.xadvance,
+                };
+                Ok((to_char(c.id)?, glyph))
+            })
+            .collect::<Result<_, String>>()?;
+        let kernings = font
+            .kernings
+            .iter()
+            .map(|k| Ok(((to_char(k.first)?, to_char(k.second)?), k.amount)))
+            .collect::<Result<_, String>>()?;
+
+        Ok(Self {
+            line_height: font.common.line_height,
+            atlas_size: (font.common.scale_w, font.common.scale_h),
+            distance_range: font.distance_field.distance_range,
+            glyphs,
+            kernings,
+        })
+    }
+
+    /// The glyph of `c`, or of `?` for characters the font does not have.
+    pub fn glyph(&self, c: char) -> Option<&Glyph> {
+        self.glyphs.get(&c).or_else(|| self.glyphs.get(&'?'))
+    }
+
+    /// How much closer `second` moves to `first` when it follows it, in atlas texels.
+    pub fn kerning(&self, first: char, second: char) -> f32 {
+        self.kernings.get(&(first, second)).copied().unwrap_or(0.0)
+    }
+
+    /// Lay out `text` in lines separated by `\n`.
+    pub fn layout(&self, text: &str, options: &TextOptions) -> TextLayout {
+        let (atlas_width, atlas_height) = (self.atlas_size.0 as f32, self.atlas_size.1 as f32);
+        let mut quads = Vec::new();
+        let mut line_widths = Vec::new();
+
+        for (line, text) in text.split('\n').enumerate() {
+            let top = line as f32 * self.line_height;
+            let first_quad = quads.len();
+            let mut pen = 0.0;
+            let mut previous = None;
+            for c in text.chars() {
+                let Some(glyph) = self.glyph(c) else {
+                    continue;
+                };
+                if let (Some(previous), true) = (previous, options.kerning) {
+                    pen += self.kerning(previous, c);
+                }
+                previous = Some(c);
+
+                if glyph.width > 0.0 && glyph.height > 0.0 {
+                    let left = pen + glyph.x_offset;
+                    let glyph_top = -(top + glyph.y_offset);
+                    quads.push(GlyphQuad {
+                        rect: [
+                            left,
+                            glyph_top - glyph.height,
+                            left + glyph.width,
+                            glyph_top,
+                        ],
+                        uv: [
+                            glyph.x / atlas_width,
+                            (glyph.y + glyph.height) / atlas_height,
+                            (glyph.x + glyph.width) / atlas_width,
+                            glyph.y / atlas_height,
+                        ],
+                    });
+                }
+                pen += glyph.x_advance;
+            }
+
+            if options.centered {
+                for quad in &mut quads[first_quad..] {
+                    quad.rect[0] -= pen / 2.0;
+                    quad.rect[2] -= pen / 2.0;
+                }
+            }
+            line_widths.push(pen);
+        }
+
+        let height = line_widths.len() as f32 * self.line_height;
+        for quad in &mut quads {
+            if options.centered {
+                quad.rect[1] += height / 2.0;
+                quad.rect[3] += height / 2.0;
+            }
+            for value in &mut quad.rect {
+                *value *= options.pixel_scale;
+            }
+        }
+
+        TextLayout {
+            quads,
+            width: line_widths.into_iter().fold(0.0, f32::max) * options.pixel_scale,
+            height: height * options.pixel_scale,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FONT: &str = r#"{
+        "pages": ["atlas.png"],
+        "chars": [
+            {"id": 65, "x": 0, "y": 0, "width": 10, "height": 12, "xoffset": 1, "yoffset": 2, "xadvance": 11},
+            {"id": 86, "x": 10, "y": 0, "width": 10, "height": 12, "xoffset": 0, "yoffset": 2, "xadvance": 10},
+            {"id": 32, "x": 0, "y": 0, "width": 0, "height": 0, "xoffset": 0, "yoffset": 0, "xadvance": 4}
+        ],
+        "common": {"lineHeight": 16, "base": 13, "scaleW": 32, "scaleH": 16},
+        "distanceField": {"fieldType": "msdf", "distanceRange": 4},
+        "kernings": [{"first": 65, "second": 86, "amount": -3}]
+    }"#;
+
+    fn options(centered: bool, kerning: bool) -> TextOptions {
+        TextOptions {
+            centered,
+            pixel_scale: 1.0,
+            kerning,
+        }
+    }
+
+    #[test]
+    fn parses_metrics_and_kerning() {
+        let font = FontMetrics::parse(FONT).unwrap();
+        assert_eq!(font.line_height, 16.0);
+        assert_eq!(font.atlas_size, (32, 16));
+        assert_eq!(font.distance_range, 4.0);
+        assert_eq!(font.glyph('V').unwrap().x, 10.0);
+        assert_eq!(font.kerning('A', 'V'), -3.0);
+        assert_eq!(font.kerning('V', 'A'), 0.0);
+    }
+
+    #[test]
+    fn lays_out_glyphs_along_the_pen_with_kerning() {
+        let font = FontMetrics::parse(FONT).unwrap();
+
+        let layout = font.layout("AV A", &options(false, true));
+        // Spaces advance the pen without a quad
+        assert_eq!(layout.quads.len(), 3);
+        assert_eq!(layout.quads[0].rect, [1.0, -14.0, 11.0, -2.0]);
+        assert_eq!(layout.quads[0].uv, [0.0, 0.75, 10.0 / 32.0, 0.0]);
+        // After the advance of A, pulled back by the kerning pair
+        assert_eq!(layout.quads[1].rect[0], 11.0 - 3.0);
+        assert_eq!(layout.quads[2].rect[0], 11.0 - 3.0 + 10.0 + 4.0 + 1.0);
+        assert_eq!(layout.width, 11.0 - 3.0 + 10.0 + 4.0 + 11.0);
+        assert_eq!(layout.height, 16.0);
+
+        let unkerned = font.layout("AV A", &options(false, false));
+        assert_eq!(unkerned.quads[1].rect[0], 11.0);
+        assert_eq!(unkerned.width, layout.width + 3.0);
+    }
+
+    #[test]
+    fn lines_stack_downwards_and_center_independently() {
+        let font = FontMetrics::parse(FONT).unwrap();
+
+        let layout = font.layout("A\nAA", &options(false, true));
+        assert_eq!(layout.quads[1].rect[3], -(16.0 + 2.0));
+        assert_eq!(layout.height, 32.0);
+
+        let centered = font.layout("A\nAA", &options(true, true));
+        assert_eq!(centered.quads[0].rect[0], 1.0 - 11.0 / 2.0);
+        assert_eq!(centered.quads[1].rect[0], 1.0 - 22.0 / 2.0);
+        assert_eq!(centered.quads[0].rect[3], 16.0 - 2.0);
+    }
+
+    #[test]
+    fn unknown_characters_fall_back_or_are_skipped() {
+        let font = FontMetrics::parse(FONT).unwrap();
+        // No `?` in this font either, so `é` is skipped entirely
+        assert_eq!(font.layout("AéA", &options(false, true)).quads.len(), 2);
+    }
+
+    #[test]
+    fn bundled_font_parses() {
+        let font = FontMetrics::parse(include_str!("assets/dejavu-sans-msdf.json")).unwrap();
+        assert!(font.glyph('A').is_some());
+        assert!(font.kerning('A', 'V') < 0.0);
+    }
+}
@@ -0,0 +1,376 @@
+//! Text rendered from a multi-channel signed distance field (MSDF) font atlas, which stays crisp
+//! at any scale and angle, so it can label things in 3D.
+//!
+//! A [`MsdfTextRenderer`] holds the pipeline and the camera, a [`MsdfFont`] the atlas, and each
+//! [`MsdfText`] a laid out string with its own transform and color. Texts are drawn with
+//! [`MsdfTextRenderer::draw`] into any render pass with the formats the renderer was created
+//! for, typically the pass of an [`OffscreenTarget`](crate::offscreen::OffscreenTarget).
+
+mod font;
+
+pub use font::{FontMetrics, TextOptions};
+
+use glam::Mat4;
+use wgpu::util::DeviceExt;
+
+use crate::stats::{CountedPass, FrameStats};
+use font::GlyphQuad;
+
+/// Laid out to match `Text` in `msdf.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct TextUniforms {
+    transform: [[f32; 4]; 4],
+    color: [f32; 4],
+}
+
+/// Laid out to match `Font` in `msdf.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct FontUniforms {
+    distance_range: f32,
+    _padding: [f32; 3],
+}
+
+pub struct MsdfFont {
+    pub metrics: FontMetrics,
+    bind_group: wgpu::BindGroup,
+}
+
+/// A string laid out with a font, ready to draw.
+pub struct MsdfText {
+    /// Size of the text, in text space before its transform.
+    pub width: f32,
+    pub height: f32,
+    glyph_buffer: Option<wgpu::Buffer>,
+    glyph_count: u32,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl MsdfText {
+    /// Place the text in the scene and set its color, which may be translucent.
+    pub fn update(
+        &self,
+        queue: &wgpu::Queue,
+        stats: &FrameStats,
+        transform: Mat4,
+        color: [f32; 4],
+    ) {
+        stats.write_buffer(
+            queue,
+            &self.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&TextUniforms {
+                transform: transform.to_cols_array_2d(),
+                color,
+            }),
+        );
+    }
+}
+
+pub struct MsdfTextRenderer {
+    pipeline: wgpu::RenderPipeline,
+    font_bind_group_layout: wgpu::BindGroupLayout,
+    text_bind_group_layout: wgpu::BindGroupLayout,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    sampler: wgpu::Sampler,
+}
+
+impl MsdfTextRenderer {
+    /// Create a renderer for passes with a `color_format` attachment and, if given, a depth
+    /// attachment that text is tested against but does not write to.
+    pub fn new(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        depth_format: Option<wgpu::TextureFormat>,
+    ) -> Self {
+        let uniform_entry = |binding, visibility| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let camera_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("MSDF Text Camera Bind Group Layout"),
+                entries: &[uniform_entry(0, wgpu::ShaderStages::VERTEX)],
+            });
+        let font_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("MSDF Text Font Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    uniform_entry(2, wgpu::ShaderStages::FRAGMENT),
+                ],
+            });
+        let text_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("MSDF Text Bind Group Layout"),
+                entries: &[uniform_entry(
+                    0,
+                    wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                )],
+            });
+
+        let camera_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("MSDF Text Camera Buffer"),
+            size: std::mem::size_of::<Mat4>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("MSDF Text Camera Bind Group"),
+            layout: &camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("MSDF Text Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("MSDF Text Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("./msdf.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("MSDF Text Pipeline Layout"),
+            bind_group_layouts: &[
+                &camera_bind_group_layout,
+                &font_bind_group_layout,
+                &text_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("MSDF Text Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<GlyphQuad>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            // Text is readable from behind too, mirrored
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
+                format,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            pipeline,
+            font_bind_group_layout,
+            text_bind_group_layout,
+            camera_buffer,
+            camera_bind_group,
+            sampler,
+        }
+    }
+
+    /// Load a font from its BMFont JSON description and the atlas image it refers to.
+    pub fn create_font(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        json: &str,
+        atlas: &[u8],
+    ) -> Result<MsdfFont, String> {
+        let metrics = FontMetrics::parse(json)?;
+        let image = image::load_from_memory(atlas)
+            .map_err(|e| e.to_string())?
+            .to_rgba8();
+
+        // The channels are distances, not colors, so they must not be decoded as sRGB
+        let texture_view = device
+            .create_texture_with_data(
+                queue,
+                &wgpu::TextureDescriptor {
+                    label: Some("MSDF Text Atlas"),
+                    size: wgpu::Extent3d {
+                        width: image.width(),
+                        height: image.height(),
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::Rgba8Unorm,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                    view_formats: &[],
+                },
+                wgpu::util::TextureDataOrder::LayerMajor,
+                &image,
+            )
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("MSDF Text Font Buffer"),
+            contents: bytemuck::bytes_of(&FontUniforms {
+                distance_range: metrics.distance_range,
+                _padding: [0.0; 3],
+            }),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("MSDF Text Font Bind Group"),
+            layout: &self.font_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        Ok(MsdfFont {
+            metrics,
+            bind_group,
+        })
+    }
+
+    /// DejaVu Sans with the printable ASCII characters, 42 atlas texels to the em.
+    pub fn default_font(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> MsdfFont {
+        self.create_font(
+            device,
+            queue,
+            include_str!("assets/dejavu-sans-msdf.json"),
+            include_bytes!("assets/dejavu-sans-msdf.png"),
+        )
+        .unwrap()
+    }
+
+    /// Lay out `text` with `font`. The text starts out white, at the origin of the scene.
+    pub fn create_text(
+        &self,
+        device: &wgpu::Device,
+        font: &MsdfFont,
+        text: &str,
+        options: &TextOptions,
+    ) -> MsdfText {
+        let layout = font.metrics.layout(text, options);
+
+        let glyph_buffer = (!layout.quads.is_empty()).then(|| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("MSDF Text Glyph Buffer"),
+                contents: bytemuck::cast_slice(&layout.quads),
+                usage: wgpu::BufferUsages::VERTEX,
+            })
+        });
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("MSDF Text Uniform Buffer"),
+            contents: bytemuck::bytes_of(&TextUniforms {
+                transform: Mat4::IDENTITY.to_cols_array_2d(),
+                color: [1.0; 4],
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("MSDF Text Bind Group"),
+            layout: &self.text_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        MsdfText {
+            width: layout.width,
+            height: layout.height,
+            glyph_buffer,
+            glyph_count: layout.quads.len() as u32,
+            uniform_buffer,
+            bind_group,
+        }
+    }
+
+    pub fn update_camera(&self, queue: &wgpu::Queue, stats: &FrameStats, view_proj: Mat4) {
+        stats.write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&view_proj.to_cols_array_2d()),
+        );
+    }
+
+    /// Draw texts laid out with `font`. This changes the pipeline and bind groups of the pass.
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut CountedPass<'_, 'a>,
+        font: &'a MsdfFont,
+        texts: impl IntoIterator<Item = &'a MsdfText>,
+    ) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &font.bind_group, &[]);
+        for text in texts {
+            let Some(glyph_buffer) = &text.glyph_buffer else {
+                continue;
+            };
+            render_pass.set_bind_group(2, &text.bind_group, &[]);
+            render_pass.set_vertex_buffer(0, glyph_buffer.slice(..));
+            render_pass.draw(0..4, 0..text.glyph_count);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+struct Camera {
+    view_proj: mat4x4f,
+}
+
+struct Font {
+    // Atlas texels the distance field spans from fully outside to fully inside
+    distance_range: f32,
+}
+
+struct Text {
+    transform: mat4x4f,
+    color: vec4f,
+}
+
+@group(0) @binding(0) var<uniform> camera: Camera;
+
+@group(1) @binding(0) var atlas: texture_2d<f32>;
+@group(1) @binding(1) var atlas_sampler: sampler;
+@group(1) @binding(2) var<uniform> font: Font;
+
+@group(2) @binding(0) var<uniform> text: Text;
+
+struct GlyphInput {
+    // Left, bottom, right, top
+    @location(0) rect: vec4f,
+    @location(1) uv: vec4f,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) uv: vec2f,
+}
+
+// One instance per glyph, drawn as a 4 vertex triangle strip
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, glyph: GlyphInput) -> VertexOutput {
+    let corner = vec2f(f32(vertex_index & 1u), f32(vertex_index >> 1u));
+    let position = mix(glyph.rect.xy, glyph.rect.zw, corner);
+
+    var out: VertexOutput;
+    out.position = camera.view_proj * text.transform * vec4f(position, 0.0, 1.0);
+    out.uv = mix(glyph.uv.xy, glyph.uv.zw, corner);
+    return out;
+}
+
+fn median(r: f32, g: f32, b: f32) -> f32 {
+    return max(min(r, g), min(max(r, g), b));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let msd = textureSample(atlas, atlas_sampler, in.uv).rgb;
+    let signed_distance = median(msd.r, msd.g, msd.b) - 0.5;
+
+    // How many screen pixels the distance range covers, so edges stay one pixel wide at any scale
+    let unit_range = vec2f(font.distance_range) / vec2f(textureDimensions(atlas));
+    let screen_texture_size = vec2f(1.0) / fwidth(in.uv);
+    let screen_px_range = max(0.5 * dot(unit_range, screen_texture_size), 1.0);
+
+    let alpha = clamp(screen_px_range * signed_distance + 0.5, 0.0, 1.0);
+    if alpha < 0.001 {
+        discard;
+    }
+    return vec4f(text.color.rgb, text.color.a * alpha);
+}